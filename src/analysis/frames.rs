@@ -0,0 +1,232 @@
+use crate::analysis::check_max_stack;
+use crate::analysis::layout::{instruction_offsets, relative_offsets};
+use crate::analysis::stack::field_type_length;
+use crate::{Attribute, JVMClass, JavaError, StackMapFrame, StackMapFrameType, VerificationType};
+
+/// A stack map frame with its absolute pc and its complete locals.
+#[derive(Debug, Clone)]
+pub(crate) struct Frame {
+    pub(crate) pc: u32,
+    pub(crate) locals: Vec<VerificationType>,
+    pub(crate) stack: Vec<VerificationType>,
+}
+
+/// Decodes the frames of a `StackMapTable`, each one being relative to the previous one.
+/// `initial_locals` is the implicit first frame, i.e. `this` and the parameters.
+pub(crate) fn expand_frames(
+    initial_locals: &[VerificationType],
+    frames: &[StackMapFrame],
+) -> Result<Vec<Frame>, JavaError> {
+    let mut expanded: Vec<Frame> = Vec::with_capacity(frames.len());
+    let mut locals = initial_locals.to_vec();
+
+    for frame in frames {
+        let offset_delta = match frame.frame_type {
+            StackMapFrameType::SameFrame(frame_type) => frame_type as u32,
+            StackMapFrameType::SameLocals1StackItemFrame(frame_type) => frame_type as u32 - 64,
+            _ => frame.offset_delta as u32,
+        };
+        let pc = match expanded.last() {
+            Some(previous) => previous.pc + offset_delta + 1,
+            None => offset_delta,
+        };
+
+        match frame.frame_type {
+            StackMapFrameType::ChopFrame(frame_type) => {
+                let chopped = 251 - frame_type as usize;
+                if chopped > locals.len() {
+                    return Err(JavaError::InvalidStackMapFrame(pc));
+                }
+                locals.truncate(locals.len() - chopped);
+            }
+            StackMapFrameType::AppendFrame(..) => locals.extend(frame.locals.iter().cloned()),
+            StackMapFrameType::FullFrame => locals = frame.locals.clone(),
+            _ => {}
+        }
+
+        expanded.push(Frame {
+            pc,
+            locals: locals.clone(),
+            stack: frame.stack.clone(),
+        });
+    }
+
+    Ok(expanded)
+}
+
+/// Encodes frames back into a `StackMapTable`, using the most compact form for each one.
+pub(crate) fn compress_frames(
+    initial_locals: &[VerificationType],
+    frames: &[Frame],
+) -> Vec<StackMapFrame> {
+    let mut compressed = Vec::with_capacity(frames.len());
+    let mut previous_locals = initial_locals;
+    let mut previous_pc = None;
+
+    for frame in frames {
+        let offset_delta = match previous_pc {
+            Some(pc) => frame.pc - pc - 1,
+            None => frame.pc,
+        } as u16;
+
+        let locals = &frame.locals;
+        let same_locals = locals == previous_locals;
+
+        let mut encoded = StackMapFrame {
+            frame_type: StackMapFrameType::FullFrame,
+            offset_delta,
+            locals: vec![],
+            stack: vec![],
+        };
+
+        if same_locals && frame.stack.is_empty() {
+            encoded.frame_type = if offset_delta < 64 {
+                encoded.offset_delta = 0;
+                StackMapFrameType::SameFrame(offset_delta as u8)
+            } else {
+                StackMapFrameType::SameFrameExtended
+            };
+        } else if same_locals && frame.stack.len() == 1 {
+            encoded.frame_type = if offset_delta < 64 {
+                encoded.offset_delta = 0;
+                StackMapFrameType::SameLocals1StackItemFrame(64 + offset_delta as u8)
+            } else {
+                StackMapFrameType::SameLocals1StackItemFrameExtended
+            };
+            encoded.stack = frame.stack.clone();
+        } else if frame.stack.is_empty()
+            && locals.len() > previous_locals.len()
+            && locals.len() - previous_locals.len() <= 3
+            && locals.starts_with(previous_locals)
+        {
+            let appended = &locals[previous_locals.len()..];
+            encoded.frame_type = StackMapFrameType::AppendFrame(251 + appended.len() as u8);
+            encoded.locals = appended.to_vec();
+        } else if frame.stack.is_empty()
+            && locals.len() < previous_locals.len()
+            && previous_locals.len() - locals.len() <= 3
+            && previous_locals.starts_with(locals)
+        {
+            let chopped = previous_locals.len() - locals.len();
+            encoded.frame_type = StackMapFrameType::ChopFrame(251 - chopped as u8);
+        } else {
+            encoded.locals = locals.clone();
+            encoded.stack = frame.stack.clone();
+        }
+
+        compressed.push(encoded);
+        previous_locals = locals;
+        previous_pc = Some(frame.pc);
+    }
+
+    compressed
+}
+
+/// Checks that the `StackMapTable` of a `Code` attribute is consistent with its code: every frame
+/// is on an instruction and fits in `max_locals` and `max_stack`, and, from version 51 on, every
+/// branch target and exception handler has a frame. `max_stack` itself is checked too.
+///
+/// The types in the frames aren't checked against the code, only their shape.
+pub(crate) fn check_stack_map(
+    code_attribute: &Attribute,
+    is_static: bool,
+    descriptor: &str,
+    class: &JVMClass,
+) -> Result<(), JavaError> {
+    let Attribute::Code {
+        code,
+        max_stack,
+        max_locals,
+        exception_table,
+        attributes,
+        ..
+    } = code_attribute
+    else {
+        return Ok(());
+    };
+
+    check_max_stack(
+        code,
+        exception_table,
+        *max_stack,
+        is_static,
+        descriptor,
+        class,
+    )?;
+
+    let frames = attributes
+        .iter()
+        .find_map(|attr| match attr {
+            Attribute::StackMapTable(frames) => Some(frames.as_slice()),
+            _ => None,
+        })
+        .unwrap_or_default();
+    let frames = expand_frames(&initial_locals(is_static, descriptor)?, frames)?;
+
+    let offsets = instruction_offsets(code);
+    for frame in &frames {
+        if offsets.binary_search(&frame.pc).is_err()
+            || slots(&frame.locals) > *max_locals as u32
+            || slots(&frame.stack) > *max_stack as u32
+        {
+            return Err(JavaError::InvalidStackMapFrame(frame.pc));
+        }
+    }
+
+    if class.major < 51 {
+        return Ok(());
+    }
+
+    let branch_targets = code.iter().zip(&offsets).flat_map(|(inst, pc)| {
+        relative_offsets(inst)
+            .into_iter()
+            .map(move |offset| (*pc as i64 + offset as i64) as u32)
+    });
+    let handlers = exception_table.iter().map(|entry| entry.handler_pc as u32);
+    for pc in branch_targets.chain(handlers) {
+        if frames.binary_search_by_key(&pc, |frame| frame.pc).is_err() {
+            return Err(JavaError::InvalidStackMapFrame(pc));
+        }
+    }
+
+    Ok(())
+}
+
+/// The implicit first frame of a method. References aren't resolved to constants: `this` and the
+/// objects have a `cpool_index` of 0, only the shape of the frame is meaningful.
+pub(crate) fn initial_locals(
+    is_static: bool,
+    descriptor: &str,
+) -> Result<Vec<VerificationType>, JavaError> {
+    let invalid = || JavaError::InvalidDescriptor(descriptor.to_string());
+
+    let mut locals = vec![];
+    if !is_static {
+        locals.push(VerificationType::Object { cpool_index: 0 });
+    }
+
+    let mut rest = descriptor.strip_prefix('(').ok_or_else(invalid)?;
+    while !rest.starts_with(')') {
+        let length = field_type_length(rest).ok_or_else(invalid)?;
+        locals.push(match rest.as_bytes()[0] {
+            b'B' | b'C' | b'I' | b'S' | b'Z' => VerificationType::Integer,
+            b'F' => VerificationType::Float,
+            b'J' => VerificationType::Long,
+            b'D' => VerificationType::Double,
+            _ => VerificationType::Object { cpool_index: 0 },
+        });
+        rest = &rest[length..];
+    }
+
+    Ok(locals)
+}
+
+fn slots(types: &[VerificationType]) -> u32 {
+    types
+        .iter()
+        .map(|verification_type| match verification_type {
+            VerificationType::Long | VerificationType::Double => 2,
+            _ => 1,
+        })
+        .sum()
+}
@@ -0,0 +1,162 @@
+use crate::{Instruction, JavaError};
+
+/// Computes the byte offset of each instruction from the start of the code array.
+pub(crate) fn instruction_offsets(code: &[Instruction]) -> Vec<u32> {
+    let mut offsets = Vec::with_capacity(code.len());
+
+    let mut pc = 0;
+    for inst in code {
+        offsets.push(pc);
+        pc += inst.size();
+    }
+
+    offsets
+}
+
+/// Returns the branch offsets of an instruction, relative to its own opcode.
+/// For switches, the default offset comes first.
+pub(crate) fn relative_offsets(inst: &Instruction) -> Vec<i32> {
+    match inst {
+        Instruction::Goto(branch)
+        | Instruction::IfAcmpeq(branch)
+        | Instruction::IfAcmpne(branch)
+        | Instruction::Ifeq(branch)
+        | Instruction::Ifge(branch)
+        | Instruction::Ifgt(branch)
+        | Instruction::IfIcmpeq(branch)
+        | Instruction::IfIcmpge(branch)
+        | Instruction::IfIcmpgt(branch)
+        | Instruction::IfIcmple(branch)
+        | Instruction::IfIcmplt(branch)
+        | Instruction::IfIcmpne(branch)
+        | Instruction::Ifle(branch)
+        | Instruction::Iflt(branch)
+        | Instruction::Ifne(branch)
+        | Instruction::IfNonNull(branch)
+        | Instruction::IfNull(branch)
+        | Instruction::Jsr(branch) => vec![*branch as i32],
+        Instruction::GotoW(branch) | Instruction::JsrW(branch) => vec![*branch],
+        Instruction::TableSwitch {
+            jump_targets,
+            default,
+            ..
+        } => std::iter::once(default)
+            .chain(jump_targets)
+            .copied()
+            .collect(),
+        Instruction::LookupSwitch { default, pairs, .. } => std::iter::once(*default)
+            .chain(pairs.iter().map(|pair| pair.target))
+            .collect(),
+        _ => vec![],
+    }
+}
+
+/// Resolves every branch to the index of the instruction it jumps to.
+pub(crate) fn branch_indices(code: &[Instruction]) -> Result<Vec<Vec<usize>>, JavaError> {
+    let offsets = instruction_offsets(code);
+
+    code.iter()
+        .zip(&offsets)
+        .map(|(inst, pc)| {
+            relative_offsets(inst)
+                .into_iter()
+                .map(|offset| {
+                    let target = *pc as i64 + offset as i64;
+                    offsets
+                        .binary_search_by(|o| (*o as i64).cmp(&target))
+                        .map_err(|_| JavaError::InvalidBranchTarget(*pc))
+                })
+                .collect()
+        })
+        .collect()
+}
+
+/// Rewrites branch offsets so each branch jumps to the instruction index given in `targets`,
+/// recomputing switch padding and promoting `goto`/`jsr` to their wide forms when needed.
+pub(crate) fn relayout(code: &mut [Instruction], targets: &[Vec<usize>]) -> Result<(), JavaError> {
+    loop {
+        let mut pc = 0;
+        for inst in code.iter_mut() {
+            let alignment = (4 - (pc + 1) % 4) % 4;
+            match inst {
+                Instruction::TableSwitch { padding, .. }
+                | Instruction::LookupSwitch { padding, .. } => *padding = alignment,
+                _ => {}
+            }
+            pc += inst.size();
+        }
+
+        let offsets = instruction_offsets(code);
+        let mut promoted = false;
+
+        for (i, inst) in code.iter_mut().enumerate() {
+            let relative: Vec<i32> = targets[i]
+                .iter()
+                .map(|target| offsets[*target] as i32 - offsets[i] as i32)
+                .collect();
+
+            if relative.is_empty() {
+                continue;
+            }
+
+            let short = i16::try_from(relative[0]).ok();
+            match inst {
+                Instruction::Goto(branch) => match short {
+                    Some(offset) => *branch = offset,
+                    None => {
+                        *inst = Instruction::GotoW(relative[0]);
+                        promoted = true;
+                    }
+                },
+                Instruction::Jsr(branch) => match short {
+                    Some(offset) => *branch = offset,
+                    None => {
+                        *inst = Instruction::JsrW(relative[0]);
+                        promoted = true;
+                    }
+                },
+                Instruction::IfAcmpeq(branch)
+                | Instruction::IfAcmpne(branch)
+                | Instruction::Ifeq(branch)
+                | Instruction::Ifge(branch)
+                | Instruction::Ifgt(branch)
+                | Instruction::IfIcmpeq(branch)
+                | Instruction::IfIcmpge(branch)
+                | Instruction::IfIcmpgt(branch)
+                | Instruction::IfIcmple(branch)
+                | Instruction::IfIcmplt(branch)
+                | Instruction::IfIcmpne(branch)
+                | Instruction::Ifle(branch)
+                | Instruction::Iflt(branch)
+                | Instruction::Ifne(branch)
+                | Instruction::IfNonNull(branch)
+                | Instruction::IfNull(branch) => {
+                    *branch = short.ok_or(JavaError::BranchOutOfRange(offsets[i]))?;
+                }
+                Instruction::GotoW(branch) | Instruction::JsrW(branch) => {
+                    *branch = relative[0];
+                }
+                Instruction::TableSwitch {
+                    jump_targets,
+                    default,
+                    ..
+                } => {
+                    *default = relative[0];
+                    jump_targets.copy_from_slice(&relative[1..]);
+                }
+                Instruction::LookupSwitch { default, pairs, .. } => {
+                    *default = relative[0];
+                    for (pair, offset) in pairs.iter_mut().zip(&relative[1..]) {
+                        pair.target = *offset;
+                    }
+                }
+                _ => {}
+            }
+        }
+
+        // promoting an instruction changes the offsets, so the layout has to be done again
+        if !promoted {
+            return Ok(());
+        }
+    }
+}
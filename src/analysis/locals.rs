@@ -0,0 +1,364 @@
+use crate::analysis::frames::{compress_frames, expand_frames};
+use crate::analysis::layout::{branch_indices, instruction_offsets, relayout};
+use crate::analysis::stack::method_slots;
+use crate::{Attribute, Instruction, JavaError, TargetInfo, VerificationType};
+
+/// Adds `delta` to every local variable slot at or above `from_slot` used by loads, stores,
+/// `iinc` and `ret`, switching to the `wide` forms when a slot goes past 255.
+///
+/// Instructions can change size, so branch offsets are fixed up afterwards, but offsets stored
+/// outside of the instructions are not: `shift_code_locals` updates a whole `Code` attribute.
+/// On error, `code` is left untouched.
+pub fn shift_locals(
+    code: &mut Vec<Instruction>,
+    from_slot: u16,
+    delta: u16,
+) -> Result<(), JavaError> {
+    rewrite_instructions(code, |inst| shift_instruction(inst, from_slot, delta))
+}
+
+/// Replaces every instruction by `rewrite(inst)`, then fixes up the branch offsets.
+fn rewrite_instructions<F>(code: &mut Vec<Instruction>, rewrite: F) -> Result<(), JavaError>
+where
+    F: FnMut(&Instruction) -> Result<Instruction, JavaError>,
+{
+    let targets = branch_indices(code)?;
+
+    let mut rewritten = code.iter().map(rewrite).collect::<Result<Vec<_>, _>>()?;
+    relayout(&mut rewritten, &targets)?;
+
+    *code = rewritten;
+
+    Ok(())
+}
+
+/// Same as `shift_locals`, for a `Code` attribute. The exception table, `max_locals`,
+/// `LineNumberTable`, `LocalVariableTable`, `LocalVariableTypeTable`, `StackMapTable` and
+/// type annotations are updated to match the new slots and offsets.
+///
+/// `initial_locals` is the implicit first frame of the `StackMapTable`: the verification types
+/// of `this` (for instance methods) and of the parameters. Other attributes are left untouched.
+/// On error, `code_attribute` is left untouched.
+pub fn shift_code_locals(
+    code_attribute: &mut Attribute,
+    initial_locals: &[VerificationType],
+    from_slot: u16,
+    delta: u16,
+) -> Result<(), JavaError> {
+    rewrite_code_attribute(code_attribute, initial_locals, from_slot, delta, |inst| {
+        shift_instruction(inst, from_slot, delta)
+    })
+}
+
+/// Same as `shift_code_locals`, but the instructions are replaced by `rewrite(inst)`, which may
+/// change their size. The slots stored outside of the instructions are shifted by `delta` from
+/// `from_slot`; a `delta` of 0 only moves the offsets.
+pub(crate) fn rewrite_code_attribute<F>(
+    code_attribute: &mut Attribute,
+    initial_locals: &[VerificationType],
+    from_slot: u16,
+    delta: u16,
+    rewrite: F,
+) -> Result<(), JavaError>
+where
+    F: FnMut(&Instruction) -> Result<Instruction, JavaError>,
+{
+    remap_code_attribute(code_attribute, initial_locals, from_slot, delta, |code| {
+        rewrite_instructions(code, rewrite)?;
+        Ok((0..=code.len()).collect())
+    })
+}
+
+/// Same as `rewrite_code_attribute`, but `rewrite` edits the whole code and returns where each
+/// instruction went: for every instruction and the end of the code, the index of the instruction
+/// it became, or of the one that followed it if it was removed.
+pub(crate) fn remap_code_attribute<F>(
+    code_attribute: &mut Attribute,
+    initial_locals: &[VerificationType],
+    from_slot: u16,
+    delta: u16,
+    rewrite: F,
+) -> Result<(), JavaError>
+where
+    F: FnOnce(&mut Vec<Instruction>) -> Result<Vec<usize>, JavaError>,
+{
+    let mut shifted = code_attribute.clone();
+    let Attribute::Code {
+        code,
+        max_locals,
+        exception_table,
+        attributes,
+        ..
+    } = &mut shifted
+    else {
+        return Ok(());
+    };
+
+    let old_offsets = offsets_with_end(code);
+    let new_indices = rewrite(code)?;
+    let new_offsets = offsets_with_end(code);
+
+    let map_pc = |pc: u16| -> Result<u16, JavaError> {
+        let index = old_offsets
+            .binary_search(&(pc as u32))
+            .map_err(|_| JavaError::InvalidPc(pc as u32))?;
+        u16::try_from(new_offsets[new_indices[index]]).map_err(|_| JavaError::InvalidPc(pc as u32))
+    };
+    let map_range = |start_pc: u16, length: u16| -> Result<(u16, u16), JavaError> {
+        let start = map_pc(start_pc)?;
+        let end = map_pc(start_pc.saturating_add(length))?;
+        Ok((start, end - start))
+    };
+    let shift_slot = |slot: u16| {
+        if slot >= from_slot {
+            slot.saturating_add(delta)
+        } else {
+            slot
+        }
+    };
+
+    if *max_locals > from_slot {
+        *max_locals = max_locals.saturating_add(delta);
+    }
+
+    for entry in exception_table.iter_mut() {
+        entry.start_pc = map_pc(entry.start_pc)?;
+        entry.end_pc = map_pc(entry.end_pc)?;
+        entry.handler_pc = map_pc(entry.handler_pc)?;
+    }
+
+    for attr in attributes.iter_mut() {
+        match attr {
+            Attribute::LineNumberTable(line_number_table) => {
+                for line in line_number_table {
+                    line.start_pc = map_pc(line.start_pc)?;
+                }
+            }
+            Attribute::LocalVariableTable(local_variable_table) => {
+                for local in local_variable_table {
+                    (local.start_pc, local.length) = map_range(local.start_pc, local.length)?;
+                    local.index = shift_slot(local.index);
+                }
+            }
+            Attribute::LocalVariableTypeTable(local_variable_type_table) => {
+                for local in local_variable_type_table {
+                    (local.start_pc, local.length) = map_range(local.start_pc, local.length)?;
+                    local.index = shift_slot(local.index);
+                }
+            }
+            Attribute::StackMapTable(frames) => {
+                let mut expanded = expand_frames(initial_locals, frames)?;
+                for frame in &mut expanded {
+                    frame.pc = map_pc(frame.pc as u16)? as u32;
+                    insert_tops(&mut frame.locals, from_slot, delta);
+                    for verification_type in frame.locals.iter_mut().chain(&mut frame.stack) {
+                        if let VerificationType::Uninitialized { offset } = verification_type {
+                            *offset = map_pc(*offset)?;
+                        }
+                    }
+                }
+
+                let mut initial_locals = initial_locals.to_vec();
+                insert_tops(&mut initial_locals, from_slot, delta);
+                *frames = compress_frames(&initial_locals, &expanded);
+            }
+            Attribute::RuntimeVisibleTypeAnnotations(type_annotations)
+            | Attribute::RuntimeInvisibleTypeAnnotations(type_annotations) => {
+                for type_annotation in type_annotations {
+                    match &mut type_annotation.target_info {
+                        TargetInfo::Localvar { table, .. } => {
+                            for local in table {
+                                (local.start_pc, local.length) =
+                                    map_range(local.start_pc, local.length)?;
+                                local.index = shift_slot(local.index);
+                            }
+                        }
+                        TargetInfo::Offset { offset, .. }
+                        | TargetInfo::TypeArgument { offset, .. } => *offset = map_pc(*offset)?,
+                        _ => {}
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+
+    *code_attribute = shifted;
+
+    Ok(())
+}
+
+/// Computes the number of local variable slots needed by a method: its parameters (and `this` for
+/// instance methods), and every slot read or written by the code, longs and doubles taking two.
+pub fn compute_max_locals(
+    code: &[Instruction],
+    descriptor: &str,
+    is_static: bool,
+) -> Result<u16, JavaError> {
+    let (parameters, _) = method_slots(descriptor)?;
+    let mut max_locals = parameters + if is_static { 0 } else { 1 };
+
+    for inst in code {
+        let end = match inst {
+            Instruction::ALoad(slot)
+            | Instruction::AStore(slot)
+            | Instruction::FLoad(slot)
+            | Instruction::FStore(slot)
+            | Instruction::ILoad(slot)
+            | Instruction::IStore(slot)
+            | Instruction::IInc(slot, ..)
+            | Instruction::Ret(slot) => *slot as u32 + 1,
+            Instruction::DLoad(slot)
+            | Instruction::DStore(slot)
+            | Instruction::LLoad(slot)
+            | Instruction::LStore(slot) => *slot as u32 + 2,
+            Instruction::ALoadW(slot)
+            | Instruction::AStoreW(slot)
+            | Instruction::FLoadW(slot)
+            | Instruction::FStoreW(slot)
+            | Instruction::ILoadW(slot)
+            | Instruction::IStoreW(slot)
+            | Instruction::IIncW(slot, ..)
+            | Instruction::RetW(slot) => *slot as u32 + 1,
+            Instruction::DLoadW(slot)
+            | Instruction::DStoreW(slot)
+            | Instruction::LLoadW(slot)
+            | Instruction::LStoreW(slot) => *slot as u32 + 2,
+            _ => 0,
+        };
+        max_locals = max_locals.max(end);
+    }
+
+    u16::try_from(max_locals).map_err(|_| JavaError::InvalidLocalSlot(max_locals))
+}
+
+fn shift_instruction(
+    inst: &Instruction,
+    from_slot: u16,
+    delta: u16,
+) -> Result<Instruction, JavaError> {
+    let shift = |slot: u16| -> Result<u16, JavaError> {
+        if slot < from_slot {
+            return Ok(slot);
+        }
+
+        slot.checked_add(delta)
+            .ok_or(JavaError::InvalidLocalSlot(slot as u32 + delta as u32))
+    };
+
+    let shifted = match inst {
+        Instruction::ILoad(slot) => narrow_or_wide(
+            shift(*slot as u16)?,
+            Instruction::ILoad,
+            Instruction::ILoadW,
+        ),
+        Instruction::LLoad(slot) => narrow_or_wide(
+            shift(*slot as u16)?,
+            Instruction::LLoad,
+            Instruction::LLoadW,
+        ),
+        Instruction::FLoad(slot) => narrow_or_wide(
+            shift(*slot as u16)?,
+            Instruction::FLoad,
+            Instruction::FLoadW,
+        ),
+        Instruction::DLoad(slot) => narrow_or_wide(
+            shift(*slot as u16)?,
+            Instruction::DLoad,
+            Instruction::DLoadW,
+        ),
+        Instruction::ALoad(slot) => narrow_or_wide(
+            shift(*slot as u16)?,
+            Instruction::ALoad,
+            Instruction::ALoadW,
+        ),
+        Instruction::IStore(slot) => narrow_or_wide(
+            shift(*slot as u16)?,
+            Instruction::IStore,
+            Instruction::IStoreW,
+        ),
+        Instruction::LStore(slot) => narrow_or_wide(
+            shift(*slot as u16)?,
+            Instruction::LStore,
+            Instruction::LStoreW,
+        ),
+        Instruction::FStore(slot) => narrow_or_wide(
+            shift(*slot as u16)?,
+            Instruction::FStore,
+            Instruction::FStoreW,
+        ),
+        Instruction::DStore(slot) => narrow_or_wide(
+            shift(*slot as u16)?,
+            Instruction::DStore,
+            Instruction::DStoreW,
+        ),
+        Instruction::AStore(slot) => narrow_or_wide(
+            shift(*slot as u16)?,
+            Instruction::AStore,
+            Instruction::AStoreW,
+        ),
+        Instruction::Ret(slot) => {
+            narrow_or_wide(shift(*slot as u16)?, Instruction::Ret, Instruction::RetW)
+        }
+        Instruction::ILoadW(slot) => Instruction::ILoadW(shift(*slot)?),
+        Instruction::LLoadW(slot) => Instruction::LLoadW(shift(*slot)?),
+        Instruction::FLoadW(slot) => Instruction::FLoadW(shift(*slot)?),
+        Instruction::DLoadW(slot) => Instruction::DLoadW(shift(*slot)?),
+        Instruction::ALoadW(slot) => Instruction::ALoadW(shift(*slot)?),
+        Instruction::IStoreW(slot) => Instruction::IStoreW(shift(*slot)?),
+        Instruction::LStoreW(slot) => Instruction::LStoreW(shift(*slot)?),
+        Instruction::FStoreW(slot) => Instruction::FStoreW(shift(*slot)?),
+        Instruction::DStoreW(slot) => Instruction::DStoreW(shift(*slot)?),
+        Instruction::AStoreW(slot) => Instruction::AStoreW(shift(*slot)?),
+        Instruction::RetW(slot) => Instruction::RetW(shift(*slot)?),
+        Instruction::IInc(slot, count) => {
+            let slot = shift(*slot as u16)?;
+            match u8::try_from(slot) {
+                Ok(slot) => Instruction::IInc(slot, *count),
+                Err(_) => Instruction::IIncW(slot, *count as i16),
+            }
+        }
+        Instruction::IIncW(slot, count) => Instruction::IIncW(shift(*slot)?, *count),
+        _ => inst.clone(),
+    };
+
+    Ok(shifted)
+}
+
+fn narrow_or_wide(
+    slot: u16,
+    narrow: fn(u8) -> Instruction,
+    wide: fn(u16) -> Instruction,
+) -> Instruction {
+    match u8::try_from(slot) {
+        Ok(slot) => narrow(slot),
+        Err(_) => wide(slot),
+    }
+}
+
+/// Inserts `delta` `Top`s before the first local stored at or above `from_slot`.
+fn insert_tops(locals: &mut Vec<VerificationType>, from_slot: u16, delta: u16) {
+    let mut slot = 0;
+    let position = locals.iter().position(|local| {
+        let reached = slot >= from_slot as u32;
+        slot += match local {
+            VerificationType::Long | VerificationType::Double => 2,
+            _ => 1,
+        };
+        reached
+    });
+
+    if let Some(position) = position {
+        locals.splice(
+            position..position,
+            vec![VerificationType::Top; delta as usize],
+        );
+    }
+}
+
+/// The offsets of each instruction, followed by the length of the code.
+fn offsets_with_end(code: &[Instruction]) -> Vec<u32> {
+    let mut offsets = instruction_offsets(code);
+    offsets.push(code.iter().map(|inst| inst.size()).sum());
+    offsets
+}
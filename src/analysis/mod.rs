@@ -0,0 +1,27 @@
+//! Analysis and transformation passes over decoded method bodies.
+
+mod frames;
+pub(crate) use frames::{check_stack_map, initial_locals};
+pub(crate) mod layout;
+
+mod locals;
+pub use locals::{compute_max_locals, shift_code_locals, shift_locals};
+pub(crate) use locals::{remap_code_attribute, rewrite_code_attribute};
+
+mod peephole;
+pub use peephole::{peephole, peephole_code};
+
+mod reachability;
+pub use reachability::reachable_instructions;
+
+mod rewrite;
+pub use rewrite::{rewrite_branches, LabeledCode};
+
+mod stack;
+pub use stack::{check_max_stack, compute_max_stack};
+
+mod stack_map;
+pub use stack_map::generate_stack_map;
+
+mod subroutines;
+pub use subroutines::uses_subroutines;
@@ -0,0 +1,188 @@
+use crate::analysis::frames::expand_frames;
+use crate::analysis::layout::{branch_indices, instruction_offsets, relayout};
+use crate::analysis::remap_code_attribute;
+use crate::{Attribute, Instruction, JavaError, VerificationType};
+
+/// Performs safe local optimizations on a method body and returns the number of changes made:
+/// - removes `nop`s,
+/// - removes a `dup`/`dup2` immediately followed by a `pop`/`pop2`,
+/// - removes a constant or local variable push immediately followed by a `pop`/`pop2`.
+///
+/// Branch offsets and switch padding are fixed up afterwards. Instructions that are branch
+/// targets are never removed, so no optimization crosses one.
+///
+/// Only for code without offsets stored outside of the instructions: the exception table,
+/// `LineNumberTable`, `StackMapTable`, … of a `Code` attribute would point at the wrong
+/// instructions, use `peephole_code` instead.
+///
+/// If the code contains a branch that doesn't land on an instruction, it is left untouched.
+pub fn peephole(code: &mut Vec<Instruction>) -> usize {
+    let barriers = vec![false; code.len()];
+    optimize(code, &barriers).0
+}
+
+/// Same as `peephole`, for a `Code` attribute. The instructions its exception handlers and
+/// stack map frames start or end at are never removed either, and the exception table,
+/// `LineNumberTable`, `LocalVariableTable`, `LocalVariableTypeTable`, `StackMapTable` and type
+/// annotations are updated to the new offsets.
+///
+/// `initial_locals` is the implicit first frame of the `StackMapTable`, see `shift_code_locals`.
+/// On error, `code_attribute` is left untouched.
+pub fn peephole_code(
+    code_attribute: &mut Attribute,
+    initial_locals: &[VerificationType],
+) -> Result<usize, JavaError> {
+    let Attribute::Code {
+        code,
+        exception_table,
+        attributes,
+        ..
+    } = &*code_attribute
+    else {
+        return Ok(0);
+    };
+
+    let mut barrier_pcs = vec![];
+    for entry in exception_table {
+        barrier_pcs.extend([entry.start_pc, entry.end_pc, entry.handler_pc]);
+    }
+    for attribute in attributes {
+        if let Attribute::StackMapTable(frames) = attribute {
+            for frame in expand_frames(initial_locals, frames)? {
+                barrier_pcs.push(frame.pc as u16);
+            }
+        }
+    }
+    let barriers: Vec<bool> = instruction_offsets(code)
+        .into_iter()
+        .map(|pc| barrier_pcs.contains(&(pc as u16)))
+        .collect();
+
+    let mut changes = 0;
+    remap_code_attribute(code_attribute, initial_locals, 0, 0, |code| {
+        let (changed, new_indices) = optimize(code, &barriers);
+        changes = changed;
+        Ok(new_indices)
+    })?;
+
+    Ok(changes)
+}
+
+/// Runs the optimizations of `peephole`, without removing the instructions marked in
+/// `barriers`. Returns the number of changes and where each instruction went, like the
+/// `rewrite` of `remap_code_attribute`.
+fn optimize(code: &mut Vec<Instruction>, barriers: &[bool]) -> (usize, Vec<usize>) {
+    let unchanged = (0..=code.len()).collect();
+    let Ok(mut targets) = branch_indices(code) else {
+        return (0, unchanged);
+    };
+
+    let original = code.clone();
+    let mut barriers = barriers.to_vec();
+    let mut positions: Vec<usize> = (0..=code.len()).collect();
+    let mut changes = 0;
+
+    loop {
+        let mut is_target = barriers.clone();
+        for target in targets.iter().flatten() {
+            is_target[*target] = true;
+        }
+
+        let mut removed = vec![false; code.len()];
+        let mut changed = 0;
+
+        let mut i = 0;
+        while i < code.len() {
+            if is_target[i] {
+                i += 1;
+                continue;
+            }
+
+            if let Instruction::Nop = code[i] {
+                removed[i] = true;
+                changed += 1;
+                i += 1;
+                continue;
+            }
+
+            if i + 1 < code.len() && !is_target[i + 1] && is_dead_pair(&code[i], &code[i + 1]) {
+                removed[i] = true;
+                removed[i + 1] = true;
+                changed += 1;
+                i += 2;
+                continue;
+            }
+
+            i += 1;
+        }
+
+        if changed == 0 {
+            break;
+        }
+
+        // a removed instruction maps to the one following it, the end of the code to the end
+        let mut new_indices = vec![0; code.len() + 1];
+        let mut next = 0;
+        for (i, new_index) in new_indices.iter_mut().enumerate() {
+            *new_index = next;
+            if i < code.len() && !removed[i] {
+                next += 1;
+            }
+        }
+
+        let mut kept = removed.iter().map(|removed| !removed);
+        code.retain(|_| kept.next().unwrap());
+
+        let mut kept = removed.iter().map(|removed| !removed);
+        targets.retain(|_| kept.next().unwrap());
+        for target in targets.iter_mut().flatten() {
+            *target = new_indices[*target];
+        }
+
+        let mut kept = removed.iter().map(|removed| !removed);
+        barriers.retain(|_| kept.next().unwrap());
+        for position in &mut positions {
+            *position = new_indices[*position];
+        }
+
+        changes += changed;
+    }
+
+    if changes > 0 && relayout(code, &targets).is_err() {
+        *code = original;
+        return (0, unchanged);
+    }
+
+    (changes, positions)
+}
+
+fn is_dead_pair(first: &Instruction, second: &Instruction) -> bool {
+    match second {
+        Instruction::Pop => matches!(
+            first,
+            Instruction::Dup
+                | Instruction::ANull
+                | Instruction::IConst(..)
+                | Instruction::FConst(..)
+                | Instruction::Bipush(..)
+                | Instruction::Sipush(..)
+                | Instruction::ILoad(..)
+                | Instruction::ILoadW(..)
+                | Instruction::FLoad(..)
+                | Instruction::FLoadW(..)
+                | Instruction::ALoad(..)
+                | Instruction::ALoadW(..)
+        ),
+        Instruction::Pop2 => matches!(
+            first,
+            Instruction::Dup2
+                | Instruction::LConst(..)
+                | Instruction::DConst(..)
+                | Instruction::LLoad(..)
+                | Instruction::LLoadW(..)
+                | Instruction::DLoad(..)
+                | Instruction::DLoadW(..)
+        ),
+        _ => false,
+    }
+}
@@ -0,0 +1,78 @@
+use crate::analysis::layout::{instruction_offsets, relative_offsets};
+use crate::{ExceptionTableEntry, Instruction};
+
+/// Marks which instructions can be reached from the method entry, either by falling through,
+/// by branching, or through an exception handler covering a reachable instruction.
+pub fn reachable_instructions(
+    code: &[Instruction],
+    exception_table: &[ExceptionTableEntry],
+) -> Vec<bool> {
+    let offsets = instruction_offsets(code);
+    let index_of = |pc: i64| offsets.binary_search_by(|o| (*o as i64).cmp(&pc)).ok();
+
+    let mut reachable = vec![false; code.len()];
+    let mut pending = vec![];
+    if !code.is_empty() {
+        pending.push(0);
+    }
+
+    loop {
+        while let Some(i) = pending.pop() {
+            if reachable[i] {
+                continue;
+            }
+            reachable[i] = true;
+
+            let pc = offsets[i] as i64;
+            for offset in relative_offsets(&code[i]) {
+                if let Some(target) = index_of(pc + offset as i64) {
+                    pending.push(target);
+                }
+            }
+
+            if falls_through(&code[i]) && i + 1 < code.len() {
+                pending.push(i + 1);
+            }
+        }
+
+        for entry in exception_table {
+            let Some(handler) = index_of(entry.handler_pc as i64) else {
+                continue;
+            };
+            if reachable[handler] {
+                continue;
+            }
+
+            let covered = offsets.iter().zip(&reachable).any(|(pc, reachable)| {
+                *reachable && *pc >= entry.start_pc as u32 && *pc < entry.end_pc as u32
+            });
+            if covered {
+                pending.push(handler);
+            }
+        }
+
+        if pending.is_empty() {
+            return reachable;
+        }
+    }
+}
+
+/// Whether the instruction following this one can be executed next.
+pub(crate) fn falls_through(inst: &Instruction) -> bool {
+    !matches!(
+        inst,
+        Instruction::AReturn
+            | Instruction::AThrow
+            | Instruction::DReturn
+            | Instruction::FReturn
+            | Instruction::Goto(..)
+            | Instruction::GotoW(..)
+            | Instruction::IReturn
+            | Instruction::LookupSwitch { .. }
+            | Instruction::LReturn
+            | Instruction::Ret(..)
+            | Instruction::RetW(..)
+            | Instruction::Return
+            | Instruction::TableSwitch { .. }
+    )
+}
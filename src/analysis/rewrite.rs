@@ -0,0 +1,114 @@
+use crate::analysis::layout::{branch_indices, instruction_offsets, relative_offsets, relayout};
+use crate::{Instruction, JavaError};
+
+/// Sets the branch offsets of `code` from the instruction indices its branches hold: a
+/// `Goto(3)` jumps to `code[3]`, and so do the default and targets of the switches. The
+/// padding of the switches is computed, and `goto`/`jsr` are promoted to their wide forms if
+/// their target is too far. See `LabeledCode` to edit decoded code without handling indices.
+pub fn rewrite_branches(code: &mut [Instruction]) -> Result<(), JavaError> {
+    let targets = code
+        .iter()
+        .zip(instruction_offsets(code))
+        .map(|(inst, pc)| index_targets(inst, pc, code.len()))
+        .collect::<Result<Vec<_>, _>>()?;
+
+    relayout(code, &targets)
+}
+
+/// Reads the branches of `inst`, located at `pc`, as the indices of their targets.
+fn index_targets(inst: &Instruction, pc: u32, len: usize) -> Result<Vec<usize>, JavaError> {
+    relative_offsets(inst)
+        .into_iter()
+        .map(|target| match usize::try_from(target) {
+            Ok(target) if target < len => Ok(target),
+            _ => Err(JavaError::InvalidBranchTarget(pc)),
+        })
+        .collect()
+}
+
+/// The pc of the instruction `index` in the current layout of `code`.
+fn pc_of(code: &[Instruction], index: usize) -> u32 {
+    code[..index].iter().map(Instruction::size).sum()
+}
+
+/// Code whose branches jump to instructions rather than offsets, so instructions can be
+/// inserted and removed without breaking them.
+///
+/// Only the branches are kept up to date: the exception table, line numbers and stack maps of
+/// the `Code` attribute still use the old pcs.
+#[derive(Debug, Clone, Default)]
+pub struct LabeledCode {
+    code: Vec<Instruction>,
+    /// The indices of the instructions each instruction can jump to, default first for switches.
+    targets: Vec<Vec<usize>>,
+}
+
+impl LabeledCode {
+    /// Resolves the branch offsets of decoded code, like the one of an `Attribute::Code`.
+    pub fn new(code: Vec<Instruction>) -> Result<Self, JavaError> {
+        let targets = branch_indices(&code)?;
+
+        Ok(Self { code, targets })
+    }
+
+    pub fn instructions(&self) -> &[Instruction] {
+        &self.code
+    }
+
+    pub fn len(&self) -> usize {
+        self.code.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.code.is_empty()
+    }
+
+    /// Inserts `instruction` at `index`. The existing branches still jump to the same
+    /// instructions, so a branch to `index` doesn't execute the new one. If `instruction` is a
+    /// branch, it holds the indices of its targets once inserted, like with `rewrite_branches`.
+    pub fn insert(&mut self, index: usize, instruction: Instruction) -> Result<(), JavaError> {
+        let pc = pc_of(&self.code, index);
+        let targets = index_targets(&instruction, pc, self.code.len() + 1)?;
+
+        for target in self.targets.iter_mut().flatten() {
+            if *target >= index {
+                *target += 1;
+            }
+        }
+
+        self.code.insert(index, instruction);
+        self.targets.insert(index, targets);
+
+        Ok(())
+    }
+
+    /// Removes the instruction at `index`. The branches jumping to it now jump to the
+    /// instruction that followed it.
+    pub fn remove(&mut self, index: usize) -> Instruction {
+        self.targets.remove(index);
+
+        for target in self.targets.iter_mut().flatten() {
+            if *target > index {
+                *target -= 1;
+            }
+        }
+
+        self.code.remove(index)
+    }
+
+    /// Returns the instructions with their branch offsets, switch padding and wide forms set.
+    /// Fails with `JavaError::InvalidBranchTarget` if a branch jumped to the last instruction
+    /// and it was removed.
+    pub fn finish(mut self) -> Result<Vec<Instruction>, JavaError> {
+        let len = self.code.len();
+        for (i, targets) in self.targets.iter().enumerate() {
+            if targets.iter().any(|target| *target >= len) {
+                return Err(JavaError::InvalidBranchTarget(pc_of(&self.code, i)));
+            }
+        }
+
+        relayout(&mut self.code, &self.targets)?;
+
+        Ok(self.code)
+    }
+}
@@ -0,0 +1,367 @@
+use crate::analysis::layout::{instruction_offsets, relative_offsets};
+use crate::analysis::reachability::falls_through;
+use crate::{Constant, ExceptionTableEntry, Instruction, JVMClass, JavaError};
+
+/// Simulates the operand stack depth of a method body and checks that it never goes over
+/// `max_stack` nor below zero.
+///
+/// `is_static` and `descriptor` describe the method owning the code. The descriptor must be
+/// valid, including the 255 slots limit on parameters (counting `this` for instance methods).
+///
+/// The code reachable from the entry point and from the handlers of `exception_table` is checked,
+/// the handlers starting with the exception on the stack.
+pub fn check_max_stack(
+    code: &[Instruction],
+    exception_table: &[ExceptionTableEntry],
+    max_stack: u16,
+    is_static: bool,
+    descriptor: &str,
+    class: &JVMClass,
+) -> Result<(), JavaError> {
+    let (parameters, _) = method_slots(descriptor)?;
+    let parameters = parameters + if is_static { 0 } else { 1 };
+    if parameters > 255 {
+        return Err(JavaError::InvalidDescriptor(descriptor.to_string()));
+    }
+
+    simulate(code, exception_table, class, max_stack as u32)?;
+
+    Ok(())
+}
+
+/// Computes the deepest the operand stack can get, following branches and exception handlers,
+/// whose code starts with the exception on the stack. Fails with `StackOverflow` at the first pc
+/// going over the 65535 slots a `max_stack` can hold.
+pub fn compute_max_stack(
+    code: &[Instruction],
+    exception_table: &[ExceptionTableEntry],
+    class: &JVMClass,
+) -> Result<u16, JavaError> {
+    let max = simulate(code, exception_table, class, u16::MAX as u32)?;
+
+    Ok(max as u16)
+}
+
+/// Walks the code from the entry point and the handlers, returning the maximum stack depth.
+/// Fails as soon as the depth goes over `limit`.
+fn simulate(
+    code: &[Instruction],
+    exception_table: &[ExceptionTableEntry],
+    class: &JVMClass,
+    limit: u32,
+) -> Result<u32, JavaError> {
+    let offsets = instruction_offsets(code);
+    let index_of = |pc: i64| offsets.binary_search_by(|o| (*o as i64).cmp(&pc)).ok();
+
+    let mut depths: Vec<Option<u32>> = vec![None; code.len()];
+    let mut pending: Vec<(usize, u32)> = vec![];
+    if !code.is_empty() {
+        pending.push((0, 0));
+    }
+    for entry in exception_table {
+        let handler = index_of(entry.handler_pc as i64)
+            .ok_or(JavaError::InvalidPc(entry.handler_pc as u32))?;
+        pending.push((handler, 1));
+    }
+
+    let mut max = 0;
+    while let Some((i, depth)) = pending.pop() {
+        if depths[i].is_some() {
+            continue;
+        }
+        depths[i] = Some(depth);
+
+        let pc = offsets[i];
+        let (pop, push) = stack_effect(&code[i], class)?;
+        if pop > depth {
+            return Err(JavaError::StackUnderflow(pc));
+        }
+        let after = depth - pop + push;
+
+        // the subroutine starts with the return address on the stack
+        let branch_depth = match code[i] {
+            Instruction::Jsr(..) | Instruction::JsrW(..) => after + 1,
+            _ => after,
+        };
+        max = max.max(depth).max(after).max(branch_depth);
+        if max > limit {
+            return Err(JavaError::StackOverflow(pc));
+        }
+
+        for offset in relative_offsets(&code[i]) {
+            let target =
+                index_of(pc as i64 + offset as i64).ok_or(JavaError::InvalidBranchTarget(pc))?;
+            pending.push((target, branch_depth));
+        }
+
+        if falls_through(&code[i]) && i + 1 < code.len() {
+            pending.push((i + 1, after));
+        }
+    }
+
+    Ok(max)
+}
+
+/// Returns the number of slots popped from and pushed to the operand stack.
+fn stack_effect(inst: &Instruction, class: &JVMClass) -> Result<(u32, u32), JavaError> {
+    let effect = match inst {
+        Instruction::Nop
+        | Instruction::Goto(..)
+        | Instruction::GotoW(..)
+        | Instruction::IInc(..)
+        | Instruction::IIncW(..)
+        | Instruction::Jsr(..)
+        | Instruction::JsrW(..)
+        | Instruction::Ret(..)
+        | Instruction::RetW(..)
+        | Instruction::Return => (0, 0),
+        Instruction::ANull
+        | Instruction::IConst(..)
+        | Instruction::FConst(..)
+        | Instruction::Bipush(..)
+        | Instruction::Sipush(..)
+        | Instruction::ILoad(..)
+        | Instruction::ILoadW(..)
+        | Instruction::FLoad(..)
+        | Instruction::FLoadW(..)
+        | Instruction::ALoad(..)
+        | Instruction::ALoadW(..)
+        | Instruction::New(..) => (0, 1),
+        Instruction::LConst(..)
+        | Instruction::DConst(..)
+        | Instruction::LLoad(..)
+        | Instruction::LLoadW(..)
+        | Instruction::DLoad(..)
+        | Instruction::DLoadW(..)
+        | Instruction::Ldc2W(..) => (0, 2),
+        Instruction::Ldc(index) => (0, loadable_slots(*index as u16, class)?),
+        Instruction::LdcW(index) => (0, loadable_slots(*index, class)?),
+        Instruction::IStore(..)
+        | Instruction::IStoreW(..)
+        | Instruction::FStore(..)
+        | Instruction::FStoreW(..)
+        | Instruction::AStore(..)
+        | Instruction::AStoreW(..)
+        | Instruction::Pop
+        | Instruction::IReturn
+        | Instruction::FReturn
+        | Instruction::AReturn
+        | Instruction::AThrow
+        | Instruction::MonitorEnter
+        | Instruction::MonitorExit
+        | Instruction::Ifeq(..)
+        | Instruction::Ifne(..)
+        | Instruction::Iflt(..)
+        | Instruction::Ifge(..)
+        | Instruction::Ifgt(..)
+        | Instruction::Ifle(..)
+        | Instruction::IfNull(..)
+        | Instruction::IfNonNull(..)
+        | Instruction::TableSwitch { .. }
+        | Instruction::LookupSwitch { .. } => (1, 0),
+        Instruction::LStore(..)
+        | Instruction::LStoreW(..)
+        | Instruction::DStore(..)
+        | Instruction::DStoreW(..)
+        | Instruction::Pop2
+        | Instruction::LReturn
+        | Instruction::DReturn
+        | Instruction::IfIcmpeq(..)
+        | Instruction::IfIcmpne(..)
+        | Instruction::IfIcmplt(..)
+        | Instruction::IfIcmpge(..)
+        | Instruction::IfIcmpgt(..)
+        | Instruction::IfIcmple(..)
+        | Instruction::IfAcmpeq(..)
+        | Instruction::IfAcmpne(..) => (2, 0),
+        Instruction::IAStore
+        | Instruction::FAStore
+        | Instruction::AAStore
+        | Instruction::BAStore
+        | Instruction::CAStore
+        | Instruction::SAStore => (3, 0),
+        Instruction::LAStore | Instruction::DAStore => (4, 0),
+        Instruction::ANewArray(..)
+        | Instruction::NewArray(..)
+        | Instruction::ArrayLength
+        | Instruction::CheckCast(..)
+        | Instruction::InstanceOf(..)
+        | Instruction::INeg
+        | Instruction::FNeg
+        | Instruction::I2F
+        | Instruction::F2I
+        | Instruction::I2B
+        | Instruction::I2C
+        | Instruction::I2S => (1, 1),
+        Instruction::I2L | Instruction::I2D | Instruction::F2L | Instruction::F2D => (1, 2),
+        Instruction::L2I | Instruction::L2F | Instruction::D2I | Instruction::D2F => (2, 1),
+        Instruction::LNeg
+        | Instruction::DNeg
+        | Instruction::L2D
+        | Instruction::D2L
+        | Instruction::Swap => (2, 2),
+        Instruction::IAdd
+        | Instruction::ISub
+        | Instruction::IMul
+        | Instruction::IDiv
+        | Instruction::IRem
+        | Instruction::IAnd
+        | Instruction::IOr
+        | Instruction::IXor
+        | Instruction::IShl
+        | Instruction::IShr
+        | Instruction::IUShr
+        | Instruction::FAdd
+        | Instruction::FSub
+        | Instruction::FMul
+        | Instruction::FDiv
+        | Instruction::FRem
+        | Instruction::FCmpl
+        | Instruction::FCmpg
+        | Instruction::IALoad
+        | Instruction::FALoad
+        | Instruction::AALoad
+        | Instruction::BALoad
+        | Instruction::CALoad
+        | Instruction::SALoad => (2, 1),
+        Instruction::LALoad | Instruction::DALoad => (2, 2),
+        Instruction::LShl | Instruction::LShr | Instruction::LUShr => (3, 2),
+        Instruction::LAdd
+        | Instruction::LSub
+        | Instruction::LMul
+        | Instruction::LDiv
+        | Instruction::LRem
+        | Instruction::LAnd
+        | Instruction::LOr
+        | Instruction::LXor
+        | Instruction::DAdd
+        | Instruction::DSub
+        | Instruction::DMul
+        | Instruction::DDiv
+        | Instruction::DRem => (4, 2),
+        Instruction::LCmp | Instruction::DCmpl | Instruction::DCmpg => (4, 1),
+        Instruction::Dup => (1, 2),
+        Instruction::DupX1 => (2, 3),
+        Instruction::DupX2 => (3, 4),
+        Instruction::Dup2 => (2, 4),
+        Instruction::Dup2X1 => (3, 5),
+        Instruction::Dup2X2 => (4, 6),
+        Instruction::GetStatic(index) => (0, field_slots(member_descriptor(*index, class)?)?),
+        Instruction::GetField(index) => (1, field_slots(member_descriptor(*index, class)?)?),
+        Instruction::PutStatic(index) => (field_slots(member_descriptor(*index, class)?)?, 0),
+        Instruction::PutField(index) => (1 + field_slots(member_descriptor(*index, class)?)?, 0),
+        Instruction::InvokeStatic(index) | Instruction::InvokeDynamic(index) => {
+            method_slots(member_descriptor(*index, class)?)?
+        }
+        Instruction::InvokeVirtual(index)
+        | Instruction::InvokeSpecial(index)
+        | Instruction::InvokeInterface { index, .. } => {
+            let (parameters, result) = method_slots(member_descriptor(*index, class)?)?;
+            (parameters + 1, result)
+        }
+        Instruction::MultiANewArray(_, dimensions) => (*dimensions as u32, 1),
+    };
+
+    Ok(effect)
+}
+
+/// Returns the slots pushed by `ldc`/`ldc_w`, which can only load a `Dynamic` as a category 2 value.
+fn loadable_slots(id: u16, class: &JVMClass) -> Result<u32, JavaError> {
+    match class.constants.get(id as usize) {
+        Some(Constant::Dynamic { .. }) => field_slots(member_descriptor(id, class)?),
+        Some(_) => Ok(1),
+        None => Err(JavaError::InvalidConstantId(id)),
+    }
+}
+
+/// Returns the descriptor of a field, method or dynamic constant reference.
+pub(crate) fn member_descriptor(id: u16, class: &JVMClass) -> Result<&str, JavaError> {
+    let name_and_type_index = match class.constants.get(id as usize) {
+        Some(Constant::Fieldref {
+            name_and_type_index,
+            ..
+        })
+        | Some(Constant::Methodref {
+            name_and_type_index,
+            ..
+        })
+        | Some(Constant::InterfaceMethodref {
+            name_and_type_index,
+            ..
+        })
+        | Some(Constant::InvokeDynamic {
+            name_and_type_index,
+            ..
+        })
+        | Some(Constant::Dynamic {
+            name_and_type_index,
+            ..
+        }) => *name_and_type_index,
+        Some(constant) => {
+            return Err(JavaError::ConstantTypeError(format!(
+                "#{id} is not a member reference, but a {constant}"
+            )))
+        }
+        None => return Err(JavaError::InvalidConstantId(id)),
+    };
+
+    match class.constants.get(name_and_type_index as usize) {
+        Some(Constant::NameAndType {
+            descriptor_index, ..
+        }) => class.get_string(*descriptor_index),
+        Some(constant) => Err(JavaError::ConstantTypeError(format!(
+            "#{name_and_type_index} is not a name and type, but a {constant}"
+        ))),
+        None => Err(JavaError::InvalidConstantId(name_and_type_index)),
+    }
+}
+
+/// Returns the number of slots taken by a value of the given field type.
+fn field_slots(descriptor: &str) -> Result<u32, JavaError> {
+    match field_type_length(descriptor) {
+        Some(length) if length == descriptor.len() => Ok(slots_of(descriptor)),
+        _ => Err(JavaError::InvalidDescriptor(descriptor.to_string())),
+    }
+}
+
+/// Returns the number of slots taken by the parameters and by the return value of a method.
+pub(crate) fn method_slots(descriptor: &str) -> Result<(u32, u32), JavaError> {
+    let invalid = || JavaError::InvalidDescriptor(descriptor.to_string());
+
+    let mut rest = descriptor.strip_prefix('(').ok_or_else(invalid)?;
+    let mut parameters = 0;
+    while !rest.starts_with(')') {
+        let length = field_type_length(rest).ok_or_else(invalid)?;
+        parameters += slots_of(&rest[..length]);
+        rest = &rest[length..];
+    }
+
+    let result = &rest[1..];
+    let result = if result == "V" {
+        0
+    } else {
+        field_slots(result).map_err(|_| invalid())?
+    };
+
+    Ok((parameters, result))
+}
+
+/// Returns the length of the field type at the start of `descriptor`.
+pub(crate) fn field_type_length(descriptor: &str) -> Option<usize> {
+    let dimensions = descriptor.bytes().take_while(|c| *c == b'[').count();
+
+    let length = match descriptor.as_bytes().get(dimensions)? {
+        b'B' | b'C' | b'D' | b'F' | b'I' | b'J' | b'S' | b'Z' => 1,
+        b'L' => descriptor[dimensions..].find(';')? + 1,
+        _ => return None,
+    };
+
+    Some(dimensions + length)
+}
+
+fn slots_of(field_type: &str) -> u32 {
+    match field_type {
+        "J" | "D" => 2,
+        _ => 1,
+    }
+}
@@ -0,0 +1,767 @@
+use crate::analysis::frames::{compress_frames, Frame};
+use crate::analysis::layout::{instruction_offsets, relative_offsets};
+use crate::analysis::reachability::falls_through;
+use crate::analysis::stack::member_descriptor;
+use crate::descriptor::{
+    parse_field_descriptor, parse_method_descriptor, FieldType, MethodDescriptor,
+};
+use crate::{
+    ArrayType, Constant, ExceptionTableEntry, Instruction, JVMClass, JavaError, Resolver,
+    StackMapFrame, VerificationType,
+};
+
+const OBJECT: &str = "java/lang/Object";
+
+/// The type of a local variable or of a stack entry. Unlike in the frames, a local of type `Long`
+/// or `Double` is followed by a `Top`, and references are kept by name until the frames are built.
+#[derive(Debug, Clone, PartialEq)]
+enum Type {
+    Top,
+    Integer,
+    Float,
+    Long,
+    Double,
+    Null,
+    UninitializedThis,
+    /// Created by the `new` at this pc.
+    Uninitialized(u32),
+    /// A class by its internal name, or an array by its descriptor (e.g. `[I`).
+    Reference(String),
+}
+
+impl Type {
+    fn slots(&self) -> usize {
+        match self {
+            Type::Long | Type::Double => 2,
+            _ => 1,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+struct State {
+    locals: Vec<Type>,
+    stack: Vec<Type>,
+}
+
+/// Computes the `StackMapTable` of a method by simulating the types of its locals and operand
+/// stack, from the entry point and from the exception handlers.
+///
+/// `name`, `descriptor` and `is_static` describe the method owning the code: the `this` of a
+/// constructor (`<init>`) starts uninitialized. There is a frame for every branch target and
+/// exception handler, using the most compact form. Where paths with different classes meet,
+/// their common super class is looked up with `resolver`, or `jvm` for its own class; if the
+/// hierarchy can't be resolved, it's `java/lang/Object`. The `Class` constants the frames refer
+/// to are added to the constant pool of `jvm`.
+///
+/// Unreachable code gets no frame, which the verifier rejects, and subroutines (`jsr`/`ret`) are
+/// not supported. Both fail with `JavaError::InvalidStackMapFrame`, like code whose types don't
+/// match, e.g. popping a `long` as an `int`.
+pub fn generate_stack_map(
+    code: &[Instruction],
+    exception_table: &[ExceptionTableEntry],
+    name: &str,
+    descriptor: &MethodDescriptor,
+    is_static: bool,
+    jvm: &mut JVMClass,
+    resolver: &dyn Resolver,
+) -> Result<Vec<StackMapFrame>, JavaError> {
+    let class = &*jvm;
+    let this_class = class.get_class_name(class.this_class)?.to_string();
+    let offsets = instruction_offsets(code);
+    let code_length = offsets
+        .last()
+        .zip(code.last())
+        .map_or(0, |(pc, inst)| pc + inst.size());
+    let index_of = |pc: u32| offsets.binary_search(&pc).ok();
+
+    let mut initial = State {
+        locals: vec![],
+        stack: vec![],
+    };
+    if !is_static {
+        let this = match name {
+            "<init>" => Type::UninitializedThis,
+            _ => Type::Reference(this_class.clone()),
+        };
+        set_local(&mut initial.locals, 0, this);
+    }
+    for param in &descriptor.params {
+        let slot = initial.locals.len();
+        set_local(&mut initial.locals, slot, field_type(param));
+    }
+
+    // (first instruction, last instruction + 1, handler, exception)
+    let mut handlers = vec![];
+    for entry in exception_table {
+        let index = |pc: u16| match pc as u32 {
+            pc if pc == code_length => Some(code.len()),
+            pc => index_of(pc),
+        };
+        let (Some(start), Some(end), Some(handler)) = (
+            index(entry.start_pc),
+            index(entry.end_pc),
+            index_of(entry.handler_pc as u32),
+        ) else {
+            return Err(JavaError::InvalidPc(entry.start_pc as u32));
+        };
+        let exception = match entry.catch_type {
+            0 => "java/lang/Throwable",
+            catch_type => class.get_class_name(catch_type)?,
+        };
+        handlers.push((start, end, handler, Type::Reference(exception.into())));
+    }
+
+    let context = Context {
+        class,
+        this_class: &this_class,
+        resolver,
+        code,
+        offsets: &offsets,
+    };
+
+    let mut states: Vec<Option<State>> = vec![None; code.len()];
+    let mut needs_frame = vec![false; code.len()];
+    let mut pending = vec![];
+    if !code.is_empty() {
+        states[0] = Some(initial.clone());
+        pending.push(0);
+    }
+    for (_, _, handler, _) in &handlers {
+        needs_frame[*handler] = true;
+    }
+
+    while let Some(i) = pending.pop() {
+        let Some(state) = states[i].clone() else {
+            continue;
+        };
+        let pc = offsets[i];
+        let after = context.execute(i, &state)?;
+
+        let mut successors = vec![];
+        for (start, end, handler, exception) in &handlers {
+            if (*start..*end).contains(&i) {
+                let caught = State {
+                    locals: state.locals.clone(),
+                    stack: vec![exception.clone()],
+                };
+                successors.push((*handler, caught));
+            }
+        }
+        for offset in relative_offsets(&code[i]) {
+            let target = index_of((pc as i64 + offset as i64) as u32)
+                .ok_or(JavaError::InvalidBranchTarget(pc))?;
+            needs_frame[target] = true;
+            successors.push((target, after.clone()));
+        }
+        if falls_through(&code[i]) {
+            if i + 1 == code.len() {
+                return Err(JavaError::InvalidPc(code_length));
+            }
+            successors.push((i + 1, after));
+        }
+
+        for (target, incoming) in successors {
+            let merged = match &states[target] {
+                Some(existing) => context.merge(existing, &incoming, offsets[target])?,
+                None => incoming,
+            };
+            if states[target].as_ref() != Some(&merged) {
+                states[target] = Some(merged);
+                pending.push(target);
+            }
+        }
+    }
+
+    if let Some(unreachable) = states.iter().position(Option::is_none) {
+        return Err(JavaError::InvalidStackMapFrame(offsets[unreachable]));
+    }
+
+    let mut frames = vec![];
+    for (i, state) in states.iter().enumerate() {
+        if let (true, Some(state)) = (needs_frame[i], state) {
+            frames.push(Frame {
+                pc: offsets[i],
+                locals: verification_locals(&state.locals, jvm),
+                stack: state
+                    .stack
+                    .iter()
+                    .map(|t| verification_type(t, jvm))
+                    .collect(),
+            });
+        }
+    }
+
+    let initial_locals = verification_locals(&initial.locals, jvm);
+    Ok(compress_frames(&initial_locals, &frames))
+}
+
+struct Context<'a> {
+    class: &'a JVMClass,
+    this_class: &'a str,
+    resolver: &'a dyn Resolver,
+    code: &'a [Instruction],
+    offsets: &'a [u32],
+}
+
+impl Context<'_> {
+    /// Returns the state after the instruction `i`, if it doesn't jump nor throw.
+    fn execute(&self, i: usize, state: &State) -> Result<State, JavaError> {
+        let pc = self.offsets[i];
+        let invalid = || JavaError::InvalidStackMapFrame(pc);
+        let mut state = state.clone();
+        let State { locals, stack } = &mut state;
+
+        let mut pop = |count: usize| -> Result<Vec<Type>, JavaError> {
+            if count > stack.len() {
+                return Err(JavaError::StackUnderflow(pc));
+            }
+            Ok(stack.split_off(stack.len() - count))
+        };
+        let load =
+            |locals: &Vec<Type>, slot: u16| locals.get(slot as usize).cloned().ok_or_else(invalid);
+
+        let pushed = match &self.code[i] {
+            Instruction::Nop
+            | Instruction::Goto(..)
+            | Instruction::GotoW(..)
+            | Instruction::Return => None,
+            Instruction::IInc(slot, ..) => {
+                load(locals, *slot as u16)?;
+                None
+            }
+            Instruction::IIncW(slot, ..) => {
+                load(locals, *slot)?;
+                None
+            }
+            Instruction::ANull => Some(Type::Null),
+            Instruction::IConst(..) | Instruction::Bipush(..) | Instruction::Sipush(..) => {
+                Some(Type::Integer)
+            }
+            Instruction::FConst(..) => Some(Type::Float),
+            Instruction::LConst(..) => Some(Type::Long),
+            Instruction::DConst(..) => Some(Type::Double),
+            Instruction::Ldc(id) => Some(self.constant_type(*id as u16)?),
+            Instruction::LdcW(id) | Instruction::Ldc2W(id) => Some(self.constant_type(*id)?),
+            Instruction::ILoad(..) | Instruction::ILoadW(..) => Some(Type::Integer),
+            Instruction::FLoad(..) | Instruction::FLoadW(..) => Some(Type::Float),
+            Instruction::LLoad(..) | Instruction::LLoadW(..) => Some(Type::Long),
+            Instruction::DLoad(..) | Instruction::DLoadW(..) => Some(Type::Double),
+            Instruction::ALoad(slot) => Some(load(locals, *slot as u16)?),
+            Instruction::ALoadW(slot) => Some(load(locals, *slot)?),
+            Instruction::IStore(slot) | Instruction::FStore(slot) | Instruction::AStore(slot) => {
+                let value = pop(1)?.remove(0);
+                set_local(locals, *slot as usize, value);
+                None
+            }
+            Instruction::LStore(slot) | Instruction::DStore(slot) => {
+                let value = pop(1)?.remove(0);
+                set_local(locals, *slot as usize, value);
+                None
+            }
+            Instruction::IStoreW(slot)
+            | Instruction::FStoreW(slot)
+            | Instruction::AStoreW(slot)
+            | Instruction::LStoreW(slot)
+            | Instruction::DStoreW(slot) => {
+                let value = pop(1)?.remove(0);
+                set_local(locals, *slot as usize, value);
+                None
+            }
+            Instruction::IALoad
+            | Instruction::BALoad
+            | Instruction::CALoad
+            | Instruction::SALoad => {
+                pop(2)?;
+                Some(Type::Integer)
+            }
+            Instruction::FALoad => {
+                pop(2)?;
+                Some(Type::Float)
+            }
+            Instruction::LALoad => {
+                pop(2)?;
+                Some(Type::Long)
+            }
+            Instruction::DALoad => {
+                pop(2)?;
+                Some(Type::Double)
+            }
+            Instruction::AALoad => {
+                let array = pop(2)?.remove(0);
+                Some(match array {
+                    Type::Null => Type::Null,
+                    Type::Reference(array) => {
+                        let component = array.strip_prefix('[').ok_or_else(invalid)?;
+                        match component.strip_prefix('L') {
+                            Some(class) => Type::Reference(class.trim_end_matches(';').into()),
+                            None => Type::Reference(component.into()),
+                        }
+                    }
+                    _ => return Err(invalid()),
+                })
+            }
+            Instruction::IAStore
+            | Instruction::FAStore
+            | Instruction::AAStore
+            | Instruction::BAStore
+            | Instruction::CAStore
+            | Instruction::SAStore
+            | Instruction::LAStore
+            | Instruction::DAStore => {
+                pop(3)?;
+                None
+            }
+            Instruction::Pop => {
+                pop_slots(stack, 1, pc)?;
+                None
+            }
+            Instruction::Pop2 => {
+                pop_slots(stack, 2, pc)?;
+                None
+            }
+            Instruction::Dup => dup(stack, 1, 0, pc)?,
+            Instruction::DupX1 => dup(stack, 1, 1, pc)?,
+            Instruction::DupX2 => dup(stack, 1, 2, pc)?,
+            Instruction::Dup2 => dup(stack, 2, 0, pc)?,
+            Instruction::Dup2X1 => dup(stack, 2, 1, pc)?,
+            Instruction::Dup2X2 => dup(stack, 2, 2, pc)?,
+            Instruction::Swap => {
+                let mut values = pop_slots(stack, 2, pc)?;
+                if values.len() != 2 {
+                    return Err(invalid());
+                }
+                values.swap(0, 1);
+                stack.extend(values);
+                None
+            }
+            Instruction::IAdd
+            | Instruction::ISub
+            | Instruction::IMul
+            | Instruction::IDiv
+            | Instruction::IRem
+            | Instruction::IAnd
+            | Instruction::IOr
+            | Instruction::IXor
+            | Instruction::IShl
+            | Instruction::IShr
+            | Instruction::IUShr
+            | Instruction::LCmp
+            | Instruction::FCmpl
+            | Instruction::FCmpg
+            | Instruction::DCmpl
+            | Instruction::DCmpg => {
+                pop(2)?;
+                Some(Type::Integer)
+            }
+            Instruction::FAdd
+            | Instruction::FSub
+            | Instruction::FMul
+            | Instruction::FDiv
+            | Instruction::FRem => {
+                pop(2)?;
+                Some(Type::Float)
+            }
+            Instruction::LAdd
+            | Instruction::LSub
+            | Instruction::LMul
+            | Instruction::LDiv
+            | Instruction::LRem
+            | Instruction::LAnd
+            | Instruction::LOr
+            | Instruction::LXor
+            | Instruction::LShl
+            | Instruction::LShr
+            | Instruction::LUShr => {
+                pop(2)?;
+                Some(Type::Long)
+            }
+            Instruction::DAdd
+            | Instruction::DSub
+            | Instruction::DMul
+            | Instruction::DDiv
+            | Instruction::DRem => {
+                pop(2)?;
+                Some(Type::Double)
+            }
+            Instruction::INeg
+            | Instruction::L2I
+            | Instruction::F2I
+            | Instruction::D2I
+            | Instruction::I2B
+            | Instruction::I2C
+            | Instruction::I2S
+            | Instruction::ArrayLength
+            | Instruction::InstanceOf(..) => {
+                pop(1)?;
+                Some(Type::Integer)
+            }
+            Instruction::FNeg | Instruction::I2F | Instruction::L2F | Instruction::D2F => {
+                pop(1)?;
+                Some(Type::Float)
+            }
+            Instruction::LNeg | Instruction::I2L | Instruction::F2L | Instruction::D2L => {
+                pop(1)?;
+                Some(Type::Long)
+            }
+            Instruction::DNeg | Instruction::I2D | Instruction::L2D | Instruction::F2D => {
+                pop(1)?;
+                Some(Type::Double)
+            }
+            Instruction::Ifeq(..)
+            | Instruction::Ifne(..)
+            | Instruction::Iflt(..)
+            | Instruction::Ifge(..)
+            | Instruction::Ifgt(..)
+            | Instruction::Ifle(..)
+            | Instruction::IfNull(..)
+            | Instruction::IfNonNull(..)
+            | Instruction::TableSwitch { .. }
+            | Instruction::LookupSwitch { .. }
+            | Instruction::IReturn
+            | Instruction::LReturn
+            | Instruction::FReturn
+            | Instruction::DReturn
+            | Instruction::AReturn
+            | Instruction::AThrow
+            | Instruction::MonitorEnter
+            | Instruction::MonitorExit => {
+                pop(1)?;
+                None
+            }
+            Instruction::IfIcmpeq(..)
+            | Instruction::IfIcmpne(..)
+            | Instruction::IfIcmplt(..)
+            | Instruction::IfIcmpge(..)
+            | Instruction::IfIcmpgt(..)
+            | Instruction::IfIcmple(..)
+            | Instruction::IfAcmpeq(..)
+            | Instruction::IfAcmpne(..) => {
+                pop(2)?;
+                None
+            }
+            Instruction::GetStatic(id) => Some(self.field_type(*id)?),
+            Instruction::GetField(id) => {
+                pop(1)?;
+                Some(self.field_type(*id)?)
+            }
+            Instruction::PutStatic(..) => {
+                pop(1)?;
+                None
+            }
+            Instruction::PutField(..) => {
+                pop(2)?;
+                None
+            }
+            Instruction::InvokeStatic(id) | Instruction::InvokeDynamic(id) => {
+                let method = parse_method_descriptor(member_descriptor(*id, self.class)?)?;
+                pop(method.params.len())?;
+                method.ret.as_ref().map(field_type)
+            }
+            Instruction::InvokeVirtual(id) | Instruction::InvokeInterface { index: id, .. } => {
+                let method = parse_method_descriptor(member_descriptor(*id, self.class)?)?;
+                pop(method.params.len() + 1)?;
+                method.ret.as_ref().map(field_type)
+            }
+            Instruction::InvokeSpecial(id) => {
+                let (_, name, descriptor) = self.class.resolve_ref(*id)?;
+                let method = parse_method_descriptor(&descriptor)?;
+                let receiver = pop(method.params.len() + 1)?.remove(0);
+
+                if name == "<init>" {
+                    let initialized = match &receiver {
+                        Type::UninitializedThis => Type::Reference(self.this_class.into()),
+                        Type::Uninitialized(new_pc) => Type::Reference(self.new_class(*new_pc)?),
+                        _ => return Err(invalid()),
+                    };
+                    for t in locals.iter_mut().chain(stack.iter_mut()) {
+                        if *t == receiver {
+                            *t = initialized.clone();
+                        }
+                    }
+                }
+                method.ret.as_ref().map(field_type)
+            }
+            Instruction::New(..) => Some(Type::Uninitialized(pc)),
+            Instruction::NewArray(atype) => {
+                pop(1)?;
+                Some(Type::Reference(format!(
+                    "[{}",
+                    primitive_descriptor(*atype)
+                )))
+            }
+            Instruction::ANewArray(id) => {
+                pop(1)?;
+                let component = self.class.get_class_name(*id)?;
+                Some(Type::Reference(match component.starts_with('[') {
+                    true => format!("[{component}"),
+                    false => format!("[L{component};"),
+                }))
+            }
+            Instruction::MultiANewArray(id, dimensions) => {
+                pop(*dimensions as usize)?;
+                Some(Type::Reference(self.class.get_class_name(*id)?.into()))
+            }
+            Instruction::CheckCast(id) => {
+                pop(1)?;
+                Some(Type::Reference(self.class.get_class_name(*id)?.into()))
+            }
+            Instruction::Jsr(..)
+            | Instruction::JsrW(..)
+            | Instruction::Ret(..)
+            | Instruction::RetW(..) => return Err(invalid()),
+        };
+
+        if let Some(pushed) = pushed {
+            stack.push(pushed);
+        }
+
+        Ok(state)
+    }
+
+    fn constant_type(&self, id: u16) -> Result<Type, JavaError> {
+        Ok(match self.class.get_constant(id)? {
+            Constant::Integer(..) => Type::Integer,
+            Constant::Float(..) => Type::Float,
+            Constant::Long(..) => Type::Long,
+            Constant::Double(..) => Type::Double,
+            Constant::String { .. } => Type::Reference("java/lang/String".into()),
+            Constant::Class { .. } => Type::Reference("java/lang/Class".into()),
+            Constant::MethodType { .. } => Type::Reference("java/lang/invoke/MethodType".into()),
+            Constant::MethodHandle { .. } => {
+                Type::Reference("java/lang/invoke/MethodHandle".into())
+            }
+            Constant::Dynamic { .. } => self.field_type(id)?,
+            constant => {
+                return Err(JavaError::ConstantTypeError(format!(
+                    "#{id} is not loadable, but a {constant}"
+                )))
+            }
+        })
+    }
+
+    /// The type of a field or dynamic constant reference.
+    fn field_type(&self, id: u16) -> Result<Type, JavaError> {
+        let descriptor = member_descriptor(id, self.class)?;
+        Ok(field_type(&parse_field_descriptor(descriptor)?))
+    }
+
+    /// The class instantiated by the `new` at `pc`.
+    fn new_class(&self, pc: u32) -> Result<String, JavaError> {
+        let index = self
+            .offsets
+            .binary_search(&pc)
+            .map_err(|_| JavaError::InvalidPc(pc))?;
+        match &self.code[index] {
+            Instruction::New(id) => Ok(self.class.get_class_name(*id)?.into()),
+            _ => Err(JavaError::InvalidStackMapFrame(pc)),
+        }
+    }
+
+    /// Merges the states of two paths reaching the instruction at `pc`.
+    fn merge(&self, a: &State, b: &State, pc: u32) -> Result<State, JavaError> {
+        if a.stack.len() != b.stack.len() {
+            return Err(JavaError::InvalidStackMapFrame(pc));
+        }
+
+        let mut stack = vec![];
+        for (a, b) in a.stack.iter().zip(&b.stack) {
+            match self.merge_types(a, b) {
+                Type::Top => return Err(JavaError::InvalidStackMapFrame(pc)),
+                merged => stack.push(merged),
+            }
+        }
+
+        let length = a.locals.len().max(b.locals.len());
+        let mut locals: Vec<Type> = (0..length)
+            .map(|slot| match (a.locals.get(slot), b.locals.get(slot)) {
+                (Some(a), Some(b)) => self.merge_types(a, b),
+                _ => Type::Top,
+            })
+            .collect();
+        // a long or a double without its second slot is lost
+        for slot in 0..length {
+            if locals[slot].slots() == 2 && locals.get(slot + 1) != Some(&Type::Top) {
+                locals[slot] = Type::Top;
+            }
+        }
+
+        Ok(State { locals, stack })
+    }
+
+    fn merge_types(&self, a: &Type, b: &Type) -> Type {
+        match (a, b) {
+            _ if a == b => a.clone(),
+            (Type::Null, Type::Reference(..)) => b.clone(),
+            (Type::Reference(..), Type::Null) => a.clone(),
+            (Type::Reference(a), Type::Reference(b)) => Type::Reference(self.common_super(a, b)),
+            _ => Type::Top,
+        }
+    }
+
+    /// The nearest common super class of two classes or arrays.
+    fn common_super(&self, a: &str, b: &str) -> String {
+        if let (Some(a), Some(b)) = (a.strip_prefix('['), b.strip_prefix('[')) {
+            let reference = |component: &str| component.starts_with(['L', '['].as_slice());
+            if !reference(a) || !reference(b) {
+                return OBJECT.into();
+            }
+
+            let class = |component: &str| match component.strip_prefix('L') {
+                Some(class) => class.trim_end_matches(';').to_string(),
+                None => component.to_string(),
+            };
+            return match self.common_super(&class(a), &class(b)) {
+                common if common.starts_with('[') => format!("[{common}"),
+                common => format!("[L{common};"),
+            };
+        }
+        if a.starts_with('[') || b.starts_with('[') {
+            return OBJECT.into();
+        }
+
+        let supers = self.supers(a);
+        self.supers(b)
+            .into_iter()
+            .find(|class| supers.contains(class))
+            .unwrap_or_else(|| OBJECT.into())
+    }
+
+    /// `class` and its super classes, nearest first.
+    fn supers(&self, class: &str) -> Vec<String> {
+        let mut chain = vec![class.to_string()];
+        let chain_of = |jvm: &JVMClass| jvm.super_chain(self.resolver).unwrap_or_default();
+        if class == self.this_class {
+            chain.extend(chain_of(self.class));
+        } else if let Some(jvm) = self.resolver.resolve(class) {
+            chain.extend(chain_of(&jvm));
+        }
+        chain
+    }
+}
+
+/// Stores `value` in `slot`, invalidating the long or double it overwrites half of.
+fn set_local(locals: &mut Vec<Type>, slot: usize, value: Type) {
+    let end = slot + value.slots();
+    if locals.len() < end {
+        locals.resize(end, Type::Top);
+    }
+    if slot > 0 && locals[slot - 1].slots() == 2 {
+        locals[slot - 1] = Type::Top;
+    }
+    if value.slots() == 2 {
+        locals[slot + 1] = Type::Top;
+    }
+    locals[slot] = value;
+}
+
+/// Pops the values taking the top `slots` slots of the stack.
+fn pop_slots(stack: &mut Vec<Type>, slots: usize, pc: u32) -> Result<Vec<Type>, JavaError> {
+    let mut taken = 0;
+    let mut count = 0;
+    while taken < slots {
+        let value = stack
+            .iter()
+            .rev()
+            .nth(count)
+            .ok_or(JavaError::StackUnderflow(pc))?;
+        taken += value.slots();
+        count += 1;
+    }
+    if taken != slots {
+        return Err(JavaError::InvalidStackMapFrame(pc));
+    }
+
+    Ok(stack.split_off(stack.len() - count))
+}
+
+/// The `dup` instructions: copies the top `slots` slots under the `under` slots below them.
+fn dup(
+    stack: &mut Vec<Type>,
+    slots: usize,
+    under: usize,
+    pc: u32,
+) -> Result<Option<Type>, JavaError> {
+    let top = pop_slots(stack, slots, pc)?;
+    let below = pop_slots(stack, under, pc)?;
+    stack.extend(top.iter().cloned());
+    stack.extend(below);
+    stack.extend(top);
+
+    Ok(None)
+}
+
+fn field_type(field_type: &FieldType) -> Type {
+    match field_type {
+        FieldType::Byte
+        | FieldType::Char
+        | FieldType::Short
+        | FieldType::Boolean
+        | FieldType::Int => Type::Integer,
+        FieldType::Float => Type::Float,
+        FieldType::Long => Type::Long,
+        FieldType::Double => Type::Double,
+        FieldType::Object(class) => Type::Reference(class.clone()),
+        FieldType::Array(..) => Type::Reference(array_descriptor(field_type)),
+    }
+}
+
+fn array_descriptor(field_type: &FieldType) -> String {
+    match field_type {
+        FieldType::Byte => "B".into(),
+        FieldType::Char => "C".into(),
+        FieldType::Double => "D".into(),
+        FieldType::Float => "F".into(),
+        FieldType::Int => "I".into(),
+        FieldType::Long => "J".into(),
+        FieldType::Short => "S".into(),
+        FieldType::Boolean => "Z".into(),
+        FieldType::Object(class) => format!("L{class};"),
+        FieldType::Array(component) => format!("[{}", array_descriptor(component)),
+    }
+}
+
+fn primitive_descriptor(atype: ArrayType) -> char {
+    match atype {
+        ArrayType::Boolean => 'Z',
+        ArrayType::Char => 'C',
+        ArrayType::Float => 'F',
+        ArrayType::Double => 'D',
+        ArrayType::Byte => 'B',
+        ArrayType::Short => 'S',
+        ArrayType::Int => 'I',
+        ArrayType::Long => 'J',
+    }
+}
+
+/// The locals of a frame: longs and doubles take one entry, and the trailing `Top`s are dropped.
+fn verification_locals(locals: &[Type], jvm: &mut JVMClass) -> Vec<VerificationType> {
+    let mut verification_types = vec![];
+    let mut slot = 0;
+    while slot < locals.len() {
+        verification_types.push(verification_type(&locals[slot], jvm));
+        slot += locals[slot].slots();
+    }
+
+    while verification_types.last() == Some(&VerificationType::Top) {
+        verification_types.pop();
+    }
+
+    verification_types
+}
+
+fn verification_type(t: &Type, jvm: &mut JVMClass) -> VerificationType {
+    match t {
+        Type::Top => VerificationType::Top,
+        Type::Integer => VerificationType::Integer,
+        Type::Float => VerificationType::Float,
+        Type::Long => VerificationType::Long,
+        Type::Double => VerificationType::Double,
+        Type::Null => VerificationType::Null,
+        Type::UninitializedThis => VerificationType::UninitializedThis,
+        Type::Uninitialized(pc) => VerificationType::Uninitialized { offset: *pc as u16 },
+        Type::Reference(class) => VerificationType::Object {
+            cpool_index: jvm.constant_pool().intern_class(class),
+        },
+    }
+}
@@ -0,0 +1,16 @@
+use crate::Instruction;
+
+/// Checks if the code uses the subroutines of old compilers (`jsr`, `jsr_w`, `ret`), typically
+/// for `finally` blocks. Class files of version 51 and later can't contain them, and
+/// `generate_stack_map` doesn't support them.
+pub fn uses_subroutines(code: &[Instruction]) -> bool {
+    code.iter().any(|inst| {
+        matches!(
+            inst,
+            Instruction::Jsr(..)
+                | Instruction::JsrW(..)
+                | Instruction::Ret(..)
+                | Instruction::RetW(..)
+        )
+    })
+}
@@ -0,0 +1,101 @@
+use std::collections::HashMap;
+
+use crate::analysis::layout::{instruction_offsets, relative_offsets, relayout};
+use crate::{Instruction, JavaError};
+
+/// Emits instructions with symbolic labels, resolved to branch offsets when finishing.
+#[derive(Debug, Clone, Default)]
+pub struct Assembler {
+    code: Vec<Instruction>,
+    labels: HashMap<String, usize>,
+    branches: HashMap<usize, Vec<String>>,
+}
+
+impl Assembler {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Places `name` on the next emitted instruction. Defining a label again moves it.
+    pub fn label(&mut self, name: &str) -> &mut Self {
+        self.labels.insert(name.into(), self.code.len());
+        self
+    }
+
+    /// Emits an instruction as is. Its branch offsets, if any, must be valid for the code
+    /// emitted so far, as if all the labelled branches were in their short form.
+    pub fn emit(&mut self, instruction: Instruction) -> &mut Self {
+        self.code.push(instruction);
+        self
+    }
+
+    /// Emits a branch instruction (`goto`, `if<cond>`, `jsr`, …) jumping to `label`.
+    /// The offset of `instruction` is ignored.
+    pub fn branch(&mut self, instruction: Instruction, label: &str) -> &mut Self {
+        self.branches.insert(self.code.len(), vec![label.into()]);
+        self.code.push(instruction);
+        self
+    }
+
+    /// Emits a `tableswitch` or `lookupswitch` jumping to `default` and to `labels`, one per
+    /// jump target or pair, in order. The offsets of `instruction` are ignored.
+    pub fn switch(
+        &mut self,
+        instruction: Instruction,
+        default: &str,
+        labels: &[&str],
+    ) -> &mut Self {
+        let labels = std::iter::once(default)
+            .chain(labels.iter().copied())
+            .map(String::from)
+            .collect();
+        self.branches.insert(self.code.len(), labels);
+        self.code.push(instruction);
+        self
+    }
+
+    pub fn goto_label(&mut self, label: &str) -> &mut Self {
+        self.branch(Instruction::Goto(0), label)
+    }
+
+    /// Resolves the labels and returns the instructions with their branch offsets set.
+    /// `goto` and `jsr` are promoted to `goto_w` and `jsr_w` if their target is too far, and the
+    /// padding of the switches is computed.
+    pub fn finish(&self) -> Result<Vec<Instruction>, JavaError> {
+        let mut code = self.code.clone();
+        let offsets = instruction_offsets(&code);
+
+        let mut targets = Vec::with_capacity(code.len());
+        for (i, inst) in code.iter().enumerate() {
+            let relative = relative_offsets(inst);
+
+            let branch_targets = match self.branches.get(&i) {
+                Some(labels) if labels.len() != relative.len() => {
+                    return Err(JavaError::InvalidLabel(labels.join(", ")));
+                }
+                Some(labels) => labels
+                    .iter()
+                    .map(|label| match self.labels.get(label) {
+                        Some(target) if *target < code.len() => Ok(*target),
+                        _ => Err(JavaError::InvalidLabel(label.clone())),
+                    })
+                    .collect::<Result<_, _>>()?,
+                None => relative
+                    .iter()
+                    .map(|offset| {
+                        let target = offsets[i] as i64 + *offset as i64;
+                        offsets
+                            .binary_search_by(|o| (*o as i64).cmp(&target))
+                            .map_err(|_| JavaError::InvalidBranchTarget(offsets[i]))
+                    })
+                    .collect::<Result<_, _>>()?,
+            };
+
+            targets.push(branch_targets);
+        }
+
+        relayout(&mut code, &targets)?;
+
+        Ok(code)
+    }
+}
@@ -0,0 +1,209 @@
+use crate::attribute_names;
+use crate::{
+    AccessFlag, Assembler, Attribute, ClassVersion, ExceptionTableEntry, Instruction, JVMClass,
+    JavaError, LocalVariable,
+};
+
+/// Creates a class from scratch, with the constants and flags the JVM needs to load it.
+#[derive(Debug, Clone)]
+pub struct ClassBuilder {
+    jvm: JVMClass,
+    super_name: String,
+}
+
+impl ClassBuilder {
+    /// Starts a public Java 17 class. Names are internal names, e.g. `java/lang/Object`.
+    pub fn new(name: &str, super_name: &str) -> Self {
+        let mut jvm = JVMClass::new();
+        jvm.set_version(ClassVersion::Java17);
+        jvm.access_flags = vec![AccessFlag::Public, AccessFlag::Super];
+
+        let mut constant_pool = jvm.constant_pool();
+        let this_class = constant_pool.intern_class(name);
+        let super_class = constant_pool.intern_class(super_name);
+        jvm.this_class = this_class;
+        jvm.super_class = super_class;
+
+        Self {
+            jvm,
+            super_name: super_name.into(),
+        }
+    }
+
+    pub fn version(&mut self, version: ClassVersion) -> &mut Self {
+        self.jvm.set_version(version);
+        self
+    }
+
+    /// Adds a public `<init>()V` only calling the constructor of the super class.
+    pub fn add_default_constructor(&mut self) -> &mut Self {
+        let super_init =
+            self.jvm
+                .constant_pool()
+                .intern_methodref(&self.super_name, "<init>", "()V");
+
+        // no labels to resolve, so this can't fail like `build`
+        let code = CodeBuilder::new().max_stack(1).max_locals(1).attribute(
+            &mut self.jvm,
+            vec![
+                Instruction::ALoad(0),
+                Instruction::InvokeSpecial(super_init),
+                Instruction::Return,
+            ],
+        );
+
+        self.jvm
+            .add_method("<init>", "()V", vec![AccessFlag::Public], Some(code));
+        self
+    }
+
+    pub fn build(&self) -> JVMClass {
+        self.jvm.clone()
+    }
+}
+
+/// Assembles the body of a method into an `Attribute::Code`. Branches can jump to labels, like
+/// with an `Assembler`.
+#[derive(Debug, Clone, Default)]
+pub struct CodeBuilder {
+    code: Assembler,
+    max_stack: u16,
+    max_locals: u16,
+    exception_table: Vec<ExceptionTableEntry>,
+    locals: Vec<NamedLocal>,
+}
+
+#[derive(Debug, Clone)]
+struct NamedLocal {
+    name: String,
+    descriptor: String,
+    slot: u16,
+    start_pc: u16,
+    length: u16,
+}
+
+impl CodeBuilder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn emit(&mut self, instruction: Instruction) -> &mut Self {
+        self.code.emit(instruction);
+        self
+    }
+
+    /// Emits a `ldc` or a `ldc_w` of the constant `index`, see `Instruction::ldc`.
+    pub fn ldc(&mut self, index: u16) -> &mut Self {
+        self.emit(Instruction::ldc(index))
+    }
+
+    /// Places `name` on the next emitted instruction. Defining a label again moves it.
+    pub fn label(&mut self, name: &str) -> &mut Self {
+        self.code.label(name);
+        self
+    }
+
+    /// Emits a `goto` to `label`, which becomes a `goto_w` if the target is too far.
+    pub fn goto(&mut self, label: &str) -> &mut Self {
+        self.code.goto_label(label);
+        self
+    }
+
+    /// Emits a branch instruction jumping to `label`. The offset of `instruction` is ignored.
+    pub fn branch(&mut self, instruction: Instruction, label: &str) -> &mut Self {
+        self.code.branch(instruction, label);
+        self
+    }
+
+    /// Emits a `tableswitch` or `lookupswitch` jumping to `default` and to `labels`, one per
+    /// jump target or pair. Its offsets and padding are ignored.
+    pub fn switch(
+        &mut self,
+        instruction: Instruction,
+        default: &str,
+        labels: &[&str],
+    ) -> &mut Self {
+        self.code.switch(instruction, default, labels);
+        self
+    }
+
+    pub fn max_stack(&mut self, max_stack: u16) -> &mut Self {
+        self.max_stack = max_stack;
+        self
+    }
+
+    pub fn max_locals(&mut self, max_locals: u16) -> &mut Self {
+        self.max_locals = max_locals;
+        self
+    }
+
+    pub fn exception_handler(&mut self, entry: ExceptionTableEntry) -> &mut Self {
+        self.exception_table.push(entry);
+        self
+    }
+
+    /// Names the local variable in `slot` for the `length` bytes of code starting at `start_pc`.
+    /// The names end up in a `LocalVariableTable` attribute.
+    pub fn local(
+        &mut self,
+        name: &str,
+        descriptor: &str,
+        slot: u16,
+        start_pc: u16,
+        length: u16,
+    ) -> &mut Self {
+        self.locals.push(NamedLocal {
+            name: name.into(),
+            descriptor: descriptor.into(),
+            slot,
+            start_pc,
+            length,
+        });
+        self
+    }
+
+    /// Resolves the labels and returns the instructions, with their branch offsets and switch
+    /// padding set.
+    pub fn instructions(&self) -> Result<Vec<Instruction>, JavaError> {
+        self.code.finish()
+    }
+
+    /// Produces the `Code` attribute, adding the strings it needs to the constant pool of `jvm`.
+    /// Fails with `JavaError::InvalidLabel` if a branch jumps to an undefined label.
+    pub fn build(&self, jvm: &mut JVMClass) -> Result<Attribute, JavaError> {
+        let code = self.instructions()?;
+        Ok(self.attribute(jvm, code))
+    }
+
+    fn attribute(&self, jvm: &mut JVMClass, code: Vec<Instruction>) -> Attribute {
+        jvm.get_or_add_utf8_index(attribute_names::CODE);
+
+        let mut attributes = vec![];
+
+        if !self.locals.is_empty() {
+            jvm.get_or_add_utf8_index(attribute_names::LOCAL_VARIABLE_TABLE);
+
+            let local_variable_table = self
+                .locals
+                .iter()
+                .map(|local| LocalVariable {
+                    start_pc: local.start_pc,
+                    length: local.length,
+                    name_index: jvm.get_or_add_utf8_index(&local.name),
+                    descriptor_index: jvm.get_or_add_utf8_index(&local.descriptor),
+                    index: local.slot,
+                })
+                .collect();
+
+            attributes.push(Attribute::LocalVariableTable(local_variable_table));
+        }
+
+        Attribute::Code {
+            code,
+            max_stack: self.max_stack,
+            max_locals: self.max_locals,
+            exception_table: self.exception_table.clone(),
+            attributes,
+        }
+    }
+}
@@ -0,0 +1,440 @@
+use crate::analysis::{initial_locals, rewrite_code_attribute};
+use crate::indices::{
+    collect_attribute_names, visit_attribute_indices, visit_class_indices, visit_constant_indices,
+};
+use crate::{Attribute, BootstrapMethod, Constant, Instruction, JVMClass, JavaError, Method};
+
+/// Adds constants to a constant pool, reusing the existing entries when possible.
+///
+/// Empty pools get the unused entry 0, and longs and doubles are followed by the
+/// `Constant::Invalid` taking their second slot.
+#[derive(Debug)]
+pub struct ConstantPool<'a> {
+    constants: &'a mut Vec<Constant>,
+}
+
+impl<'a> ConstantPool<'a> {
+    pub fn new(constants: &'a mut Vec<Constant>) -> Self {
+        Self { constants }
+    }
+
+    pub fn intern_utf8(&mut self, string: &str) -> u16 {
+        self.intern(Constant::Utf8(string.into()))
+    }
+
+    pub fn intern_integer(&mut self, value: i32) -> u16 {
+        self.intern(Constant::Integer(value))
+    }
+
+    /// Floats are compared by their bit pattern, so each NaN payload gets its own entry.
+    pub fn intern_float(&mut self, value: f32) -> u16 {
+        self.intern(Constant::Float(value))
+    }
+
+    pub fn intern_long(&mut self, value: i64) -> u16 {
+        self.intern(Constant::Long(value))
+    }
+
+    /// Doubles are compared by their bit pattern, so each NaN payload gets its own entry.
+    pub fn intern_double(&mut self, value: f64) -> u16 {
+        self.intern(Constant::Double(value))
+    }
+
+    /// Interns a class from its internal name (e.g. `java/lang/Object`).
+    pub fn intern_class(&mut self, internal_name: &str) -> u16 {
+        let name_index = self.intern_utf8(internal_name);
+        self.intern(Constant::Class { name_index })
+    }
+
+    pub fn intern_string(&mut self, string: &str) -> u16 {
+        let string_index = self.intern_utf8(string);
+        self.intern(Constant::String { string_index })
+    }
+
+    pub fn intern_name_and_type(&mut self, name: &str, descriptor: &str) -> u16 {
+        let name_index = self.intern_utf8(name);
+        let descriptor_index = self.intern_utf8(descriptor);
+        self.intern(Constant::NameAndType {
+            name_index,
+            descriptor_index,
+        })
+    }
+
+    pub fn intern_fieldref(&mut self, class: &str, name: &str, descriptor: &str) -> u16 {
+        let class_index = self.intern_class(class);
+        let name_and_type_index = self.intern_name_and_type(name, descriptor);
+        self.intern(Constant::Fieldref {
+            class_index,
+            name_and_type_index,
+        })
+    }
+
+    pub fn intern_methodref(&mut self, class: &str, name: &str, descriptor: &str) -> u16 {
+        let class_index = self.intern_class(class);
+        let name_and_type_index = self.intern_name_and_type(name, descriptor);
+        self.intern(Constant::Methodref {
+            class_index,
+            name_and_type_index,
+        })
+    }
+
+    pub fn intern_interface_methodref(&mut self, class: &str, name: &str, descriptor: &str) -> u16 {
+        let class_index = self.intern_class(class);
+        let name_and_type_index = self.intern_name_and_type(name, descriptor);
+        self.intern(Constant::InterfaceMethodref {
+            class_index,
+            name_and_type_index,
+        })
+    }
+
+    /// Returns the index of a constant equal to `constant`, adding it if there is none.
+    pub fn intern(&mut self, constant: Constant) -> u16 {
+        match self
+            .constants
+            .iter()
+            .position(|existing| same_constant(existing, &constant))
+        {
+            Some(index) => index as u16,
+            None => self.push(constant),
+        }
+    }
+
+    /// Appends `constant` without looking for an equal one, and returns its index.
+    pub fn push(&mut self, constant: Constant) -> u16 {
+        if self.constants.is_empty() {
+            self.constants.push(Constant::Invalid);
+        }
+
+        let two_slots = matches!(constant, Constant::Long(..) | Constant::Double(..));
+        self.constants.push(constant);
+        let index = (self.constants.len() - 1) as u16;
+
+        if two_slots {
+            self.constants.push(Constant::Invalid);
+        }
+
+        index
+    }
+}
+
+/// Same as `==`, except that the unusable `Constant::Invalid` entries never match.
+fn same_constant(a: &Constant, b: &Constant) -> bool {
+    !matches!(a, Constant::Invalid) && a == b
+}
+
+impl JVMClass {
+    pub fn constant_pool(&mut self) -> ConstantPool<'_> {
+        ConstantPool::new(&mut self.constants)
+    }
+
+    /// Appends `constant` to the pool and returns its index. Longs and doubles are followed by
+    /// the `Constant::Invalid` taking their second slot.
+    pub fn push_constant(&mut self, constant: Constant) -> u16 {
+        self.constant_pool().push(constant)
+    }
+
+    /// Inserts `constant` at `index`, moving the following constants up by one slot (two for a
+    /// long or a double), and updates every index referring to them.
+    ///
+    /// `index` can be the end of the pool, but not 0 nor the second slot of a long or a double.
+    /// The `ldc`s whose constant moves past index 255 become `ldc_w`s, like in `remap_method`.
+    /// On error, the class is left untouched.
+    pub fn insert_constant(&mut self, index: u16, constant: Constant) -> Result<(), JavaError> {
+        if index == 0
+            || index as usize > self.constants.len().max(1)
+            || matches!(self.constants.get(index as usize), Some(Constant::Invalid))
+        {
+            return Err(JavaError::InvalidConstantId(index));
+        }
+
+        let slots = match constant {
+            Constant::Long(..) | Constant::Double(..) => 2,
+            _ => 1,
+        };
+
+        let mut methods = self.methods.clone();
+        for method in &mut methods {
+            let descriptor = self.get_string(method.0.descriptor)?;
+            widen_ldcs(method, descriptor, |id| {
+                id >= index && id + slots > u8::MAX as u16
+            })?;
+        }
+        self.methods = methods;
+
+        let mut shift = |id: &mut u16| {
+            if *id >= index {
+                *id += slots;
+            }
+        };
+        visit_class_indices(self, &mut shift);
+        for constant in &mut self.constants {
+            visit_constant_indices(constant, &mut shift);
+        }
+
+        if self.constants.is_empty() {
+            self.constants.push(Constant::Invalid);
+        }
+        if slots == 2 {
+            self.constants.insert(index as usize, Constant::Invalid);
+        }
+        self.constants.insert(index as usize, constant);
+
+        Ok(())
+    }
+
+    /// Makes the constant pool start with its `Constant::Invalid` entry, and adds the
+    /// `Constant::Invalid` taking the second slot of each long and double where it's missing,
+    /// like `read` and `push_constant` do. `store` does it on a copy when needed.
+    ///
+    /// The indices are taken as positions in the pool once it starts with its `Invalid` entry,
+    /// i.e. numbered from 1 if it's missing, like in a class file. So only the constants after a
+    /// long or a double without its second slot move, and the indices referring to them are
+    /// updated, widening the `ldc`s that end up past 255 like `insert_constant`.
+    pub fn normalize_pool(&mut self) -> Result<(), JavaError> {
+        if !matches!(self.constants.first(), Some(Constant::Invalid)) {
+            self.constants.insert(0, Constant::Invalid);
+        }
+        if self.is_pool_normalized() {
+            return Ok(());
+        }
+
+        let mut normalized = Vec::with_capacity(self.constants.len());
+        let mut new_ids = Vec::with_capacity(self.constants.len());
+        for (i, constant) in self.constants.iter().enumerate() {
+            new_ids.push(normalized.len() as u16);
+            normalized.push(constant.clone());
+
+            let is_wide = matches!(constant, Constant::Long(..) | Constant::Double(..));
+            if is_wide && !matches!(self.constants.get(i + 1), Some(Constant::Invalid)) {
+                normalized.push(Constant::Invalid);
+            }
+        }
+        let new_id = |id: u16| new_ids.get(id as usize).copied().unwrap_or(id);
+
+        let mut methods = self.methods.clone();
+        for method in &mut methods {
+            let descriptor = self.get_string(method.0.descriptor)?;
+            widen_ldcs(method, descriptor, |id| new_id(id) > u8::MAX as u16)?;
+        }
+        self.methods = methods;
+
+        let mut renumber = |id: &mut u16| *id = new_id(*id);
+        visit_class_indices(self, &mut renumber);
+        for constant in &mut normalized {
+            visit_constant_indices(constant, &mut renumber);
+        }
+        self.constants = normalized;
+
+        Ok(())
+    }
+
+    /// Whether the pool starts with its `Constant::Invalid` entry and each long and double is
+    /// followed by one, see `normalize_pool`.
+    pub(crate) fn is_pool_normalized(&self) -> bool {
+        matches!(self.constants.first(), Some(Constant::Invalid))
+            && self.constants.iter().enumerate().all(|(i, constant)| {
+                !matches!(constant, Constant::Long(..) | Constant::Double(..))
+                    || matches!(self.constants.get(i + 1), Some(Constant::Invalid))
+            })
+    }
+
+    /// Removes the constants nothing refers to, directly or through other constants, and
+    /// renumbers the remaining ones everywhere. The names of the attributes are kept.
+    /// Returns the number of pool entries removed, longs and doubles counting as two.
+    ///
+    /// The content of `Attribute::Unknown`s is opaque, so constants only used there are removed
+    /// and their indices are not updated.
+    pub fn prune_constants(&mut self) -> usize {
+        let mut pending = vec![];
+        visit_class_indices(self, &mut |id| pending.push(*id));
+
+        let mut names = vec![];
+        collect_attribute_names(&self.attributes, &mut names);
+        for member in self
+            .fields
+            .iter()
+            .map(|field| &field.0)
+            .chain(self.methods.iter().map(|method| &method.0))
+        {
+            collect_attribute_names(&member.attributes, &mut names);
+        }
+        pending.extend(
+            names
+                .iter()
+                .filter_map(|name| self.get_string_index(name).ok()),
+        );
+
+        let mut used = vec![false; self.constants.len()];
+        while let Some(id) = pending.pop() {
+            match used.get_mut(id as usize) {
+                Some(used) if !*used && id != 0 => *used = true,
+                _ => continue,
+            }
+            let mut constant = self.constants[id as usize].clone();
+            visit_constant_indices(&mut constant, &mut |id| pending.push(*id));
+        }
+
+        let mut mapping = vec![0u16; self.constants.len()];
+        let mut constants = vec![];
+        let mut keep_next = false;
+        for (id, constant) in std::mem::take(&mut self.constants).into_iter().enumerate() {
+            // the entry 0 and the second slot of kept longs and doubles stay
+            let keep = id == 0 || used[id] || keep_next;
+            keep_next = used[id] && matches!(constant, Constant::Long(..) | Constant::Double(..));
+            if keep {
+                mapping[id] = constants.len() as u16;
+                constants.push(constant);
+            }
+        }
+        let removed = mapping.len() - constants.len();
+        self.constants = constants;
+
+        let mut remap = |id: &mut u16| {
+            if let Some(new_id) = mapping.get(*id as usize) {
+                *id = *new_id;
+            }
+        };
+        visit_class_indices(self, &mut remap);
+        for constant in &mut self.constants {
+            visit_constant_indices(constant, &mut remap);
+        }
+
+        removed
+    }
+
+    /// Copies the constant `id` of `other` into this pool, along with the constants it refers
+    /// to, and returns its index here. Equal constants are reused instead of being added again.
+    ///
+    /// For `Dynamic` and `InvokeDynamic` constants, the bootstrap method is also copied to the
+    /// `BootstrapMethods` attribute, which is created if needed. Indices that aren't constants
+    /// of `other`, like 0, give 0.
+    pub fn import_constant(&mut self, other: &JVMClass, id: u16) -> u16 {
+        let Ok(constant) = other.get_constant(id) else {
+            return 0;
+        };
+
+        let mut constant = constant.clone();
+        visit_constant_indices(&mut constant, &mut |id| {
+            *id = self.import_constant(other, *id)
+        });
+        if let Constant::Dynamic {
+            bootstrap_method_attr_index,
+            ..
+        }
+        | Constant::InvokeDynamic {
+            bootstrap_method_attr_index,
+            ..
+        } = &mut constant
+        {
+            if let Some(index) = self.import_bootstrap_method(other, *bootstrap_method_attr_index) {
+                *bootstrap_method_attr_index = index;
+            }
+        }
+
+        self.constant_pool().intern(constant)
+    }
+
+    /// Returns a copy of `method`, a method of `other`, whose indices (including the ones in its
+    /// attributes and code) refer to constants imported into this pool with `import_constant`.
+    ///
+    /// An `ldc` whose constant ends up past index 255 becomes an `ldc_w`. The offsets in the
+    /// code and its attributes are then updated like `shift_code_locals` does, which fails on
+    /// the same errors.
+    pub fn remap_method(&mut self, other: &JVMClass, method: &Method) -> Result<Method, JavaError> {
+        let mut method = method.clone();
+        let descriptor = other.get_string(method.0.descriptor)?;
+        // the indices are still the ones of `other`, they are all remapped below
+        widen_ldcs(&mut method, descriptor, |id| {
+            self.import_constant(other, id) > u8::MAX as u16
+        })?;
+
+        method.0.name = self.import_constant(other, method.0.name);
+        method.0.descriptor = self.import_constant(other, method.0.descriptor);
+        visit_attribute_indices(&mut method.0.attributes, &mut |id| {
+            *id = self.import_constant(other, *id)
+        });
+
+        let mut names = vec![];
+        collect_attribute_names(&method.0.attributes, &mut names);
+        for name in names {
+            self.get_or_add_utf8_index(name);
+        }
+
+        Ok(method)
+    }
+
+    /// Copies the bootstrap method `index` of `other` (and its constants), and returns its index
+    /// in the `BootstrapMethods` attribute, or `None` if `other` has no such bootstrap method.
+    fn import_bootstrap_method(&mut self, other: &JVMClass, index: u16) -> Option<u16> {
+        let bootstrap_method = other.attributes.iter().find_map(|attr| match attr {
+            Attribute::BootstrapMethods(bootstrap_methods) => bootstrap_methods.get(index as usize),
+            _ => None,
+        })?;
+
+        let imported = BootstrapMethod {
+            bootstrap_method_ref: self
+                .import_constant(other, bootstrap_method.bootstrap_method_ref),
+            bootstrap_arguments: bootstrap_method
+                .bootstrap_arguments
+                .iter()
+                .map(|argument| self.import_constant(other, *argument))
+                .collect(),
+        };
+
+        if !self
+            .attributes
+            .iter()
+            .any(|attr| matches!(attr, Attribute::BootstrapMethods(..)))
+        {
+            self.get_or_add_utf8_index("BootstrapMethods");
+            self.attributes.push(Attribute::BootstrapMethods(vec![]));
+        }
+        let bootstrap_methods = self.attributes.iter_mut().find_map(|attr| match attr {
+            Attribute::BootstrapMethods(bootstrap_methods) => Some(bootstrap_methods),
+            _ => None,
+        })?;
+
+        let index = match bootstrap_methods
+            .iter()
+            .position(|existing| *existing == imported)
+        {
+            Some(index) => index,
+            None => {
+                bootstrap_methods.push(imported);
+                bootstrap_methods.len() - 1
+            }
+        };
+        Some(index as u16)
+    }
+}
+
+/// Turns the `ldc`s of `method` whose index is `too_far` into `ldc_w`s, and updates the offsets
+/// in its `Code` attribute like `shift_code_locals` does. `descriptor` is the one of `method`.
+fn widen_ldcs<F: FnMut(u16) -> bool>(
+    method: &mut Method,
+    descriptor: &str,
+    mut too_far: F,
+) -> Result<(), JavaError> {
+    let is_static = method.is_static();
+
+    let mut too_far = |inst: &Instruction| match inst {
+        Instruction::Ldc(id) => too_far(*id as u16),
+        _ => false,
+    };
+    for attribute in &mut method.0.attributes {
+        match attribute {
+            Attribute::Code { code, .. } if code.iter().any(&mut too_far) => {}
+            _ => continue,
+        }
+
+        let initial_locals = initial_locals(is_static, descriptor)?;
+        rewrite_code_attribute(attribute, &initial_locals, 0, 0, |inst| {
+            Ok(match inst {
+                Instruction::Ldc(id) if too_far(inst) => Instruction::LdcW(*id as u16),
+                _ => inst.clone(),
+            })
+        })?;
+    }
+
+    Ok(())
+}
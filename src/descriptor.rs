@@ -0,0 +1,128 @@
+//! Field and method descriptors, e.g. `[Ljava/lang/String;` or `(IJ)V`.
+
+use crate::{JVMClass, JavaError, Method};
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum FieldType {
+    Byte,
+    Char,
+    Double,
+    Float,
+    Int,
+    Long,
+    Short,
+    Boolean,
+    /// A class or interface, by its internal name (e.g. `java/lang/String`).
+    Object(String),
+    Array(Box<FieldType>),
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct MethodDescriptor {
+    pub params: Vec<FieldType>,
+    /// `None` for `void`.
+    pub ret: Option<FieldType>,
+}
+
+impl FieldType {
+    /// Renders the type as in Java source, with dotted class names: `int[][]` for `[[I`, or
+    /// `java.lang.String` for `Ljava/lang/String;`.
+    pub fn to_java_string(&self) -> String {
+        match self {
+            FieldType::Byte => "byte".into(),
+            FieldType::Char => "char".into(),
+            FieldType::Double => "double".into(),
+            FieldType::Float => "float".into(),
+            FieldType::Int => "int".into(),
+            FieldType::Long => "long".into(),
+            FieldType::Short => "short".into(),
+            FieldType::Boolean => "boolean".into(),
+            FieldType::Object(name) => name.replace('/', "."),
+            FieldType::Array(component) => format!("{}[]", component.to_java_string()),
+        }
+    }
+}
+
+impl MethodDescriptor {
+    /// Renders the descriptor as in Java source, e.g. `void (java.lang.String, int)`, or
+    /// `void foo(java.lang.String, int)` when given the name of the method.
+    pub fn to_java_string(&self, method_name: Option<&str>) -> String {
+        let ret = match &self.ret {
+            Some(ret) => ret.to_java_string(),
+            None => "void".into(),
+        };
+        let params = self
+            .params
+            .iter()
+            .map(FieldType::to_java_string)
+            .collect::<Vec<_>>()
+            .join(", ");
+
+        match method_name {
+            Some(name) => format!("{ret} {name}({params})"),
+            None => format!("{ret} ({params})"),
+        }
+    }
+}
+
+pub fn parse_field_descriptor(descriptor: &str) -> Result<FieldType, JavaError> {
+    match parse_field_type(descriptor) {
+        Some((field_type, "")) => Ok(field_type),
+        _ => Err(JavaError::InvalidDescriptor(descriptor.to_string())),
+    }
+}
+
+pub fn parse_method_descriptor(descriptor: &str) -> Result<MethodDescriptor, JavaError> {
+    let invalid = || JavaError::InvalidDescriptor(descriptor.to_string());
+
+    let mut rest = descriptor.strip_prefix('(').ok_or_else(invalid)?;
+    let mut params = vec![];
+    while !rest.starts_with(')') {
+        let (param, next) = parse_field_type(rest).ok_or_else(invalid)?;
+        params.push(param);
+        rest = next;
+    }
+
+    let ret = match &rest[1..] {
+        "V" => None,
+        ret => Some(parse_field_descriptor(ret).map_err(|_| invalid())?),
+    };
+
+    Ok(MethodDescriptor { params, ret })
+}
+
+/// Parses the field type at the start of `descriptor`, returning it with the rest of the string.
+fn parse_field_type(descriptor: &str) -> Option<(FieldType, &str)> {
+    let rest = descriptor.get(1..)?;
+
+    let field_type = match descriptor.as_bytes().first()? {
+        b'B' => FieldType::Byte,
+        b'C' => FieldType::Char,
+        b'D' => FieldType::Double,
+        b'F' => FieldType::Float,
+        b'I' => FieldType::Int,
+        b'J' => FieldType::Long,
+        b'S' => FieldType::Short,
+        b'Z' => FieldType::Boolean,
+        b'L' => {
+            let end = rest.find(';').filter(|end| *end > 0)?;
+            return Some((FieldType::Object(rest[..end].to_string()), &rest[end + 1..]));
+        }
+        b'[' => {
+            let (component, rest) = parse_field_type(rest)?;
+            return Some((FieldType::Array(Box::new(component)), rest));
+        }
+        _ => return None,
+    };
+
+    Some((field_type, rest))
+}
+
+impl Method {
+    /// Resolves and parses the descriptor of the method.
+    pub fn parsed_descriptor(&self, jvm: &JVMClass) -> Result<MethodDescriptor, JavaError> {
+        parse_method_descriptor(jvm.get_string(self.0.descriptor)?)
+    }
+}
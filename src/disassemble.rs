@@ -0,0 +1,298 @@
+//! A javap-like listing of a class, with the constant pool references resolved.
+
+use crate::{AccessFlag, Attribute, Constant, Instruction, JVMClass, JavaError, MemberData};
+
+impl JVMClass {
+    /// Lists the class, its fields and its methods, with their code.
+    ///
+    /// Constant pool references are followed by what they resolve to, and branches by their
+    /// absolute target. This is meant to be read, not parsed.
+    pub fn disassemble(&self) -> Result<String, JavaError> {
+        let mut out = String::new();
+
+        out += &flags_prefix(&self.access_flags);
+        out += &format!("class {}", self.get_class_name(self.this_class)?);
+        if self.super_class != 0 {
+            out += &format!(" extends {}", self.get_class_name(self.super_class)?);
+        }
+        if !self.interfaces.is_empty() {
+            let interfaces = self
+                .interfaces
+                .iter()
+                .map(|id| self.get_class_name(*id))
+                .collect::<Result<Vec<_>, _>>()?;
+            out += &format!(" implements {}", interfaces.join(", "));
+        }
+        out += &format!("\n  version: {}.{}\n", self.major, self.minor);
+
+        if !self.fields.is_empty() {
+            out += "\nFields:\n";
+            for field in &self.fields {
+                out += &format!("  {}\n", self.member_signature(&field.0)?);
+            }
+        }
+
+        if !self.methods.is_empty() {
+            out += "\nMethods:\n";
+            for method in &self.methods {
+                out += &format!("  {}\n", self.member_signature(&method.0)?);
+                for attr in &method.0.attributes {
+                    if let Attribute::Code { .. } = attr {
+                        out += &self.disassemble_code(attr)?;
+                    }
+                }
+            }
+        }
+
+        Ok(out)
+    }
+
+    fn member_signature(&self, member: &MemberData) -> Result<String, JavaError> {
+        Ok(format!(
+            "{}{}:{}",
+            flags_prefix(&member.access_flags),
+            self.get_string(member.name)?,
+            self.get_string(member.descriptor)?
+        ))
+    }
+
+    fn disassemble_code(&self, code_attribute: &Attribute) -> Result<String, JavaError> {
+        let Attribute::Code {
+            code,
+            max_stack,
+            max_locals,
+            exception_table,
+            ..
+        } = code_attribute
+        else {
+            return Ok(String::new());
+        };
+
+        let mut out = format!("    Code: stack={max_stack}, locals={max_locals}\n");
+
+        let mut pc = 0;
+        for inst in code {
+            out += &format!("{pc:>8}: {}\n", self.disassemble_instruction(inst, pc)?);
+            pc += inst.size();
+        }
+
+        if !exception_table.is_empty() {
+            out += "    Exception table:\n";
+            for entry in exception_table {
+                let catch_type = match entry.catch_type {
+                    0 => "any",
+                    id => self.get_class_name(id)?,
+                };
+                out += &format!(
+                    "      {} -> {}, handler {}: {catch_type}\n",
+                    entry.start_pc, entry.end_pc, entry.handler_pc
+                );
+            }
+        }
+
+        Ok(out)
+    }
+
+    fn disassemble_instruction(&self, inst: &Instruction, pc: u32) -> Result<String, JavaError> {
+        let name = inst.mnemonic();
+        let target = |offset: i32| (pc as i64 + offset as i64).to_string();
+
+        let text = match inst {
+            // constants without a compact form
+            Instruction::IConst(value) if inst.opcode() >= 0x10 => format!("{name:<13} {value}"),
+            Instruction::LConst(value) if inst.opcode() >= 0x10 => format!("{name:<13} {value}"),
+            Instruction::FConst(value) if inst.opcode() >= 0x10 => format!("{name:<13} {value}"),
+            Instruction::DConst(value) if inst.opcode() >= 0x10 => format!("{name:<13} {value}"),
+            Instruction::ALoad(slot)
+            | Instruction::AStore(slot)
+            | Instruction::DLoad(slot)
+            | Instruction::DStore(slot)
+            | Instruction::FLoad(slot)
+            | Instruction::FStore(slot)
+            | Instruction::ILoad(slot)
+            | Instruction::IStore(slot)
+            | Instruction::LLoad(slot)
+            | Instruction::LStore(slot)
+                if *slot <= 3 =>
+            {
+                name.to_string()
+            }
+            Instruction::ALoad(slot)
+            | Instruction::AStore(slot)
+            | Instruction::DLoad(slot)
+            | Instruction::DStore(slot)
+            | Instruction::FLoad(slot)
+            | Instruction::FStore(slot)
+            | Instruction::ILoad(slot)
+            | Instruction::IStore(slot)
+            | Instruction::LLoad(slot)
+            | Instruction::LStore(slot)
+            | Instruction::Ret(slot) => format!("{name:<13} {slot}"),
+            Instruction::ALoadW(slot)
+            | Instruction::AStoreW(slot)
+            | Instruction::DLoadW(slot)
+            | Instruction::DStoreW(slot)
+            | Instruction::FLoadW(slot)
+            | Instruction::FStoreW(slot)
+            | Instruction::ILoadW(slot)
+            | Instruction::IStoreW(slot)
+            | Instruction::LLoadW(slot)
+            | Instruction::LStoreW(slot)
+            | Instruction::RetW(slot) => format!("wide {name:<8} {slot}"),
+            Instruction::IInc(slot, count) => format!("{name:<13} {slot}, {count}"),
+            Instruction::IIncW(slot, count) => format!("wide {name:<8} {slot}, {count}"),
+            Instruction::Bipush(value) => format!("{name:<13} {}", *value as i8),
+            Instruction::Sipush(value) => format!("{name:<13} {value}"),
+            Instruction::NewArray(atype) => format!("{name:<13} {}", atype.name()),
+            Instruction::Ldc(id) => self.with_constant(name, format!("#{id}"), *id as u16)?,
+            Instruction::ANewArray(id)
+            | Instruction::CheckCast(id)
+            | Instruction::GetField(id)
+            | Instruction::GetStatic(id)
+            | Instruction::InstanceOf(id)
+            | Instruction::InvokeDynamic(id)
+            | Instruction::InvokeSpecial(id)
+            | Instruction::InvokeStatic(id)
+            | Instruction::InvokeVirtual(id)
+            | Instruction::Ldc2W(id)
+            | Instruction::LdcW(id)
+            | Instruction::New(id)
+            | Instruction::PutField(id)
+            | Instruction::PutStatic(id) => self.with_constant(name, format!("#{id}"), *id)?,
+            Instruction::InvokeInterface { index, count } => {
+                self.with_constant(name, format!("#{index}, {count}"), *index)?
+            }
+            Instruction::MultiANewArray(id, dimensions) => {
+                self.with_constant(name, format!("#{id}, {dimensions}"), *id)?
+            }
+            Instruction::TableSwitch {
+                minimum,
+                maximum,
+                jump_targets,
+                default,
+                ..
+            } => {
+                let mut text = format!("{name:<13} {{ // {minimum} to {maximum}\n");
+                for (i, offset) in jump_targets.iter().enumerate() {
+                    let value = minimum.wrapping_add(i as i32);
+                    text += &format!("{:>22}: {}\n", value, target(*offset));
+                }
+                text += &format!("{:>22}: {}\n          }}", "default", target(*default));
+                text
+            }
+            Instruction::LookupSwitch { default, pairs, .. } => {
+                let mut text = format!("{name:<13} {{ // {}\n", pairs.len());
+                for pair in pairs {
+                    text += &format!("{:>22}: {}\n", pair.value, target(pair.target));
+                }
+                text += &format!("{:>22}: {}\n          }}", "default", target(*default));
+                text
+            }
+            _ => match inst.branch_target(pc) {
+                Some(target) => format!("{name:<13} {target}"),
+                None => name.to_string(),
+            },
+        };
+
+        Ok(text)
+    }
+
+    /// Formats an instruction referring to the constant `id`, followed by what it resolves to.
+    fn with_constant(&self, name: &str, operands: String, id: u16) -> Result<String, JavaError> {
+        Ok(format!(
+            "{name:<13} {operands:<18} // {}",
+            self.describe_constant(id)?
+        ))
+    }
+
+    fn describe_constant(&self, id: u16) -> Result<String, JavaError> {
+        let description = match self.constants.get(id as usize) {
+            Some(Constant::Class { .. }) => format!("class {}", self.get_class_name(id)?),
+            Some(Constant::Double(value)) => format!("double {value}"),
+            Some(Constant::Float(value)) => format!("float {value}"),
+            Some(Constant::Integer(value)) => format!("int {value}"),
+            Some(Constant::Long(value)) => format!("long {value}"),
+            Some(Constant::String { string_index }) => {
+                match self.constants.get(*string_index as usize) {
+                    Some(Constant::Utf8Bytes(bytes)) => format!("String {bytes:02x?}"),
+                    _ => format!("String {:?}", self.get_string(*string_index)?),
+                }
+            }
+            Some(Constant::Utf8(string)) => format!("Utf8 {string:?}"),
+            Some(Constant::Utf8Bytes(bytes)) => format!("Utf8 {bytes:02x?}"),
+            Some(Constant::Fieldref {
+                class_index,
+                name_and_type_index,
+            }) => format!(
+                "Field {}.{}",
+                self.get_class_name(*class_index)?,
+                self.describe_name_and_type(*name_and_type_index)?
+            ),
+            Some(Constant::Methodref {
+                class_index,
+                name_and_type_index,
+            }) => format!(
+                "Method {}.{}",
+                self.get_class_name(*class_index)?,
+                self.describe_name_and_type(*name_and_type_index)?
+            ),
+            Some(Constant::InterfaceMethodref {
+                class_index,
+                name_and_type_index,
+            }) => format!(
+                "InterfaceMethod {}.{}",
+                self.get_class_name(*class_index)?,
+                self.describe_name_and_type(*name_and_type_index)?
+            ),
+            Some(Constant::NameAndType { .. }) => {
+                format!("NameAndType {}", self.describe_name_and_type(id)?)
+            }
+            Some(Constant::Dynamic {
+                bootstrap_method_attr_index,
+                name_and_type_index,
+            }) => format!(
+                "Dynamic #{bootstrap_method_attr_index}:{}",
+                self.describe_name_and_type(*name_and_type_index)?
+            ),
+            Some(Constant::InvokeDynamic {
+                bootstrap_method_attr_index,
+                name_and_type_index,
+            }) => format!(
+                "InvokeDynamic #{bootstrap_method_attr_index}:{}",
+                self.describe_name_and_type(*name_and_type_index)?
+            ),
+            Some(Constant::MethodHandle {
+                reference_kind,
+                reference_index,
+            }) => format!(
+                "MethodHandle {}:{}",
+                reference_kind.name(),
+                self.describe_constant(*reference_index)?
+            ),
+            Some(Constant::MethodType { descriptor_index }) => {
+                format!("MethodType {}", self.get_string(*descriptor_index)?)
+            }
+            Some(Constant::Module { name_index }) => {
+                format!("Module {}", self.get_string(*name_index)?)
+            }
+            Some(Constant::Package { name_index }) => {
+                format!("Package {}", self.get_string(*name_index)?)
+            }
+            Some(Constant::Invalid) | None => return Err(JavaError::InvalidConstantId(id)),
+        };
+
+        Ok(description)
+    }
+
+    fn describe_name_and_type(&self, id: u16) -> Result<String, JavaError> {
+        let (name, descriptor) = self.resolve_name_and_type(id)?;
+        Ok(format!("{name}:{descriptor}"))
+    }
+}
+
+fn flags_prefix(flags: &[AccessFlag]) -> String {
+    flags
+        .iter()
+        .map(|flag| format!("{flag:?} ").to_lowercase())
+        .collect()
+}
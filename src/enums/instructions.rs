@@ -1,6 +1,9 @@
+use crate::analysis::layout::relative_offsets;
+use crate::enums::ArrayType;
 use crate::structs::LookupSwitchPair;
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum Instruction {
     AALoad,
     AAStore,
@@ -27,6 +30,7 @@ pub enum Instruction {
     DAStore,
     DCmpg,
     DCmpl,
+    /// Written as `dconst_0` or `dconst_1` if possible, otherwise as a `ldc2_w` of the value.
     DConst(f64),
     DDiv,
     DLoad(u8),
@@ -52,6 +56,7 @@ pub enum Instruction {
     FAStore,
     FCmpg,
     FCmpl,
+    /// Written as `fconst_<n>` if possible, otherwise as a `ldc_w` of the value.
     FConst(f32),
     FDiv,
     FLoad(u8),
@@ -66,7 +71,7 @@ pub enum Instruction {
     GetField(u16),
     GetStatic(u16),
     Goto(i16),
-    GotoW(u32),
+    GotoW(i32),
     I2B,
     I2C,
     I2D,
@@ -77,6 +82,8 @@ pub enum Instruction {
     IALoad,
     IAnd,
     IAStore,
+    /// Written as `iconst_<n>` if possible, otherwise as the shortest of `bipush`, `sipush` and
+    /// `ldc_w` able to push the value.
     IConst(i32),
     IDiv,
     IfAcmpeq(i16),
@@ -96,7 +103,7 @@ pub enum Instruction {
     IfNonNull(i16),
     IfNull(i16),
     IInc(u8, i8),
-    IIncW(u16, u16),
+    IIncW(u16, i16),
     ILoad(u8),
     ILoadW(u16),
     IMul,
@@ -121,7 +128,7 @@ pub enum Instruction {
     IUShr,
     IXor,
     Jsr(i16),
-    JsrW(u32),
+    JsrW(i32),
     L2D,
     L2F,
     L2I,
@@ -130,6 +137,7 @@ pub enum Instruction {
     LAnd,
     LAStore,
     LCmp,
+    /// Written as `lconst_0` or `lconst_1` if possible, otherwise as a `ldc2_w` of the value.
     LConst(i64),
     Ldc(u8),
     Ldc2W(u16),
@@ -140,8 +148,10 @@ pub enum Instruction {
     LMul,
     LNeg,
     LookupSwitch {
+        /// The number of zero bytes after the opcode, so that the operands start at a multiple
+        /// of 4 bytes from the beginning of the code.
         padding: u32,
-        default: u32,
+        default: i32,
         pairs: Vec<LookupSwitchPair>,
     },
     LOr,
@@ -158,7 +168,7 @@ pub enum Instruction {
     MonitorExit,
     MultiANewArray(u16, u8),
     New(u16),
-    NewArray(u8),
+    NewArray(ArrayType),
     Nop,
     Pop,
     Pop2,
@@ -172,15 +182,26 @@ pub enum Instruction {
     Sipush(i16),
     Swap,
     TableSwitch {
+        /// The number of zero bytes after the opcode, so that the operands start at a multiple
+        /// of 4 bytes from the beginning of the code.
         padding: u32,
-        minimum: u32,
-        maximum: u32,
-        jump_targets: Vec<u32>,
-        default: u32,
+        minimum: i32,
+        maximum: i32,
+        jump_targets: Vec<i32>,
+        default: i32,
     },
 }
 
 impl Instruction {
+    /// Loads the constant `index`: a `ldc` if the index fits in a byte, otherwise a `ldc_w`.
+    /// Longs and doubles need a `Ldc2W` instead.
+    pub fn ldc(index: u16) -> Instruction {
+        match u8::try_from(index) {
+            Ok(index) => Instruction::Ldc(index),
+            Err(_) => Instruction::LdcW(index),
+        }
+    }
+
     pub fn size(&self) -> u32 {
         match self {
             Instruction::AALoad
@@ -201,7 +222,6 @@ impl Instruction {
             | Instruction::DAStore
             | Instruction::DCmpg
             | Instruction::DCmpl
-            | Instruction::DConst(..)
             | Instruction::DDiv
             | Instruction::DMul
             | Instruction::DNeg
@@ -222,7 +242,6 @@ impl Instruction {
             | Instruction::FAStore
             | Instruction::FCmpg
             | Instruction::FCmpl
-            | Instruction::FConst(..)
             | Instruction::FDiv
             | Instruction::FMul
             | Instruction::FNeg
@@ -239,7 +258,6 @@ impl Instruction {
             | Instruction::IALoad
             | Instruction::IAnd
             | Instruction::IAStore
-            | Instruction::IConst(..)
             | Instruction::IDiv
             | Instruction::IMul
             | Instruction::INeg
@@ -259,7 +277,6 @@ impl Instruction {
             | Instruction::LAnd
             | Instruction::LAStore
             | Instruction::LCmp
-            | Instruction::LConst(..)
             | Instruction::LDiv
             | Instruction::LMul
             | Instruction::LNeg
@@ -346,14 +363,24 @@ impl Instruction {
             | Instruction::FLoadW(..)
             | Instruction::FStoreW(..)
             | Instruction::ILoadW(..)
-            | Instruction::InvokeInterface { .. }
             | Instruction::IStoreW(..)
             | Instruction::LLoadW(..)
             | Instruction::LStoreW(..)
             | Instruction::MultiANewArray(..)
             | Instruction::RetW(..) => 4,
-            Instruction::GotoW(..) | Instruction::InvokeDynamic(..) | Instruction::JsrW(..) => 5,
+            Instruction::GotoW(..)
+            | Instruction::InvokeDynamic(..)
+            | Instruction::InvokeInterface { .. }
+            | Instruction::JsrW(..) => 5,
             Instruction::IIncW(..) => 6,
+            Instruction::DConst(..)
+            | Instruction::FConst(..)
+            | Instruction::IConst(..)
+            | Instruction::LConst(..) => match self.opcode() {
+                0x10 => 2,
+                0x11 | 0x13 | 0x14 => 3,
+                _ => 1,
+            },
             Instruction::LookupSwitch {
                 padding,
                 default: _,
@@ -365,7 +392,369 @@ impl Instruction {
                 maximum: _,
                 jump_targets,
                 default: _,
-            } => 1 + padding + 12 + jump_targets.len() as u32,
+            } => 1 + padding + 12 + jump_targets.len() as u32 * 4,
+        }
+    }
+
+    /// Returns the opcode the instruction is written with, `0xC4` for the `wide` forms.
+    ///
+    /// Loads, stores and constants use their compact form when there is one (`ILoad(0)` is
+    /// `0x1A`). A constant that has none, like `IConst(42)`, reports the instruction able to
+    /// push it: `bipush`, `sipush`, `ldc_w` or `ldc2_w`.
+    pub fn opcode(&self) -> u8 {
+        match self {
+            Instruction::Nop => 0x00,
+            Instruction::ANull => 0x01,
+            Instruction::IConst(value @ -1..=5) => (0x03 + value) as u8,
+            Instruction::IConst(value) if i8::try_from(*value).is_ok() => 0x10,
+            Instruction::IConst(value) if i16::try_from(*value).is_ok() => 0x11,
+            Instruction::IConst(_) => 0x13,
+            Instruction::LConst(value @ 0..=1) => 0x09 + *value as u8,
+            Instruction::LConst(_) => 0x14,
+            Instruction::FConst(value) if value.to_bits() == 0.0f32.to_bits() => 0x0B,
+            Instruction::FConst(value) if *value == 1.0 => 0x0C,
+            Instruction::FConst(value) if *value == 2.0 => 0x0D,
+            Instruction::FConst(_) => 0x13,
+            Instruction::DConst(value) if value.to_bits() == 0.0f64.to_bits() => 0x0E,
+            Instruction::DConst(value) if *value == 1.0 => 0x0F,
+            Instruction::DConst(_) => 0x14,
+            Instruction::Bipush(..) => 0x10,
+            Instruction::Sipush(..) => 0x11,
+            Instruction::Ldc(..) => 0x12,
+            Instruction::LdcW(..) => 0x13,
+            Instruction::Ldc2W(..) => 0x14,
+            Instruction::ILoad(slot @ 0..=3) => 0x1A + slot,
+            Instruction::ILoad(_) => 0x15,
+            Instruction::LLoad(slot @ 0..=3) => 0x1E + slot,
+            Instruction::LLoad(_) => 0x16,
+            Instruction::FLoad(slot @ 0..=3) => 0x22 + slot,
+            Instruction::FLoad(_) => 0x17,
+            Instruction::DLoad(slot @ 0..=3) => 0x26 + slot,
+            Instruction::DLoad(_) => 0x18,
+            Instruction::ALoad(slot @ 0..=3) => 0x2A + slot,
+            Instruction::ALoad(_) => 0x19,
+            Instruction::IALoad => 0x2E,
+            Instruction::LALoad => 0x2F,
+            Instruction::FALoad => 0x30,
+            Instruction::DALoad => 0x31,
+            Instruction::AALoad => 0x32,
+            Instruction::BALoad => 0x33,
+            Instruction::CALoad => 0x34,
+            Instruction::SALoad => 0x35,
+            Instruction::IStore(slot @ 0..=3) => 0x3B + slot,
+            Instruction::IStore(_) => 0x36,
+            Instruction::LStore(slot @ 0..=3) => 0x3F + slot,
+            Instruction::LStore(_) => 0x37,
+            Instruction::FStore(slot @ 0..=3) => 0x43 + slot,
+            Instruction::FStore(_) => 0x38,
+            Instruction::DStore(slot @ 0..=3) => 0x47 + slot,
+            Instruction::DStore(_) => 0x39,
+            Instruction::AStore(slot @ 0..=3) => 0x4B + slot,
+            Instruction::AStore(_) => 0x3A,
+            Instruction::IAStore => 0x4F,
+            Instruction::LAStore => 0x50,
+            Instruction::FAStore => 0x51,
+            Instruction::DAStore => 0x52,
+            Instruction::AAStore => 0x53,
+            Instruction::BAStore => 0x54,
+            Instruction::CAStore => 0x55,
+            Instruction::SAStore => 0x56,
+            Instruction::Pop => 0x57,
+            Instruction::Pop2 => 0x58,
+            Instruction::Dup => 0x59,
+            Instruction::DupX1 => 0x5A,
+            Instruction::DupX2 => 0x5B,
+            Instruction::Dup2 => 0x5C,
+            Instruction::Dup2X1 => 0x5D,
+            Instruction::Dup2X2 => 0x5E,
+            Instruction::Swap => 0x5F,
+            Instruction::IAdd => 0x60,
+            Instruction::LAdd => 0x61,
+            Instruction::FAdd => 0x62,
+            Instruction::DAdd => 0x63,
+            Instruction::ISub => 0x64,
+            Instruction::LSub => 0x65,
+            Instruction::FSub => 0x66,
+            Instruction::DSub => 0x67,
+            Instruction::IMul => 0x68,
+            Instruction::LMul => 0x69,
+            Instruction::FMul => 0x6A,
+            Instruction::DMul => 0x6B,
+            Instruction::IDiv => 0x6C,
+            Instruction::LDiv => 0x6D,
+            Instruction::FDiv => 0x6E,
+            Instruction::DDiv => 0x6F,
+            Instruction::IRem => 0x70,
+            Instruction::LRem => 0x71,
+            Instruction::FRem => 0x72,
+            Instruction::DRem => 0x73,
+            Instruction::INeg => 0x74,
+            Instruction::LNeg => 0x75,
+            Instruction::FNeg => 0x76,
+            Instruction::DNeg => 0x77,
+            Instruction::IShl => 0x78,
+            Instruction::LShl => 0x79,
+            Instruction::IShr => 0x7A,
+            Instruction::LShr => 0x7B,
+            Instruction::IUShr => 0x7C,
+            Instruction::LUShr => 0x7D,
+            Instruction::IAnd => 0x7E,
+            Instruction::LAnd => 0x7F,
+            Instruction::IOr => 0x80,
+            Instruction::LOr => 0x81,
+            Instruction::IXor => 0x82,
+            Instruction::LXor => 0x83,
+            Instruction::IInc(..) => 0x84,
+            Instruction::I2L => 0x85,
+            Instruction::I2F => 0x86,
+            Instruction::I2D => 0x87,
+            Instruction::L2I => 0x88,
+            Instruction::L2F => 0x89,
+            Instruction::L2D => 0x8A,
+            Instruction::F2I => 0x8B,
+            Instruction::F2L => 0x8C,
+            Instruction::F2D => 0x8D,
+            Instruction::D2I => 0x8E,
+            Instruction::D2L => 0x8F,
+            Instruction::D2F => 0x90,
+            Instruction::I2B => 0x91,
+            Instruction::I2C => 0x92,
+            Instruction::I2S => 0x93,
+            Instruction::LCmp => 0x94,
+            Instruction::FCmpl => 0x95,
+            Instruction::FCmpg => 0x96,
+            Instruction::DCmpl => 0x97,
+            Instruction::DCmpg => 0x98,
+            Instruction::Ifeq(..) => 0x99,
+            Instruction::Ifne(..) => 0x9A,
+            Instruction::Iflt(..) => 0x9B,
+            Instruction::Ifge(..) => 0x9C,
+            Instruction::Ifgt(..) => 0x9D,
+            Instruction::Ifle(..) => 0x9E,
+            Instruction::IfIcmpeq(..) => 0x9F,
+            Instruction::IfIcmpne(..) => 0xA0,
+            Instruction::IfIcmplt(..) => 0xA1,
+            Instruction::IfIcmpge(..) => 0xA2,
+            Instruction::IfIcmpgt(..) => 0xA3,
+            Instruction::IfIcmple(..) => 0xA4,
+            Instruction::IfAcmpeq(..) => 0xA5,
+            Instruction::IfAcmpne(..) => 0xA6,
+            Instruction::Goto(..) => 0xA7,
+            Instruction::Jsr(..) => 0xA8,
+            Instruction::Ret(..) => 0xA9,
+            Instruction::TableSwitch { .. } => 0xAA,
+            Instruction::LookupSwitch { .. } => 0xAB,
+            Instruction::IReturn => 0xAC,
+            Instruction::LReturn => 0xAD,
+            Instruction::FReturn => 0xAE,
+            Instruction::DReturn => 0xAF,
+            Instruction::AReturn => 0xB0,
+            Instruction::Return => 0xB1,
+            Instruction::GetStatic(..) => 0xB2,
+            Instruction::PutStatic(..) => 0xB3,
+            Instruction::GetField(..) => 0xB4,
+            Instruction::PutField(..) => 0xB5,
+            Instruction::InvokeVirtual(..) => 0xB6,
+            Instruction::InvokeSpecial(..) => 0xB7,
+            Instruction::InvokeStatic(..) => 0xB8,
+            Instruction::InvokeInterface { .. } => 0xB9,
+            Instruction::InvokeDynamic(..) => 0xBA,
+            Instruction::New(..) => 0xBB,
+            Instruction::NewArray(..) => 0xBC,
+            Instruction::ANewArray(..) => 0xBD,
+            Instruction::ArrayLength => 0xBE,
+            Instruction::AThrow => 0xBF,
+            Instruction::CheckCast(..) => 0xC0,
+            Instruction::InstanceOf(..) => 0xC1,
+            Instruction::MonitorEnter => 0xC2,
+            Instruction::MonitorExit => 0xC3,
+            Instruction::MultiANewArray(..) => 0xC5,
+            Instruction::IfNull(..) => 0xC6,
+            Instruction::IfNonNull(..) => 0xC7,
+            Instruction::GotoW(..) => 0xC8,
+            Instruction::JsrW(..) => 0xC9,
+            Instruction::ALoadW(..)
+            | Instruction::AStoreW(..)
+            | Instruction::DLoadW(..)
+            | Instruction::DStoreW(..)
+            | Instruction::FLoadW(..)
+            | Instruction::FStoreW(..)
+            | Instruction::IIncW(..)
+            | Instruction::ILoadW(..)
+            | Instruction::IStoreW(..)
+            | Instruction::LLoadW(..)
+            | Instruction::LStoreW(..)
+            | Instruction::RetW(..) => 0xC4,
+        }
+    }
+
+    /// Returns the mnemonic of the instruction, like `iload_0` or `invokevirtual`.
+    ///
+    /// The `wide` forms return the mnemonic of the instruction they modify (`ILoadW` is `iload`).
+    pub fn mnemonic(&self) -> &'static str {
+        match self {
+            Instruction::ALoadW(..) => MNEMONICS[0x19],
+            Instruction::AStoreW(..) => MNEMONICS[0x3A],
+            Instruction::DLoadW(..) => MNEMONICS[0x18],
+            Instruction::DStoreW(..) => MNEMONICS[0x39],
+            Instruction::FLoadW(..) => MNEMONICS[0x17],
+            Instruction::FStoreW(..) => MNEMONICS[0x38],
+            Instruction::IIncW(..) => MNEMONICS[0x84],
+            Instruction::ILoadW(..) => MNEMONICS[0x15],
+            Instruction::IStoreW(..) => MNEMONICS[0x36],
+            Instruction::LLoadW(..) => MNEMONICS[0x16],
+            Instruction::LStoreW(..) => MNEMONICS[0x37],
+            Instruction::RetW(..) => MNEMONICS[0xA9],
+            _ => MNEMONICS[self.opcode() as usize],
+        }
+    }
+
+    /// Returns the absolute pc a `goto`, `jsr` or `if<cond>` located at `pc` jumps to.
+    /// Switches have several targets and return `None`, see `Attribute::absolute_targets`.
+    pub fn branch_target(&self, pc: u32) -> Option<u32> {
+        match relative_offsets(self).as_slice() {
+            [offset]
+                if !matches!(
+                    self,
+                    Instruction::TableSwitch { .. } | Instruction::LookupSwitch { .. }
+                ) =>
+            {
+                u32::try_from(pc as i64 + *offset as i64).ok()
+            }
+            _ => None,
         }
     }
 }
+
+/// Renders the instruction compactly, without resolving its constants: `iload_2`,
+/// `invokevirtual #12`, or `goto +15` with the branch offsets relative to the instruction.
+impl std::fmt::Display for Instruction {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> Result<(), std::fmt::Error> {
+        let name = self.mnemonic();
+
+        match self {
+            // constants without a compact form
+            Instruction::IConst(value) if self.opcode() >= 0x10 => write!(f, "{name} {value}"),
+            Instruction::LConst(value) if self.opcode() >= 0x10 => write!(f, "{name} {value}"),
+            Instruction::FConst(value) if self.opcode() >= 0x10 => write!(f, "{name} {value}"),
+            Instruction::DConst(value) if self.opcode() >= 0x10 => write!(f, "{name} {value}"),
+            Instruction::ALoad(slot)
+            | Instruction::AStore(slot)
+            | Instruction::DLoad(slot)
+            | Instruction::DStore(slot)
+            | Instruction::FLoad(slot)
+            | Instruction::FStore(slot)
+            | Instruction::ILoad(slot)
+            | Instruction::IStore(slot)
+            | Instruction::LLoad(slot)
+            | Instruction::LStore(slot)
+                if *slot <= 3 =>
+            {
+                write!(f, "{name}")
+            }
+            Instruction::ALoad(slot)
+            | Instruction::AStore(slot)
+            | Instruction::DLoad(slot)
+            | Instruction::DStore(slot)
+            | Instruction::FLoad(slot)
+            | Instruction::FStore(slot)
+            | Instruction::ILoad(slot)
+            | Instruction::IStore(slot)
+            | Instruction::LLoad(slot)
+            | Instruction::LStore(slot)
+            | Instruction::Ret(slot) => write!(f, "{name} {slot}"),
+            Instruction::ALoadW(slot)
+            | Instruction::AStoreW(slot)
+            | Instruction::DLoadW(slot)
+            | Instruction::DStoreW(slot)
+            | Instruction::FLoadW(slot)
+            | Instruction::FStoreW(slot)
+            | Instruction::ILoadW(slot)
+            | Instruction::IStoreW(slot)
+            | Instruction::LLoadW(slot)
+            | Instruction::LStoreW(slot)
+            | Instruction::RetW(slot) => write!(f, "wide {name} {slot}"),
+            Instruction::IInc(slot, count) => write!(f, "{name} {slot}, {count}"),
+            Instruction::IIncW(slot, count) => write!(f, "wide {name} {slot}, {count}"),
+            Instruction::Bipush(value) => write!(f, "{name} {}", *value as i8),
+            Instruction::Sipush(value) => write!(f, "{name} {value}"),
+            Instruction::NewArray(atype) => write!(f, "{name} {}", atype.name()),
+            Instruction::Ldc(id) => write!(f, "{name} #{id}"),
+            Instruction::ANewArray(id)
+            | Instruction::CheckCast(id)
+            | Instruction::GetField(id)
+            | Instruction::GetStatic(id)
+            | Instruction::InstanceOf(id)
+            | Instruction::InvokeDynamic(id)
+            | Instruction::InvokeSpecial(id)
+            | Instruction::InvokeStatic(id)
+            | Instruction::InvokeVirtual(id)
+            | Instruction::Ldc2W(id)
+            | Instruction::LdcW(id)
+            | Instruction::New(id)
+            | Instruction::PutField(id)
+            | Instruction::PutStatic(id) => write!(f, "{name} #{id}"),
+            Instruction::InvokeInterface { index, count } => write!(f, "{name} #{index}, {count}"),
+            Instruction::MultiANewArray(id, dimensions) => write!(f, "{name} #{id}, {dimensions}"),
+            Instruction::TableSwitch {
+                minimum,
+                maximum,
+                jump_targets,
+                default,
+                ..
+            } => {
+                let targets: Vec<_> = jump_targets
+                    .iter()
+                    .map(|offset| format!("{offset:+}"))
+                    .collect();
+                write!(
+                    f,
+                    "{name} {minimum} to {maximum} [{}], default {default:+}",
+                    targets.join(", ")
+                )
+            }
+            Instruction::LookupSwitch { default, pairs, .. } => {
+                let pairs: Vec<_> = pairs
+                    .iter()
+                    .map(|pair| format!("{}: {:+}", pair.value, pair.target))
+                    .collect();
+                write!(f, "{name} [{}], default {default:+}", pairs.join(", "))
+            }
+            _ => match relative_offsets(self).as_slice() {
+                [offset] => write!(f, "{name} {offset:+}"),
+                _ => write!(f, "{name}"),
+            },
+        }
+    }
+}
+
+/// The mnemonic of each opcode, with the compact forms (`iload_0`, `iconst_m1`...).
+#[rustfmt::skip]
+const MNEMONICS: [&str; 0xCA] = [
+    /* 0x00 */ "nop", "aconst_null", "iconst_m1", "iconst_0", "iconst_1", "iconst_2", "iconst_3", "iconst_4",
+    /* 0x08 */ "iconst_5", "lconst_0", "lconst_1", "fconst_0", "fconst_1", "fconst_2", "dconst_0", "dconst_1",
+    /* 0x10 */ "bipush", "sipush", "ldc", "ldc_w", "ldc2_w", "iload", "lload", "fload",
+    /* 0x18 */ "dload", "aload", "iload_0", "iload_1", "iload_2", "iload_3", "lload_0", "lload_1",
+    /* 0x20 */ "lload_2", "lload_3", "fload_0", "fload_1", "fload_2", "fload_3", "dload_0", "dload_1",
+    /* 0x28 */ "dload_2", "dload_3", "aload_0", "aload_1", "aload_2", "aload_3", "iaload", "laload",
+    /* 0x30 */ "faload", "daload", "aaload", "baload", "caload", "saload", "istore", "lstore",
+    /* 0x38 */ "fstore", "dstore", "astore", "istore_0", "istore_1", "istore_2", "istore_3", "lstore_0",
+    /* 0x40 */ "lstore_1", "lstore_2", "lstore_3", "fstore_0", "fstore_1", "fstore_2", "fstore_3", "dstore_0",
+    /* 0x48 */ "dstore_1", "dstore_2", "dstore_3", "astore_0", "astore_1", "astore_2", "astore_3", "iastore",
+    /* 0x50 */ "lastore", "fastore", "dastore", "aastore", "bastore", "castore", "sastore", "pop",
+    /* 0x58 */ "pop2", "dup", "dup_x1", "dup_x2", "dup2", "dup2_x1", "dup2_x2", "swap",
+    /* 0x60 */ "iadd", "ladd", "fadd", "dadd", "isub", "lsub", "fsub", "dsub",
+    /* 0x68 */ "imul", "lmul", "fmul", "dmul", "idiv", "ldiv", "fdiv", "ddiv",
+    /* 0x70 */ "irem", "lrem", "frem", "drem", "ineg", "lneg", "fneg", "dneg",
+    /* 0x78 */ "ishl", "lshl", "ishr", "lshr", "iushr", "lushr", "iand", "land",
+    /* 0x80 */ "ior", "lor", "ixor", "lxor", "iinc", "i2l", "i2f", "i2d",
+    /* 0x88 */ "l2i", "l2f", "l2d", "f2i", "f2l", "f2d", "d2i", "d2l",
+    /* 0x90 */ "d2f", "i2b", "i2c", "i2s", "lcmp", "fcmpl", "fcmpg", "dcmpl",
+    /* 0x98 */ "dcmpg", "ifeq", "ifne", "iflt", "ifge", "ifgt", "ifle", "if_icmpeq",
+    /* 0xA0 */ "if_icmpne", "if_icmplt", "if_icmpge", "if_icmpgt", "if_icmple", "if_acmpeq", "if_acmpne", "goto",
+    /* 0xA8 */ "jsr", "ret", "tableswitch", "lookupswitch", "ireturn", "lreturn", "freturn", "dreturn",
+    /* 0xB0 */ "areturn", "return", "getstatic", "putstatic", "getfield", "putfield", "invokevirtual", "invokespecial",
+    /* 0xB8 */ "invokestatic", "invokeinterface", "invokedynamic", "new", "newarray", "anewarray", "arraylength", "athrow",
+    /* 0xC0 */ "checkcast", "instanceof", "monitorenter", "monitorexit", "wide", "multianewarray", "ifnull", "ifnonnull",
+    /* 0xC8 */ "goto_w", "jsr_w",
+];
@@ -4,10 +4,14 @@ use crate::structs::{
     ModuleRequires, RecordComponent, StackMapFrame, TypeAnnotation,
 };
 
+use crate::analysis::layout::{branch_indices, instruction_offsets};
+use crate::{attribute_names, JVMClass, JavaError};
+
 mod instructions;
 pub use instructions::Instruction;
 
 #[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum AccessFlag {
     /// - class: Declared abstract; must not be instantiated.
     /// - inner class: Marked or implicitly abstract in source.
@@ -75,11 +79,13 @@ pub enum AccessFlag {
 }
 
 #[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum Constant {
     Class {
         name_index: u16,
     },
-    Double(f64),
+    /// Read and written through its bit pattern, so NaN payloads and negative zero are preserved.
+    Double(#[cfg_attr(feature = "serde", serde(with = "crate::serde_bits::f64"))] f64),
     Dynamic {
         bootstrap_method_attr_index: u16,
         name_and_type_index: u16,
@@ -88,7 +94,8 @@ pub enum Constant {
         class_index: u16,
         name_and_type_index: u16,
     },
-    Float(f32),
+    /// Read and written through its bit pattern, so NaN payloads and negative zero are preserved.
+    Float(#[cfg_attr(feature = "serde", serde(with = "crate::serde_bits::f32"))] f32),
     Integer(i32),
     InterfaceMethodref {
         class_index: u16,
@@ -102,7 +109,7 @@ pub enum Constant {
     },
     Long(i64),
     MethodHandle {
-        reference_kind: u8,
+        reference_kind: ReferenceKind,
         reference_index: u16,
     },
     Methodref {
@@ -125,8 +132,111 @@ pub enum Constant {
     String {
         string_index: u16,
     },
-    /// ⚠️ It is using Rust's String type and not the JVM's modified UTF-8. If you have a string that makes that crate panic, open an issue.
+    /// Decoded from the JVM's modified UTF-8.
     Utf8(String),
+    /// A `Utf8` holding an unpaired surrogate, which javac emits for string literals like
+    /// `"\uD800"` but a `String` can't hold. It is kept as its modified UTF-8 bytes, so it is
+    /// stored unchanged, but `get_string` fails on it.
+    Utf8Bytes(Vec<u8>),
+}
+
+/// Floats and doubles are compared by their bit pattern, so a NaN is equal to itself and
+/// classes loaded from the same bytes are always equal.
+impl PartialEq for Constant {
+    fn eq(&self, other: &Self) -> bool {
+        match (self, other) {
+            (Constant::Double(a), Constant::Double(b)) => a.to_bits() == b.to_bits(),
+            (Constant::Float(a), Constant::Float(b)) => a.to_bits() == b.to_bits(),
+            (Constant::Integer(a), Constant::Integer(b)) => a == b,
+            (Constant::Long(a), Constant::Long(b)) => a == b,
+            (Constant::Utf8(a), Constant::Utf8(b)) => a == b,
+            (Constant::Utf8Bytes(a), Constant::Utf8Bytes(b)) => a == b,
+            (Constant::Invalid, Constant::Invalid) => true,
+            (Constant::Class { name_index: a }, Constant::Class { name_index: b })
+            | (Constant::Module { name_index: a }, Constant::Module { name_index: b })
+            | (Constant::Package { name_index: a }, Constant::Package { name_index: b })
+            | (Constant::String { string_index: a }, Constant::String { string_index: b })
+            | (
+                Constant::MethodType {
+                    descriptor_index: a,
+                },
+                Constant::MethodType {
+                    descriptor_index: b,
+                },
+            ) => a == b,
+            (
+                Constant::Dynamic {
+                    bootstrap_method_attr_index: a1,
+                    name_and_type_index: a2,
+                },
+                Constant::Dynamic {
+                    bootstrap_method_attr_index: b1,
+                    name_and_type_index: b2,
+                },
+            )
+            | (
+                Constant::InvokeDynamic {
+                    bootstrap_method_attr_index: a1,
+                    name_and_type_index: a2,
+                },
+                Constant::InvokeDynamic {
+                    bootstrap_method_attr_index: b1,
+                    name_and_type_index: b2,
+                },
+            )
+            | (
+                Constant::Fieldref {
+                    class_index: a1,
+                    name_and_type_index: a2,
+                },
+                Constant::Fieldref {
+                    class_index: b1,
+                    name_and_type_index: b2,
+                },
+            )
+            | (
+                Constant::Methodref {
+                    class_index: a1,
+                    name_and_type_index: a2,
+                },
+                Constant::Methodref {
+                    class_index: b1,
+                    name_and_type_index: b2,
+                },
+            )
+            | (
+                Constant::InterfaceMethodref {
+                    class_index: a1,
+                    name_and_type_index: a2,
+                },
+                Constant::InterfaceMethodref {
+                    class_index: b1,
+                    name_and_type_index: b2,
+                },
+            )
+            | (
+                Constant::NameAndType {
+                    name_index: a1,
+                    descriptor_index: a2,
+                },
+                Constant::NameAndType {
+                    name_index: b1,
+                    descriptor_index: b2,
+                },
+            ) => a1 == b1 && a2 == b2,
+            (
+                Constant::MethodHandle {
+                    reference_kind: a1,
+                    reference_index: a2,
+                },
+                Constant::MethodHandle {
+                    reference_kind: b1,
+                    reference_index: b2,
+                },
+            ) => a1 == b1 && a2 == b2,
+            _ => false,
+        }
+    }
 }
 
 impl std::fmt::Display for Constant {
@@ -171,7 +281,8 @@ impl std::fmt::Display for Constant {
                 reference_index,
             } => write!(
                 f,
-                "Constant::MethodHandle #{reference_kind}, #{reference_index}"
+                "Constant::MethodHandle {}, #{reference_index}",
+                reference_kind.name()
             ),
             Constant::Methodref {
                 class_index,
@@ -194,11 +305,13 @@ impl std::fmt::Display for Constant {
             Constant::Package { name_index } => write!(f, "Constant::Package #{name_index}"),
             Constant::String { string_index } => write!(f, "Constant::String #{string_index}"),
             Constant::Utf8(s) => write!(f, "Constant::Utf8({s})"),
+            Constant::Utf8Bytes(bytes) => write!(f, "Constant::Utf8Bytes({bytes:02x?})"),
         }
     }
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum Attribute {
     AnnotationDefault(ElementValue),
     BootstrapMethods(Vec<BootstrapMethod>),
@@ -262,7 +375,274 @@ pub enum Attribute {
     },
 }
 
-#[derive(Debug, Clone)]
+impl Attribute {
+    /// The name of the attribute, as stored in the constant pool.
+    pub fn name(&self) -> &str {
+        match self {
+            Attribute::AnnotationDefault(..) => attribute_names::ANNOTATION_DEFAULT,
+            Attribute::BootstrapMethods(..) => attribute_names::BOOTSTRAP_METHODS,
+            Attribute::Code { .. } => attribute_names::CODE,
+            Attribute::ConstantValue { .. } => attribute_names::CONSTANT_VALUE,
+            Attribute::Deprecated => attribute_names::DEPRECATED,
+            Attribute::EnclosingMethod { .. } => attribute_names::ENCLOSING_METHOD,
+            Attribute::Exceptions(..) => attribute_names::EXCEPTIONS,
+            Attribute::InnerClasses(..) => attribute_names::INNER_CLASSES,
+            Attribute::LineNumberTable(..) => attribute_names::LINE_NUMBER_TABLE,
+            Attribute::LocalVariableTable(..) => attribute_names::LOCAL_VARIABLE_TABLE,
+            Attribute::LocalVariableTypeTable(..) => attribute_names::LOCAL_VARIABLE_TYPE_TABLE,
+            Attribute::MethodParameters(..) => attribute_names::METHOD_PARAMETERS,
+            Attribute::Module { .. } => attribute_names::MODULE,
+            Attribute::ModuleMainClass(..) => attribute_names::MODULE_MAIN_CLASS,
+            Attribute::ModulePackages(..) => attribute_names::MODULE_PACKAGES,
+            Attribute::NestHost(..) => attribute_names::NEST_HOST,
+            Attribute::NestMembers(..) => attribute_names::NEST_MEMBERS,
+            Attribute::PermittedSubclasses(..) => attribute_names::PERMITTED_SUBCLASSES,
+            Attribute::Record(..) => attribute_names::RECORD,
+            Attribute::RuntimeInvisibleAnnotations(..) => {
+                attribute_names::RUNTIME_INVISIBLE_ANNOTATIONS
+            }
+            Attribute::RuntimeInvisibleParameterAnnotations(..) => {
+                attribute_names::RUNTIME_INVISIBLE_PARAMETER_ANNOTATIONS
+            }
+            Attribute::RuntimeInvisibleTypeAnnotations(..) => {
+                attribute_names::RUNTIME_INVISIBLE_TYPE_ANNOTATIONS
+            }
+            Attribute::RuntimeVisibleAnnotations(..) => {
+                attribute_names::RUNTIME_VISIBLE_ANNOTATIONS
+            }
+            Attribute::RuntimeVisibleParameterAnnotations(..) => {
+                attribute_names::RUNTIME_VISIBLE_PARAMETER_ANNOTATIONS
+            }
+            Attribute::RuntimeVisibleTypeAnnotations(..) => {
+                attribute_names::RUNTIME_VISIBLE_TYPE_ANNOTATIONS
+            }
+            Attribute::Signature { .. } => attribute_names::SIGNATURE,
+            Attribute::SourceDebugExtension { .. } => attribute_names::SOURCE_DEBUG_EXTENSION,
+            Attribute::SourceFile { .. } => attribute_names::SOURCE_FILE,
+            Attribute::StackMapTable(..) => attribute_names::STACK_MAP_TABLE,
+            Attribute::Synthetic => attribute_names::SYNTHETIC,
+            Attribute::Unknown { name, .. } => name,
+        }
+    }
+
+    /// The source line of the instruction at `pc`, from the `LineNumberTable`s of a `Code`
+    /// attribute: the line of the entry with the greatest `start_pc <= pc`. Entries can be in any
+    /// order. `None` if `pc` is before the first entry.
+    pub fn line_for_pc(&self, pc: u16) -> Option<u16> {
+        let Attribute::Code { attributes, .. } = self else {
+            return None;
+        };
+
+        attributes
+            .iter()
+            .filter_map(|attr| match attr {
+                Attribute::LineNumberTable(table) => Some(table),
+                _ => None,
+            })
+            .flatten()
+            .filter(|line| line.start_pc <= pc)
+            .max_by_key(|line| line.start_pc)
+            .map(|line| line.line_number)
+    }
+
+    /// Finds the local variable stored in `slot` at `pc`, using the `LocalVariableTable` of a `Code` attribute.
+    pub fn local_variable_at(&self, slot: u16, pc: u16) -> Option<&LocalVariable> {
+        let Attribute::Code { attributes, .. } = self else {
+            return None;
+        };
+
+        attributes
+            .iter()
+            .filter_map(|attr| match attr {
+                Attribute::LocalVariableTable(table) => Some(table),
+                _ => None,
+            })
+            .flatten()
+            .find(|local| {
+                local.index == slot
+                    && local.start_pc <= pc
+                    && (pc as u32) < local.start_pc as u32 + local.length as u32
+            })
+    }
+
+    /// The instructions of a `Code` attribute, empty for other attributes.
+    pub fn instructions(&self) -> &[Instruction] {
+        match self {
+            Attribute::Code { code, .. } => code,
+            _ => &[],
+        }
+    }
+
+    /// The `max_stack` of a `Code` attribute.
+    pub fn max_stack(&self) -> Option<u16> {
+        match self {
+            Attribute::Code { max_stack, .. } => Some(*max_stack),
+            _ => None,
+        }
+    }
+
+    /// The `max_locals` of a `Code` attribute.
+    pub fn max_locals(&self) -> Option<u16> {
+        match self {
+            Attribute::Code { max_locals, .. } => Some(*max_locals),
+            _ => None,
+        }
+    }
+
+    /// Resolves the branches of a `Code` attribute: for each instruction, the indices of the
+    /// instructions it can jump to (default first for switches). Other attributes have no code.
+    pub fn absolute_targets(&self) -> Result<Vec<Vec<usize>>, JavaError> {
+        let Attribute::Code { code, .. } = self else {
+            return Ok(vec![]);
+        };
+
+        branch_indices(code)
+    }
+
+    /// The pc of each instruction of a `Code` attribute, i.e. its offset in the code array,
+    /// counting the padding of the switches. Other attributes have no code.
+    pub fn instruction_offsets(&self) -> Vec<u32> {
+        let Attribute::Code { code, .. } = self else {
+            return vec![];
+        };
+
+        instruction_offsets(code)
+    }
+}
+
+/// Renders the name of the attribute and a summary of its content, without resolving its
+/// constants: `Code[max_stack=2, max_locals=1, 7 instructions]`, `SourceFile[#12]`.
+impl std::fmt::Display for Attribute {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> Result<(), std::fmt::Error> {
+        let name = self.name();
+
+        match self {
+            Attribute::Code {
+                code,
+                max_stack,
+                max_locals,
+                ..
+            } => write!(
+                f,
+                "{name}[max_stack={max_stack}, max_locals={max_locals}, {}]",
+                counted(code.len(), "instruction", "instructions")
+            ),
+            Attribute::ConstantValue {
+                constantvalue_index: id,
+            }
+            | Attribute::ModuleMainClass(id)
+            | Attribute::NestHost(id)
+            | Attribute::Signature {
+                signature_index: id,
+            }
+            | Attribute::SourceFile {
+                sourcefile_index: id,
+            } => write!(f, "{name}[#{id}]"),
+            Attribute::EnclosingMethod {
+                class_index,
+                method_index,
+            } => write!(f, "{name}[#{class_index}, #{method_index}]"),
+            Attribute::Module {
+                module_name_index, ..
+            } => write!(f, "{name}[#{module_name_index}]"),
+            Attribute::BootstrapMethods(methods) => {
+                write!(f, "{name}[{}]", counted(methods.len(), "method", "methods"))
+            }
+            Attribute::Exceptions(classes)
+            | Attribute::NestMembers(classes)
+            | Attribute::PermittedSubclasses(classes) => {
+                write!(f, "{name}[{}]", counted(classes.len(), "class", "classes"))
+            }
+            Attribute::InnerClasses(classes) => {
+                write!(f, "{name}[{}]", counted(classes.len(), "class", "classes"))
+            }
+            Attribute::LineNumberTable(lines) => {
+                write!(f, "{name}[{}]", counted(lines.len(), "line", "lines"))
+            }
+            Attribute::LocalVariableTable(variables) => {
+                write!(
+                    f,
+                    "{name}[{}]",
+                    counted(variables.len(), "variable", "variables")
+                )
+            }
+            Attribute::LocalVariableTypeTable(variables) => {
+                write!(
+                    f,
+                    "{name}[{}]",
+                    counted(variables.len(), "variable", "variables")
+                )
+            }
+            Attribute::MethodParameters(parameters) => {
+                write!(
+                    f,
+                    "{name}[{}]",
+                    counted(parameters.len(), "parameter", "parameters")
+                )
+            }
+            Attribute::RuntimeInvisibleParameterAnnotations(parameters)
+            | Attribute::RuntimeVisibleParameterAnnotations(parameters) => {
+                write!(
+                    f,
+                    "{name}[{}]",
+                    counted(parameters.len(), "parameter", "parameters")
+                )
+            }
+            Attribute::ModulePackages(packages) => {
+                write!(
+                    f,
+                    "{name}[{}]",
+                    counted(packages.len(), "package", "packages")
+                )
+            }
+            Attribute::Record(components) => {
+                write!(
+                    f,
+                    "{name}[{}]",
+                    counted(components.len(), "component", "components")
+                )
+            }
+            Attribute::RuntimeInvisibleAnnotations(annotations)
+            | Attribute::RuntimeVisibleAnnotations(annotations) => {
+                write!(
+                    f,
+                    "{name}[{}]",
+                    counted(annotations.len(), "annotation", "annotations")
+                )
+            }
+            Attribute::RuntimeInvisibleTypeAnnotations(annotations)
+            | Attribute::RuntimeVisibleTypeAnnotations(annotations) => {
+                write!(
+                    f,
+                    "{name}[{}]",
+                    counted(annotations.len(), "annotation", "annotations")
+                )
+            }
+            Attribute::StackMapTable(frames) => {
+                write!(f, "{name}[{}]", counted(frames.len(), "frame", "frames"))
+            }
+            Attribute::SourceDebugExtension {
+                debug_extension: data,
+            }
+            | Attribute::Unknown { data, .. } => {
+                write!(f, "{name}[{}]", counted(data.len(), "byte", "bytes"))
+            }
+            Attribute::AnnotationDefault(..) | Attribute::Deprecated | Attribute::Synthetic => {
+                write!(f, "{name}")
+            }
+        }
+    }
+}
+
+fn counted(count: usize, one: &str, many: &str) -> String {
+    match count {
+        1 => format!("1 {one}"),
+        _ => format!("{count} {many}"),
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum StackMapFrameType {
     AppendFrame(u8),
     ChopFrame(u8),
@@ -273,7 +653,228 @@ pub enum StackMapFrameType {
     SameLocals1StackItemFrameExtended,
 }
 
-#[derive(Debug, Clone)]
+/// What a `Constant::MethodHandle` does with the member it refers to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum ReferenceKind {
+    GetField,
+    GetStatic,
+    PutField,
+    PutStatic,
+    InvokeVirtual,
+    InvokeStatic,
+    InvokeSpecial,
+    NewInvokeSpecial,
+    InvokeInterface,
+}
+
+impl ReferenceKind {
+    /// Decodes the `reference_kind` of a method handle, `None` if it isn't between 1 and 9.
+    pub fn from_kind(kind: u8) -> Option<Self> {
+        match kind {
+            1 => Some(ReferenceKind::GetField),
+            2 => Some(ReferenceKind::GetStatic),
+            3 => Some(ReferenceKind::PutField),
+            4 => Some(ReferenceKind::PutStatic),
+            5 => Some(ReferenceKind::InvokeVirtual),
+            6 => Some(ReferenceKind::InvokeStatic),
+            7 => Some(ReferenceKind::InvokeSpecial),
+            8 => Some(ReferenceKind::NewInvokeSpecial),
+            9 => Some(ReferenceKind::InvokeInterface),
+            _ => None,
+        }
+    }
+
+    /// The `reference_kind` the kind is written as.
+    pub fn kind(&self) -> u8 {
+        match self {
+            ReferenceKind::GetField => 1,
+            ReferenceKind::GetStatic => 2,
+            ReferenceKind::PutField => 3,
+            ReferenceKind::PutStatic => 4,
+            ReferenceKind::InvokeVirtual => 5,
+            ReferenceKind::InvokeStatic => 6,
+            ReferenceKind::InvokeSpecial => 7,
+            ReferenceKind::NewInvokeSpecial => 8,
+            ReferenceKind::InvokeInterface => 9,
+        }
+    }
+
+    /// The name used by the JVM specification, e.g. `REF_invokeStatic`.
+    pub fn name(&self) -> &'static str {
+        match self {
+            ReferenceKind::GetField => "REF_getField",
+            ReferenceKind::GetStatic => "REF_getStatic",
+            ReferenceKind::PutField => "REF_putField",
+            ReferenceKind::PutStatic => "REF_putStatic",
+            ReferenceKind::InvokeVirtual => "REF_invokeVirtual",
+            ReferenceKind::InvokeStatic => "REF_invokeStatic",
+            ReferenceKind::InvokeSpecial => "REF_invokeSpecial",
+            ReferenceKind::NewInvokeSpecial => "REF_newInvokeSpecial",
+            ReferenceKind::InvokeInterface => "REF_invokeInterface",
+        }
+    }
+}
+
+/// The element type of a `newarray`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum ArrayType {
+    Boolean,
+    Char,
+    Float,
+    Double,
+    Byte,
+    Short,
+    Int,
+    Long,
+}
+
+impl ArrayType {
+    /// Decodes the `atype` operand, `None` if it isn't one of the 8 primitive types.
+    pub fn from_atype(atype: u8) -> Option<Self> {
+        match atype {
+            4 => Some(ArrayType::Boolean),
+            5 => Some(ArrayType::Char),
+            6 => Some(ArrayType::Float),
+            7 => Some(ArrayType::Double),
+            8 => Some(ArrayType::Byte),
+            9 => Some(ArrayType::Short),
+            10 => Some(ArrayType::Int),
+            11 => Some(ArrayType::Long),
+            _ => None,
+        }
+    }
+
+    /// The `atype` operand the type is written as.
+    pub fn atype(&self) -> u8 {
+        match self {
+            ArrayType::Boolean => 4,
+            ArrayType::Char => 5,
+            ArrayType::Float => 6,
+            ArrayType::Double => 7,
+            ArrayType::Byte => 8,
+            ArrayType::Short => 9,
+            ArrayType::Int => 10,
+            ArrayType::Long => 11,
+        }
+    }
+
+    /// The Java name of the type, e.g. `int`.
+    pub fn name(&self) -> &'static str {
+        match self {
+            ArrayType::Boolean => "boolean",
+            ArrayType::Char => "char",
+            ArrayType::Float => "float",
+            ArrayType::Double => "double",
+            ArrayType::Byte => "byte",
+            ArrayType::Short => "short",
+            ArrayType::Int => "int",
+            ArrayType::Long => "long",
+        }
+    }
+}
+
+/// A Java release, as identified by the major version of a class file.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum ClassVersion {
+    Java1_1,
+    Java1_2,
+    Java1_3,
+    Java1_4,
+    Java5,
+    Java6,
+    Java7,
+    Java8,
+    Java9,
+    Java10,
+    Java11,
+    Java12,
+    Java13,
+    Java14,
+    Java15,
+    Java16,
+    Java17,
+    Java18,
+    Java19,
+    Java20,
+    Java21,
+    /// Any other version, as `(major, minor)`.
+    Unknown(u16, u16),
+}
+
+impl ClassVersion {
+    /// Identifies the release from the major version. The minor version only matters for `Unknown`.
+    pub fn from_version(major: u16, minor: u16) -> Self {
+        match major {
+            45 => ClassVersion::Java1_1,
+            46 => ClassVersion::Java1_2,
+            47 => ClassVersion::Java1_3,
+            48 => ClassVersion::Java1_4,
+            49 => ClassVersion::Java5,
+            50 => ClassVersion::Java6,
+            51 => ClassVersion::Java7,
+            52 => ClassVersion::Java8,
+            53 => ClassVersion::Java9,
+            54 => ClassVersion::Java10,
+            55 => ClassVersion::Java11,
+            56 => ClassVersion::Java12,
+            57 => ClassVersion::Java13,
+            58 => ClassVersion::Java14,
+            59 => ClassVersion::Java15,
+            60 => ClassVersion::Java16,
+            61 => ClassVersion::Java17,
+            62 => ClassVersion::Java18,
+            63 => ClassVersion::Java19,
+            64 => ClassVersion::Java20,
+            65 => ClassVersion::Java21,
+            _ => ClassVersion::Unknown(major, minor),
+        }
+    }
+
+    /// The `(major, minor)` version written by the compiler of the release.
+    pub fn version(&self) -> (u16, u16) {
+        match self {
+            ClassVersion::Java1_1 => (45, 3),
+            ClassVersion::Java1_2 => (46, 0),
+            ClassVersion::Java1_3 => (47, 0),
+            ClassVersion::Java1_4 => (48, 0),
+            ClassVersion::Java5 => (49, 0),
+            ClassVersion::Java6 => (50, 0),
+            ClassVersion::Java7 => (51, 0),
+            ClassVersion::Java8 => (52, 0),
+            ClassVersion::Java9 => (53, 0),
+            ClassVersion::Java10 => (54, 0),
+            ClassVersion::Java11 => (55, 0),
+            ClassVersion::Java12 => (56, 0),
+            ClassVersion::Java13 => (57, 0),
+            ClassVersion::Java14 => (58, 0),
+            ClassVersion::Java15 => (59, 0),
+            ClassVersion::Java16 => (60, 0),
+            ClassVersion::Java17 => (61, 0),
+            ClassVersion::Java18 => (62, 0),
+            ClassVersion::Java19 => (63, 0),
+            ClassVersion::Java20 => (64, 0),
+            ClassVersion::Java21 => (65, 0),
+            ClassVersion::Unknown(major, minor) => (*major, *minor),
+        }
+    }
+}
+
+/// The value of a constant field. `boolean`, `byte`, `char` and `short` fields use `Int`.
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum ConstValue {
+    Int(i32),
+    Long(i64),
+    Float(#[cfg_attr(feature = "serde", serde(with = "crate::serde_bits::f32"))] f32),
+    Double(#[cfg_attr(feature = "serde", serde(with = "crate::serde_bits::f64"))] f64),
+    String(String),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum VerificationType {
     Double,
     Float,
@@ -286,7 +887,8 @@ pub enum VerificationType {
     UninitializedThis,
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum ElementValue {
     AnnotationValue(Annotation),
     ArrayValue(Vec<ElementValue>),
@@ -301,7 +903,121 @@ pub enum ElementValue {
     },
 }
 
-#[derive(Debug, Clone)]
+impl ElementValue {
+    /// Builds an `int[]` value, adding the integers to the constant pool of `jvm`.
+    pub fn int_array(jvm: &mut JVMClass, values: &[i32]) -> ElementValue {
+        ElementValue::ArrayValue(
+            values
+                .iter()
+                .map(|value| ElementValue::ConstValueIndex {
+                    tag: b'I',
+                    const_value_index: jvm.get_or_add_integer_index(*value),
+                })
+                .collect(),
+        )
+    }
+
+    /// Builds a `String[]` value, adding the strings to the constant pool of `jvm`.
+    pub fn string_array(jvm: &mut JVMClass, values: &[&str]) -> ElementValue {
+        ElementValue::ArrayValue(
+            values
+                .iter()
+                .map(|value| ElementValue::ConstValueIndex {
+                    tag: b's',
+                    const_value_index: jvm.get_or_add_utf8_index(value),
+                })
+                .collect(),
+        )
+    }
+
+    pub fn nested(annotation: Annotation) -> ElementValue {
+        ElementValue::AnnotationValue(annotation)
+    }
+
+    /// Replaces the constant pool indices of the value with what they point to in `jvm`.
+    pub fn resolve(&self, jvm: &JVMClass) -> Result<ResolvedElementValue, JavaError> {
+        let resolved = match self {
+            ElementValue::AnnotationValue(annotation) => ResolvedElementValue::Annotation {
+                type_descriptor: jvm.get_string(annotation.type_index)?.into(),
+                values: annotation
+                    .element_value_pairs
+                    .iter()
+                    .map(|pair| {
+                        let name = jvm.get_string(pair.element_name_index)?;
+                        Ok((name.into(), pair.value.resolve(jvm)?))
+                    })
+                    .collect::<Result<_, JavaError>>()?,
+            },
+            ElementValue::ArrayValue(values) => ResolvedElementValue::Array(
+                values
+                    .iter()
+                    .map(|value| value.resolve(jvm))
+                    .collect::<Result<_, _>>()?,
+            ),
+            ElementValue::ClassInfoIndex(class_info_index) => {
+                ResolvedElementValue::Class(jvm.get_string(*class_info_index)?.into())
+            }
+            ElementValue::ConstValueIndex {
+                tag,
+                const_value_index,
+            } => {
+                let id = *const_value_index;
+                match tag {
+                    b'B' => ResolvedElementValue::Byte(jvm.get_integer(id)? as i8),
+                    b'C' => ResolvedElementValue::Char(jvm.get_integer(id)? as u16),
+                    b'D' => ResolvedElementValue::Double(jvm.get_double(id)?),
+                    b'F' => ResolvedElementValue::Float(jvm.get_float(id)?),
+                    b'I' => ResolvedElementValue::Int(jvm.get_integer(id)?),
+                    b'J' => ResolvedElementValue::Long(jvm.get_long(id)?),
+                    b'S' => ResolvedElementValue::Short(jvm.get_integer(id)? as i16),
+                    b'Z' => ResolvedElementValue::Boolean(jvm.get_integer(id)? != 0),
+                    b's' => ResolvedElementValue::String(jvm.get_string(id)?.into()),
+                    _ => return Err(JavaError::UnknownElementValueTag(*tag)),
+                }
+            }
+            ElementValue::EnumConstValue {
+                type_name_index,
+                const_name_index,
+            } => ResolvedElementValue::Enum {
+                type_descriptor: jvm.get_string(*type_name_index)?.into(),
+                constant_name: jvm.get_string(*const_name_index)?.into(),
+            },
+        };
+
+        Ok(resolved)
+    }
+}
+
+/// An `ElementValue` with its constants resolved, see `ElementValue::resolve`. Types are field
+/// descriptors, e.g. `Ljava/lang/annotation/RetentionPolicy;`.
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum ResolvedElementValue {
+    Boolean(bool),
+    Byte(i8),
+    /// A UTF-16 code unit, like a Java `char`.
+    Char(u16),
+    Short(i16),
+    Int(i32),
+    Long(i64),
+    Float(#[cfg_attr(feature = "serde", serde(with = "crate::serde_bits::f32"))] f32),
+    Double(#[cfg_attr(feature = "serde", serde(with = "crate::serde_bits::f64"))] f64),
+    String(String),
+    Enum {
+        type_descriptor: String,
+        constant_name: String,
+    },
+    /// The return descriptor of the class, e.g. `Ljava/lang/String;`, `I` or `V`.
+    Class(String),
+    Annotation {
+        type_descriptor: String,
+        values: Vec<(String, ResolvedElementValue)>,
+    },
+    Array(Vec<ResolvedElementValue>),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum TargetInfo {
     TypeParameter {
         target_type: u8,
@@ -1,10 +1,87 @@
 use std::fmt::Formatter;
+use std::io;
 
 #[derive(Debug)]
 pub enum JavaError {
     ConstantTypeError(String),
     InvalidConstantId(u16),
     StringNotFound,
+    SelfInheritance(String),
+    DuplicateInterface(String),
+    BootstrapMethodNotFound(u16),
+    InvalidBranchTarget(u32),
+    BranchOutOfRange(u32),
+    InvalidModifiedUtf8(usize),
+    InvalidDescriptor(String),
+    InvalidSignature(String),
+    StackOverflow(u32),
+    StackUnderflow(u32),
+    InvalidLabel(String),
+    MissingSuperClass(String),
+    InvalidModuleInfo(String),
+    InvalidPc(u32),
+    InvalidLocalSlot(u32),
+    InvalidStackMapFrame(u32),
+    UnsupportedVersion(u16, u16),
+    InvalidAccessFlags(String),
+    DuplicateAttribute(String),
+    MissingCode(String),
+    UnexpectedCode(String),
+    UnknownOpcode {
+        opcode: u8,
+        offset: u32,
+    },
+    /// The bytes `invokeinterface` and `invokedynamic` reserve after their operands aren't 0.
+    NonZeroPadding {
+        opcode: u8,
+        offset: u32,
+    },
+    UnknownConstantTag(u8),
+    UnknownElementValueTag(u8),
+    UnknownVerificationTypeTag(u8),
+    UnknownTargetType(u8),
+    UnknownFrameType(u8),
+    InvalidArrayType {
+        atype: u8,
+        offset: u32,
+    },
+    InvalidReferenceKind(u8),
+    MissingAttributeName(String),
+    AttributeLengthMismatch {
+        name: String,
+        declared: u32,
+        read: u32,
+    },
+    MaxStackTooSmall {
+        declared: u16,
+        needed: u16,
+    },
+    MaxLocalsTooSmall {
+        declared: u16,
+        needed: u16,
+    },
+    InvalidMagic(u32),
+    MissingBootstrapMethods,
+    /// The class file ends in the middle of `section`, e.g. `method attributes`.
+    UnexpectedEof {
+        section: &'static str,
+        source: io::Error,
+    },
+    TooManyEntries {
+        section: &'static str,
+        count: usize,
+    },
+    ClassNameMismatch {
+        expected: String,
+        declared: String,
+    },
+    /// The class declares a nest host and has nest members.
+    InconsistentNest,
+    /// The content of the attribute `name` is longer than the `u32` its length is stored in.
+    AttributeTooLarge {
+        name: String,
+        len: u64,
+    },
 }
 
 impl std::fmt::Display for JavaError {
@@ -13,8 +90,129 @@ impl std::fmt::Display for JavaError {
             JavaError::ConstantTypeError(message) => write!(f, "{}", message),
             JavaError::InvalidConstantId(id) => write!(f, "Invalid constant #{id}"),
             JavaError::StringNotFound => write!(f, "String not found"),
+            JavaError::SelfInheritance(name) => write!(f, "Class {name} cannot extend itself"),
+            JavaError::DuplicateInterface(name) => {
+                write!(f, "Interface {name} is implemented more than once")
+            }
+            JavaError::BootstrapMethodNotFound(id) => write!(f, "Bootstrap method #{id} not found"),
+            JavaError::InvalidBranchTarget(pc) => {
+                write!(f, "Branch at pc {pc} doesn't target an instruction")
+            }
+            JavaError::BranchOutOfRange(pc) => {
+                write!(f, "Branch at pc {pc} is too far for a 16-bit offset")
+            }
+            JavaError::InvalidModifiedUtf8(offset) => {
+                write!(f, "Invalid modified UTF-8 at byte {offset}")
+            }
+            JavaError::InvalidDescriptor(descriptor) => {
+                write!(f, "Invalid descriptor {descriptor}")
+            }
+            JavaError::InvalidSignature(signature) => {
+                write!(f, "Invalid signature {signature}")
+            }
+            JavaError::StackOverflow(pc) => write!(f, "Stack exceeds max_stack at pc {pc}"),
+            JavaError::StackUnderflow(pc) => write!(f, "Stack underflow at pc {pc}"),
+            JavaError::InvalidLabel(label) => write!(f, "Label {label} can't be resolved"),
+            JavaError::MissingSuperClass(name) => write!(f, "Class {name} has no super class"),
+            JavaError::InvalidModuleInfo(reason) => write!(f, "Invalid module-info: {reason}"),
+            JavaError::InvalidPc(pc) => write!(f, "No instruction at pc {pc}"),
+            JavaError::InvalidLocalSlot(slot) => write!(f, "Local variable slot {slot} is too big"),
+            JavaError::InvalidStackMapFrame(pc) => write!(f, "Invalid stack map frame at pc {pc}"),
+            JavaError::UnsupportedVersion(major, minor) => {
+                write!(f, "Unsupported class file version {major}.{minor}")
+            }
+            JavaError::InvalidAccessFlags(reason) => write!(f, "Invalid access flags: {reason}"),
+            JavaError::DuplicateAttribute(name) => {
+                write!(f, "Attribute {name} appears more than once")
+            }
+            JavaError::MissingCode(name) => write!(f, "Method {name} has no Code attribute"),
+            JavaError::UnexpectedCode(name) => {
+                write!(f, "Abstract or native method {name} has a Code attribute")
+            }
+            JavaError::UnknownOpcode { opcode, offset } => {
+                write!(f, "Unknown opcode {opcode:#X} at pc {offset}")
+            }
+            JavaError::NonZeroPadding { opcode, offset } => {
+                write!(
+                    f,
+                    "Opcode {opcode:#X} at pc {offset} has non-zero reserved bytes"
+                )
+            }
+            JavaError::UnknownConstantTag(tag) => write!(f, "Unknown constant tag {tag}"),
+            JavaError::UnknownElementValueTag(tag) => {
+                write!(f, "Unknown annotation element value tag {tag}")
+            }
+            JavaError::UnknownVerificationTypeTag(tag) => {
+                write!(f, "Unknown verification type tag {tag}")
+            }
+            JavaError::UnknownTargetType(target_type) => {
+                write!(f, "Unknown type annotation target type {target_type:#X}")
+            }
+            JavaError::UnknownFrameType(frame_type) => {
+                write!(f, "Unknown stack map frame type {frame_type}")
+            }
+            JavaError::InvalidArrayType { atype, offset } => {
+                write!(f, "Invalid newarray type {atype} at pc {offset}")
+            }
+            JavaError::InvalidReferenceKind(kind) => {
+                write!(f, "Invalid method handle reference kind {kind}")
+            }
+            JavaError::MissingAttributeName(name) => {
+                write!(
+                    f,
+                    "The name of the attribute {name} is not in the constant pool"
+                )
+            }
+            JavaError::AttributeLengthMismatch {
+                name,
+                declared,
+                read,
+            } => write!(
+                f,
+                "Attribute {name} is {declared} bytes long, but {read} were read"
+            ),
+            JavaError::MaxStackTooSmall { declared, needed } => {
+                write!(f, "max_stack is {declared}, but the code needs {needed}")
+            }
+            JavaError::MaxLocalsTooSmall { declared, needed } => {
+                write!(f, "max_locals is {declared}, but the code needs {needed}")
+            }
+            JavaError::InvalidMagic(magic) => {
+                write!(f, "Invalid magic number 0x{magic:08X}, not a class file")
+            }
+            JavaError::MissingBootstrapMethods => {
+                write!(
+                    f,
+                    "The class has dynamic constants but no BootstrapMethods attribute"
+                )
+            }
+            JavaError::UnexpectedEof { section, .. } => {
+                write!(f, "Unexpected EOF while reading {section}")
+            }
+            JavaError::TooManyEntries { section, count } => {
+                write!(f, "Too many entries in {section} ({count})")
+            }
+            JavaError::ClassNameMismatch { expected, declared } => {
+                write!(
+                    f,
+                    "Expected the class {expected}, but it declares {declared}"
+                )
+            }
+            JavaError::InconsistentNest => {
+                write!(f, "A nest host can't be a member of another nest")
+            }
+            JavaError::AttributeTooLarge { name, len } => {
+                write!(f, "Attribute {name} is too large ({len} bytes)")
+            }
         }
     }
 }
 
-impl std::error::Error for JavaError {}
+impl std::error::Error for JavaError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            JavaError::UnexpectedEof { source, .. } => Some(source),
+            _ => None,
+        }
+    }
+}
@@ -0,0 +1,307 @@
+//! Visits the constant pool indices stored in a class, to follow or rewrite them.
+//!
+//! Optional indices, where 0 means "none", are visited like the others. The content of the
+//! `Attribute::Unknown`s can't be interpreted, so their indices are never visited.
+
+use crate::{
+    Annotation, Attribute, Constant, ElementValue, Instruction, JVMClass, VerificationType,
+};
+
+/// Calls `f` on every index of the class, except the ones stored in its constants.
+pub(crate) fn visit_class_indices<F: FnMut(&mut u16)>(jvm: &mut JVMClass, f: &mut F) {
+    f(&mut jvm.this_class);
+    f(&mut jvm.super_class);
+    for interface in &mut jvm.interfaces {
+        f(interface);
+    }
+
+    let members = jvm
+        .fields
+        .iter_mut()
+        .map(|field| &mut field.0)
+        .chain(jvm.methods.iter_mut().map(|method| &mut method.0));
+    for member in members {
+        f(&mut member.name);
+        f(&mut member.descriptor);
+        visit_attribute_indices(&mut member.attributes, f);
+    }
+
+    visit_attribute_indices(&mut jvm.attributes, f);
+}
+
+/// Calls `f` on every index referred to by a constant.
+pub(crate) fn visit_constant_indices<F: FnMut(&mut u16)>(constant: &mut Constant, f: &mut F) {
+    match constant {
+        Constant::Class { name_index }
+        | Constant::Module { name_index }
+        | Constant::Package { name_index } => f(name_index),
+        Constant::String { string_index } => f(string_index),
+        Constant::MethodType { descriptor_index } => f(descriptor_index),
+        Constant::NameAndType {
+            name_index,
+            descriptor_index,
+        } => {
+            f(name_index);
+            f(descriptor_index);
+        }
+        Constant::Fieldref {
+            class_index,
+            name_and_type_index,
+        }
+        | Constant::Methodref {
+            class_index,
+            name_and_type_index,
+        }
+        | Constant::InterfaceMethodref {
+            class_index,
+            name_and_type_index,
+        } => {
+            f(class_index);
+            f(name_and_type_index);
+        }
+        // the bootstrap method index refers to the BootstrapMethods attribute, not to the pool
+        Constant::Dynamic {
+            name_and_type_index,
+            ..
+        }
+        | Constant::InvokeDynamic {
+            name_and_type_index,
+            ..
+        } => f(name_and_type_index),
+        Constant::MethodHandle {
+            reference_index, ..
+        } => f(reference_index),
+        Constant::Double(..)
+        | Constant::Float(..)
+        | Constant::Integer(..)
+        | Constant::Invalid
+        | Constant::Long(..)
+        | Constant::Utf8(..)
+        | Constant::Utf8Bytes(..) => {}
+    }
+}
+
+/// Calls `f` on every index of the attributes, including the nested ones and the code.
+pub(crate) fn visit_attribute_indices<F: FnMut(&mut u16)>(attributes: &mut [Attribute], f: &mut F) {
+    for attribute in attributes {
+        match attribute {
+            Attribute::AnnotationDefault(value) => visit_element_value(value, f),
+            Attribute::BootstrapMethods(bootstrap_methods) => {
+                for bootstrap_method in bootstrap_methods {
+                    f(&mut bootstrap_method.bootstrap_method_ref);
+                    for argument in &mut bootstrap_method.bootstrap_arguments {
+                        f(argument);
+                    }
+                }
+            }
+            Attribute::Code {
+                code,
+                exception_table,
+                attributes,
+                ..
+            } => {
+                for inst in code {
+                    visit_instruction(inst, f);
+                }
+                for entry in exception_table {
+                    f(&mut entry.catch_type);
+                }
+                visit_attribute_indices(attributes, f);
+            }
+            Attribute::ConstantValue {
+                constantvalue_index,
+            } => f(constantvalue_index),
+            Attribute::EnclosingMethod {
+                class_index,
+                method_index,
+            } => {
+                f(class_index);
+                f(method_index);
+            }
+            Attribute::Exceptions(classes)
+            | Attribute::NestMembers(classes)
+            | Attribute::PermittedSubclasses(classes)
+            | Attribute::ModulePackages(classes) => {
+                for class in classes {
+                    f(class);
+                }
+            }
+            Attribute::NestHost(class) | Attribute::ModuleMainClass(class) => f(class),
+            Attribute::InnerClasses(inner_classes) => {
+                for inner_class in inner_classes {
+                    f(&mut inner_class.inner_class_info_index);
+                    f(&mut inner_class.outer_class_info_index);
+                    f(&mut inner_class.inner_name_index);
+                }
+            }
+            Attribute::LocalVariableTable(locals) => {
+                for local in locals {
+                    f(&mut local.name_index);
+                    f(&mut local.descriptor_index);
+                }
+            }
+            Attribute::LocalVariableTypeTable(locals) => {
+                for local in locals {
+                    f(&mut local.name_index);
+                    f(&mut local.signature_index);
+                }
+            }
+            Attribute::MethodParameters(parameters) => {
+                for parameter in parameters {
+                    f(&mut parameter.name_index);
+                }
+            }
+            Attribute::Module {
+                module_name_index,
+                module_version_index,
+                requires,
+                exports,
+                opens,
+                uses,
+                provides,
+                ..
+            } => {
+                f(module_name_index);
+                f(module_version_index);
+                for require in requires {
+                    f(&mut require.requires_index);
+                    f(&mut require.requires_version_index);
+                }
+                for export in exports {
+                    f(&mut export.exports_index);
+                    for module in &mut export.exports_to_index {
+                        f(module);
+                    }
+                }
+                for open in opens {
+                    f(&mut open.opens_index);
+                    for module in &mut open.opens_to_index {
+                        f(module);
+                    }
+                }
+                for service in uses {
+                    f(service);
+                }
+                for provide in provides {
+                    f(&mut provide.provides_index);
+                    for implementation in &mut provide.provides_with_index {
+                        f(implementation);
+                    }
+                }
+            }
+            Attribute::Record(components) => {
+                for component in components {
+                    f(&mut component.name_index);
+                    f(&mut component.descriptor_index);
+                    visit_attribute_indices(&mut component.attributes, f);
+                }
+            }
+            Attribute::RuntimeInvisibleAnnotations(annotations)
+            | Attribute::RuntimeVisibleAnnotations(annotations) => {
+                for annotation in annotations {
+                    visit_annotation(annotation, f);
+                }
+            }
+            Attribute::RuntimeInvisibleParameterAnnotations(parameters)
+            | Attribute::RuntimeVisibleParameterAnnotations(parameters) => {
+                for annotation in parameters.iter_mut().flatten() {
+                    visit_annotation(annotation, f);
+                }
+            }
+            Attribute::RuntimeInvisibleTypeAnnotations(annotations)
+            | Attribute::RuntimeVisibleTypeAnnotations(annotations) => {
+                for type_annotation in annotations {
+                    visit_annotation(&mut type_annotation.annotation, f);
+                }
+            }
+            Attribute::Signature { signature_index } => f(signature_index),
+            Attribute::SourceFile { sourcefile_index } => f(sourcefile_index),
+            Attribute::StackMapTable(frames) => {
+                for verification_type in frames
+                    .iter_mut()
+                    .flat_map(|frame| frame.locals.iter_mut().chain(&mut frame.stack))
+                {
+                    if let VerificationType::Object { cpool_index } = verification_type {
+                        f(cpool_index);
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+}
+
+/// `ldc` only has a byte for its index: `f` must not move it past 255.
+fn visit_instruction<F: FnMut(&mut u16)>(inst: &mut Instruction, f: &mut F) {
+    match inst {
+        Instruction::ANewArray(id)
+        | Instruction::CheckCast(id)
+        | Instruction::GetField(id)
+        | Instruction::GetStatic(id)
+        | Instruction::InstanceOf(id)
+        | Instruction::InvokeDynamic(id)
+        | Instruction::InvokeSpecial(id)
+        | Instruction::InvokeStatic(id)
+        | Instruction::InvokeVirtual(id)
+        | Instruction::Ldc2W(id)
+        | Instruction::LdcW(id)
+        | Instruction::MultiANewArray(id, _)
+        | Instruction::New(id)
+        | Instruction::PutField(id)
+        | Instruction::PutStatic(id)
+        | Instruction::InvokeInterface { index: id, .. } => f(id),
+        Instruction::Ldc(id) => {
+            let mut wide = *id as u16;
+            f(&mut wide);
+            *id = wide as u8;
+        }
+        _ => {}
+    }
+}
+
+fn visit_annotation<F: FnMut(&mut u16)>(annotation: &mut Annotation, f: &mut F) {
+    f(&mut annotation.type_index);
+    for pair in &mut annotation.element_value_pairs {
+        f(&mut pair.element_name_index);
+        visit_element_value(&mut pair.value, f);
+    }
+}
+
+fn visit_element_value<F: FnMut(&mut u16)>(value: &mut ElementValue, f: &mut F) {
+    match value {
+        ElementValue::AnnotationValue(annotation) => visit_annotation(annotation, f),
+        ElementValue::ArrayValue(values) => {
+            for value in values {
+                visit_element_value(value, f);
+            }
+        }
+        ElementValue::ClassInfoIndex(class_info_index) => f(class_info_index),
+        ElementValue::ConstValueIndex {
+            const_value_index, ..
+        } => f(const_value_index),
+        ElementValue::EnumConstValue {
+            type_name_index,
+            const_name_index,
+        } => {
+            f(type_name_index);
+            f(const_name_index);
+        }
+    }
+}
+
+/// Collects the names of the attributes, including the nested ones. They aren't stored as
+/// indices, but must be in the constant pool when the class is written.
+pub(crate) fn collect_attribute_names<'a>(attributes: &'a [Attribute], names: &mut Vec<&'a str>) {
+    for attribute in attributes {
+        names.push(attribute.name());
+        match attribute {
+            Attribute::Code { attributes, .. } => collect_attribute_names(attributes, names),
+            Attribute::Record(components) => {
+                for component in components {
+                    collect_attribute_names(&component.attributes, names);
+                }
+            }
+            _ => {}
+        }
+    }
+}
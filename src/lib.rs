@@ -2,48 +2,99 @@
 //!
 //! Reads a .class file into an almost 1-to-1 matching struct or generates a .class file from said structure.
 
-use byteorder::{BigEndian, ReadBytesExt, WriteBytesExt};
+use byteorder::{BigEndian, ReadBytesExt};
+use std::collections::HashMap;
 use std::error::Error;
-use std::io::{Read, Seek, Write};
+use std::fmt::Formatter;
+use std::fs::File;
+use std::io::{BufReader, Cursor, Read, Write};
+use std::path::Path;
+
+pub mod analysis;
+use crate::analysis::{
+    compute_max_locals, compute_max_stack, generate_stack_map, uses_subroutines,
+};
+
+pub mod attribute_names;
+
+pub mod descriptor;
+use crate::descriptor::parse_method_descriptor;
+
+pub mod signature;
+
+mod disassemble;
 
 mod enums;
 pub use enums::{
-    AccessFlag, Attribute, Constant, ElementValue, Instruction, StackMapFrameType, TargetInfo,
+    AccessFlag, ArrayType, Attribute, ClassVersion, ConstValue, Constant, ElementValue,
+    Instruction, ReferenceKind, ResolvedElementValue, StackMapFrameType, TargetInfo,
     VerificationType,
 };
 
 mod structs;
 pub use structs::{
-    Annotation, BootstrapMethod, ElementValuePair, ExceptionTableEntry, Field, InnerClass,
-    LineNumber, LocalVar, LocalVariable, LocalVariableType, LookupSwitchPair, MemberData, Method,
-    MethodParameter, ModuleExports, ModuleOpens, ModuleProvides, ModuleRequires, StackMapFrame,
-    TypeAnnotation, TypePath,
+    Annotation, BootstrapMethod, ClassMetadata, DynamicInfo, ElementValuePair, EnclosingMethod,
+    ExceptionTableEntry, Field, InnerClass, LineNumber, LocalVar, LocalVariable, LocalVariableType,
+    LookupSwitchPair, MemberData, Method, MethodParameter, ModuleExports, ModuleInfo, ModuleOpens,
+    ModuleProvides, ModuleRequires, RecordComponent, StackMapFrame, TypeAnnotation, TypePath,
 };
 
 mod reader;
 use crate::reader::{
-    extract_class_flags, read_attributes, read_constant_pool, read_fields, read_interfaces,
+    eof_in, extract_class_flags, read_attributes, read_constant_pool, read_fields, read_interfaces,
     read_methods,
 };
+pub use reader::decode_modified_utf8;
 
 mod writer;
 use crate::writer::{
-    compact_class_flags, write_attributes, write_constant_pool, write_fields, write_interfaces,
-    write_methods,
+    conflicting_access_levels, invalid_ldc, missing_attribute_name, unwrap_java_error,
+    with_missing_constants, write_class,
 };
+pub use writer::encode_modified_utf8;
+
+#[cfg(feature = "serde")]
+mod serde_bits;
+
+#[cfg(feature = "testing")]
+pub mod testing;
 
 mod errors;
 pub use errors::JavaError;
 
+mod builder;
+pub use builder::{ClassBuilder, CodeBuilder};
+
+mod constant_pool;
+pub use constant_pool::ConstantPool;
+
+mod indices;
+use crate::indices::collect_attribute_names;
+
+mod assembler;
+pub use assembler::Assembler;
+
 mod mapping;
 
-#[derive(Debug)]
+mod resolver;
+pub use resolver::{MapResolver, Resolver};
+
+mod validation;
+pub use validation::{validate_class_name, VerifyOptions};
+
+mod view;
+pub use view::{AttributeView, ClassView, MemberView};
+
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct JVMClass {
     pub major: u16,
     pub minor: u16,
     pub access_flags: Vec<AccessFlag>,
     pub this_class: u16,
     pub super_class: u16,
+    /// Indexed like in the class file: entry 0 and the entry following each long and double are
+    /// `Constant::Invalid`. `ConstantPool` keeps that layout when adding constants.
     pub constants: Vec<Constant>,
     pub interfaces: Vec<u16>,
     pub fields: Vec<Field>,
@@ -52,6 +103,8 @@ pub struct JVMClass {
 }
 
 impl JVMClass {
+    /// An empty class, without version nor constants, to fill field by field. Use `template` to
+    /// start from a class the JVM can load.
     pub fn new() -> Self {
         Self {
             major: 0,
@@ -67,66 +120,173 @@ impl JVMClass {
         }
     }
 
-    pub fn load<R: Read>(&mut self, r: &mut R) -> Result<(), Box<dyn Error>> {
+    /// A minimal class that can be stored and loaded: a Java 8 class `name` (an internal name,
+    /// e.g. `com/example/Foo`) extending `java/lang/Object`, with the `Super` flag and no members.
+    /// `ClassBuilder` can also add a constructor.
+    pub fn template(name: &str) -> Self {
+        let mut jvm = JVMClass::new();
+        jvm.set_version(ClassVersion::Java8);
+        jvm.access_flags = vec![AccessFlag::Super];
+
+        let mut constant_pool = jvm.constant_pool();
+        let this_class = constant_pool.intern_class(name);
+        let super_class = constant_pool.intern_class("java/lang/Object");
+        jvm.this_class = this_class;
+        jvm.super_class = super_class;
+
+        jvm
+    }
+
+    pub fn from_reader<R: Read>(r: &mut R) -> Result<JVMClass, Box<dyn Error>> {
+        JVMClass::read(r, false)
+    }
+
+    fn read<R: Read>(r: &mut R, strict: bool) -> Result<JVMClass, Box<dyn Error>> {
+        let mut jvm = JVMClass::new();
+
         let magic = r.read_u32::<BigEndian>()?;
-        assert_eq!(magic, 0xCAFEBABE);
+        if magic != 0xCAFEBABE {
+            return Err(JavaError::InvalidMagic(magic).into());
+        }
 
-        self.minor = r.read_u16::<BigEndian>()?;
-        self.major = r.read_u16::<BigEndian>()?;
+        jvm.minor = r.read_u16::<BigEndian>()?;
+        jvm.major = r.read_u16::<BigEndian>()?;
 
-        self.constants = read_constant_pool(r)?;
+        jvm.constants = read_constant_pool(r)?;
 
-        let access_flags = r.read_u16::<BigEndian>()?;
-        self.access_flags = extract_class_flags(access_flags);
+        let header = eof_in("the class header");
+        let access_flags = r.read_u16::<BigEndian>().map_err(&header)?;
+        jvm.access_flags = extract_class_flags(access_flags);
 
-        self.this_class = r.read_u16::<BigEndian>()?;
-        self.super_class = r.read_u16::<BigEndian>()?;
+        jvm.this_class = r.read_u16::<BigEndian>().map_err(&header)?;
+        jvm.super_class = r.read_u16::<BigEndian>().map_err(&header)?;
 
-        self.interfaces = read_interfaces(r)?;
-        self.fields = read_fields(&self, r)?;
-        self.methods = read_methods(&self, r)?;
-        self.attributes = read_attributes(&self, r)?;
+        jvm.interfaces = read_interfaces(r).map_err(eof_in("interfaces"))?;
+        jvm.fields = read_fields(&jvm, r, strict)?;
+        jvm.methods = read_methods(&jvm, r, strict)?;
+        jvm.attributes = read_attributes(&jvm, r, strict, "class attributes")?;
 
-        Ok(())
+        Ok(jvm)
     }
 
-    pub fn store<W: Write + Seek>(&self, w: &mut W) -> Result<(), Box<dyn Error>> {
-        w.write_u32::<BigEndian>(0xCAFEBABE)?;
+    /// Reads only the header of a class: its version, constant pool, access flags, name and
+    /// super class. The interfaces, fields, methods and attributes are left unread in `r`.
+    pub fn peek_metadata<R: Read>(r: &mut R) -> Result<ClassMetadata, Box<dyn Error>> {
+        let mut jvm = JVMClass::new();
 
-        w.write_u16::<BigEndian>(self.minor)?;
-        w.write_u16::<BigEndian>(self.major)?;
+        let magic = r.read_u32::<BigEndian>()?;
+        if magic != 0xCAFEBABE {
+            return Err(JavaError::InvalidMagic(magic).into());
+        }
 
-        write_constant_pool(w, &self.constants)?;
+        jvm.minor = r.read_u16::<BigEndian>()?;
+        jvm.major = r.read_u16::<BigEndian>()?;
+        jvm.constants = read_constant_pool(r)?;
 
-        let access_flags = compact_class_flags(&self.access_flags);
-        w.write_u16::<BigEndian>(access_flags)?;
+        let flags = extract_class_flags(r.read_u16::<BigEndian>()?);
+        let this_class = r.read_u16::<BigEndian>()?;
+        let super_class = r.read_u16::<BigEndian>()?;
 
-        w.write_u16::<BigEndian>(self.this_class)?;
-        w.write_u16::<BigEndian>(self.super_class)?;
+        let super_name = match super_class {
+            0 => None,
+            id => Some(jvm.get_class_name(id)?.to_string()),
+        };
 
-        write_interfaces(w, &self.interfaces)?;
-        write_fields(w, &self.fields, self)?;
-        write_methods(w, &self.methods, self)?;
-        write_attributes(w, &self.attributes, self)?;
+        Ok(ClassMetadata {
+            version: jvm.version(),
+            name: jvm.get_class_name(this_class)?.to_string(),
+            super_name,
+            flags,
+        })
+    }
+
+    pub fn from_bytes(bytes: &[u8]) -> Result<JVMClass, Box<dyn Error>> {
+        JVMClass::from_reader(&mut Cursor::new(bytes))
+    }
+
+    pub fn from_file<P: AsRef<Path>>(path: P) -> Result<JVMClass, Box<dyn Error>> {
+        let mut file = BufReader::new(File::open(path)?);
+        JVMClass::from_reader(&mut file)
+    }
+
+    /// Replaces the content of this class with the one read from `r`.
+    /// On error, the class is left unchanged.
+    pub fn load<R: Read>(&mut self, r: &mut R) -> Result<(), Box<dyn Error>> {
+        *self = JVMClass::from_reader(r)?;
 
         Ok(())
     }
 
+    /// Same as `load`, except that an attribute whose content doesn't match its declared length
+    /// is a `JavaError::AttributeLengthMismatch` instead of being read as far as it makes sense.
+    ///
+    /// Each attribute is read into a buffer of its declared length before being parsed, so a
+    /// mismatch never shifts the following attributes, and `r` doesn't need to be `Seek`.
+    pub fn load_checked<R: Read>(&mut self, r: &mut R) -> Result<(), Box<dyn Error>> {
+        *self = JVMClass::read(r, true)?;
+
+        Ok(())
+    }
+
+    /// Writes the class. The names of `Attribute::Unknown`s and the values of the constant
+    /// instructions written as `ldc_w`/`ldc2_w` (e.g. `IConst(100000)`) that are missing from the
+    /// constant pool are added to the written pool, without modifying this class. The names of
+    /// the other attributes must be in the pool, or it fails with `JavaError::MissingAttributeName`.
+    /// A `Ldc` of a constant missing from the pool fails with `JavaError::InvalidConstantId`, and
+    /// a field or method with more than one access level with `JavaError::InvalidAccessFlags`.
+    /// The pool is expected to be normalized; if it isn't, a normalized copy is written, see
+    /// `normalize_pool`.
+    pub fn store<W: Write>(&self, w: &mut W) -> Result<(), Box<dyn Error>> {
+        let normalized;
+        let jvm = match self.is_pool_normalized() {
+            true => self,
+            false => {
+                let mut copy = self.clone();
+                copy.normalize_pool()?;
+                normalized = copy;
+                &normalized
+            }
+        };
+        let jvm = with_missing_constants(jvm);
+        let jvm = jvm.as_ref();
+
+        if let Some(name) = missing_attribute_name(jvm) {
+            return Err(JavaError::MissingAttributeName(name.into()).into());
+        }
+
+        if let Some((kind, name)) = conflicting_access_levels(jvm) {
+            let name = jvm.display_index(name);
+            return Err(JavaError::InvalidAccessFlags(format!(
+                "{kind} {name} has more than one access level"
+            ))
+            .into());
+        }
+
+        if let Some(id) = invalid_ldc(jvm) {
+            return Err(JavaError::InvalidConstantId(id as u16).into());
+        }
+
+        write_class(w, jvm).map_err(unwrap_java_error)
+    }
+
+    /// Stores the class into a new buffer.
+    pub fn to_bytes(&self) -> Result<Vec<u8>, Box<dyn Error>> {
+        let mut buffer = vec![];
+        self.store(&mut buffer)?;
+
+        Ok(buffer)
+    }
+
     pub fn get_string(&self, id: u16) -> Result<&str, JavaError> {
         let id = id as usize;
 
         if let Some(constant) = self.constants.get(id) {
             match constant {
-                Constant::Class { name_index } => {
-                    let cname = self.get_string(*name_index)?;
-
-                    if cname.starts_with("java/lang/") {
-                        Ok(&cname[10..])
-                    } else {
-                        Ok(cname)
-                    }
-                }
+                Constant::Class { name_index } => self.get_string(*name_index),
                 Constant::Utf8(string) => Ok(string),
+                Constant::Utf8Bytes(_) => Err(JavaError::ConstantTypeError(format!(
+                    "#{id} is a Utf8 with an unpaired surrogate, which a string can't hold"
+                ))),
                 Constant::String { string_index } => self.get_string(*string_index),
                 _ => Err(JavaError::ConstantTypeError(format!(
                     "#{id} is not a string, but a {constant}"
@@ -137,6 +297,111 @@ impl JVMClass {
         }
     }
 
+    /// Same as `get_string`, except that the index 0, used by optional references like the
+    /// name of an anonymous class, is `None`.
+    pub fn get_string_opt(&self, id: u16) -> Result<Option<&str>, JavaError> {
+        match id {
+            0 => Ok(None),
+            id => self.get_string(id).map(Some),
+        }
+    }
+
+    /// Returns the internal name (e.g. `java/lang/Object`) of a `Constant::Class`.
+    pub fn get_class_name(&self, id: u16) -> Result<&str, JavaError> {
+        match self.constants.get(id as usize) {
+            Some(Constant::Class { name_index }) => self.get_string(*name_index),
+            Some(constant) => Err(JavaError::ConstantTypeError(format!(
+                "#{id} is not a class, but a {constant}"
+            ))),
+            None => Err(JavaError::InvalidConstantId(id)),
+        }
+    }
+
+    /// The internal name of this class, e.g. `com/example/Foo`.
+    pub fn this_class_name(&self) -> Result<&str, JavaError> {
+        self.get_class_name(self.this_class)
+    }
+
+    /// Returns the name of a `Constant::Module`, e.g. `java.base`.
+    pub fn get_module_name(&self, id: u16) -> Result<&str, JavaError> {
+        match self.constants.get(id as usize) {
+            Some(Constant::Module { name_index }) => self.get_string(*name_index),
+            Some(constant) => Err(JavaError::ConstantTypeError(format!(
+                "#{id} is not a module, but a {constant}"
+            ))),
+            None => Err(JavaError::InvalidConstantId(id)),
+        }
+    }
+
+    /// Returns the internal name (e.g. `java/lang`) of a `Constant::Package`.
+    pub fn get_package_name(&self, id: u16) -> Result<&str, JavaError> {
+        match self.constants.get(id as usize) {
+            Some(Constant::Package { name_index }) => self.get_string(*name_index),
+            Some(constant) => Err(JavaError::ConstantTypeError(format!(
+                "#{id} is not a package, but a {constant}"
+            ))),
+            None => Err(JavaError::InvalidConstantId(id)),
+        }
+    }
+
+    /// Same as `get_string`, except that classes from `java/lang/` lose their package (e.g. `Object`).
+    pub fn get_display_name(&self, id: u16) -> Result<&str, JavaError> {
+        let name = self.get_string(id)?;
+
+        match self.constants.get(id as usize) {
+            Some(Constant::Class { .. }) => Ok(name.strip_prefix("java/lang/").unwrap_or(name)),
+            _ => Ok(name),
+        }
+    }
+
+    /// Returns the `(name, descriptor)` of a `Constant::NameAndType`.
+    pub fn resolve_name_and_type(&self, id: u16) -> Result<(String, String), JavaError> {
+        match self.constants.get(id as usize) {
+            Some(Constant::NameAndType {
+                name_index,
+                descriptor_index,
+            }) => Ok((
+                self.get_string(*name_index)?.to_string(),
+                self.get_string(*descriptor_index)?.to_string(),
+            )),
+            Some(constant) => Err(JavaError::ConstantTypeError(format!(
+                "#{id} is not a name and type, but a {constant}"
+            ))),
+            None => Err(JavaError::InvalidConstantId(id)),
+        }
+    }
+
+    /// Returns the `(owner class, name, descriptor)` of a `Constant::Fieldref`,
+    /// `Constant::Methodref` or `Constant::InterfaceMethodref`.
+    pub fn resolve_ref(&self, id: u16) -> Result<(String, String, String), JavaError> {
+        match self.constants.get(id as usize) {
+            Some(
+                Constant::Fieldref {
+                    class_index,
+                    name_and_type_index,
+                }
+                | Constant::Methodref {
+                    class_index,
+                    name_and_type_index,
+                }
+                | Constant::InterfaceMethodref {
+                    class_index,
+                    name_and_type_index,
+                },
+            ) => {
+                let owner = self.get_class_name(*class_index)?.to_string();
+                let (name, descriptor) = self.resolve_name_and_type(*name_and_type_index)?;
+                Ok((owner, name, descriptor))
+            }
+            Some(constant) => Err(JavaError::ConstantTypeError(format!(
+                "#{id} is not a field or method reference, but a {constant}"
+            ))),
+            None => Err(JavaError::InvalidConstantId(id)),
+        }
+    }
+
+    /// The index of the first `Constant::Utf8` equal to `string`. This scans the pool, so look up
+    /// many strings with a map of the pool instead.
     pub fn get_string_index(&self, string: &str) -> Result<u16, JavaError> {
         for (index, constant) in self.constants.iter().enumerate() {
             if let Constant::Utf8(s) = constant {
@@ -149,13 +414,179 @@ impl JVMClass {
         Err(JavaError::StringNotFound)
     }
 
+    /// Returns the index of the `Constant::Utf8` matching `string`, adding it to the pool if missing.
+    pub fn get_or_add_utf8_index(&mut self, string: &str) -> u16 {
+        self.constant_pool().intern_utf8(string)
+    }
+
+    /// Returns the index of the `Constant::Integer` matching `value`, adding it to the pool if missing.
+    pub fn get_or_add_integer_index(&mut self, value: i32) -> u16 {
+        self.constant_pool().intern_integer(value)
+    }
+
+    /// Applies `f` to every `Constant::Utf8`, replacing the ones for which it returns `Some`.
+    /// Returns the number of constants changed.
+    ///
+    /// Everything refers to names and descriptors by index, so this renames them everywhere at
+    /// once, e.g. to move the classes of a package.
+    pub fn map_utf8<F: FnMut(&str) -> Option<String>>(&mut self, mut f: F) -> usize {
+        let mut changed = 0;
+
+        for constant in &mut self.constants {
+            if let Constant::Utf8(string) = constant {
+                if let Some(replacement) = f(string) {
+                    if *string != replacement {
+                        *string = replacement;
+                        changed += 1;
+                    }
+                }
+            }
+        }
+
+        changed
+    }
+
+    /// The first attribute of the class named `name` (e.g. `SourceFile`), see `Attribute::name`.
+    pub fn find_attribute(&self, name: &str) -> Option<&Attribute> {
+        self.attributes.iter().find(|attr| attr.name() == name)
+    }
+
+    /// The name of the file the class was compiled from, `None` if it has no `SourceFile` attribute.
+    pub fn source_file(&self) -> Option<Result<&str, JavaError>> {
+        self.attributes.iter().find_map(|attr| match attr {
+            Attribute::SourceFile { sourcefile_index } => Some(self.get_string(*sourcefile_index)),
+            _ => None,
+        })
+    }
+
+    /// Replaces the `SourceFile` attribute, or adds one if the class has none.
+    pub fn set_source_file(&mut self, name: &str) {
+        let sourcefile_index = self.get_or_add_utf8_index(name);
+        self.set_attribute(Attribute::SourceFile { sourcefile_index });
+    }
+
+    /// The class and method enclosing this local or anonymous class, `None` if it has no
+    /// `EnclosingMethod` attribute.
+    pub fn enclosing_method(&self) -> Option<Result<EnclosingMethod<'_>, JavaError>> {
+        self.attributes.iter().find_map(|attr| match attr {
+            Attribute::EnclosingMethod {
+                class_index,
+                method_index,
+            } => Some(self.resolve_enclosing_method(*class_index, *method_index)),
+            _ => None,
+        })
+    }
+
+    fn resolve_enclosing_method(
+        &self,
+        class_index: u16,
+        method_index: u16,
+    ) -> Result<EnclosingMethod<'_>, JavaError> {
+        let method = match method_index {
+            0 => None,
+            id => match self.constants.get(id as usize) {
+                Some(Constant::NameAndType {
+                    name_index,
+                    descriptor_index,
+                }) => Some((
+                    self.get_string(*name_index)?,
+                    self.get_string(*descriptor_index)?,
+                )),
+                Some(constant) => {
+                    return Err(JavaError::ConstantTypeError(format!(
+                        "#{id} is not a name and type, but a {constant}"
+                    )))
+                }
+                None => return Err(JavaError::InvalidConstantId(id)),
+            },
+        };
+
+        Ok(EnclosingMethod {
+            class: self.get_class_name(class_index)?,
+            method,
+        })
+    }
+
+    /// The name of the host of the nest of this class, `None` if it has no `NestHost` attribute.
+    pub fn nest_host(&self) -> Option<Result<&str, JavaError>> {
+        self.attributes.iter().find_map(|attr| match attr {
+            Attribute::NestHost(host_class_index) => Some(self.get_class_name(*host_class_index)),
+            _ => None,
+        })
+    }
+
+    /// The names of the members of the nest hosted by this class, `None` if it has no
+    /// `NestMembers` attribute.
+    pub fn nest_members(&self) -> Option<Result<Vec<&str>, JavaError>> {
+        self.attributes.iter().find_map(|attr| match attr {
+            Attribute::NestMembers(classes) => Some(
+                classes
+                    .iter()
+                    .map(|class| self.get_class_name(*class))
+                    .collect(),
+            ),
+            _ => None,
+        })
+    }
+
+    /// Replaces the `NestHost` attribute, or adds one. A nest host can't be a member of another
+    /// nest, so it fails with `JavaError::InconsistentNest` if the class has `NestMembers`.
+    pub fn set_nest_host(&mut self, name: &str) -> Result<(), JavaError> {
+        if self.nest_members().is_some() {
+            return Err(JavaError::InconsistentNest);
+        }
+
+        let host_class_index = self.constant_pool().intern_class(name);
+        self.set_attribute(Attribute::NestHost(host_class_index));
+
+        Ok(())
+    }
+
+    /// Replaces the `NestMembers` attribute, or adds one. Fails with
+    /// `JavaError::InconsistentNest` if the class has a `NestHost`, see `set_nest_host`.
+    pub fn set_nest_members(&mut self, names: &[&str]) -> Result<(), JavaError> {
+        if self.nest_host().is_some() {
+            return Err(JavaError::InconsistentNest);
+        }
+
+        let mut constant_pool = self.constant_pool();
+        let classes = names
+            .iter()
+            .map(|name| constant_pool.intern_class(name))
+            .collect();
+        self.set_attribute(Attribute::NestMembers(classes));
+
+        Ok(())
+    }
+
+    /// Replaces the first class attribute with the same name as `attribute`, or adds it, adding
+    /// its name to the constant pool.
+    fn set_attribute(&mut self, attribute: Attribute) {
+        self.get_or_add_utf8_index(attribute.name());
+
+        match self
+            .attributes
+            .iter_mut()
+            .find(|attr| attr.name() == attribute.name())
+        {
+            Some(existing) => *existing = attribute,
+            None => self.attributes.push(attribute),
+        }
+    }
+
+    /// The content of the `BootstrapMethods` attribute, `None` if the class has none.
+    pub fn bootstrap_methods(&self) -> Option<&[BootstrapMethod]> {
+        self.attributes.iter().find_map(|attr| match attr {
+            Attribute::BootstrapMethods(bootstrap_methods) => Some(bootstrap_methods.as_slice()),
+            _ => None,
+        })
+    }
+
+    /// Panics if the class has no `BootstrapMethods` attribute, see `bootstrap_methods`.
     pub fn get_bootstrap_methods(&self) -> &Vec<BootstrapMethod> {
         for attr in &self.attributes {
-            match attr {
-                Attribute::BootstrapMethods(bootstrap_methods) => {
-                    return bootstrap_methods;
-                }
-                _ => {}
+            if let Attribute::BootstrapMethods(bootstrap_methods) = attr {
+                return bootstrap_methods;
             }
         }
 
@@ -168,7 +599,634 @@ impl JVMClass {
         &methods[id as usize]
     }
 
-    pub fn get_constant(&self, id: &u16) -> &Constant {
-        self.constants.get(*id as usize).unwrap()
+    /// Returns the constant at `id`, or `JavaError::InvalidConstantId` if there is none. The
+    /// unusable entries (0, and the one after a long or a double) don't count as constants.
+    pub fn get_constant(&self, id: u16) -> Result<&Constant, JavaError> {
+        match self.constants.get(id as usize) {
+            Some(Constant::Invalid) | None => Err(JavaError::InvalidConstantId(id)),
+            Some(constant) => Ok(constant),
+        }
     }
+
+    /// Same as `get_constant`, but mutable.
+    pub fn get_constant_mut(&mut self, id: u16) -> Result<&mut Constant, JavaError> {
+        match self.constants.get_mut(id as usize) {
+            Some(Constant::Invalid) | None => Err(JavaError::InvalidConstantId(id)),
+            Some(constant) => Ok(constant),
+        }
+    }
+
+    /// Returns the value of a `Constant::Integer`.
+    pub fn get_integer(&self, id: u16) -> Result<i32, JavaError> {
+        match self.get_constant(id)? {
+            Constant::Integer(value) => Ok(*value),
+            constant => Err(JavaError::ConstantTypeError(format!(
+                "#{id} is not an integer, but a {constant}"
+            ))),
+        }
+    }
+
+    /// Returns the value of a `Constant::Long`.
+    pub fn get_long(&self, id: u16) -> Result<i64, JavaError> {
+        match self.get_constant(id)? {
+            Constant::Long(value) => Ok(*value),
+            constant => Err(JavaError::ConstantTypeError(format!(
+                "#{id} is not a long, but a {constant}"
+            ))),
+        }
+    }
+
+    /// Returns the value of a `Constant::Float`.
+    pub fn get_float(&self, id: u16) -> Result<f32, JavaError> {
+        match self.get_constant(id)? {
+            Constant::Float(value) => Ok(*value),
+            constant => Err(JavaError::ConstantTypeError(format!(
+                "#{id} is not a float, but a {constant}"
+            ))),
+        }
+    }
+
+    /// Returns the value of a `Constant::Double`.
+    pub fn get_double(&self, id: u16) -> Result<f64, JavaError> {
+        match self.get_constant(id)? {
+            Constant::Double(value) => Ok(*value),
+            constant => Err(JavaError::ConstantTypeError(format!(
+                "#{id} is not a double, but a {constant}"
+            ))),
+        }
+    }
+
+    /// Resolves a `Constant::InvokeDynamic` or `Constant::Dynamic` to its name, descriptor and bootstrap method.
+    pub fn dynamic_info(&self, id: u16) -> Result<DynamicInfo<'_>, JavaError> {
+        let (bootstrap_method_attr_index, name_and_type_index) =
+            match self.constants.get(id as usize) {
+                Some(Constant::InvokeDynamic {
+                    bootstrap_method_attr_index,
+                    name_and_type_index,
+                })
+                | Some(Constant::Dynamic {
+                    bootstrap_method_attr_index,
+                    name_and_type_index,
+                }) => (*bootstrap_method_attr_index, *name_and_type_index),
+                Some(constant) => {
+                    return Err(JavaError::ConstantTypeError(format!(
+                        "#{id} is not a dynamic constant, but a {constant}"
+                    )))
+                }
+                None => return Err(JavaError::InvalidConstantId(id)),
+            };
+
+        let (name_index, descriptor_index) = match self.constants.get(name_and_type_index as usize)
+        {
+            Some(Constant::NameAndType {
+                name_index,
+                descriptor_index,
+            }) => (*name_index, *descriptor_index),
+            Some(constant) => {
+                return Err(JavaError::ConstantTypeError(format!(
+                    "#{name_and_type_index} is not a name and type, but a {constant}"
+                )))
+            }
+            None => return Err(JavaError::InvalidConstantId(name_and_type_index)),
+        };
+
+        let bootstrap = self
+            .bootstrap_methods()
+            .ok_or(JavaError::MissingBootstrapMethods)?
+            .get(bootstrap_method_attr_index as usize)
+            .ok_or(JavaError::BootstrapMethodNotFound(
+                bootstrap_method_attr_index,
+            ))?;
+
+        Ok(DynamicInfo {
+            name: self.get_string(name_index)?,
+            descriptor: self.get_string(descriptor_index)?,
+            bootstrap,
+        })
+    }
+
+    /// Resolves the `Constant::InvokeDynamic` of an `invokedynamic` instruction to its bootstrap
+    /// method and the name and descriptor of the call site. Fails with
+    /// `JavaError::MissingBootstrapMethods` if the class has no `BootstrapMethods` attribute.
+    pub fn resolve_indy(&self, id: u16) -> Result<(BootstrapMethod, String, String), JavaError> {
+        match self.get_constant(id)? {
+            Constant::InvokeDynamic { .. } => {}
+            constant => {
+                return Err(JavaError::ConstantTypeError(format!(
+                    "#{id} is not an invokedynamic, but a {constant}"
+                )))
+            }
+        }
+
+        let info = self.dynamic_info(id)?;
+        Ok((
+            info.bootstrap.clone(),
+            info.name.to_string(),
+            info.descriptor.to_string(),
+        ))
+    }
+
+    pub fn version(&self) -> ClassVersion {
+        ClassVersion::from_version(self.major, self.minor)
+    }
+
+    /// Sets `major` and `minor` to the version of the release. Preview features are disabled.
+    pub fn set_version(&mut self, version: ClassVersion) {
+        (self.major, self.minor) = version.version();
+    }
+
+    /// Checks if the class depends on the preview features of its release.
+    pub fn supports_preview(&self) -> bool {
+        self.is_preview()
+    }
+
+    /// Checks if the class is marked as using preview features, i.e. has a `minor` of `0xFFFF`.
+    pub fn is_preview(&self) -> bool {
+        self.minor == 0xFFFF
+    }
+
+    /// Sets `minor` to `0xFFFF` to mark the class as using the preview features of its release, or
+    /// to 0. Only Java 12 and later have preview features, see `validate_version`.
+    pub fn set_preview(&mut self, preview: bool) {
+        self.minor = if preview { 0xFFFF } else { 0 };
+    }
+
+    /// Resolves the `Module` attribute, `None` if the class isn't a module.
+    pub fn module_info(&self) -> Option<Result<ModuleInfo, JavaError>> {
+        self.attributes.iter().find_map(|attr| match attr {
+            Attribute::Module {
+                module_name_index,
+                module_flags,
+                module_version_index,
+                requires,
+                exports,
+                opens,
+                uses,
+                provides,
+            } => Some(self.resolve_module(ModuleAttribute {
+                module_name_index: *module_name_index,
+                module_flags,
+                module_version_index: *module_version_index,
+                requires,
+                exports,
+                opens,
+                uses,
+                provides,
+            })),
+            _ => None,
+        })
+    }
+
+    fn resolve_module(&self, module: ModuleAttribute) -> Result<ModuleInfo, JavaError> {
+        let ModuleAttribute {
+            module_name_index,
+            module_flags,
+            module_version_index,
+            requires,
+            exports,
+            opens,
+            uses,
+            provides,
+        } = module;
+
+        let names = |indices: &[u16], get: fn(&Self, u16) -> Result<&str, JavaError>| {
+            indices
+                .iter()
+                .map(|id| get(self, *id).map(String::from))
+                .collect::<Result<Vec<_>, _>>()
+        };
+
+        Ok(ModuleInfo {
+            name: self.get_module_name(module_name_index)?.into(),
+            flags: module_flags.to_vec(),
+            version: self.get_string_opt(module_version_index)?.map(String::from),
+            requires: requires
+                .iter()
+                .map(|require| {
+                    Ok((
+                        self.get_module_name(require.requires_index)?.into(),
+                        require.requires_flags.clone(),
+                        require.version(self)?.map(String::from),
+                    ))
+                })
+                .collect::<Result<_, JavaError>>()?,
+            exports: exports
+                .iter()
+                .map(|export| {
+                    Ok((
+                        self.get_package_name(export.exports_index)?.into(),
+                        export.exports_flags.clone(),
+                        names(&export.exports_to_index, Self::get_module_name)?,
+                    ))
+                })
+                .collect::<Result<_, JavaError>>()?,
+            opens: opens
+                .iter()
+                .map(|open| {
+                    Ok((
+                        self.get_package_name(open.opens_index)?.into(),
+                        open.opens_flags.clone(),
+                        names(&open.opens_to_index, Self::get_module_name)?,
+                    ))
+                })
+                .collect::<Result<_, JavaError>>()?,
+            uses: names(uses, Self::get_class_name)?,
+            provides: provides
+                .iter()
+                .map(|provide| {
+                    Ok((
+                        self.get_class_name(provide.provides_index)?.into(),
+                        names(&provide.provides_with_index, Self::get_class_name)?,
+                    ))
+                })
+                .collect::<Result<_, JavaError>>()?,
+        })
+    }
+
+    /// Returns the version of the module declared by this class, or `None` if it is unversioned or not a module.
+    pub fn module_version(&self) -> Result<Option<&str>, JavaError> {
+        for attr in &self.attributes {
+            if let Attribute::Module {
+                module_version_index,
+                ..
+            } = attr
+            {
+                return self.get_string_opt(*module_version_index);
+            }
+        }
+
+        Ok(None)
+    }
+
+    /// Returns the name and descriptor of each component of a record, in declaration order.
+    /// `None` if the class has no `Record` attribute.
+    pub fn record_components(&self) -> Option<Result<Vec<(String, String)>, JavaError>> {
+        let components = self
+            .attributes
+            .iter()
+            .find_map(|attribute| match attribute {
+                Attribute::Record(components) => Some(components),
+                _ => None,
+            })?;
+
+        Some(
+            components
+                .iter()
+                .map(|component| {
+                    let name = self.get_string(component.name_index)?;
+                    let descriptor = self.get_string(component.descriptor_index)?;
+                    Ok((name.to_string(), descriptor.to_string()))
+                })
+                .collect(),
+        )
+    }
+
+    /// Returns the visible and invisible type annotations of a field or a method, including the
+    /// ones of its `Code` attribute (on local variables, casts, ...).
+    pub fn type_annotations<'a>(&self, member: &'a MemberData) -> Vec<&'a TypeAnnotation> {
+        let code_attributes = member
+            .attributes
+            .iter()
+            .flat_map(|attribute| match attribute {
+                Attribute::Code { attributes, .. } => attributes.as_slice(),
+                _ => &[],
+            });
+
+        member
+            .attributes
+            .iter()
+            .chain(code_attributes)
+            .flat_map(|attribute| match attribute {
+                Attribute::RuntimeVisibleTypeAnnotations(annotations)
+                | Attribute::RuntimeInvisibleTypeAnnotations(annotations) => annotations.as_slice(),
+                _ => &[],
+            })
+            .collect()
+    }
+
+    /// Checks if the class has the access flag `flag`.
+    pub fn has_flag(&self, flag: AccessFlag) -> bool {
+        self.access_flags.contains(&flag)
+    }
+
+    pub fn is_public(&self) -> bool {
+        self.has_flag(AccessFlag::Public)
+    }
+
+    pub fn is_final(&self) -> bool {
+        self.has_flag(AccessFlag::Final)
+    }
+
+    pub fn is_interface(&self) -> bool {
+        self.has_flag(AccessFlag::Interface)
+    }
+
+    pub fn is_abstract(&self) -> bool {
+        self.has_flag(AccessFlag::Abstract)
+    }
+
+    pub fn is_synthetic(&self) -> bool {
+        self.has_flag(AccessFlag::Synthetic)
+    }
+
+    pub fn is_annotation(&self) -> bool {
+        self.has_flag(AccessFlag::Annotation)
+    }
+
+    pub fn is_enum(&self) -> bool {
+        self.has_flag(AccessFlag::Enum)
+    }
+
+    pub fn is_module(&self) -> bool {
+        self.has_flag(AccessFlag::Module)
+    }
+
+    /// Finds a field by its name. Fields can't be overloaded, so the descriptor isn't needed.
+    pub fn field_by_name(&self, name: &str) -> Option<&Field> {
+        self.fields
+            .iter()
+            .find(|field| self.get_string(field.0.name).ok() == Some(name))
+    }
+
+    pub fn field_by_name_mut(&mut self, name: &str) -> Option<&mut Field> {
+        let index = self
+            .fields
+            .iter()
+            .position(|field| self.get_string(field.0.name).ok() == Some(name))?;
+        self.fields.get_mut(index)
+    }
+
+    pub fn find_field(&self, name: &str, descriptor: &str) -> Option<&Field> {
+        self.fields
+            .iter()
+            .find(|field| self.is_member(&field.0, name, descriptor))
+    }
+
+    pub fn find_method(&self, name: &str, descriptor: &str) -> Option<&Method> {
+        self.methods
+            .iter()
+            .find(|method| self.is_member(&method.0, name, descriptor))
+    }
+
+    /// Returns every method called `name`, i.e. all the overloads.
+    pub fn find_methods_named<'a>(&'a self, name: &'a str) -> impl Iterator<Item = &'a Method> {
+        self.methods
+            .iter()
+            .filter(move |method| self.get_string(method.0.name).ok() == Some(name))
+    }
+
+    /// Appends a field, interning its name and descriptor.
+    pub fn add_field(
+        &mut self,
+        name: &str,
+        descriptor: &str,
+        flags: Vec<AccessFlag>,
+    ) -> &mut Field {
+        let member = self.new_member(name, descriptor, flags, vec![]);
+        self.fields.push(Field(member));
+        self.fields.last_mut().unwrap()
+    }
+
+    /// Removes the field `name` with the type `descriptor`. Its constants stay in the pool,
+    /// see `prune_constants`.
+    pub fn remove_field(&mut self, name: &str, descriptor: &str) -> Option<Field> {
+        let index = self
+            .fields
+            .iter()
+            .position(|field| self.is_member(&field.0, name, descriptor))?;
+        Some(self.fields.remove(index))
+    }
+
+    /// Appends a method, interning its name and descriptor. `code` is its `Attribute::Code`,
+    /// `None` for abstract and native methods. The names of the attributes it contains (e.g.
+    /// `LineNumberTable`) are interned too.
+    pub fn add_method(
+        &mut self,
+        name: &str,
+        descriptor: &str,
+        flags: Vec<AccessFlag>,
+        code: Option<Attribute>,
+    ) -> &mut Method {
+        let member = self.new_member(name, descriptor, flags, code.into_iter().collect());
+        self.methods.push(Method(member));
+        self.methods.last_mut().unwrap()
+    }
+
+    /// Removes the method `name` with the descriptor `descriptor`. Its constants stay in the
+    /// pool, see `prune_constants`.
+    pub fn remove_method(&mut self, name: &str, descriptor: &str) -> Option<Method> {
+        let index = self
+            .methods
+            .iter()
+            .position(|method| self.is_member(&method.0, name, descriptor))?;
+        Some(self.methods.remove(index))
+    }
+
+    /// Replaces the body of the method at `index` in `methods` by `code`, whose branch offsets
+    /// are set, e.g. by `rewrite_branches`, and whose exception handlers are `exception_table`.
+    /// `max_locals` and `max_stack` are computed, and from version 50 the `StackMapTable` is
+    /// generated, without resolving other classes (see `generate_stack_map`); version 50 still
+    /// allows subroutines, so it has no table if `code` uses them. The attributes of the previous
+    /// code (line numbers, local variables, …) are dropped, as their pcs don't match `code`.
+    /// Methods without code get one.
+    ///
+    /// The constants the new attribute refers to (the names of the attributes, the classes of the
+    /// frames) are interned. On error, the class is left untouched.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `index` is out of bounds.
+    pub fn set_method_code(
+        &mut self,
+        index: usize,
+        code: Vec<Instruction>,
+        exception_table: Vec<ExceptionTableEntry>,
+    ) -> Result<(), JavaError> {
+        let method = &self.methods[index];
+        let is_static = method.is_static();
+        let name = self.get_string(method.0.name)?.to_string();
+        let descriptor = self.get_string(method.0.descriptor)?.to_string();
+
+        let max_locals = compute_max_locals(&code, &descriptor, is_static)?;
+        let max_stack = compute_max_stack(&code, &exception_table, self)?;
+
+        let mut attributes = vec![];
+        if self.major > 50 || (self.major == 50 && !uses_subroutines(&code)) {
+            let constants = self.constants.len();
+            let frames = parse_method_descriptor(&descriptor).and_then(|parsed| {
+                generate_stack_map(
+                    &code,
+                    &exception_table,
+                    &name,
+                    &parsed,
+                    is_static,
+                    self,
+                    &MapResolver::new(),
+                )
+            });
+            let frames = match frames {
+                Ok(frames) => frames,
+                Err(e) => {
+                    self.constants.truncate(constants);
+                    return Err(e);
+                }
+            };
+            if !frames.is_empty() {
+                self.get_or_add_utf8_index(attribute_names::STACK_MAP_TABLE);
+                attributes.push(Attribute::StackMapTable(frames));
+            }
+        }
+
+        self.get_or_add_utf8_index(attribute_names::CODE);
+        let attribute = Attribute::Code {
+            max_stack,
+            max_locals,
+            code,
+            exception_table,
+            attributes,
+        };
+
+        let method = &mut self.methods[index].0;
+        match method
+            .attributes
+            .iter_mut()
+            .find(|attr| matches!(attr, Attribute::Code { .. }))
+        {
+            Some(previous) => *previous = attribute,
+            None => method.attributes.push(attribute),
+        }
+
+        Ok(())
+    }
+
+    fn new_member(
+        &mut self,
+        name: &str,
+        descriptor: &str,
+        access_flags: Vec<AccessFlag>,
+        attributes: Vec<Attribute>,
+    ) -> MemberData {
+        let mut names = vec![];
+        collect_attribute_names(&attributes, &mut names);
+        for name in names {
+            self.get_or_add_utf8_index(name);
+        }
+
+        MemberData {
+            access_flags,
+            name: self.get_or_add_utf8_index(name),
+            descriptor: self.get_or_add_utf8_index(descriptor),
+            attributes,
+        }
+    }
+
+    /// Counts the instructions of all the methods by mnemonic (see `Instruction::mnemonic`), so
+    /// `iload_0` and `iload` are counted apart, but `wide iload` is an `iload`.
+    pub fn opcode_histogram(&self) -> HashMap<&'static str, u64> {
+        let mut histogram = HashMap::new();
+        for inst in self.instructions() {
+            *histogram.entry(inst.mnemonic()).or_insert(0) += 1;
+        }
+
+        histogram
+    }
+
+    /// The number of instructions of all the methods.
+    pub fn instruction_count(&self) -> u64 {
+        self.instructions().count() as u64
+    }
+
+    /// The methods with a body, each with its `Code` attribute.
+    pub fn all_code(&self) -> impl Iterator<Item = (&Method, &Attribute)> {
+        self.methods
+            .iter()
+            .filter_map(|method| Some((method, method.code()?)))
+    }
+
+    /// The instructions of every `Code` attribute. Abstract and native methods have none.
+    fn instructions(&self) -> impl Iterator<Item = &Instruction> {
+        self.all_code().flat_map(|(_, code)| code.instructions())
+    }
+
+    fn is_member(&self, member: &MemberData, name: &str, descriptor: &str) -> bool {
+        self.get_string(member.name).ok() == Some(name)
+            && self.get_string(member.descriptor).ok() == Some(descriptor)
+    }
+
+    /// Adds an interface to the class, checking that `id` refers to a `Constant::Class`.
+    pub fn add_interface_index(&mut self, id: u16) -> Result<(), JavaError> {
+        self.get_class_name(id)?;
+        self.interfaces.push(id);
+
+        Ok(())
+    }
+
+    /// Removes an interface by its internal name (e.g. `java/lang/Runnable`).
+    /// Returns `false` if the class doesn't implement it.
+    pub fn remove_interface(&mut self, internal_name: &str) -> bool {
+        let interfaces = std::mem::take(&mut self.interfaces);
+        let count = interfaces.len();
+
+        self.interfaces = interfaces
+            .into_iter()
+            .filter(|id| self.get_class_name(*id).ok() != Some(internal_name))
+            .collect();
+
+        self.interfaces.len() != count
+    }
+}
+
+/// An empty class, see `JVMClass::new`.
+impl Default for JVMClass {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl std::fmt::Display for JVMClass {
+    fn fmt(&self, f: &mut Formatter<'_>) -> Result<(), std::fmt::Error> {
+        for flag in &self.access_flags {
+            write!(f, "{} ", format!("{flag:?}").to_lowercase())?;
+        }
+
+        match self.get_display_name(self.this_class) {
+            Ok(name) => write!(f, "class {name}")?,
+            Err(_) => write!(f, "class #{}", self.this_class)?,
+        }
+
+        if self.super_class != 0 {
+            match self.get_display_name(self.super_class) {
+                Ok(name) => write!(f, " extends {name}")?,
+                Err(_) => write!(f, " extends #{}", self.super_class)?,
+            }
+        }
+
+        let java = if self.major >= 49 {
+            format!("Java {}", self.major - 44)
+        } else {
+            format!("Java 1.{}", self.major.saturating_sub(44))
+        };
+
+        write!(
+            f,
+            " ({} interfaces, {} fields, {} methods, {java}, version {}.{})",
+            self.interfaces.len(),
+            self.fields.len(),
+            self.methods.len(),
+            self.major,
+            self.minor
+        )
+    }
+}
+
+/// The fields of a `Module` attribute, for `JVMClass::resolve_module`.
+struct ModuleAttribute<'a> {
+    module_name_index: u16,
+    module_flags: &'a [AccessFlag],
+    module_version_index: u16,
+    requires: &'a [ModuleRequires],
+    exports: &'a [ModuleExports],
+    opens: &'a [ModuleOpens],
+    uses: &'a [u16],
+    provides: &'a [ModuleProvides],
 }
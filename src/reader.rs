@@ -3,9 +3,10 @@ use std::io::{self, Cursor, Read, Seek, SeekFrom};
 
 use byteorder::{BigEndian, ReadBytesExt};
 
+use crate::attribute_names;
 use crate::enums::{
-    AccessFlag, Attribute, Constant, ElementValue, Instruction, StackMapFrameType, TargetInfo,
-    VerificationType,
+    AccessFlag, ArrayType, Attribute, Constant, ElementValue, Instruction, ReferenceKind,
+    StackMapFrameType, TargetInfo, VerificationType,
 };
 use crate::structs::{
     Annotation, BootstrapMethod, ElementValuePair, ExceptionTableEntry, Field, InnerClass,
@@ -13,153 +14,263 @@ use crate::structs::{
     MethodParameter, ModuleExports, ModuleOpens, ModuleProvides, ModuleRequires, RecordComponent,
     StackMapFrame, TypeAnnotation, TypePath,
 };
-use crate::JVMClass;
+use crate::{JVMClass, JavaError};
 
 use crate::mapping::{
     CLASS_FLAGS, FIELD_FLAGS, INNER_CLASS_FLAGS, METHOD_FLAGS, METHOD_PARAMETER_FLAGS,
     MODULE_EXPORTS_FLAGS, MODULE_FLAGS, MODULE_OPENS_FLAGS, MODULE_REQUIRES_FLAGS,
 };
 
-pub fn read_constant_pool<R: Read>(r: &mut R) -> Result<Vec<Constant>, io::Error> {
+/// Turns an `io::ErrorKind::UnexpectedEof` into a `JavaError::UnexpectedEof` telling where it
+/// happened. Other errors, including the EOFs already annotated by a nested reader, are kept.
+pub(crate) fn eof_in<E: Into<Box<dyn Error>>>(
+    section: &'static str,
+) -> impl Fn(E) -> Box<dyn Error> {
+    move |error| match error.into().downcast::<io::Error>() {
+        Ok(source) if source.kind() == io::ErrorKind::UnexpectedEof => JavaError::UnexpectedEof {
+            section,
+            source: *source,
+        }
+        .into(),
+        Ok(source) => source,
+        Err(error) => error,
+    }
+}
+
+/// Reads `length` bytes. The length comes from the class file, so the buffer only grows with the
+/// bytes actually read: a bogus length fails with an EOF instead of allocating that much.
+fn read_bytes<R: Read>(r: &mut R, length: u32) -> Result<Vec<u8>, io::Error> {
+    let mut bytes = vec![];
+    r.take(length as u64).read_to_end(&mut bytes)?;
+
+    if bytes.len() != length as usize {
+        return Err(io::ErrorKind::UnexpectedEof.into());
+    }
+
+    Ok(bytes)
+}
+
+pub fn read_constant_pool<R: Read>(r: &mut R) -> Result<Vec<Constant>, Box<dyn Error>> {
+    read_constants(r).map_err(eof_in("the constant pool"))
+}
+
+fn read_constants<R: Read>(r: &mut R) -> Result<Vec<Constant>, Box<dyn Error>> {
     let count = r.read_u16::<BigEndian>()?;
 
     let mut constants = vec![Constant::Invalid];
 
-    let mut double_constants = 0;
-    for i in 1..count {
-        if i >= count - double_constants {
-            break;
-        }
-        let tag = r.read_u8()?;
-        let cnst = match tag {
-            1 => {
-                let length = r.read_u16::<BigEndian>()? as usize;
-                let mut buff = vec![0u8; length as usize];
-                r.read(&mut buff).unwrap();
-
-                let string = String::from_utf8(buff).unwrap();
+    // longs and doubles take two entries
+    let mut i = 1;
+    while i < count {
+        let cnst = read_constant(r)?;
 
-                Constant::Utf8(string)
-            }
-            3 => {
-                let value = r.read_i32::<BigEndian>()?;
-                Constant::Integer(value)
-            }
-            4 => {
-                let value = r.read_f32::<BigEndian>()?;
-                Constant::Float(value)
-            }
-            5 => {
-                double_constants += 1;
-                let value = r.read_i64::<BigEndian>()?;
-                Constant::Long(value)
+        match cnst {
+            Constant::Double(..) | Constant::Long(..) => {
+                constants.push(cnst);
+                constants.push(Constant::Invalid);
+                i += 2;
             }
-            6 => {
-                double_constants += 1;
-                let value = r.read_f64::<BigEndian>()?;
-                Constant::Double(value)
+            _ => {
+                constants.push(cnst);
+                i += 1;
             }
-            7 => {
-                let name_index = r.read_u16::<BigEndian>()?;
+        }
+    }
 
-                Constant::Class { name_index }
-            }
-            8 => {
-                let string_index = r.read_u16::<BigEndian>()?;
+    Ok(constants)
+}
 
-                Constant::String { string_index }
+/// Reads one entry of the constant pool, tag included.
+pub(crate) fn read_constant<R: Read>(r: &mut R) -> Result<Constant, Box<dyn Error>> {
+    let tag = r.read_u8()?;
+    let cnst = match tag {
+        1 => {
+            let length = r.read_u16::<BigEndian>()? as usize;
+            let mut buff = vec![0u8; length];
+            r.read_exact(&mut buff)?;
+
+            // string literals can hold unpaired surrogates, and some compilers emit overlong
+            // forms, but the bytes must be well-formed
+            match decode_modified_utf8(&buff) {
+                Ok(string) => Constant::Utf8(string),
+                Err(_) if decode_utf16_units(&buff, true).is_ok() => Constant::Utf8Bytes(buff),
+                Err(error) => return Err(error.into()),
             }
-            9 => {
-                let class_index = r.read_u16::<BigEndian>()?;
-                let name_and_type_index = r.read_u16::<BigEndian>()?;
+        }
+        3 => {
+            let value = r.read_i32::<BigEndian>()?;
+            Constant::Integer(value)
+        }
+        4 => {
+            let value = f32::from_bits(r.read_u32::<BigEndian>()?);
+            Constant::Float(value)
+        }
+        5 => {
+            let value = r.read_i64::<BigEndian>()?;
+            Constant::Long(value)
+        }
+        6 => {
+            let value = f64::from_bits(r.read_u64::<BigEndian>()?);
+            Constant::Double(value)
+        }
+        7 => {
+            let name_index = r.read_u16::<BigEndian>()?;
 
-                Constant::Fieldref {
-                    class_index,
-                    name_and_type_index,
-                }
-            }
-            10 => {
-                let class_index = r.read_u16::<BigEndian>()?;
-                let name_and_type_index = r.read_u16::<BigEndian>()?;
+            Constant::Class { name_index }
+        }
+        8 => {
+            let string_index = r.read_u16::<BigEndian>()?;
 
-                Constant::Methodref {
-                    class_index,
-                    name_and_type_index,
-                }
-            }
-            11 => {
-                let class_index = r.read_u16::<BigEndian>()?;
-                let name_and_type_index = r.read_u16::<BigEndian>()?;
+            Constant::String { string_index }
+        }
+        9 => {
+            let class_index = r.read_u16::<BigEndian>()?;
+            let name_and_type_index = r.read_u16::<BigEndian>()?;
 
-                Constant::InterfaceMethodref {
-                    class_index,
-                    name_and_type_index,
-                }
+            Constant::Fieldref {
+                class_index,
+                name_and_type_index,
             }
-            12 => {
-                let name_index = r.read_u16::<BigEndian>()?;
-                let descriptor_index = r.read_u16::<BigEndian>()?;
+        }
+        10 => {
+            let class_index = r.read_u16::<BigEndian>()?;
+            let name_and_type_index = r.read_u16::<BigEndian>()?;
 
-                Constant::NameAndType {
-                    name_index,
-                    descriptor_index,
-                }
+            Constant::Methodref {
+                class_index,
+                name_and_type_index,
             }
-            15 => {
-                let reference_kind = r.read_u8()?;
-                let reference_index = r.read_u16::<BigEndian>()?;
+        }
+        11 => {
+            let class_index = r.read_u16::<BigEndian>()?;
+            let name_and_type_index = r.read_u16::<BigEndian>()?;
 
-                Constant::MethodHandle {
-                    reference_kind,
-                    reference_index,
-                }
+            Constant::InterfaceMethodref {
+                class_index,
+                name_and_type_index,
             }
-            16 => {
-                let descriptor_index = r.read_u16::<BigEndian>()?;
+        }
+        12 => {
+            let name_index = r.read_u16::<BigEndian>()?;
+            let descriptor_index = r.read_u16::<BigEndian>()?;
 
-                Constant::MethodType { descriptor_index }
+            Constant::NameAndType {
+                name_index,
+                descriptor_index,
             }
-            17 => {
-                let bootstrap_method_attr_index = r.read_u16::<BigEndian>()?;
-                let name_and_type_index = r.read_u16::<BigEndian>()?;
+        }
+        15 => {
+            let kind = r.read_u8()?;
+            let reference_kind =
+                ReferenceKind::from_kind(kind).ok_or(JavaError::InvalidReferenceKind(kind))?;
+            let reference_index = r.read_u16::<BigEndian>()?;
 
-                Constant::Dynamic {
-                    bootstrap_method_attr_index,
-                    name_and_type_index,
-                }
+            Constant::MethodHandle {
+                reference_kind,
+                reference_index,
             }
-            18 => {
-                let bootstrap_method_attr_index = r.read_u16::<BigEndian>()?;
-                let name_and_type_index = r.read_u16::<BigEndian>()?;
+        }
+        16 => {
+            let descriptor_index = r.read_u16::<BigEndian>()?;
 
-                Constant::InvokeDynamic {
-                    bootstrap_method_attr_index,
-                    name_and_type_index,
-                }
-            }
-            19 => {
-                let name_index = r.read_u16::<BigEndian>()?;
+            Constant::MethodType { descriptor_index }
+        }
+        17 => {
+            let bootstrap_method_attr_index = r.read_u16::<BigEndian>()?;
+            let name_and_type_index = r.read_u16::<BigEndian>()?;
 
-                Constant::Module { name_index }
+            Constant::Dynamic {
+                bootstrap_method_attr_index,
+                name_and_type_index,
             }
-            20 => {
-                let name_index = r.read_u16::<BigEndian>()?;
+        }
+        18 => {
+            let bootstrap_method_attr_index = r.read_u16::<BigEndian>()?;
+            let name_and_type_index = r.read_u16::<BigEndian>()?;
 
-                Constant::Package { name_index }
+            Constant::InvokeDynamic {
+                bootstrap_method_attr_index,
+                name_and_type_index,
             }
-            _ => panic!("Unknown constant type: {tag}"),
+        }
+        19 => {
+            let name_index = r.read_u16::<BigEndian>()?;
+
+            Constant::Module { name_index }
+        }
+        20 => {
+            let name_index = r.read_u16::<BigEndian>()?;
+
+            Constant::Package { name_index }
+        }
+        _ => return Err(JavaError::UnknownConstantTag(tag).into()),
+    };
+
+    Ok(cnst)
+}
+
+/// Decodes the JVM's modified UTF-8: NUL is encoded on two bytes and supplementary characters
+/// as a surrogate pair of three bytes each. A `String` can't hold unpaired surrogates, so they
+/// fail like malformed bytes, at the offset of their first byte. So do the overlong forms other
+/// than the NUL's `C0 80`, as encoding the string again wouldn't give the same bytes.
+pub fn decode_modified_utf8(bytes: &[u8]) -> Result<String, JavaError> {
+    let mut units = decode_utf16_units(bytes, false)?.into_iter().peekable();
+
+    let mut string = String::with_capacity(bytes.len());
+    while let Some((offset, unit)) = units.next() {
+        let c = match unit {
+            0xD800..=0xDBFF => units
+                .next_if(|(_, low)| (0xDC00..=0xDFFF).contains(low))
+                .and_then(|(_, low)| {
+                    char::from_u32(0x10000 + ((unit as u32 - 0xD800) << 10) + (low as u32 - 0xDC00))
+                }),
+            _ => char::from_u32(unit as u32),
         };
+        string.push(c.ok_or(JavaError::InvalidModifiedUtf8(offset))?);
+    }
 
-        match cnst {
-            Constant::Double(..) | Constant::Long(..) => {
-                constants.push(cnst);
-                constants.push(Constant::Invalid);
+    Ok(string)
+}
+
+/// Decodes modified UTF-8 into UTF-16 code units, each with the offset of its first byte.
+/// Overlong forms, e.g. `C1 81` for `A`, are malformed unless `allow_overlong` is set.
+fn decode_utf16_units(bytes: &[u8], allow_overlong: bool) -> Result<Vec<(usize, u16)>, JavaError> {
+    let mut units = Vec::with_capacity(bytes.len());
+
+    let mut i = 0;
+    while i < bytes.len() {
+        let continuation = |offset: usize| match bytes.get(i + offset) {
+            Some(byte) if byte & 0xC0 == 0x80 => Ok((byte & 0x3F) as u16),
+            _ => Err(JavaError::InvalidModifiedUtf8(i)),
+        };
+
+        let byte = bytes[i];
+        match byte {
+            0x01..=0x7F => {
+                units.push((i, byte as u16));
+                i += 1;
+            }
+            0xC0..=0xDF => {
+                let unit = ((byte & 0x1F) as u16) << 6 | continuation(1)?;
+                if unit != 0 && unit < 0x80 && !allow_overlong {
+                    return Err(JavaError::InvalidModifiedUtf8(i));
+                }
+                units.push((i, unit));
+                i += 2;
+            }
+            0xE0..=0xEF => {
+                let unit = ((byte & 0x0F) as u16) << 12 | continuation(1)? << 6 | continuation(2)?;
+                if unit < 0x800 && !allow_overlong {
+                    return Err(JavaError::InvalidModifiedUtf8(i));
+                }
+                units.push((i, unit));
+                i += 3;
             }
-            _ => constants.push(cnst),
+            _ => return Err(JavaError::InvalidModifiedUtf8(i)),
         }
     }
 
-    Ok(constants)
+    Ok(units)
 }
 
 fn extract_flags<T: Copy>(flags: u16, mapping: &[(u16, T)]) -> Vec<T> {
@@ -178,11 +289,11 @@ fn extract_inner_class_flags(flags: u16) -> Vec<AccessFlag> {
     extract_flags(flags, &INNER_CLASS_FLAGS)
 }
 
-fn extract_field_flags(flags: u16) -> Vec<AccessFlag> {
+pub(crate) fn extract_field_flags(flags: u16) -> Vec<AccessFlag> {
     extract_flags(flags, &FIELD_FLAGS)
 }
 
-fn extract_method_flags(flags: u16) -> Vec<AccessFlag> {
+pub(crate) fn extract_method_flags(flags: u16) -> Vec<AccessFlag> {
     extract_flags(flags, &METHOD_FLAGS)
 }
 
@@ -219,53 +330,64 @@ pub fn read_interfaces<R: Read>(r: &mut R) -> Result<Vec<u16>, io::Error> {
     Ok(interfaces)
 }
 
-pub fn read_fields<R: Read>(jvm: &JVMClass, r: &mut R) -> Result<Vec<Field>, Box<dyn Error>> {
-    let count = r.read_u16::<BigEndian>()?;
+pub fn read_fields<R: Read>(
+    jvm: &JVMClass,
+    r: &mut R,
+    strict: bool,
+) -> Result<Vec<Field>, Box<dyn Error>> {
+    let count = r.read_u16::<BigEndian>().map_err(eof_in("fields"))?;
 
     let mut fields = vec![];
 
     for _ in 0..count {
-        let access_flags = r.read_u16::<BigEndian>()?;
-        let access_flags = extract_field_flags(access_flags);
-        let name = r.read_u16::<BigEndian>()?;
-        let descriptor = r.read_u16::<BigEndian>()?;
-        let attributes = read_attributes(jvm, r)?;
-
-        fields.push(Field(MemberData {
-            access_flags,
-            name,
-            descriptor,
-            attributes,
-        }));
+        let member = read_member(jvm, r, strict, extract_field_flags, "field attributes")
+            .map_err(eof_in("fields"))?;
+        fields.push(Field(member));
     }
 
     Ok(fields)
 }
 
-pub fn read_methods<R: Read>(jvm: &JVMClass, r: &mut R) -> Result<Vec<Method>, Box<dyn Error>> {
-    let count = r.read_u16::<BigEndian>()?;
+pub fn read_methods<R: Read>(
+    jvm: &JVMClass,
+    r: &mut R,
+    strict: bool,
+) -> Result<Vec<Method>, Box<dyn Error>> {
+    let count = r.read_u16::<BigEndian>().map_err(eof_in("methods"))?;
 
     let mut methods = vec![];
 
     for _ in 0..count {
-        let access_flags = r.read_u16::<BigEndian>()?;
-        let access_flags = extract_method_flags(access_flags);
-        let name = r.read_u16::<BigEndian>()?;
-        let descriptor = r.read_u16::<BigEndian>()?;
-        let attributes = read_attributes(jvm, r)?;
-
-        methods.push(Method(MemberData {
-            access_flags,
-            name,
-            descriptor,
-            attributes,
-        }));
+        let member = read_member(jvm, r, strict, extract_method_flags, "method attributes")
+            .map_err(eof_in("methods"))?;
+        methods.push(Method(member));
     }
 
     Ok(methods)
 }
 
-pub fn read_annotations<R: Read>(r: &mut R) -> Result<Vec<Annotation>, io::Error> {
+/// Reads a field or a method, its attributes being reported as `attributes_section` if truncated.
+fn read_member<R: Read>(
+    jvm: &JVMClass,
+    r: &mut R,
+    strict: bool,
+    extract_flags: fn(u16) -> Vec<AccessFlag>,
+    attributes_section: &'static str,
+) -> Result<MemberData, Box<dyn Error>> {
+    let access_flags = extract_flags(r.read_u16::<BigEndian>()?);
+    let name = r.read_u16::<BigEndian>()?;
+    let descriptor = r.read_u16::<BigEndian>()?;
+    let attributes = read_attributes(jvm, r, strict, attributes_section)?;
+
+    Ok(MemberData {
+        access_flags,
+        name,
+        descriptor,
+        attributes,
+    })
+}
+
+pub fn read_annotations<R: Read>(r: &mut R) -> Result<Vec<Annotation>, Box<dyn Error>> {
     let num_annotations = r.read_u16::<BigEndian>()?;
 
     let mut annotations = vec![];
@@ -278,7 +400,7 @@ pub fn read_annotations<R: Read>(r: &mut R) -> Result<Vec<Annotation>, io::Error
     Ok(annotations)
 }
 
-fn read_annotation<R: Read>(r: &mut R) -> Result<Annotation, io::Error> {
+fn read_annotation<R: Read>(r: &mut R) -> Result<Annotation, Box<dyn Error>> {
     let type_index = r.read_u16::<BigEndian>()?;
     let num_element_value_pairs = r.read_u16::<BigEndian>()?;
 
@@ -300,7 +422,7 @@ fn read_annotation<R: Read>(r: &mut R) -> Result<Annotation, io::Error> {
     })
 }
 
-fn read_element_value<R: Read>(r: &mut R) -> Result<ElementValue, io::Error> {
+fn read_element_value<R: Read>(r: &mut R) -> Result<ElementValue, Box<dyn Error>> {
     let tag = r.read_u8()?;
     Ok(match tag {
         b'B' | b'C' | b'D' | b'F' | b'I' | b'J' | b'S' | b'Z' | b's' => {
@@ -337,454 +459,469 @@ fn read_element_value<R: Read>(r: &mut R) -> Result<ElementValue, io::Error> {
 
             ElementValue::ArrayValue(values)
         }
-        _ => unreachable!(),
+        _ => return Err(JavaError::UnknownElementValueTag(tag).into()),
     })
 }
 
+/// Reads the attributes. Each one is parsed from its `attribute_length` bytes, so a parser
+/// disagreeing with the declared length can't desynchronize the following attributes. When
+/// `strict`, such a disagreement is a `JavaError::AttributeLengthMismatch`. A truncated attribute
+/// is a `JavaError::UnexpectedEof` in `section`, e.g. `method attributes`.
 pub fn read_attributes<R: Read>(
     jvm: &JVMClass,
     r: &mut R,
+    strict: bool,
+    section: &'static str,
 ) -> Result<Vec<Attribute>, Box<dyn Error>> {
-    let attributes_count = r.read_u16::<BigEndian>()?;
+    let attributes_count = r.read_u16::<BigEndian>().map_err(eof_in(section))?;
 
     let mut attributes = vec![];
 
     for _ in 0..attributes_count {
-        let attribute_name_index = r.read_u16::<BigEndian>()?;
-        let attribute_length = r.read_u32::<BigEndian>()?;
-
-        let name = jvm.get_string(attribute_name_index)?;
-        let attr = match name {
-            "ConstantValue" => {
-                let constantvalue_index = r.read_u16::<BigEndian>()?;
-                Attribute::ConstantValue {
-                    constantvalue_index,
-                }
+        attributes.push(read_attribute(jvm, r, strict).map_err(eof_in(section))?);
+    }
+
+    Ok(attributes)
+}
+
+fn read_attribute<R: Read>(
+    jvm: &JVMClass,
+    r: &mut R,
+    strict: bool,
+) -> Result<Attribute, Box<dyn Error>> {
+    let attribute_name_index = r.read_u16::<BigEndian>()?;
+    let attribute_length = r.read_u32::<BigEndian>()?;
+
+    let name = jvm.get_string(attribute_name_index)?;
+
+    let data = read_bytes(r, attribute_length)?;
+    let r = &mut Cursor::new(data);
+
+    let attr = match name {
+        attribute_names::CONSTANT_VALUE => {
+            let constantvalue_index = r.read_u16::<BigEndian>()?;
+            Attribute::ConstantValue {
+                constantvalue_index,
             }
-            "Code" => {
-                let max_stack = r.read_u16::<BigEndian>()?;
-                let max_locals = r.read_u16::<BigEndian>()?;
-                let code = decompile(r)?;
-
-                let exception_table_length = r.read_u16::<BigEndian>()?;
-                let mut exception_table = vec![];
-                for _ in 0..exception_table_length {
-                    let start_pc = r.read_u16::<BigEndian>()?;
-                    let end_pc = r.read_u16::<BigEndian>()?;
-                    let handler_pc = r.read_u16::<BigEndian>()?;
-                    let catch_type = r.read_u16::<BigEndian>()?;
-
-                    exception_table.push(ExceptionTableEntry {
-                        start_pc,
-                        end_pc,
-                        handler_pc,
-                        catch_type,
-                    });
-                }
-                let attributes = read_attributes(jvm, r)?;
+        }
+        attribute_names::CODE => {
+            let max_stack = r.read_u16::<BigEndian>()?;
+            let max_locals = r.read_u16::<BigEndian>()?;
+            let code_length = r.read_u32::<BigEndian>()?;
+            let code = decompile(&read_bytes(r, code_length)?)?;
+
+            let exception_table_length = r.read_u16::<BigEndian>()?;
+            let mut exception_table = vec![];
+            for _ in 0..exception_table_length {
+                let start_pc = r.read_u16::<BigEndian>()?;
+                let end_pc = r.read_u16::<BigEndian>()?;
+                let handler_pc = r.read_u16::<BigEndian>()?;
+                let catch_type = r.read_u16::<BigEndian>()?;
 
-                Attribute::Code {
-                    code,
-                    max_stack,
-                    max_locals,
-                    exception_table,
-                    attributes,
-                }
+                exception_table.push(ExceptionTableEntry {
+                    start_pc,
+                    end_pc,
+                    handler_pc,
+                    catch_type,
+                });
             }
-            "StackMapTable" => {
-                let number_of_entries = r.read_u16::<BigEndian>()?;
-
-                let mut frames = vec![];
-                for _ in 0..number_of_entries {
-                    let mut frame = StackMapFrame {
-                        frame_type: StackMapFrameType::SameFrame(0),
-                        offset_delta: 0,
-                        locals: vec![],
-                        stack: vec![],
-                    };
-
-                    let frame_type = r.read_u8()?;
-                    frame.frame_type = match frame_type {
-                        0..=63 => StackMapFrameType::SameFrame(frame_type),
-                        64..=127 => {
-                            frame.stack.push(read_verification_type(r)?);
-                            StackMapFrameType::SameLocals1StackItemFrame(frame_type)
-                        }
-                        247 => {
-                            frame.stack.push(read_verification_type(r)?);
-                            StackMapFrameType::SameLocals1StackItemFrameExtended
-                        }
-                        248..=250 => {
-                            frame.offset_delta = r.read_u16::<BigEndian>()?;
-                            StackMapFrameType::ChopFrame(frame_type)
-                        }
-                        251 => {
-                            frame.offset_delta = r.read_u16::<BigEndian>()?;
-                            StackMapFrameType::SameFrameExtended
+            let attributes = read_attributes(jvm, r, strict, "code attributes")?;
+
+            Attribute::Code {
+                code,
+                max_stack,
+                max_locals,
+                exception_table,
+                attributes,
+            }
+        }
+        attribute_names::STACK_MAP_TABLE => {
+            let number_of_entries = r.read_u16::<BigEndian>()?;
+
+            let mut frames = vec![];
+            for _ in 0..number_of_entries {
+                let mut frame = StackMapFrame {
+                    frame_type: StackMapFrameType::SameFrame(0),
+                    offset_delta: 0,
+                    locals: vec![],
+                    stack: vec![],
+                };
+
+                let frame_type = r.read_u8()?;
+                frame.frame_type = match frame_type {
+                    0..=63 => StackMapFrameType::SameFrame(frame_type),
+                    64..=127 => {
+                        frame.stack.push(read_verification_type(r)?);
+                        StackMapFrameType::SameLocals1StackItemFrame(frame_type)
+                    }
+                    247 => {
+                        frame.offset_delta = r.read_u16::<BigEndian>()?;
+                        frame.stack.push(read_verification_type(r)?);
+                        StackMapFrameType::SameLocals1StackItemFrameExtended
+                    }
+                    248..=250 => {
+                        frame.offset_delta = r.read_u16::<BigEndian>()?;
+                        StackMapFrameType::ChopFrame(frame_type)
+                    }
+                    251 => {
+                        frame.offset_delta = r.read_u16::<BigEndian>()?;
+                        StackMapFrameType::SameFrameExtended
+                    }
+                    252..=254 => {
+                        frame.offset_delta = r.read_u16::<BigEndian>()?;
+
+                        for _ in 0..(frame_type - 251) {
+                            let verification_type = read_verification_type(r)?;
+                            frame.locals.push(verification_type);
                         }
-                        252..=254 => {
-                            frame.offset_delta = r.read_u16::<BigEndian>()?;
 
-                            for _ in 0..(frame_type - 251) {
-                                let verification_type = read_verification_type(r)?;
-                                frame.locals.push(verification_type);
-                            }
+                        StackMapFrameType::AppendFrame(frame_type)
+                    }
+                    255 => {
+                        frame.offset_delta = r.read_u16::<BigEndian>()?;
 
-                            StackMapFrameType::AppendFrame(frame_type)
+                        let number_of_locals = r.read_u16::<BigEndian>()?;
+                        for _ in 0..number_of_locals {
+                            let verification_type = read_verification_type(r)?;
+                            frame.locals.push(verification_type);
                         }
-                        255 => {
-                            frame.offset_delta = r.read_u16::<BigEndian>()?;
-
-                            let number_of_locals = r.read_u16::<BigEndian>()?;
-                            for _ in 0..number_of_locals {
-                                let verification_type = read_verification_type(r)?;
-                                frame.locals.push(verification_type);
-                            }
-
-                            let number_of_stack_items = r.read_u16::<BigEndian>()?;
-                            for _ in 0..number_of_stack_items {
-                                let verification_type = read_verification_type(r)?;
-                                frame.stack.push(verification_type);
-                            }
-
-                            StackMapFrameType::FullFrame
+
+                        let number_of_stack_items = r.read_u16::<BigEndian>()?;
+                        for _ in 0..number_of_stack_items {
+                            let verification_type = read_verification_type(r)?;
+                            frame.stack.push(verification_type);
                         }
-                        _ => unreachable!(),
-                    };
 
-                    frames.push(frame);
-                }
+                        StackMapFrameType::FullFrame
+                    }
+                    _ => return Err(JavaError::UnknownFrameType(frame_type).into()),
+                };
 
-                Attribute::StackMapTable(frames)
+                frames.push(frame);
             }
-            "Exceptions" => {
-                let number_of_exceptions = r.read_u16::<BigEndian>()?;
 
-                let mut exceptions = vec![];
-                for _ in 0..number_of_exceptions {
-                    exceptions.push(r.read_u16::<BigEndian>()?);
-                }
-
-                Attribute::Exceptions(exceptions)
-            }
-            "InnerClasses" => {
-                let number_of_classes = r.read_u16::<BigEndian>()?;
-
-                let mut inner_classes = vec![];
-                for _ in 0..number_of_classes {
-                    let inner_class_info_index = r.read_u16::<BigEndian>()?;
-                    let outer_class_info_index = r.read_u16::<BigEndian>()?;
-                    let inner_name_index = r.read_u16::<BigEndian>()?;
-                    let inner_class_access_flags = r.read_u16::<BigEndian>()?;
-                    let inner_class_access_flags =
-                        extract_inner_class_flags(inner_class_access_flags);
-
-                    inner_classes.push(InnerClass {
-                        inner_class_info_index,
-                        outer_class_info_index,
-                        inner_name_index,
-                        inner_class_access_flags,
-                    });
-                }
+            Attribute::StackMapTable(frames)
+        }
+        attribute_names::EXCEPTIONS => {
+            let number_of_exceptions = r.read_u16::<BigEndian>()?;
 
-                Attribute::InnerClasses(inner_classes)
+            let mut exceptions = vec![];
+            for _ in 0..number_of_exceptions {
+                exceptions.push(r.read_u16::<BigEndian>()?);
             }
-            "EnclosingMethod" => {
-                assert_eq!(attribute_length, 4);
-                let class_index = r.read_u16::<BigEndian>()?;
-                let method_index = r.read_u16::<BigEndian>()?;
 
-                Attribute::EnclosingMethod {
-                    class_index,
-                    method_index,
-                }
-            }
-            "Synthetic" => {
-                assert_eq!(attribute_length, 0);
-                Attribute::Synthetic
-            }
-            "Signature" => {
-                let signature_index = r.read_u16::<BigEndian>()?;
-                Attribute::Signature { signature_index }
+            Attribute::Exceptions(exceptions)
+        }
+        attribute_names::INNER_CLASSES => {
+            let number_of_classes = r.read_u16::<BigEndian>()?;
+
+            let mut inner_classes = vec![];
+            for _ in 0..number_of_classes {
+                let inner_class_info_index = r.read_u16::<BigEndian>()?;
+                let outer_class_info_index = r.read_u16::<BigEndian>()?;
+                let inner_name_index = r.read_u16::<BigEndian>()?;
+                let inner_class_access_flags = r.read_u16::<BigEndian>()?;
+                let inner_class_access_flags = extract_inner_class_flags(inner_class_access_flags);
+
+                inner_classes.push(InnerClass {
+                    inner_class_info_index,
+                    outer_class_info_index,
+                    inner_name_index,
+                    inner_class_access_flags,
+                });
             }
-            "SourceFile" => {
-                assert_eq!(attribute_length, 2);
 
-                let sourcefile_index = r.read_u16::<BigEndian>()?;
-                Attribute::SourceFile { sourcefile_index }
-            }
-            "SourceDebugExtension" => {
-                let mut debug_extension = vec![0u8; attribute_length as usize];
-                r.read(&mut debug_extension)?;
+            Attribute::InnerClasses(inner_classes)
+        }
+        attribute_names::ENCLOSING_METHOD => {
+            let class_index = r.read_u16::<BigEndian>()?;
+            let method_index = r.read_u16::<BigEndian>()?;
 
-                Attribute::SourceDebugExtension { debug_extension }
+            Attribute::EnclosingMethod {
+                class_index,
+                method_index,
             }
-            "LineNumberTable" => {
-                let line_number_table_length = r.read_u16::<BigEndian>()?;
+        }
+        attribute_names::SYNTHETIC => Attribute::Synthetic,
+        attribute_names::SIGNATURE => {
+            let signature_index = r.read_u16::<BigEndian>()?;
+            Attribute::Signature { signature_index }
+        }
+        attribute_names::SOURCE_FILE => {
+            let sourcefile_index = r.read_u16::<BigEndian>()?;
+            Attribute::SourceFile { sourcefile_index }
+        }
+        attribute_names::SOURCE_DEBUG_EXTENSION => {
+            let debug_extension = read_bytes(r, attribute_length)?;
 
-                let mut line_number_table = vec![];
-                for _ in 0..line_number_table_length {
-                    let start_pc = r.read_u16::<BigEndian>()?;
-                    let line_number = r.read_u16::<BigEndian>()?;
+            Attribute::SourceDebugExtension { debug_extension }
+        }
+        attribute_names::LINE_NUMBER_TABLE => {
+            let line_number_table_length = r.read_u16::<BigEndian>()?;
 
-                    line_number_table.push(LineNumber {
-                        start_pc,
-                        line_number,
-                    });
-                }
+            let mut line_number_table = vec![];
+            for _ in 0..line_number_table_length {
+                let start_pc = r.read_u16::<BigEndian>()?;
+                let line_number = r.read_u16::<BigEndian>()?;
 
-                Attribute::LineNumberTable(line_number_table)
-            }
-            "LocalVariableTable" => {
-                let local_variable_table_length = r.read_u16::<BigEndian>()?;
-
-                let mut local_variable_table = vec![];
-                for _ in 0..local_variable_table_length {
-                    let start_pc = r.read_u16::<BigEndian>()?;
-                    let length = r.read_u16::<BigEndian>()?;
-                    let name_index = r.read_u16::<BigEndian>()?;
-                    let descriptor_index = r.read_u16::<BigEndian>()?;
-                    let index = r.read_u16::<BigEndian>()?;
-
-                    local_variable_table.push(LocalVariable {
-                        start_pc,
-                        length,
-                        name_index,
-                        descriptor_index,
-                        index,
-                    });
-                }
+                line_number_table.push(LineNumber {
+                    start_pc,
+                    line_number,
+                });
+            }
 
-                Attribute::LocalVariableTable(local_variable_table)
-            }
-            "LocalVariableTypeTable" => {
-                let local_variable_type_table_length = r.read_u16::<BigEndian>()?;
-
-                let mut local_variable_type_table = vec![];
-                for _ in 0..local_variable_type_table_length {
-                    let start_pc = r.read_u16::<BigEndian>()?;
-                    let length = r.read_u16::<BigEndian>()?;
-                    let name_index = r.read_u16::<BigEndian>()?;
-                    let signature_index = r.read_u16::<BigEndian>()?;
-                    let index = r.read_u16::<BigEndian>()?;
-
-                    local_variable_type_table.push(LocalVariableType {
-                        start_pc,
-                        length,
-                        name_index,
-                        signature_index,
-                        index,
-                    });
-                }
+            Attribute::LineNumberTable(line_number_table)
+        }
+        attribute_names::LOCAL_VARIABLE_TABLE => {
+            let local_variable_table_length = r.read_u16::<BigEndian>()?;
 
-                Attribute::LocalVariableTypeTable(local_variable_type_table)
-            }
-            "Deprecated" => {
-                assert_eq!(attribute_length, 0);
-                Attribute::Deprecated
-            }
-            "RuntimeVisibleAnnotations" => {
-                let annotations = read_annotations(r)?;
-                Attribute::RuntimeVisibleAnnotations(annotations)
-            }
-            "RuntimeInvisibleAnnotations" => {
-                let annotations = read_annotations(r)?;
-                Attribute::RuntimeInvisibleAnnotations(annotations)
+            let mut local_variable_table = vec![];
+            for _ in 0..local_variable_table_length {
+                let start_pc = r.read_u16::<BigEndian>()?;
+                let length = r.read_u16::<BigEndian>()?;
+                let name_index = r.read_u16::<BigEndian>()?;
+                let descriptor_index = r.read_u16::<BigEndian>()?;
+                let index = r.read_u16::<BigEndian>()?;
+
+                local_variable_table.push(LocalVariable {
+                    start_pc,
+                    length,
+                    name_index,
+                    descriptor_index,
+                    index,
+                });
             }
-            "RuntimeVisibleParameterAnnotations" => {
-                let num_parameters = r.read_u8()?;
 
-                let mut parameters_annotations = vec![];
-                for _ in 0..num_parameters {
-                    let annotations = read_annotations(r)?;
-                    parameters_annotations.push(annotations);
-                }
+            Attribute::LocalVariableTable(local_variable_table)
+        }
+        attribute_names::LOCAL_VARIABLE_TYPE_TABLE => {
+            let local_variable_type_table_length = r.read_u16::<BigEndian>()?;
 
-                Attribute::RuntimeVisibleParameterAnnotations(parameters_annotations)
+            let mut local_variable_type_table = vec![];
+            for _ in 0..local_variable_type_table_length {
+                let start_pc = r.read_u16::<BigEndian>()?;
+                let length = r.read_u16::<BigEndian>()?;
+                let name_index = r.read_u16::<BigEndian>()?;
+                let signature_index = r.read_u16::<BigEndian>()?;
+                let index = r.read_u16::<BigEndian>()?;
+
+                local_variable_type_table.push(LocalVariableType {
+                    start_pc,
+                    length,
+                    name_index,
+                    signature_index,
+                    index,
+                });
             }
-            "RuntimeInvisibleParameterAnnotations" => {
-                let num_parameters = r.read_u8()?;
 
-                let mut parameters_annotations = vec![];
-                for _ in 0..num_parameters {
-                    let annotations = read_annotations(r)?;
-                    parameters_annotations.push(annotations);
-                }
+            Attribute::LocalVariableTypeTable(local_variable_type_table)
+        }
+        attribute_names::DEPRECATED => Attribute::Deprecated,
+        attribute_names::RUNTIME_VISIBLE_ANNOTATIONS => {
+            let annotations = read_annotations(r)?;
+            Attribute::RuntimeVisibleAnnotations(annotations)
+        }
+        attribute_names::RUNTIME_INVISIBLE_ANNOTATIONS => {
+            let annotations = read_annotations(r)?;
+            Attribute::RuntimeInvisibleAnnotations(annotations)
+        }
+        attribute_names::RUNTIME_VISIBLE_PARAMETER_ANNOTATIONS => {
+            let num_parameters = r.read_u8()?;
 
-                Attribute::RuntimeInvisibleParameterAnnotations(parameters_annotations)
+            let mut parameters_annotations = vec![];
+            for _ in 0..num_parameters {
+                let annotations = read_annotations(r)?;
+                parameters_annotations.push(annotations);
             }
-            "AnnotationDefault" => {
-                let element_value = read_element_value(r)?;
-                Attribute::AnnotationDefault(element_value)
+
+            Attribute::RuntimeVisibleParameterAnnotations(parameters_annotations)
+        }
+        attribute_names::RUNTIME_INVISIBLE_PARAMETER_ANNOTATIONS => {
+            let num_parameters = r.read_u8()?;
+
+            let mut parameters_annotations = vec![];
+            for _ in 0..num_parameters {
+                let annotations = read_annotations(r)?;
+                parameters_annotations.push(annotations);
             }
-            "BootstrapMethods" => {
-                let num_bootstrap_methods = r.read_u16::<BigEndian>()?;
 
-                let mut bootstrap_methods = vec![];
+            Attribute::RuntimeInvisibleParameterAnnotations(parameters_annotations)
+        }
+        attribute_names::ANNOTATION_DEFAULT => {
+            let element_value = read_element_value(r)?;
+            Attribute::AnnotationDefault(element_value)
+        }
+        attribute_names::BOOTSTRAP_METHODS => {
+            let num_bootstrap_methods = r.read_u16::<BigEndian>()?;
 
-                for _ in 0..num_bootstrap_methods {
-                    let bootstrap_method_ref = r.read_u16::<BigEndian>()?;
-                    let num_bootstrap_arguments = r.read_u16::<BigEndian>()?;
+            let mut bootstrap_methods = vec![];
 
-                    let mut bootstrap_arguments = vec![];
-                    for _ in 0..num_bootstrap_arguments {
-                        let bootstrap_argument = r.read_u16::<BigEndian>()?;
-                        bootstrap_arguments.push(bootstrap_argument);
-                    }
+            for _ in 0..num_bootstrap_methods {
+                let bootstrap_method_ref = r.read_u16::<BigEndian>()?;
+                let num_bootstrap_arguments = r.read_u16::<BigEndian>()?;
 
-                    bootstrap_methods.push(BootstrapMethod {
-                        bootstrap_method_ref,
-                        bootstrap_arguments,
-                    });
+                let mut bootstrap_arguments = vec![];
+                for _ in 0..num_bootstrap_arguments {
+                    let bootstrap_argument = r.read_u16::<BigEndian>()?;
+                    bootstrap_arguments.push(bootstrap_argument);
                 }
 
-                Attribute::BootstrapMethods(bootstrap_methods)
+                bootstrap_methods.push(BootstrapMethod {
+                    bootstrap_method_ref,
+                    bootstrap_arguments,
+                });
             }
-            "MethodParameters" => {
-                let parameters_count = r.read_u8()?;
-
-                let mut parameters = vec![];
-                for _ in 0..parameters_count {
-                    let name_index = r.read_u16::<BigEndian>()?;
-                    let access_flags = r.read_u16::<BigEndian>()?;
-                    let access_flags = extract_method_parameter_flags(access_flags);
-                    parameters.push(MethodParameter {
-                        name_index,
-                        access_flags,
-                    });
-                }
 
-                Attribute::MethodParameters(parameters)
-            }
-            "Module" => {
-                let module_name_index = r.read_u16::<BigEndian>()?;
-                let module_flags = r.read_u16::<BigEndian>()?;
-                let module_flags = extract_module_flags(module_flags);
-                let module_version_index = r.read_u16::<BigEndian>()?;
-                let requires = read_module_requires(r)?;
-                let exports = read_module_exports(r)?;
-                let opens = read_module_opens(r)?;
-
-                let uses_count = r.read_u16::<BigEndian>()?;
-                let mut uses = vec![];
-                for _ in 0..uses_count {
-                    uses.push(r.read_u16::<BigEndian>()?);
-                }
+            Attribute::BootstrapMethods(bootstrap_methods)
+        }
+        attribute_names::METHOD_PARAMETERS => {
+            let parameters_count = r.read_u8()?;
 
-                let provides = read_module_provides(r)?;
-
-                Attribute::Module {
-                    module_name_index,
-                    module_flags,
-                    module_version_index,
-                    requires,
-                    exports,
-                    opens,
-                    uses,
-                    provides,
-                }
-            }
-            "ModuleMainClass" => {
-                assert_eq!(attribute_length, 2);
-                let main_class_index = r.read_u16::<BigEndian>()?;
-                Attribute::ModuleMainClass(main_class_index)
+            let mut parameters = vec![];
+            for _ in 0..parameters_count {
+                let name_index = r.read_u16::<BigEndian>()?;
+                let access_flags = r.read_u16::<BigEndian>()?;
+                let access_flags = extract_method_parameter_flags(access_flags);
+                parameters.push(MethodParameter {
+                    name_index,
+                    access_flags,
+                });
             }
-            "ModulePackages" => {
-                let packages_count = r.read_u16::<BigEndian>()?;
 
-                let mut packages_index = vec![];
-                for _ in 0..packages_count {
-                    let package_index = r.read_u16::<BigEndian>()?;
-                    packages_index.push(package_index);
-                }
-
-                Attribute::ModulePackages(packages_index)
+            Attribute::MethodParameters(parameters)
+        }
+        attribute_names::MODULE => {
+            let module_name_index = r.read_u16::<BigEndian>()?;
+            let module_flags = r.read_u16::<BigEndian>()?;
+            let module_flags = extract_module_flags(module_flags);
+            let module_version_index = r.read_u16::<BigEndian>()?;
+            let requires = read_module_requires(r)?;
+            let exports = read_module_exports(r)?;
+            let opens = read_module_opens(r)?;
+
+            let uses_count = r.read_u16::<BigEndian>()?;
+            let mut uses = vec![];
+            for _ in 0..uses_count {
+                uses.push(r.read_u16::<BigEndian>()?);
+            }
+
+            let provides = read_module_provides(r)?;
+
+            Attribute::Module {
+                module_name_index,
+                module_flags,
+                module_version_index,
+                requires,
+                exports,
+                opens,
+                uses,
+                provides,
             }
-            "NestHost" => {
-                assert_eq!(attribute_length, 2);
-                let host_class_index = r.read_u16::<BigEndian>()?;
-                Attribute::NestHost(host_class_index)
+        }
+        attribute_names::MODULE_MAIN_CLASS => {
+            let main_class_index = r.read_u16::<BigEndian>()?;
+            Attribute::ModuleMainClass(main_class_index)
+        }
+        attribute_names::MODULE_PACKAGES => {
+            let packages_count = r.read_u16::<BigEndian>()?;
+
+            let mut packages_index = vec![];
+            for _ in 0..packages_count {
+                let package_index = r.read_u16::<BigEndian>()?;
+                packages_index.push(package_index);
             }
-            "NestMembers" => {
-                let number_of_classes = r.read_u16::<BigEndian>()?;
 
-                let mut classes = vec![];
-                for _ in 0..number_of_classes {
-                    let class = r.read_u16::<BigEndian>()?;
-                    classes.push(class);
-                }
+            Attribute::ModulePackages(packages_index)
+        }
+        attribute_names::NEST_HOST => {
+            let host_class_index = r.read_u16::<BigEndian>()?;
+            Attribute::NestHost(host_class_index)
+        }
+        attribute_names::NEST_MEMBERS => {
+            let number_of_classes = r.read_u16::<BigEndian>()?;
 
-                Attribute::NestMembers(classes)
+            let mut classes = vec![];
+            for _ in 0..number_of_classes {
+                let class = r.read_u16::<BigEndian>()?;
+                classes.push(class);
             }
-            "PermittedSubclasses" => {
-                let number_of_classes = r.read_u16::<BigEndian>()?;
 
-                let mut classes = vec![];
-                for _ in 0..number_of_classes {
-                    let class = r.read_u16::<BigEndian>()?;
-                    classes.push(class);
-                }
+            Attribute::NestMembers(classes)
+        }
+        attribute_names::PERMITTED_SUBCLASSES => {
+            let number_of_classes = r.read_u16::<BigEndian>()?;
 
-                Attribute::PermittedSubclasses(classes)
+            let mut classes = vec![];
+            for _ in 0..number_of_classes {
+                let class = r.read_u16::<BigEndian>()?;
+                classes.push(class);
             }
-            "Record" => {
-                let components_count = r.read_u16::<BigEndian>()?;
 
-                let mut components = vec![];
-                for _ in 0..components_count {
-                    let name_index = r.read_u16::<BigEndian>()?;
-                    let descriptor_index = r.read_u16::<BigEndian>()?;
-                    let attributes = read_attributes(jvm, r)?;
+            Attribute::PermittedSubclasses(classes)
+        }
+        attribute_names::RECORD => {
+            let components_count = r.read_u16::<BigEndian>()?;
 
-                    components.push(RecordComponent {
-                        name_index,
-                        descriptor_index,
-                        attributes,
-                    });
-                }
+            let mut components = vec![];
+            for _ in 0..components_count {
+                let name_index = r.read_u16::<BigEndian>()?;
+                let descriptor_index = r.read_u16::<BigEndian>()?;
+                let attributes = read_attributes(jvm, r, strict, "record component attributes")?;
 
-                Attribute::Record(components)
+                components.push(RecordComponent {
+                    name_index,
+                    descriptor_index,
+                    attributes,
+                });
             }
-            "RuntimeInvisibleTypeAnnotations" => {
-                let num_annotations = r.read_u16::<BigEndian>()?;
 
-                let mut annotations = vec![];
-                for _ in 0..num_annotations {
-                    let annotation = read_type_annotation(r)?;
-                    annotations.push(annotation);
-                }
+            Attribute::Record(components)
+        }
+        attribute_names::RUNTIME_INVISIBLE_TYPE_ANNOTATIONS => {
+            let num_annotations = r.read_u16::<BigEndian>()?;
 
-                Attribute::RuntimeInvisibleTypeAnnotations(annotations)
+            let mut annotations = vec![];
+            for _ in 0..num_annotations {
+                let annotation = read_type_annotation(r)?;
+                annotations.push(annotation);
             }
-            "RuntimeVisibleTypeAnnotations" => {
-                let num_annotations = r.read_u16::<BigEndian>()?;
 
-                let mut annotations = vec![];
-                for _ in 0..num_annotations {
-                    let annotation = read_type_annotation(r)?;
-                    annotations.push(annotation);
-                }
+            Attribute::RuntimeInvisibleTypeAnnotations(annotations)
+        }
+        attribute_names::RUNTIME_VISIBLE_TYPE_ANNOTATIONS => {
+            let num_annotations = r.read_u16::<BigEndian>()?;
 
-                Attribute::RuntimeVisibleTypeAnnotations(annotations)
+            let mut annotations = vec![];
+            for _ in 0..num_annotations {
+                let annotation = read_type_annotation(r)?;
+                annotations.push(annotation);
             }
-            _ => {
-                let mut data = vec![0u8; attribute_length as usize];
-                r.read(&mut data)?;
 
-                Attribute::Unknown {
-                    name: name.into(),
-                    data,
-                }
+            Attribute::RuntimeVisibleTypeAnnotations(annotations)
+        }
+        _ => {
+            let data = read_bytes(r, attribute_length)?;
+
+            Attribute::Unknown {
+                name: name.into(),
+                data,
             }
-        };
+        }
+    };
 
-        attributes.push(attr);
+    if strict && r.position() != attribute_length as u64 {
+        return Err(JavaError::AttributeLengthMismatch {
+            name: name.into(),
+            declared: attribute_length,
+            read: r.position() as u32,
+        }
+        .into());
     }
 
-    Ok(attributes)
+    Ok(attr)
 }
 
-fn read_type_annotation<R: Read>(r: &mut R) -> Result<TypeAnnotation, io::Error> {
+fn read_type_annotation<R: Read>(r: &mut R) -> Result<TypeAnnotation, Box<dyn Error>> {
     let target_info = read_target_info(r)?;
 
     let mut target_path = vec![];
@@ -806,7 +943,7 @@ fn read_type_annotation<R: Read>(r: &mut R) -> Result<TypeAnnotation, io::Error>
     })
 }
 
-fn read_target_info<R: Read>(r: &mut R) -> Result<TargetInfo, io::Error> {
+fn read_target_info<R: Read>(r: &mut R) -> Result<TargetInfo, Box<dyn Error>> {
     let target_type = r.read_u8()?;
 
     Ok(match target_type {
@@ -833,7 +970,7 @@ fn read_target_info<R: Read>(r: &mut R) -> Result<TargetInfo, io::Error> {
                 bound_index,
             }
         }
-        0x13 | 0x14 | 0x15 => TargetInfo::Empty(target_type),
+        0x13..=0x15 => TargetInfo::Empty(target_type),
         0x16 => {
             let formal_parameter_index = r.read_u8()?;
 
@@ -870,7 +1007,7 @@ fn read_target_info<R: Read>(r: &mut R) -> Result<TargetInfo, io::Error> {
                 exception_table_index,
             }
         }
-        0x43 | 0x44 | 0x45 | 0x46 => {
+        0x43..=0x46 => {
             let offset = r.read_u16::<BigEndian>()?;
 
             TargetInfo::Offset {
@@ -878,7 +1015,7 @@ fn read_target_info<R: Read>(r: &mut R) -> Result<TargetInfo, io::Error> {
                 offset,
             }
         }
-        0x47 | 0x48 | 0x49 | 0x4A | 0x4B => {
+        0x47..=0x4B => {
             let offset = r.read_u16::<BigEndian>()?;
             let type_argument_index = r.read_u8()?;
 
@@ -888,7 +1025,7 @@ fn read_target_info<R: Read>(r: &mut R) -> Result<TargetInfo, io::Error> {
                 type_argument_index,
             }
         }
-        _ => unreachable!(),
+        _ => return Err(JavaError::UnknownTargetType(target_type).into()),
     })
 }
 
@@ -987,7 +1124,7 @@ fn read_module_provides<R: Read>(r: &mut R) -> Result<Vec<ModuleProvides>, io::E
     Ok(provides)
 }
 
-fn read_verification_type<R: Read>(r: &mut R) -> Result<VerificationType, io::Error> {
+fn read_verification_type<R: Read>(r: &mut R) -> Result<VerificationType, Box<dyn Error>> {
     let tag = r.read_u8()?;
 
     Ok(match tag {
@@ -1006,23 +1143,22 @@ fn read_verification_type<R: Read>(r: &mut R) -> Result<VerificationType, io::Er
             let offset = r.read_u16::<BigEndian>()?;
             VerificationType::Uninitialized { offset }
         }
-        _ => unreachable!(),
+        _ => return Err(JavaError::UnknownVerificationTypeTag(tag).into()),
     })
 }
 
-fn decompile<R: Read>(r: &mut R) -> Result<Vec<Instruction>, io::Error> {
+pub(crate) fn decompile(code: &[u8]) -> Result<Vec<Instruction>, Box<dyn Error>> {
+    read_instructions(code).map_err(eof_in("code"))
+}
+
+fn read_instructions(code: &[u8]) -> Result<Vec<Instruction>, Box<dyn Error>> {
     let mut instructions = vec![];
 
-    let code_length = r.read_u32::<BigEndian>()? as u64;
-    let mut code = vec![0u8; code_length as usize];
-    r.read(&mut code).unwrap();
+    let code_length = code.len() as u64;
     let mut cursor = Cursor::new(code);
 
-    // so we can't read from r instead of cursor
-    #[allow(unused)]
-    let r: &R = r;
-
-    while cursor.seek(SeekFrom::Current(0))? < code_length {
+    while cursor.stream_position()? < code_length {
+        let offset = cursor.position() as u32;
         let opcode = cursor.read_u8()?;
 
         let inst = match opcode {
@@ -1297,22 +1433,21 @@ fn decompile<R: Read>(r: &mut R) -> Result<Vec<Instruction>, io::Error> {
                 Instruction::Ret(index)
             }
             0xAA => {
-                let pos = cursor.seek(SeekFrom::Current(0))?;
+                let pos = cursor.stream_position()?;
                 let offset = ((4 - (pos % 4)) % 4) as i64;
                 let padding = offset as u32;
                 cursor.seek(SeekFrom::Current(offset))?;
 
-                let default = cursor.read_u32::<BigEndian>()?;
-                let minimum = cursor.read_u32::<BigEndian>()?;
-                let maximum = cursor.read_u32::<BigEndian>()?;
+                let default = cursor.read_i32::<BigEndian>()?;
+                let minimum = cursor.read_i32::<BigEndian>()?;
+                let maximum = cursor.read_i32::<BigEndian>()?;
 
                 let mut jump_targets = vec![];
 
                 for _ in minimum..=maximum {
-                    let jump_target = cursor.read_u32::<BigEndian>()?;
+                    let jump_target = cursor.read_i32::<BigEndian>()?;
                     jump_targets.push(jump_target);
                 }
-                assert_eq!(jump_targets.len() as u32, maximum - minimum + 1);
 
                 Instruction::TableSwitch {
                     padding,
@@ -1323,19 +1458,19 @@ fn decompile<R: Read>(r: &mut R) -> Result<Vec<Instruction>, io::Error> {
                 }
             }
             0xAB => {
-                let pos = cursor.seek(SeekFrom::Current(0))?;
+                let pos = cursor.stream_position()?;
                 let offset = ((4 - (pos % 4)) % 4) as i64;
-                let padding = (offset as u64 - pos) as u32;
+                let padding = offset as u32;
                 cursor.seek(SeekFrom::Current(offset))?;
 
-                let default = cursor.read_u32::<BigEndian>()?;
+                let default = cursor.read_i32::<BigEndian>()?;
                 let npairs = cursor.read_u32::<BigEndian>()?;
 
                 let mut pairs = vec![];
 
                 for _ in 0..npairs {
-                    let value = cursor.read_u32::<BigEndian>()?;
-                    let target = cursor.read_u32::<BigEndian>()?;
+                    let value = cursor.read_i32::<BigEndian>()?;
+                    let target = cursor.read_i32::<BigEndian>()?;
 
                     pairs.push(LookupSwitchPair { value, target });
                 }
@@ -1383,12 +1518,16 @@ fn decompile<R: Read>(r: &mut R) -> Result<Vec<Instruction>, io::Error> {
             0xB9 => {
                 let index = cursor.read_u16::<BigEndian>()?;
                 let count = cursor.read_u8()?;
+                if cursor.read_u8()? != 0 {
+                    return Err(JavaError::NonZeroPadding { opcode, offset }.into());
+                }
                 Instruction::InvokeInterface { index, count }
             }
             0xBA => {
                 let index = cursor.read_u16::<BigEndian>()?;
-                let zero = cursor.read_u16::<BigEndian>()?;
-                assert_eq!(zero, 0);
+                if cursor.read_u16::<BigEndian>()? != 0 {
+                    return Err(JavaError::NonZeroPadding { opcode, offset }.into());
+                }
                 Instruction::InvokeDynamic(index)
             }
             0xBB => {
@@ -1397,7 +1536,9 @@ fn decompile<R: Read>(r: &mut R) -> Result<Vec<Instruction>, io::Error> {
             }
             0xBC => {
                 let atype = cursor.read_u8()?;
-                Instruction::NewArray(atype)
+                let array_type = ArrayType::from_atype(atype)
+                    .ok_or(JavaError::InvalidArrayType { atype, offset })?;
+                Instruction::NewArray(array_type)
             }
             0xBD => {
                 let index = cursor.read_u16::<BigEndian>()?;
@@ -1432,10 +1573,10 @@ fn decompile<R: Read>(r: &mut R) -> Result<Vec<Instruction>, io::Error> {
                     0x3A => Instruction::AStoreW(index),
                     0xA9 => Instruction::RetW(index),
                     0x84 => {
-                        let count = cursor.read_u16::<BigEndian>()?;
+                        let count = cursor.read_i16::<BigEndian>()?;
                         Instruction::IIncW(index, count)
                     }
-                    _ => unreachable!(),
+                    _ => return Err(JavaError::UnknownOpcode { opcode, offset }.into()),
                 }
             }
             0xC5 => {
@@ -1452,14 +1593,14 @@ fn decompile<R: Read>(r: &mut R) -> Result<Vec<Instruction>, io::Error> {
                 Instruction::IfNonNull(branch)
             }
             0xC8 => {
-                let branch = cursor.read_u32::<BigEndian>()?;
+                let branch = cursor.read_i32::<BigEndian>()?;
                 Instruction::GotoW(branch)
             }
             0xC9 => {
-                let branch = cursor.read_u32::<BigEndian>()?;
+                let branch = cursor.read_i32::<BigEndian>()?;
                 Instruction::JsrW(branch)
             }
-            _ => panic!("Invalid opcode: {opcode:#X}"),
+            _ => return Err(JavaError::UnknownOpcode { opcode, offset }.into()),
         };
 
         instructions.push(inst);
@@ -0,0 +1,127 @@
+use std::collections::HashMap;
+
+use crate::{AccessFlag, JVMClass, JavaError, Method};
+
+/// Loads other classes by their internal name (e.g. `java/lang/Object`), typically from a classpath.
+pub trait Resolver {
+    fn resolve(&self, internal_name: &str) -> Option<JVMClass>;
+}
+
+/// A `Resolver` backed by classes already in memory.
+#[derive(Debug, Default)]
+pub struct MapResolver {
+    pub classes: HashMap<String, JVMClass>,
+}
+
+impl MapResolver {
+    pub fn new() -> Self {
+        Self {
+            classes: HashMap::new(),
+        }
+    }
+
+    pub fn insert(&mut self, internal_name: &str, class: JVMClass) {
+        self.classes.insert(internal_name.into(), class);
+    }
+}
+
+impl Resolver for MapResolver {
+    fn resolve(&self, internal_name: &str) -> Option<JVMClass> {
+        self.classes.get(internal_name).cloned()
+    }
+}
+
+impl JVMClass {
+    /// Returns the internal names of all the super classes, nearest first.
+    /// The chain stops at the first class the resolver can't load.
+    pub fn super_chain(&self, resolver: &dyn Resolver) -> Result<Vec<String>, JavaError> {
+        let mut chain: Vec<String> = vec![];
+
+        let mut current = match self.super_class {
+            0 => None,
+            id => Some(self.get_class_name(id)?.to_string()),
+        };
+
+        while let Some(name) = current.take() {
+            if chain.contains(&name) {
+                break;
+            }
+
+            if let Some(class) = resolver.resolve(&name) {
+                if class.super_class != 0 {
+                    current = Some(class.get_class_name(class.super_class)?.to_string());
+                }
+            }
+
+            chain.push(name);
+        }
+
+        Ok(chain)
+    }
+
+    /// Checks if `method`, declared in this class, overrides a method of a super class or interface.
+    pub fn overrides(&self, method: &Method, resolver: &dyn Resolver) -> Result<bool, JavaError> {
+        let data = &method.0;
+        let name = self.get_string(data.name)?;
+        if name.starts_with('<') || method.is_private() || method.is_static() {
+            return Ok(false);
+        }
+        let descriptor = self.get_string(data.descriptor)?;
+        let package = package_of(self.get_class_name(self.this_class)?);
+
+        let mut visited: Vec<String> = vec![];
+        let mut pending = self.direct_supertypes()?;
+
+        while let Some(class_name) = pending.pop() {
+            if visited.contains(&class_name) {
+                continue;
+            }
+
+            if let Some(class) = resolver.resolve(&class_name) {
+                for candidate in &class.methods {
+                    let candidate = &candidate.0;
+                    if class.get_string(candidate.name)? != name
+                        || class.get_string(candidate.descriptor)? != descriptor
+                        || candidate.has_flag(AccessFlag::Private)
+                        || candidate.has_flag(AccessFlag::Static)
+                    {
+                        continue;
+                    }
+
+                    let package_private = !candidate.has_flag(AccessFlag::Public)
+                        && !candidate.has_flag(AccessFlag::Protected);
+                    if !package_private || package_of(&class_name) == package {
+                        return Ok(true);
+                    }
+                }
+
+                pending.extend(class.direct_supertypes()?);
+            }
+
+            visited.push(class_name);
+        }
+
+        Ok(false)
+    }
+
+    fn direct_supertypes(&self) -> Result<Vec<String>, JavaError> {
+        let mut supertypes = vec![];
+
+        if self.super_class != 0 {
+            supertypes.push(self.get_class_name(self.super_class)?.to_string());
+        }
+
+        for interface in &self.interfaces {
+            supertypes.push(self.get_class_name(*interface)?.to_string());
+        }
+
+        Ok(supertypes)
+    }
+}
+
+fn package_of(internal_name: &str) -> &str {
+    match internal_name.rfind('/') {
+        Some(index) => &internal_name[..index],
+        None => "",
+    }
+}
@@ -0,0 +1,316 @@
+//! Generic signatures of `Signature` attributes, e.g. `<T:Ljava/lang/Object;>(TT;)Ljava/util/List<TT;>;`.
+
+use crate::{Attribute, JVMClass, JavaError, Method};
+
+/// The type of a field, parameter or return value, with its type arguments.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum TypeSignature {
+    Byte,
+    Char,
+    Double,
+    Float,
+    Int,
+    Long,
+    Short,
+    Boolean,
+    Class(ClassTypeSignature),
+    /// A type variable, by its name (e.g. `T`).
+    TypeVariable(String),
+    Array(Box<TypeSignature>),
+}
+
+/// A class type, e.g. `java/util/Map<TK;TV;>.Entry<TK;TV;>`: the outermost class by its internal
+/// name, followed by the simple names of the inner classes, each with its type arguments.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct ClassTypeSignature {
+    pub segments: Vec<SimpleClassTypeSignature>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct SimpleClassTypeSignature {
+    pub name: String,
+    pub type_arguments: Vec<TypeArgument>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum TypeArgument {
+    /// `*`, i.e. `?` in Java.
+    Any,
+    Exact(TypeSignature),
+    /// `+`, i.e. `? extends` in Java.
+    Extends(TypeSignature),
+    /// `-`, i.e. `? super` in Java.
+    Super(TypeSignature),
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct TypeParameter {
+    pub name: String,
+    /// `None` when the parameter is only bounded by interfaces, e.g. `T::Ljava/lang/Runnable;`.
+    pub class_bound: Option<TypeSignature>,
+    pub interface_bounds: Vec<TypeSignature>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct ClassSignature {
+    pub type_parameters: Vec<TypeParameter>,
+    pub super_class: ClassTypeSignature,
+    pub interfaces: Vec<ClassTypeSignature>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct MethodSignature {
+    pub type_parameters: Vec<TypeParameter>,
+    pub params: Vec<TypeSignature>,
+    /// `None` for `void`.
+    pub ret: Option<TypeSignature>,
+    /// The exceptions, classes or type variables.
+    pub throws: Vec<TypeSignature>,
+}
+
+impl ClassTypeSignature {
+    /// The internal name of the class, e.g. `java/util/Map$Entry`.
+    pub fn name(&self) -> String {
+        self.segments
+            .iter()
+            .map(|segment| segment.name.as_str())
+            .collect::<Vec<_>>()
+            .join("$")
+    }
+}
+
+pub fn parse_class_signature(signature: &str) -> Result<ClassSignature, JavaError> {
+    let invalid = || JavaError::InvalidSignature(signature.to_string());
+
+    let (type_parameters, rest) = parse_type_parameters(signature).ok_or_else(invalid)?;
+    let (super_class, mut rest) = parse_class_type(rest).ok_or_else(invalid)?;
+
+    let mut interfaces = vec![];
+    while !rest.is_empty() {
+        let (interface, next) = parse_class_type(rest).ok_or_else(invalid)?;
+        interfaces.push(interface);
+        rest = next;
+    }
+
+    Ok(ClassSignature {
+        type_parameters,
+        super_class,
+        interfaces,
+    })
+}
+
+pub fn parse_method_signature(signature: &str) -> Result<MethodSignature, JavaError> {
+    let invalid = || JavaError::InvalidSignature(signature.to_string());
+
+    let (type_parameters, rest) = parse_type_parameters(signature).ok_or_else(invalid)?;
+    let mut rest = rest.strip_prefix('(').ok_or_else(invalid)?;
+
+    let mut params = vec![];
+    while !rest.starts_with(')') {
+        let (param, next) = parse_type(rest).ok_or_else(invalid)?;
+        params.push(param);
+        rest = next;
+    }
+    rest = &rest[1..];
+
+    let ret = match rest.strip_prefix('V') {
+        Some(next) => {
+            rest = next;
+            None
+        }
+        None => {
+            let (ret, next) = parse_type(rest).ok_or_else(invalid)?;
+            rest = next;
+            Some(ret)
+        }
+    };
+
+    let mut throws = vec![];
+    while let Some(next) = rest.strip_prefix('^') {
+        let (exception, next) = parse_reference_type(next).ok_or_else(invalid)?;
+        throws.push(exception);
+        rest = next;
+    }
+
+    if !rest.is_empty() {
+        return Err(invalid());
+    }
+
+    Ok(MethodSignature {
+        type_parameters,
+        params,
+        ret,
+        throws,
+    })
+}
+
+/// Parses the signature of a field, which is a class, a type variable or an array.
+pub fn parse_field_signature(signature: &str) -> Result<TypeSignature, JavaError> {
+    match parse_reference_type(signature) {
+        Some((field_type, "")) => Ok(field_type),
+        _ => Err(JavaError::InvalidSignature(signature.to_string())),
+    }
+}
+
+/// Parses the optional `<…>` at the start of a class or method signature.
+fn parse_type_parameters(signature: &str) -> Option<(Vec<TypeParameter>, &str)> {
+    let Some(mut rest) = signature.strip_prefix('<') else {
+        return Some((vec![], signature));
+    };
+
+    let mut type_parameters = vec![];
+    while let Some((name, next)) = rest.split_once(':') {
+        if !is_identifier(name) {
+            break;
+        }
+
+        let (class_bound, mut next) = match next.starts_with(':') {
+            true => (None, next),
+            false => {
+                let (bound, next) = parse_reference_type(next)?;
+                (Some(bound), next)
+            }
+        };
+
+        let mut interface_bounds = vec![];
+        while let Some(bound) = next.strip_prefix(':') {
+            let (bound, after) = parse_reference_type(bound)?;
+            interface_bounds.push(bound);
+            next = after;
+        }
+
+        type_parameters.push(TypeParameter {
+            name: name.to_string(),
+            class_bound,
+            interface_bounds,
+        });
+        rest = next;
+
+        if let Some(next) = rest.strip_prefix('>') {
+            return Some((type_parameters, next));
+        }
+    }
+
+    None
+}
+
+/// Parses the type at the start of `signature`, returning it with the rest of the string.
+fn parse_type(signature: &str) -> Option<(TypeSignature, &str)> {
+    let rest = signature.get(1..)?;
+
+    let base_type = match signature.as_bytes().first()? {
+        b'B' => TypeSignature::Byte,
+        b'C' => TypeSignature::Char,
+        b'D' => TypeSignature::Double,
+        b'F' => TypeSignature::Float,
+        b'I' => TypeSignature::Int,
+        b'J' => TypeSignature::Long,
+        b'S' => TypeSignature::Short,
+        b'Z' => TypeSignature::Boolean,
+        _ => return parse_reference_type(signature),
+    };
+
+    Some((base_type, rest))
+}
+
+fn parse_reference_type(signature: &str) -> Option<(TypeSignature, &str)> {
+    let rest = signature.get(1..)?;
+
+    match signature.as_bytes().first()? {
+        b'L' => {
+            let (class_type, rest) = parse_class_type(signature)?;
+            Some((TypeSignature::Class(class_type), rest))
+        }
+        b'T' => {
+            let (name, rest) = rest.split_once(';')?;
+            is_identifier(name).then(|| (TypeSignature::TypeVariable(name.to_string()), rest))
+        }
+        b'[' => {
+            let (component, rest) = parse_type(rest)?;
+            Some((TypeSignature::Array(Box::new(component)), rest))
+        }
+        _ => None,
+    }
+}
+
+/// Parses a `L…;` class type.
+fn parse_class_type(signature: &str) -> Option<(ClassTypeSignature, &str)> {
+    let mut rest = signature.strip_prefix('L')?;
+
+    let mut segments = vec![];
+    loop {
+        let end = rest.find([';', '<', '.'])?;
+        let name = &rest[..end];
+        let valid = match segments.is_empty() {
+            true => name.split('/').all(is_identifier),
+            false => is_identifier(name),
+        };
+        if !valid {
+            return None;
+        }
+        rest = &rest[end..];
+
+        let mut type_arguments = vec![];
+        if let Some(mut next) = rest.strip_prefix('<') {
+            while !next.starts_with('>') {
+                let (argument, after) = parse_type_argument(next)?;
+                type_arguments.push(argument);
+                next = after;
+            }
+            if type_arguments.is_empty() {
+                return None;
+            }
+            rest = &next[1..];
+        }
+
+        segments.push(SimpleClassTypeSignature {
+            name: name.to_string(),
+            type_arguments,
+        });
+
+        match rest.as_bytes().first()? {
+            b';' => return Some((ClassTypeSignature { segments }, &rest[1..])),
+            b'.' => rest = &rest[1..],
+            _ => return None,
+        }
+    }
+}
+
+fn parse_type_argument(signature: &str) -> Option<(TypeArgument, &str)> {
+    let rest = signature.get(1..)?;
+
+    let (wrap, rest): (fn(TypeSignature) -> TypeArgument, _) = match signature.as_bytes().first()? {
+        b'*' => return Some((TypeArgument::Any, rest)),
+        b'+' => (TypeArgument::Extends, rest),
+        b'-' => (TypeArgument::Super, rest),
+        _ => (TypeArgument::Exact, signature),
+    };
+
+    let (argument, rest) = parse_reference_type(rest)?;
+    Some((wrap(argument), rest))
+}
+
+/// An unqualified name, which can't contain `.`, `;`, `[`, `/`, `<`, `>` or `:`.
+fn is_identifier(name: &str) -> bool {
+    !name.is_empty() && !name.contains(['.', ';', '[', '/', '<', '>', ':'])
+}
+
+impl Method {
+    /// Parses the `Signature` attribute of the method, `None` if it has none.
+    pub fn generic_signature(&self, jvm: &JVMClass) -> Option<Result<MethodSignature, JavaError>> {
+        self.0.attributes.iter().find_map(|attr| match attr {
+            Attribute::Signature { signature_index } => Some(
+                jvm.get_string(*signature_index)
+                    .and_then(parse_method_signature),
+            ),
+            _ => None,
+        })
+    }
+}
@@ -1,8 +1,14 @@
+use crate::analysis::{compute_max_locals, compute_max_stack};
+use crate::attribute_names;
+use crate::descriptor::parse_field_descriptor;
 use crate::enums::{
-    AccessFlag, Attribute, ElementValue, StackMapFrameType, TargetInfo, VerificationType,
+    AccessFlag, Attribute, ClassVersion, ConstValue, ElementValue, StackMapFrameType, TargetInfo,
+    VerificationType,
 };
+use crate::{JVMClass, JavaError};
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct MemberData {
     pub access_flags: Vec<AccessFlag>,
     pub name: u16,
@@ -10,43 +16,288 @@ pub struct MemberData {
     pub attributes: Vec<Attribute>,
 }
 
-#[derive(Debug, Clone)]
+const ACCESS_LEVELS: [AccessFlag; 3] = [
+    AccessFlag::Public,
+    AccessFlag::Private,
+    AccessFlag::Protected,
+];
+
+impl MemberData {
+    /// Checks if the member has the access flag `flag`.
+    pub fn has_flag(&self, flag: AccessFlag) -> bool {
+        self.access_flags.contains(&flag)
+    }
+
+    /// Adds `flag` if `set` and it is missing, removes it otherwise.
+    pub fn set_flag(&mut self, flag: AccessFlag, set: bool) {
+        if !set {
+            self.access_flags.retain(|other| *other != flag);
+        } else if !self.has_flag(flag) {
+            self.access_flags.push(flag);
+        }
+    }
+
+    /// Removes `Public`, `Private` and `Protected`, which exclude each other, to set another
+    /// access level (or none, for package-private members).
+    pub fn clear_access_level(&mut self) {
+        self.access_flags
+            .retain(|flag| !ACCESS_LEVELS.contains(flag));
+    }
+
+    /// The number of access levels the member has. `store` refuses more than one.
+    pub(crate) fn access_levels(&self) -> usize {
+        ACCESS_LEVELS
+            .into_iter()
+            .filter(|flag| self.has_flag(*flag))
+            .count()
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Field(pub MemberData);
 
-#[derive(Debug, Clone)]
+impl Field {
+    pub fn is_public(&self) -> bool {
+        self.0.has_flag(AccessFlag::Public)
+    }
+
+    pub fn is_private(&self) -> bool {
+        self.0.has_flag(AccessFlag::Private)
+    }
+
+    pub fn is_protected(&self) -> bool {
+        self.0.has_flag(AccessFlag::Protected)
+    }
+
+    pub fn is_static(&self) -> bool {
+        self.0.has_flag(AccessFlag::Static)
+    }
+
+    pub fn is_final(&self) -> bool {
+        self.0.has_flag(AccessFlag::Final)
+    }
+
+    pub fn is_volatile(&self) -> bool {
+        self.0.has_flag(AccessFlag::Volatile)
+    }
+
+    pub fn is_transient(&self) -> bool {
+        self.0.has_flag(AccessFlag::Transient)
+    }
+
+    pub fn is_synthetic(&self) -> bool {
+        self.0.has_flag(AccessFlag::Synthetic)
+    }
+
+    pub fn is_enum(&self) -> bool {
+        self.0.has_flag(AccessFlag::Enum)
+    }
+
+    /// Makes the field a constant, adding the value to the constant pool of `jvm` and setting
+    /// its `ConstantValue` attribute.
+    pub fn set_constant_value(&mut self, jvm: &mut JVMClass, value: ConstValue) {
+        jvm.get_or_add_utf8_index(attribute_names::CONSTANT_VALUE);
+
+        let mut constant_pool = jvm.constant_pool();
+        let constantvalue_index = match value {
+            ConstValue::Int(value) => constant_pool.intern_integer(value),
+            ConstValue::Long(value) => constant_pool.intern_long(value),
+            ConstValue::Float(value) => constant_pool.intern_float(value),
+            ConstValue::Double(value) => constant_pool.intern_double(value),
+            ConstValue::String(value) => constant_pool.intern_string(&value),
+        };
+
+        self.0
+            .attributes
+            .retain(|attr| !matches!(attr, Attribute::ConstantValue { .. }));
+        self.0.attributes.push(Attribute::ConstantValue {
+            constantvalue_index,
+        });
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Method(pub MemberData);
 
-#[derive(Debug, Clone)]
+impl Method {
+    pub fn is_public(&self) -> bool {
+        self.0.has_flag(AccessFlag::Public)
+    }
+
+    pub fn is_private(&self) -> bool {
+        self.0.has_flag(AccessFlag::Private)
+    }
+
+    pub fn is_protected(&self) -> bool {
+        self.0.has_flag(AccessFlag::Protected)
+    }
+
+    pub fn is_static(&self) -> bool {
+        self.0.has_flag(AccessFlag::Static)
+    }
+
+    pub fn is_final(&self) -> bool {
+        self.0.has_flag(AccessFlag::Final)
+    }
+
+    pub fn is_synchronized(&self) -> bool {
+        self.0.has_flag(AccessFlag::Synchronized)
+    }
+
+    pub fn is_bridge(&self) -> bool {
+        self.0.has_flag(AccessFlag::Bridge)
+    }
+
+    pub fn is_varargs(&self) -> bool {
+        self.0.has_flag(AccessFlag::VarArgs)
+    }
+
+    pub fn is_native(&self) -> bool {
+        self.0.has_flag(AccessFlag::Native)
+    }
+
+    pub fn is_abstract(&self) -> bool {
+        self.0.has_flag(AccessFlag::Abstract)
+    }
+
+    pub fn is_strict(&self) -> bool {
+        self.0.has_flag(AccessFlag::Strict)
+    }
+
+    pub fn is_synthetic(&self) -> bool {
+        self.0.has_flag(AccessFlag::Synthetic)
+    }
+
+    /// The `Code` attribute of the method, `None` for abstract and native methods.
+    pub fn code(&self) -> Option<&Attribute> {
+        self.0
+            .attributes
+            .iter()
+            .find(|attr| matches!(attr, Attribute::Code { .. }))
+    }
+
+    pub fn code_mut(&mut self) -> Option<&mut Attribute> {
+        self.0
+            .attributes
+            .iter_mut()
+            .find(|attr| matches!(attr, Attribute::Code { .. }))
+    }
+
+    /// The source line of the instruction at `pc`, see `Attribute::line_for_pc`. `None` for
+    /// methods without code or line numbers.
+    pub fn line_for_pc(&self, pc: u16) -> Option<u16> {
+        self.0
+            .attributes
+            .iter()
+            .find_map(|attr| attr.line_for_pc(pc))
+    }
+
+    /// Checks that the `max_stack` and `max_locals` of the `Code` attribute are big enough for
+    /// its code. Methods without code are fine.
+    pub fn verify_frame_sizes(&self, jvm: &JVMClass) -> Result<(), JavaError> {
+        let descriptor = jvm.get_string(self.0.descriptor)?;
+
+        for attr in &self.0.attributes {
+            let Attribute::Code {
+                code,
+                max_stack,
+                max_locals,
+                exception_table,
+                ..
+            } = attr
+            else {
+                continue;
+            };
+
+            let needed = compute_max_locals(code, descriptor, self.is_static())?;
+            if needed > *max_locals {
+                return Err(JavaError::MaxLocalsTooSmall {
+                    declared: *max_locals,
+                    needed,
+                });
+            }
+
+            let needed = compute_max_stack(code, exception_table, jvm)?;
+            if needed > *max_stack {
+                return Err(JavaError::MaxStackTooSmall {
+                    declared: *max_stack,
+                    needed,
+                });
+            }
+        }
+
+        Ok(())
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct LineNumber {
     pub start_pc: u16,
     pub line_number: u16,
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Annotation {
     pub type_index: u16,
     pub element_value_pairs: Vec<ElementValuePair>,
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct ElementValuePair {
     pub element_name_index: u16,
     pub value: ElementValue,
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct LookupSwitchPair {
-    pub value: u32,
-    pub target: u32,
+    pub value: i32,
+    pub target: i32,
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct BootstrapMethod {
     pub bootstrap_method_ref: u16,
     pub bootstrap_arguments: Vec<u16>,
 }
 
-#[derive(Debug, Clone)]
+/// The header of a class, as read by `JVMClass::peek_metadata`.
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct ClassMetadata {
+    pub version: ClassVersion,
+    pub name: String,
+    /// `None` for `java/lang/Object` and modules.
+    pub super_name: Option<String>,
+    pub flags: Vec<AccessFlag>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct DynamicInfo<'a> {
+    pub name: &'a str,
+    pub descriptor: &'a str,
+    pub bootstrap: &'a BootstrapMethod,
+}
+
+/// The `EnclosingMethod` of a local or anonymous class, as returned by
+/// `JVMClass::enclosing_method`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct EnclosingMethod<'a> {
+    /// The internal name of the enclosing class.
+    pub class: &'a str,
+    /// The `(name, descriptor)` of the method the class is declared in, `None` if it isn't
+    /// declared in a method (e.g. in an initializer).
+    pub method: Option<(&'a str, &'a str)>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct InnerClass {
     pub inner_class_info_index: u16,
     pub outer_class_info_index: u16,
@@ -54,7 +305,29 @@ pub struct InnerClass {
     pub inner_class_access_flags: Vec<AccessFlag>,
 }
 
-#[derive(Debug, Clone)]
+impl InnerClass {
+    /// Returns the internal name of the inner class, e.g. `java/util/Map$Entry`.
+    pub fn inner_class<'a>(&self, jvm: &'a JVMClass) -> Result<&'a str, JavaError> {
+        jvm.get_class_name(self.inner_class_info_index)
+    }
+
+    /// Returns the internal name of the class declaring the inner class as a member, or `None`
+    /// for top-level, local and anonymous classes.
+    pub fn outer_class<'a>(&self, jvm: &'a JVMClass) -> Result<Option<&'a str>, JavaError> {
+        match self.outer_class_info_index {
+            0 => Ok(None),
+            id => jvm.get_class_name(id).map(Some),
+        }
+    }
+
+    /// Returns the simple name of the inner class, or `None` if it is anonymous.
+    pub fn inner_name<'a>(&self, jvm: &'a JVMClass) -> Result<Option<&'a str>, JavaError> {
+        jvm.get_string_opt(self.inner_name_index)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct StackMapFrame {
     pub frame_type: StackMapFrameType,
     pub offset_delta: u16,
@@ -62,7 +335,8 @@ pub struct StackMapFrame {
     pub stack: Vec<VerificationType>,
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct LocalVariable {
     pub start_pc: u16,
     pub length: u16,
@@ -71,7 +345,8 @@ pub struct LocalVariable {
     pub index: u16,
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct LocalVariableType {
     pub start_pc: u16,
     pub length: u16,
@@ -80,67 +355,255 @@ pub struct LocalVariableType {
     pub index: u16,
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct MethodParameter {
     pub name_index: u16,
     pub access_flags: Vec<AccessFlag>,
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct ModuleRequires {
     pub requires_index: u16,
     pub requires_flags: Vec<AccessFlag>,
     pub requires_version_index: u16,
 }
 
-#[derive(Debug, Clone)]
+impl ModuleRequires {
+    /// Returns the version of the required module, or `None` if the dependence is unversioned.
+    pub fn version<'a>(&self, jvm: &'a JVMClass) -> Result<Option<&'a str>, JavaError> {
+        jvm.get_string_opt(self.requires_version_index)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct ModuleExports {
     pub exports_index: u16,
     pub exports_flags: Vec<AccessFlag>,
     pub exports_to_index: Vec<u16>,
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct ModuleOpens {
     pub opens_index: u16,
     pub opens_flags: Vec<AccessFlag>,
     pub opens_to_index: Vec<u16>,
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct ModuleProvides {
     pub provides_index: u16,
     pub provides_with_index: Vec<u16>,
 }
 
-#[derive(Debug, Clone)]
+/// The `Module` attribute of a `module-info.class` with its indices resolved to names, as
+/// returned by `JVMClass::module_info`. Packages and classes use internal names.
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct ModuleInfo {
+    pub name: String,
+    pub flags: Vec<AccessFlag>,
+    pub version: Option<String>,
+    /// The required modules, with their flags and the version they were compiled against.
+    pub requires: Vec<(String, Vec<AccessFlag>, Option<String>)>,
+    /// The exported packages, with their flags and the modules they are exported to (empty if
+    /// exported to all of them).
+    pub exports: Vec<(String, Vec<AccessFlag>, Vec<String>)>,
+    /// The opened packages, like `exports`.
+    pub opens: Vec<(String, Vec<AccessFlag>, Vec<String>)>,
+    /// The services used by the module.
+    pub uses: Vec<String>,
+    /// The services provided by the module, with their implementations.
+    pub provides: Vec<(String, Vec<String>)>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct RecordComponent {
     pub name_index: u16,
     pub descriptor_index: u16,
     pub attributes: Vec<Attribute>,
 }
 
-#[derive(Debug, Clone)]
+impl RecordComponent {
+    /// The generic type of the component (e.g. `Ljava/util/List<Ljava/lang/String;>;`), from its
+    /// `Signature` attribute. `None` if it has none, or if the signature can't be resolved.
+    pub fn signature(&self, jvm: &JVMClass) -> Option<String> {
+        self.attributes
+            .iter()
+            .find_map(|attribute| match attribute {
+                Attribute::Signature { signature_index } => {
+                    jvm.get_string(*signature_index).ok().map(String::from)
+                }
+                _ => None,
+            })
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct LocalVar {
     pub start_pc: u16,
     pub length: u16,
     pub index: u16,
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct TypePath {
     pub type_path_kind: u8,
     pub type_argument_index: u8,
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct TypeAnnotation {
     pub target_info: TargetInfo,
     pub target_path: Vec<TypePath>,
     pub annotation: Annotation,
 }
 
-#[derive(Debug, Clone)]
+impl TypeAnnotation {
+    /// Describes the annotation and the type it annotates, e.g. `@NonNull on type of field name`
+    /// or `@A on type parameter 0 bound 1 of method foo, type argument 0`. `member` is the field
+    /// or method the annotation was read from, `None` for the annotations of the class itself.
+    /// The annotation type is resolved with `jvm`, as is the interface of an `implements` clause.
+    pub fn describe(&self, jvm: &JVMClass, member: Option<&MemberData>) -> String {
+        let annotation = match jvm.get_string(self.annotation.type_index) {
+            Ok(descriptor) => match parse_field_descriptor(descriptor) {
+                Ok(field_type) => field_type.to_java_string(),
+                Err(_) => descriptor.to_string(),
+            },
+            Err(_) => format!("#{}", self.annotation.type_index),
+        };
+
+        let owner = |kind: &str| match member {
+            Some(member) => match jvm.get_string(member.name) {
+                Ok(name) => format!("{kind} {name}"),
+                Err(_) => format!("{kind} #{}", member.name),
+            },
+            None => kind.to_string(),
+        };
+        let declaration = |target_type: u8| match target_type {
+            0x00 | 0x11 => match jvm.this_class_name() {
+                Ok(name) => format!("class {}", name.replace('/', ".")),
+                Err(_) => "class".to_string(),
+            },
+            _ => owner("method"),
+        };
+
+        let mut target = match &self.target_info {
+            TargetInfo::TypeParameter {
+                target_type,
+                type_parameter_index,
+            } => format!(
+                "type parameter {type_parameter_index} of {}",
+                declaration(*target_type)
+            ),
+            TargetInfo::Supertype {
+                supertype_index: u16::MAX,
+            } => "extends clause".to_string(),
+            TargetInfo::Supertype { supertype_index } => {
+                match jvm
+                    .interfaces
+                    .get(*supertype_index as usize)
+                    .and_then(|id| jvm.get_class_name(*id).ok())
+                {
+                    Some(name) => format!("implements clause {}", name.replace('/', ".")),
+                    None => format!("implements clause {supertype_index}"),
+                }
+            }
+            TargetInfo::TypeParameterBound {
+                target_type,
+                type_parameter_index,
+                bound_index,
+            } => format!(
+                "type parameter {type_parameter_index} bound {bound_index} of {}",
+                declaration(*target_type)
+            ),
+            TargetInfo::Empty(0x13) => format!("type of {}", owner("field")),
+            TargetInfo::Empty(0x14) => format!("return type of {}", owner("method")),
+            TargetInfo::Empty(_) => format!("receiver type of {}", owner("method")),
+            TargetInfo::FormalParameter {
+                formal_parameter_index,
+            } => format!("parameter {formal_parameter_index} of {}", owner("method")),
+            TargetInfo::Throws { throws_type_index } => {
+                format!("throws clause {throws_type_index} of {}", owner("method"))
+            }
+            TargetInfo::Localvar {
+                target_type: 0x41, ..
+            } => format!("resource variable in {}", owner("method")),
+            TargetInfo::Localvar { .. } => format!("local variable in {}", owner("method")),
+            TargetInfo::Catch {
+                exception_table_index,
+            } => format!(
+                "catch clause of exception handler {exception_table_index} in {}",
+                owner("method")
+            ),
+            TargetInfo::Offset {
+                target_type,
+                offset,
+            } => {
+                let expression = match target_type {
+                    0x43 => "instanceof",
+                    0x44 => "new",
+                    0x45 => "::new",
+                    _ => "method reference",
+                };
+                format!("{expression} at pc {offset} in {}", owner("method"))
+            }
+            // the index of a cast is the position of the type in an intersection type
+            TargetInfo::TypeArgument {
+                target_type: 0x47,
+                offset,
+                type_argument_index: 0,
+            } => format!("cast at pc {offset} in {}", owner("method")),
+            TargetInfo::TypeArgument {
+                target_type: 0x47,
+                offset,
+                type_argument_index,
+            } => format!(
+                "type {type_argument_index} of cast at pc {offset} in {}",
+                owner("method")
+            ),
+            TargetInfo::TypeArgument {
+                target_type,
+                offset,
+                type_argument_index,
+            } => {
+                let expression = match target_type {
+                    0x48 => "constructor call",
+                    0x49 => "method call",
+                    0x4A => "::new",
+                    _ => "method reference",
+                };
+                format!(
+                    "type argument {type_argument_index} of {expression} at pc {offset} in {}",
+                    owner("method")
+                )
+            }
+        };
+
+        for step in &self.target_path {
+            target += match step.type_path_kind {
+                0 => ", array element".to_string(),
+                1 => ", nested type".to_string(),
+                2 => ", wildcard bound".to_string(),
+                _ => format!(", type argument {}", step.type_argument_index),
+            }
+            .as_str();
+        }
+
+        format!("@{annotation} on {target}")
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct ExceptionTableEntry {
     pub start_pc: u16,
     pub end_pc: u16,
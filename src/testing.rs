@@ -0,0 +1,44 @@
+//! Assertions for tests checking that classes survive being loaded and stored.
+//!
+//! `assert_roundtrips` is the strict one: classes written by `javac` come back byte for byte.
+//! Classes from other compilers or obfuscators can use encodings that are stored differently
+//! (e.g. non-zero switch padding, or a `ldc_w` where a `ldc` fits), in which case
+//! `assert_semantic_roundtrip` checks that nothing is lost.
+
+use crate::JVMClass;
+
+/// Loads `bytes`, stores the class and panics if the result isn't the same bytes, reporting the
+/// first differing offset.
+pub fn assert_roundtrips(bytes: &[u8]) {
+    let jvm = JVMClass::from_bytes(bytes).unwrap_or_else(|e| panic!("Loading failed: {e}"));
+    let stored = jvm
+        .to_bytes()
+        .unwrap_or_else(|e| panic!("Storing failed: {e}"));
+
+    if let Some(offset) = bytes.iter().zip(&stored).position(|(a, b)| a != b) {
+        panic!(
+            "The stored class differs at offset {offset}: {:#04X} instead of {:#04X}",
+            stored[offset], bytes[offset]
+        );
+    }
+
+    assert_eq!(
+        stored.len(),
+        bytes.len(),
+        "The stored class is {} bytes long instead of {}",
+        stored.len(),
+        bytes.len()
+    );
+}
+
+/// Loads `bytes`, stores the class, reloads it and panics if the two classes aren't equal.
+pub fn assert_semantic_roundtrip(bytes: &[u8]) {
+    let jvm = JVMClass::from_bytes(bytes).unwrap_or_else(|e| panic!("Loading failed: {e}"));
+    let stored = jvm
+        .to_bytes()
+        .unwrap_or_else(|e| panic!("Storing failed: {e}"));
+    let reloaded =
+        JVMClass::from_bytes(&stored).unwrap_or_else(|e| panic!("Reloading failed: {e}"));
+
+    assert_eq!(reloaded, jvm, "The reloaded class differs");
+}
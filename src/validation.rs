@@ -0,0 +1,863 @@
+use crate::analysis::check_stack_map;
+use crate::{
+    AccessFlag, Annotation, Attribute, ClassVersion, Constant, ElementValue, Instruction, JVMClass,
+    JavaError, MemberData, ReferenceKind, TargetInfo, VerificationType,
+};
+
+impl JVMClass {
+    /// Checks if this class is a `module-info.class`, i.e. has the `ACC_MODULE` flag.
+    pub fn is_module_info(&self) -> bool {
+        self.is_module()
+    }
+
+    /// Checks the class for structural inconsistencies, including dangling or mistyped constant
+    /// pool references, returning every problem found.
+    pub fn validate(&self) -> Result<(), Vec<JavaError>> {
+        let mut errors = vec![];
+
+        if let Err(error) = self.validate_version() {
+            errors.push(error);
+        }
+
+        if self.is_module_info() {
+            self.validate_module_info(&mut errors);
+        } else {
+            self.validate_hierarchy(&mut errors);
+        }
+
+        self.validate_constants(&mut errors);
+        self.validate_references(&mut errors);
+
+        if errors.is_empty() {
+            Ok(())
+        } else {
+            Err(errors)
+        }
+    }
+
+    /// Checks that the version exists. Versions 46 to 55 only exist with a minor of 0, and later
+    /// ones can also use 65535 for preview features, which Java 11 and earlier don't have. The
+    /// version 45 allowed any minor.
+    pub fn validate_version(&self) -> Result<(), JavaError> {
+        let valid = match self.major {
+            0..=44 => false,
+            45 => true,
+            46..=55 => self.minor == 0,
+            _ => self.minor == 0 || self.minor == 65535,
+        };
+
+        if !valid {
+            return Err(JavaError::UnsupportedVersion(self.major, self.minor));
+        }
+
+        Ok(())
+    }
+
+    /// The oldest release whose class files can hold the constants, attributes and instructions
+    /// of the class, e.g. Java 7 for an `invokedynamic` or Java 16 for a `Record` attribute.
+    /// Useful to pick the version of an assembled class, as the JVM rejects the constants and
+    /// instructions newer than the class file (and ignores the attributes).
+    pub fn min_required_version(&self) -> ClassVersion {
+        let members = self
+            .fields
+            .iter()
+            .map(|field| &field.0)
+            .chain(self.methods.iter().map(|method| &method.0));
+
+        let constants = self.constants.iter().map(constant_major);
+        let attributes = std::iter::once(&self.attributes)
+            .chain(members.map(|member| &member.attributes))
+            .map(|attributes| attributes_major(attributes));
+        let instructions = self
+            .all_code()
+            .flat_map(|(_, code)| code.instructions())
+            .map(|inst| self.instruction_major(inst));
+
+        let major = constants
+            .chain(attributes)
+            .chain(instructions)
+            .max()
+            .unwrap_or(45);
+
+        ClassVersion::from_version(major, 0)
+    }
+
+    /// The first major version with the instruction: `invokedynamic` needs 51, and `ldc` of a
+    /// class 49.
+    fn instruction_major(&self, inst: &Instruction) -> u16 {
+        match inst {
+            Instruction::InvokeDynamic(_) => 51,
+            Instruction::Ldc(id) if self.get_class_name(*id as u16).is_ok() => 49,
+            Instruction::LdcW(id) if self.get_class_name(*id).is_ok() => 49,
+            _ => 45,
+        }
+    }
+
+    /// Runs every check of `validate`, plus the access flags, the number of attributes of each
+    /// kind, and optionally the stack maps of the methods, returning every problem found.
+    pub fn verify(&self, options: VerifyOptions) -> Result<(), Vec<JavaError>> {
+        let mut errors = self.validate().err().unwrap_or_default();
+
+        self.validate_access_flags(&mut errors);
+        self.validate_attribute_counts(&mut errors, &self.attributes);
+        for member in self
+            .fields
+            .iter()
+            .map(|field| &field.0)
+            .chain(self.methods.iter().map(|method| &method.0))
+        {
+            self.validate_attribute_counts(&mut errors, &member.attributes);
+        }
+
+        if options.stack_maps {
+            self.validate_stack_maps(&mut errors);
+        }
+
+        if errors.is_empty() {
+            Ok(())
+        } else {
+            Err(errors)
+        }
+    }
+
+    fn validate_hierarchy(&self, errors: &mut Vec<JavaError>) {
+        if self.super_class == 0
+            && self.get_class_name(self.this_class).ok() != Some("java/lang/Object")
+        {
+            errors.push(JavaError::MissingSuperClass(
+                self.display_index(self.this_class),
+            ));
+        }
+
+        // Compare the names rather than the indices, as a pool can hold the same class twice.
+        // Unresolvable indices are reported by `validate_references`.
+        let this_name = self.get_class_name(self.this_class).ok();
+        if self.super_class != 0
+            && this_name.is_some()
+            && self.get_class_name(self.super_class).ok() == this_name
+        {
+            errors.push(JavaError::SelfInheritance(
+                self.display_index(self.this_class),
+            ));
+        }
+
+        let mut seen = vec![];
+        let mut duplicates = vec![];
+        for interface in &self.interfaces {
+            let Ok(name) = self.get_class_name(*interface) else {
+                continue;
+            };
+
+            if seen.contains(&name) && !duplicates.contains(&name) {
+                duplicates.push(name);
+                errors.push(JavaError::DuplicateInterface(name.into()));
+            }
+            seen.push(name);
+        }
+    }
+
+    /// A module has no super class, no members, and declares itself in a `Module` attribute.
+    fn validate_module_info(&self, errors: &mut Vec<JavaError>) {
+        if self.get_class_name(self.this_class).ok() != Some("module-info") {
+            errors.push(JavaError::InvalidModuleInfo(format!(
+                "this_class is {} instead of module-info",
+                self.display_index(self.this_class)
+            )));
+        }
+
+        if self.super_class != 0 {
+            errors.push(JavaError::InvalidModuleInfo("it has a super class".into()));
+        }
+
+        if !self.interfaces.is_empty() || !self.fields.is_empty() || !self.methods.is_empty() {
+            errors.push(JavaError::InvalidModuleInfo(
+                "it has interfaces, fields or methods".into(),
+            ));
+        }
+
+        let has_module = self
+            .attributes
+            .iter()
+            .any(|attr| matches!(attr, Attribute::Module { .. }));
+        if !has_module {
+            errors.push(JavaError::InvalidModuleInfo(
+                "it has no Module attribute".into(),
+            ));
+        }
+    }
+
+    fn validate_access_flags(&self, errors: &mut Vec<JavaError>) {
+        let class_name = self.display_index(self.this_class);
+
+        if self.is_interface()
+            && (!self.is_abstract()
+                || self.is_final()
+                || self.is_enum()
+                || self.has_flag(AccessFlag::Super))
+        {
+            errors.push(JavaError::InvalidAccessFlags(format!(
+                "interface {class_name} must be abstract, and not final, enum or super"
+            )));
+        }
+        if self.is_annotation() && !self.is_interface() {
+            errors.push(JavaError::InvalidAccessFlags(format!(
+                "annotation {class_name} must be an interface"
+            )));
+        }
+        if self.is_final() && self.is_abstract() {
+            errors.push(JavaError::InvalidAccessFlags(format!(
+                "class {class_name} can't be both final and abstract"
+            )));
+        }
+
+        for field in &self.fields {
+            let name = self.display_index(field.0.name);
+
+            if field.0.access_levels() > 1 {
+                errors.push(JavaError::InvalidAccessFlags(format!(
+                    "field {name} has more than one access level"
+                )));
+            }
+            if field.is_final() && field.is_volatile() {
+                errors.push(JavaError::InvalidAccessFlags(format!(
+                    "field {name} can't be both final and volatile"
+                )));
+            }
+            if self.is_interface() && !(field.is_public() && field.is_static() && field.is_final())
+            {
+                errors.push(JavaError::InvalidAccessFlags(format!(
+                    "field {name} of an interface must be public, static and final"
+                )));
+            }
+        }
+
+        for method in &self.methods {
+            let name = self.display_index(method.0.name);
+
+            if method.0.access_levels() > 1 {
+                errors.push(JavaError::InvalidAccessFlags(format!(
+                    "method {name} has more than one access level"
+                )));
+            }
+            if method.is_abstract()
+                && (method.is_private()
+                    || method.is_static()
+                    || method.is_final()
+                    || method.is_synchronized()
+                    || method.is_native())
+            {
+                errors.push(JavaError::InvalidAccessFlags(format!(
+                    "abstract method {name} can't be private, static, final, synchronized or native"
+                )));
+            }
+            if name == "<init>"
+                && (method.is_static()
+                    || method.is_final()
+                    || method.is_synchronized()
+                    || method.is_native()
+                    || method.is_abstract()
+                    || method.is_bridge())
+            {
+                errors.push(JavaError::InvalidAccessFlags(format!(
+                    "constructor of {class_name} can't be static, final, synchronized, native, abstract or bridge"
+                )));
+            }
+
+            let code_count = method
+                .0
+                .attributes
+                .iter()
+                .filter(|attr| matches!(attr, Attribute::Code { .. }))
+                .count();
+            let has_body = !method.is_abstract() && !method.is_native();
+            if has_body && code_count == 0 {
+                errors.push(JavaError::MissingCode(name));
+            } else if !has_body && code_count > 0 {
+                errors.push(JavaError::UnexpectedCode(name));
+            }
+        }
+    }
+
+    /// Only the tables relating to pcs can appear more than once, other known attributes can't.
+    fn validate_attribute_counts(&self, errors: &mut Vec<JavaError>, attributes: &[Attribute]) {
+        for (i, attr) in attributes.iter().enumerate() {
+            let repeatable = matches!(
+                attr,
+                Attribute::LineNumberTable(..)
+                    | Attribute::LocalVariableTable(..)
+                    | Attribute::LocalVariableTypeTable(..)
+                    | Attribute::Unknown { .. }
+            );
+            let first = !attributes[..i]
+                .iter()
+                .any(|previous| previous.name() == attr.name());
+            let duplicated = attributes[i + 1..]
+                .iter()
+                .any(|next| next.name() == attr.name());
+            if !repeatable && first && duplicated {
+                errors.push(JavaError::DuplicateAttribute(attr.name().to_string()));
+            }
+
+            match attr {
+                Attribute::Code { attributes, .. } => {
+                    self.validate_attribute_counts(errors, attributes)
+                }
+                Attribute::Record(components) => {
+                    for component in components {
+                        self.validate_attribute_counts(errors, &component.attributes);
+                    }
+                }
+                _ => {}
+            }
+        }
+    }
+
+    fn validate_stack_maps(&self, errors: &mut Vec<JavaError>) {
+        for method in &self.methods {
+            let Ok(descriptor) = self.get_string(method.0.descriptor) else {
+                // already reported by validate_references
+                continue;
+            };
+
+            for attr in &method.0.attributes {
+                if let Err(error) = check_stack_map(attr, method.is_static(), descriptor, self) {
+                    errors.push(error);
+                }
+            }
+        }
+    }
+
+    /// Checks that the constants only refer to existing constants of the right type.
+    fn validate_constants(&self, errors: &mut Vec<JavaError>) {
+        let bootstrap_methods = self.bootstrap_methods().map_or(0, |methods| methods.len());
+
+        for (id, constant) in self.constants.iter().enumerate() {
+            match constant {
+                Constant::Class { name_index }
+                | Constant::Module { name_index }
+                | Constant::Package { name_index } => self.expect(errors, *name_index, UTF8),
+                Constant::String { string_index } => self.expect(errors, *string_index, UTF8),
+                Constant::MethodType { descriptor_index } => {
+                    self.expect(errors, *descriptor_index, UTF8)
+                }
+                Constant::NameAndType {
+                    name_index,
+                    descriptor_index,
+                } => {
+                    self.expect(errors, *name_index, UTF8);
+                    self.expect(errors, *descriptor_index, UTF8);
+                }
+                Constant::Fieldref {
+                    class_index,
+                    name_and_type_index,
+                }
+                | Constant::Methodref {
+                    class_index,
+                    name_and_type_index,
+                }
+                | Constant::InterfaceMethodref {
+                    class_index,
+                    name_and_type_index,
+                } => {
+                    self.expect(errors, *class_index, CLASS);
+                    self.expect(errors, *name_and_type_index, NAME_AND_TYPE);
+                }
+                Constant::Dynamic {
+                    bootstrap_method_attr_index,
+                    name_and_type_index,
+                }
+                | Constant::InvokeDynamic {
+                    bootstrap_method_attr_index,
+                    name_and_type_index,
+                } => {
+                    if *bootstrap_method_attr_index as usize >= bootstrap_methods {
+                        errors.push(JavaError::BootstrapMethodNotFound(
+                            *bootstrap_method_attr_index,
+                        ));
+                    }
+                    self.expect(errors, *name_and_type_index, NAME_AND_TYPE);
+                }
+                Constant::MethodHandle { .. } => self.check_method_handle(errors, id as u16),
+                _ => {}
+            }
+        }
+    }
+
+    /// Checks that the `Constant::MethodHandle` at `id` refers to a member suitable for its kind:
+    /// a field for `REF_getField` to `REF_putStatic`, a method otherwise, and a constructor if
+    /// and only if it is a `REF_newInvokeSpecial`.
+    pub fn validate_method_handle(&self, id: u16) -> Result<(), JavaError> {
+        let mut errors = vec![];
+        self.check_method_handle(&mut errors, id);
+
+        match errors.into_iter().next() {
+            Some(error) => Err(error),
+            None => Ok(()),
+        }
+    }
+
+    fn check_method_handle(&self, errors: &mut Vec<JavaError>, id: u16) {
+        let Some(Constant::MethodHandle {
+            reference_kind,
+            reference_index,
+        }) = self.constants.get(id as usize)
+        else {
+            return self.expect(errors, id, METHOD_HANDLE);
+        };
+
+        let expected = match reference_kind {
+            ReferenceKind::GetField
+            | ReferenceKind::GetStatic
+            | ReferenceKind::PutField
+            | ReferenceKind::PutStatic => return self.expect(errors, *reference_index, FIELDREF),
+            ReferenceKind::InvokeVirtual | ReferenceKind::NewInvokeSpecial => METHODREF,
+            // interface methods are only allowed since Java 8
+            ReferenceKind::InvokeStatic | ReferenceKind::InvokeSpecial if self.major < 52 => {
+                METHODREF
+            }
+            ReferenceKind::InvokeStatic | ReferenceKind::InvokeSpecial => ANY_METHODREF,
+            ReferenceKind::InvokeInterface => INTERFACE_METHODREF,
+        };
+        let errors_before = errors.len();
+        self.expect(errors, *reference_index, expected);
+        if errors.len() != errors_before {
+            return;
+        }
+
+        let Ok((_, name, _)) = self.resolve_ref(*reference_index) else {
+            return;
+        };
+        let valid = match reference_kind {
+            ReferenceKind::NewInvokeSpecial => name == "<init>",
+            _ => name != "<init>" && name != "<clinit>",
+        };
+        if !valid {
+            errors.push(JavaError::ConstantTypeError(format!(
+                "#{id} is a {} method handle to {name}",
+                reference_kind.name()
+            )));
+        }
+    }
+
+    /// Checks the constants referred to by the class, its members, their attributes and code.
+    fn validate_references(&self, errors: &mut Vec<JavaError>) {
+        self.expect(errors, self.this_class, CLASS);
+        self.expect_optional(errors, self.super_class, CLASS);
+        for interface in &self.interfaces {
+            self.expect(errors, *interface, CLASS);
+        }
+
+        for member in self
+            .fields
+            .iter()
+            .map(|field| &field.0)
+            .chain(self.methods.iter().map(|method| &method.0))
+        {
+            self.validate_member(errors, member);
+        }
+
+        self.validate_attributes(errors, &self.attributes);
+    }
+
+    fn validate_member(&self, errors: &mut Vec<JavaError>, member: &MemberData) {
+        self.expect(errors, member.name, UTF8);
+        self.expect(errors, member.descriptor, UTF8);
+        self.validate_attributes(errors, &member.attributes);
+    }
+
+    fn validate_attributes(&self, errors: &mut Vec<JavaError>, attributes: &[Attribute]) {
+        for attr in attributes {
+            match attr {
+                Attribute::AnnotationDefault(value) => self.validate_element_value(errors, value),
+                Attribute::BootstrapMethods(bootstrap_methods) => {
+                    for bootstrap_method in bootstrap_methods {
+                        self.expect(errors, bootstrap_method.bootstrap_method_ref, METHOD_HANDLE);
+                        for argument in &bootstrap_method.bootstrap_arguments {
+                            self.expect(errors, *argument, LOADABLE);
+                        }
+                    }
+                }
+                Attribute::Code {
+                    code,
+                    exception_table,
+                    attributes,
+                    ..
+                } => {
+                    for inst in code {
+                        self.validate_instruction(errors, inst);
+                    }
+                    for entry in exception_table {
+                        self.expect_optional(errors, entry.catch_type, CLASS);
+                    }
+                    self.validate_attributes(errors, attributes);
+                }
+                Attribute::ConstantValue {
+                    constantvalue_index,
+                } => self.expect(errors, *constantvalue_index, CONSTANT_VALUE),
+                Attribute::EnclosingMethod {
+                    class_index,
+                    method_index,
+                } => {
+                    self.expect(errors, *class_index, CLASS);
+                    self.expect_optional(errors, *method_index, NAME_AND_TYPE);
+                }
+                Attribute::Exceptions(classes)
+                | Attribute::NestMembers(classes)
+                | Attribute::PermittedSubclasses(classes) => {
+                    for class in classes {
+                        self.expect(errors, *class, CLASS);
+                    }
+                }
+                Attribute::NestHost(class) => self.expect(errors, *class, CLASS),
+                Attribute::InnerClasses(inner_classes) => {
+                    for inner_class in inner_classes {
+                        self.expect(errors, inner_class.inner_class_info_index, CLASS);
+                        self.expect_optional(errors, inner_class.outer_class_info_index, CLASS);
+                        self.expect_optional(errors, inner_class.inner_name_index, UTF8);
+                    }
+                }
+                Attribute::LocalVariableTable(locals) => {
+                    for local in locals {
+                        self.expect(errors, local.name_index, UTF8);
+                        self.expect(errors, local.descriptor_index, UTF8);
+                    }
+                }
+                Attribute::LocalVariableTypeTable(locals) => {
+                    for local in locals {
+                        self.expect(errors, local.name_index, UTF8);
+                        self.expect(errors, local.signature_index, UTF8);
+                    }
+                }
+                Attribute::MethodParameters(parameters) => {
+                    for parameter in parameters {
+                        self.expect_optional(errors, parameter.name_index, UTF8);
+                    }
+                }
+                Attribute::Module {
+                    module_name_index,
+                    module_version_index,
+                    requires,
+                    exports,
+                    opens,
+                    uses,
+                    provides,
+                    ..
+                } => {
+                    self.expect(errors, *module_name_index, MODULE);
+                    self.expect_optional(errors, *module_version_index, UTF8);
+                    for require in requires {
+                        self.expect(errors, require.requires_index, MODULE);
+                        self.expect_optional(errors, require.requires_version_index, UTF8);
+                    }
+                    for export in exports {
+                        self.expect(errors, export.exports_index, PACKAGE);
+                        for module in &export.exports_to_index {
+                            self.expect(errors, *module, MODULE);
+                        }
+                    }
+                    for open in opens {
+                        self.expect(errors, open.opens_index, PACKAGE);
+                        for module in &open.opens_to_index {
+                            self.expect(errors, *module, MODULE);
+                        }
+                    }
+                    for service in uses {
+                        self.expect(errors, *service, CLASS);
+                    }
+                    for provide in provides {
+                        self.expect(errors, provide.provides_index, CLASS);
+                        for implementation in &provide.provides_with_index {
+                            self.expect(errors, *implementation, CLASS);
+                        }
+                    }
+                }
+                Attribute::ModuleMainClass(class) => self.expect(errors, *class, CLASS),
+                Attribute::ModulePackages(packages) => {
+                    for package in packages {
+                        self.expect(errors, *package, PACKAGE);
+                    }
+                }
+                Attribute::Record(components) => {
+                    for component in components {
+                        self.expect(errors, component.name_index, UTF8);
+                        self.expect(errors, component.descriptor_index, UTF8);
+                        self.validate_attributes(errors, &component.attributes);
+                    }
+                }
+                Attribute::RuntimeInvisibleAnnotations(annotations)
+                | Attribute::RuntimeVisibleAnnotations(annotations) => {
+                    for annotation in annotations {
+                        self.validate_annotation(errors, annotation);
+                    }
+                }
+                Attribute::RuntimeInvisibleParameterAnnotations(parameters)
+                | Attribute::RuntimeVisibleParameterAnnotations(parameters) => {
+                    for annotation in parameters.iter().flatten() {
+                        self.validate_annotation(errors, annotation);
+                    }
+                }
+                Attribute::RuntimeInvisibleTypeAnnotations(annotations)
+                | Attribute::RuntimeVisibleTypeAnnotations(annotations) => {
+                    for type_annotation in annotations {
+                        if let TargetInfo::Supertype { supertype_index } =
+                            type_annotation.target_info
+                        {
+                            if supertype_index != 65535
+                                && supertype_index as usize >= self.interfaces.len()
+                            {
+                                errors.push(JavaError::ConstantTypeError(format!(
+                                    "Supertype {supertype_index} of a type annotation doesn't exist"
+                                )));
+                            }
+                        }
+                        self.validate_annotation(errors, &type_annotation.annotation);
+                    }
+                }
+                Attribute::Signature { signature_index } => {
+                    self.expect(errors, *signature_index, UTF8)
+                }
+                Attribute::SourceFile { sourcefile_index } => {
+                    self.expect(errors, *sourcefile_index, UTF8)
+                }
+                Attribute::StackMapTable(frames) => {
+                    for verification_type in frames
+                        .iter()
+                        .flat_map(|frame| frame.locals.iter().chain(&frame.stack))
+                    {
+                        if let VerificationType::Object { cpool_index } = verification_type {
+                            self.expect(errors, *cpool_index, CLASS);
+                        }
+                    }
+                }
+                _ => {}
+            }
+        }
+    }
+
+    fn validate_annotation(&self, errors: &mut Vec<JavaError>, annotation: &Annotation) {
+        self.expect(errors, annotation.type_index, UTF8);
+        for pair in &annotation.element_value_pairs {
+            self.expect(errors, pair.element_name_index, UTF8);
+            self.validate_element_value(errors, &pair.value);
+        }
+    }
+
+    fn validate_element_value(&self, errors: &mut Vec<JavaError>, value: &ElementValue) {
+        match value {
+            ElementValue::AnnotationValue(annotation) => {
+                self.validate_annotation(errors, annotation)
+            }
+            ElementValue::ArrayValue(values) => {
+                for value in values {
+                    self.validate_element_value(errors, value);
+                }
+            }
+            ElementValue::ClassInfoIndex(class_info_index) => {
+                self.expect(errors, *class_info_index, UTF8)
+            }
+            ElementValue::ConstValueIndex {
+                tag,
+                const_value_index,
+            } => {
+                let expected = match tag {
+                    b'B' | b'C' | b'I' | b'S' | b'Z' => INTEGER,
+                    b'D' => DOUBLE,
+                    b'F' => FLOAT,
+                    b'J' => LONG,
+                    _ => UTF8,
+                };
+                self.expect(errors, *const_value_index, expected);
+            }
+            ElementValue::EnumConstValue {
+                type_name_index,
+                const_name_index,
+            } => {
+                self.expect(errors, *type_name_index, UTF8);
+                self.expect(errors, *const_name_index, UTF8);
+            }
+        }
+    }
+
+    fn validate_instruction(&self, errors: &mut Vec<JavaError>, inst: &Instruction) {
+        match inst {
+            Instruction::ANewArray(id)
+            | Instruction::CheckCast(id)
+            | Instruction::InstanceOf(id)
+            | Instruction::MultiANewArray(id, _)
+            | Instruction::New(id) => self.expect(errors, *id, CLASS),
+            Instruction::GetField(id)
+            | Instruction::GetStatic(id)
+            | Instruction::PutField(id)
+            | Instruction::PutStatic(id) => self.expect(errors, *id, FIELDREF),
+            Instruction::InvokeVirtual(id) => self.expect(errors, *id, METHODREF),
+            Instruction::InvokeSpecial(id) | Instruction::InvokeStatic(id) => {
+                self.expect(errors, *id, ANY_METHODREF)
+            }
+            Instruction::InvokeInterface { index, .. } => {
+                self.expect(errors, *index, INTERFACE_METHODREF)
+            }
+            Instruction::InvokeDynamic(id) => self.expect(errors, *id, INVOKE_DYNAMIC),
+            Instruction::Ldc(id) => self.expect(errors, *id as u16, LOADABLE),
+            Instruction::LdcW(id) => self.expect(errors, *id, LOADABLE),
+            Instruction::Ldc2W(id) => self.expect(errors, *id, LOADABLE_WIDE),
+            _ => {}
+        }
+    }
+
+    /// Records an error if `id` isn't a constant of the expected kind.
+    fn expect(&self, errors: &mut Vec<JavaError>, id: u16, (expected, is_expected): Expected) {
+        match self.constants.get(id as usize) {
+            Some(constant) if is_expected(constant) => {}
+            Some(constant) => errors.push(JavaError::ConstantTypeError(format!(
+                "#{id} should be {expected}, but is a {constant}"
+            ))),
+            None => errors.push(JavaError::InvalidConstantId(id)),
+        }
+    }
+
+    /// Same as `expect`, except that 0 is allowed to mean "none".
+    fn expect_optional(&self, errors: &mut Vec<JavaError>, id: u16, expected: Expected) {
+        if id != 0 {
+            self.expect(errors, id, expected);
+        }
+    }
+
+    pub(crate) fn display_index(&self, id: u16) -> String {
+        match self.get_string(id) {
+            Ok(name) => name.to_string(),
+            Err(_) => format!("#{id}"),
+        }
+    }
+}
+
+/// Options of `JVMClass::verify`.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct VerifyOptions {
+    /// Checks the `StackMapTable` and `max_stack` of each method, which requires simulating
+    /// their code.
+    pub stack_maps: bool,
+}
+
+/// Checks that `jvm` declares the class `expected_internal_name`, e.g. `com/example/Foo` for
+/// `com/example/Foo.class` in a JAR.
+pub fn validate_class_name(jvm: &JVMClass, expected_internal_name: &str) -> Result<(), JavaError> {
+    let declared = jvm.this_class_name()?;
+
+    if declared != expected_internal_name {
+        return Err(JavaError::ClassNameMismatch {
+            expected: expected_internal_name.into(),
+            declared: declared.into(),
+        });
+    }
+
+    Ok(())
+}
+
+/// The first major version with the kind of constant.
+fn constant_major(constant: &Constant) -> u16 {
+    match constant {
+        Constant::MethodHandle { .. }
+        | Constant::MethodType { .. }
+        | Constant::InvokeDynamic { .. } => 51,
+        Constant::Module { .. } | Constant::Package { .. } => 53,
+        Constant::Dynamic { .. } => 55,
+        _ => 45,
+    }
+}
+
+/// The first major version with any of the attributes, nested ones included.
+fn attributes_major(attributes: &[Attribute]) -> u16 {
+    attributes
+        .iter()
+        .map(|attribute| match attribute {
+            Attribute::Code { attributes, .. } => attributes_major(attributes),
+            Attribute::Record(components) => components
+                .iter()
+                .map(|component| attributes_major(&component.attributes))
+                .fold(60, u16::max),
+            Attribute::EnclosingMethod { .. }
+            | Attribute::Signature { .. }
+            | Attribute::SourceDebugExtension { .. }
+            | Attribute::LocalVariableTypeTable(_)
+            | Attribute::RuntimeVisibleAnnotations(_)
+            | Attribute::RuntimeInvisibleAnnotations(_)
+            | Attribute::RuntimeVisibleParameterAnnotations(_)
+            | Attribute::RuntimeInvisibleParameterAnnotations(_)
+            | Attribute::AnnotationDefault(_) => 49,
+            Attribute::StackMapTable(_) => 50,
+            Attribute::BootstrapMethods(_) => 51,
+            Attribute::RuntimeVisibleTypeAnnotations(_)
+            | Attribute::RuntimeInvisibleTypeAnnotations(_)
+            | Attribute::MethodParameters(_) => 52,
+            Attribute::Module { .. }
+            | Attribute::ModulePackages(_)
+            | Attribute::ModuleMainClass(_) => 53,
+            Attribute::NestHost(_) | Attribute::NestMembers(_) => 55,
+            Attribute::PermittedSubclasses(_) => 61,
+            _ => 45,
+        })
+        .max()
+        .unwrap_or(45)
+}
+
+/// A description of the expected constants, and how to recognize them.
+type Expected = (&'static str, fn(&Constant) -> bool);
+
+const UTF8: Expected = ("a Utf8", |c| {
+    matches!(c, Constant::Utf8(..) | Constant::Utf8Bytes(..))
+});
+const INTEGER: Expected = ("an Integer", |c| matches!(c, Constant::Integer(..)));
+const FLOAT: Expected = ("a Float", |c| matches!(c, Constant::Float(..)));
+const LONG: Expected = ("a Long", |c| matches!(c, Constant::Long(..)));
+const DOUBLE: Expected = ("a Double", |c| matches!(c, Constant::Double(..)));
+const CLASS: Expected = ("a Class", |c| matches!(c, Constant::Class { .. }));
+const MODULE: Expected = ("a Module", |c| matches!(c, Constant::Module { .. }));
+const PACKAGE: Expected = ("a Package", |c| matches!(c, Constant::Package { .. }));
+const NAME_AND_TYPE: Expected = ("a NameAndType", |c| {
+    matches!(c, Constant::NameAndType { .. })
+});
+const FIELDREF: Expected = ("a Fieldref", |c| matches!(c, Constant::Fieldref { .. }));
+const METHODREF: Expected = ("a Methodref", |c| matches!(c, Constant::Methodref { .. }));
+const INTERFACE_METHODREF: Expected = ("an InterfaceMethodref", |c| {
+    matches!(c, Constant::InterfaceMethodref { .. })
+});
+const ANY_METHODREF: Expected = ("a Methodref or an InterfaceMethodref", |c| {
+    matches!(
+        c,
+        Constant::Methodref { .. } | Constant::InterfaceMethodref { .. }
+    )
+});
+const METHOD_HANDLE: Expected = ("a MethodHandle", |c| {
+    matches!(c, Constant::MethodHandle { .. })
+});
+const INVOKE_DYNAMIC: Expected = ("an InvokeDynamic", |c| {
+    matches!(c, Constant::InvokeDynamic { .. })
+});
+const CONSTANT_VALUE: Expected = ("an Integer, Float, Long, Double or String", |c| {
+    matches!(
+        c,
+        Constant::Integer(..)
+            | Constant::Float(..)
+            | Constant::Long(..)
+            | Constant::Double(..)
+            | Constant::String { .. }
+    )
+});
+const LOADABLE: Expected = ("a loadable constant", |c| {
+    matches!(
+        c,
+        Constant::Integer(..)
+            | Constant::Float(..)
+            | Constant::String { .. }
+            | Constant::Class { .. }
+            | Constant::MethodHandle { .. }
+            | Constant::MethodType { .. }
+            | Constant::Dynamic { .. }
+    )
+});
+const LOADABLE_WIDE: Expected = ("a Long, Double or Dynamic", |c| {
+    matches!(
+        c,
+        Constant::Long(..) | Constant::Double(..) | Constant::Dynamic { .. }
+    )
+});
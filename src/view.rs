@@ -0,0 +1,364 @@
+//! A read-only view of a class file, borrowing its bytes and decoding the parts on access.
+
+use std::borrow::Cow;
+use std::error::Error;
+use std::io;
+
+use byteorder::{BigEndian, ByteOrder};
+
+use crate::attribute_names;
+use crate::reader::{
+    decode_modified_utf8, decompile, extract_class_flags, extract_field_flags,
+    extract_method_flags, read_constant,
+};
+use crate::{AccessFlag, ClassVersion, Constant, Instruction, JavaError};
+
+const UTF8: u8 = 1;
+const CLASS: u8 = 7;
+const STRING: u8 = 8;
+
+/// Borrows the bytes of a class file instead of reading them into a `JVMClass`.
+///
+/// Creating the view only locates the constants, members and attributes. Strings are decoded
+/// when asked for, borrowing the class file when their modified UTF-8 is also valid UTF-8, and
+/// the code of the methods is never decompiled unless `MemberView::instructions` is called.
+#[derive(Debug, Clone)]
+pub struct ClassView<'a> {
+    bytes: &'a [u8],
+    major: u16,
+    minor: u16,
+    /// The offset of the tag of each constant, 0 for the unusable entries.
+    constants: Vec<usize>,
+    access_flags: u16,
+    this_class: u16,
+    super_class: u16,
+    interfaces: &'a [u8],
+    fields: Vec<&'a [u8]>,
+    methods: Vec<&'a [u8]>,
+    attributes: &'a [u8],
+}
+
+/// A field or a method of a `ClassView`.
+#[derive(Debug, Clone, Copy)]
+pub struct MemberView<'v, 'a> {
+    class: &'v ClassView<'a>,
+    bytes: &'a [u8],
+    is_method: bool,
+}
+
+/// An attribute of a `ClassView`, left undecoded.
+#[derive(Debug, Clone, Copy)]
+pub struct AttributeView<'v, 'a> {
+    class: &'v ClassView<'a>,
+    name_index: u16,
+    data: &'a [u8],
+}
+
+impl<'a> ClassView<'a> {
+    /// Locates the parts of the class file in `bytes`. It fails if they don't fit, or if a
+    /// constant has an unknown tag, but their content is only checked on access.
+    pub fn new(bytes: &'a [u8]) -> Result<Self, Box<dyn Error>> {
+        let mut r = Bytes::new(bytes);
+
+        let magic = r.u32()?;
+        if magic != 0xCAFEBABE {
+            return Err(JavaError::InvalidMagic(magic).into());
+        }
+
+        let minor = r.u16()?;
+        let major = r.u16()?;
+
+        let count = r.u16()?;
+        let mut constants = vec![0];
+        while constants.len() < count as usize {
+            constants.push(r.position);
+            let tag = r.u8()?;
+            let size = match tag {
+                UTF8 => r.u16()? as usize,
+                3 | 4 | 9..=12 | 17 | 18 => 4,
+                5 | 6 => 8,
+                CLASS | STRING | 16 | 19 | 20 => 2,
+                15 => 3,
+                _ => return Err(JavaError::UnknownConstantTag(tag).into()),
+            };
+            r.take(size)?;
+
+            // longs and doubles take two entries
+            if matches!(tag, 5 | 6) {
+                constants.push(0);
+            }
+        }
+
+        let access_flags = r.u16()?;
+        let this_class = r.u16()?;
+        let super_class = r.u16()?;
+
+        let interfaces_count = r.u16()?;
+        let interfaces = r.take(interfaces_count as usize * 2)?;
+
+        let fields = r.members()?;
+        let methods = r.members()?;
+        let attributes = r.attributes()?;
+
+        Ok(Self {
+            bytes,
+            major,
+            minor,
+            constants,
+            access_flags,
+            this_class,
+            super_class,
+            interfaces,
+            fields,
+            methods,
+            attributes,
+        })
+    }
+
+    pub fn version(&self) -> ClassVersion {
+        ClassVersion::from_version(self.major, self.minor)
+    }
+
+    pub fn access_flags(&self) -> Vec<AccessFlag> {
+        extract_class_flags(self.access_flags)
+    }
+
+    /// The internal name of this class (e.g. `java/lang/Object`).
+    pub fn class_name(&self) -> Result<Cow<'a, str>, JavaError> {
+        self.get_class_name(self.this_class)
+    }
+
+    /// The internal name of the super class, `None` for `java/lang/Object` and modules.
+    pub fn super_class_name(&self) -> Result<Option<Cow<'a, str>>, JavaError> {
+        match self.super_class {
+            0 => Ok(None),
+            id => self.get_class_name(id).map(Some),
+        }
+    }
+
+    pub fn interface_names(&self) -> impl Iterator<Item = Result<Cow<'a, str>, JavaError>> + '_ {
+        self.interfaces
+            .chunks_exact(2)
+            .map(|id| self.get_class_name(BigEndian::read_u16(id)))
+    }
+
+    pub fn fields(&self) -> impl Iterator<Item = MemberView<'_, 'a>> {
+        self.fields.iter().map(|bytes| MemberView {
+            class: self,
+            bytes,
+            is_method: false,
+        })
+    }
+
+    pub fn methods(&self) -> impl Iterator<Item = MemberView<'_, 'a>> {
+        self.methods.iter().map(|bytes| MemberView {
+            class: self,
+            bytes,
+            is_method: true,
+        })
+    }
+
+    pub fn attributes(&self) -> impl Iterator<Item = AttributeView<'_, 'a>> {
+        attribute_views(self, self.attributes)
+    }
+
+    /// Decodes the constant at `id`. Unlike the strings returned by `get_string`, the content
+    /// of a `Constant::Utf8` is copied.
+    pub fn get_constant(&self, id: u16) -> Result<Constant, JavaError> {
+        let offset = self.offset(id)?;
+        read_constant(&mut &self.bytes[offset..]).map_err(|_| JavaError::InvalidConstantId(id))
+    }
+
+    /// Same as `JVMClass::get_string`: the content of a `Constant::Utf8`, or of the `Utf8`
+    /// referred to by a `Constant::Class` or a `Constant::String`.
+    pub fn get_string(&self, id: u16) -> Result<Cow<'a, str>, JavaError> {
+        let offset = self.offset(id)?;
+        match self.bytes[offset] {
+            UTF8 => {
+                let length = BigEndian::read_u16(&self.bytes[offset + 1..]) as usize;
+                decode_utf8(&self.bytes[offset + 3..offset + 3 + length])
+            }
+            CLASS | STRING => self.get_string(BigEndian::read_u16(&self.bytes[offset + 1..])),
+            _ => Err(JavaError::ConstantTypeError(format!(
+                "#{id} is not a string, but a {}",
+                self.get_constant(id)?
+            ))),
+        }
+    }
+
+    /// Returns the internal name (e.g. `java/lang/Object`) of a `Constant::Class`.
+    pub fn get_class_name(&self, id: u16) -> Result<Cow<'a, str>, JavaError> {
+        let offset = self.offset(id)?;
+        match self.bytes[offset] {
+            CLASS => self.get_string(BigEndian::read_u16(&self.bytes[offset + 1..])),
+            _ => Err(JavaError::ConstantTypeError(format!(
+                "#{id} is not a class, but a {}",
+                self.get_constant(id)?
+            ))),
+        }
+    }
+
+    fn offset(&self, id: u16) -> Result<usize, JavaError> {
+        match self.constants.get(id as usize) {
+            Some(offset) if *offset != 0 => Ok(*offset),
+            _ => Err(JavaError::InvalidConstantId(id)),
+        }
+    }
+}
+
+impl<'v, 'a> MemberView<'v, 'a> {
+    pub fn access_flags(&self) -> Vec<AccessFlag> {
+        let flags = BigEndian::read_u16(self.bytes);
+        if self.is_method {
+            extract_method_flags(flags)
+        } else {
+            extract_field_flags(flags)
+        }
+    }
+
+    pub fn name(&self) -> Result<Cow<'a, str>, JavaError> {
+        self.class.get_string(BigEndian::read_u16(&self.bytes[2..]))
+    }
+
+    pub fn descriptor(&self) -> Result<Cow<'a, str>, JavaError> {
+        self.class.get_string(BigEndian::read_u16(&self.bytes[4..]))
+    }
+
+    pub fn attributes(&self) -> impl Iterator<Item = AttributeView<'v, 'a>> {
+        attribute_views(self.class, &self.bytes[6..])
+    }
+
+    /// The code array of the `Code` attribute, as it is in the class file.
+    pub fn code_bytes(&self) -> Option<&'a [u8]> {
+        let data = self
+            .attributes()
+            .find(|attribute| matches!(attribute.name().as_deref(), Ok(attribute_names::CODE)))?
+            .data;
+
+        let length = BigEndian::read_u32(data.get(4..8)?) as usize;
+        data.get(8..8usize.checked_add(length)?)
+    }
+
+    /// Decompiles the code array of the `Code` attribute, `None` if there is none.
+    pub fn instructions(&self) -> Result<Option<Vec<Instruction>>, Box<dyn Error>> {
+        self.code_bytes().map(decompile).transpose()
+    }
+
+    /// Same as `instructions`, also returning the code array they were decompiled from. Unlike
+    /// the one `JVMClass::store` compiles back from them, its bytes are exactly the ones of the
+    /// class file (switch padding, `wide` prefixes, …).
+    pub fn decompile_with_raw(&self) -> Result<Option<RawCode<'a>>, Box<dyn Error>> {
+        self.code_bytes()
+            .map(|bytes| Ok((decompile(bytes)?, bytes)))
+            .transpose()
+    }
+}
+
+impl<'v, 'a> AttributeView<'v, 'a> {
+    pub fn name(&self) -> Result<Cow<'a, str>, JavaError> {
+        self.class.get_string(self.name_index)
+    }
+
+    /// The content of the attribute, without its name and length.
+    pub fn data(&self) -> &'a [u8] {
+        self.data
+    }
+}
+
+/// `bytes` starts with the number of attributes, and was checked by `Bytes::attributes`.
+fn attribute_views<'v, 'a>(
+    class: &'v ClassView<'a>,
+    bytes: &'a [u8],
+) -> impl Iterator<Item = AttributeView<'v, 'a>> {
+    let mut r = Bytes::new(bytes);
+    let count = r.u16().unwrap_or(0);
+
+    (0..count).map_while(move |_| {
+        let name_index = r.u16().ok()?;
+        let length = r.u32().ok()?;
+        let data = r.take(length as usize).ok()?;
+        Some(AttributeView {
+            class,
+            name_index,
+            data,
+        })
+    })
+}
+
+/// The modified UTF-8 of strings without NUL or supplementary characters is plain UTF-8, so
+/// they can be borrowed.
+fn decode_utf8(bytes: &[u8]) -> Result<Cow<'_, str>, JavaError> {
+    if !bytes.iter().any(|byte| *byte == 0 || *byte >= 0xF0) {
+        if let Ok(string) = std::str::from_utf8(bytes) {
+            return Ok(Cow::Borrowed(string));
+        }
+    }
+
+    decode_modified_utf8(bytes).map(Cow::Owned)
+}
+
+/// Reads a class file without copying it.
+struct Bytes<'a> {
+    bytes: &'a [u8],
+    position: usize,
+}
+
+impl<'a> Bytes<'a> {
+    fn new(bytes: &'a [u8]) -> Self {
+        Self { bytes, position: 0 }
+    }
+
+    fn take(&mut self, length: usize) -> io::Result<&'a [u8]> {
+        let taken = self
+            .position
+            .checked_add(length)
+            .and_then(|end| self.bytes.get(self.position..end))
+            .ok_or(io::ErrorKind::UnexpectedEof)?;
+        self.position += length;
+        Ok(taken)
+    }
+
+    fn u8(&mut self) -> io::Result<u8> {
+        Ok(self.take(1)?[0])
+    }
+
+    fn u16(&mut self) -> io::Result<u16> {
+        Ok(BigEndian::read_u16(self.take(2)?))
+    }
+
+    fn u32(&mut self) -> io::Result<u32> {
+        Ok(BigEndian::read_u32(self.take(4)?))
+    }
+
+    /// Skips the fields or the methods, returning the bytes of each one.
+    fn members(&mut self) -> io::Result<Vec<&'a [u8]>> {
+        let count = self.u16()?;
+
+        let mut members = Vec::with_capacity(count as usize);
+        for _ in 0..count {
+            let start = self.position;
+            self.take(6)?;
+            self.attributes()?;
+            members.push(&self.bytes[start..self.position]);
+        }
+
+        Ok(members)
+    }
+
+    /// Skips attributes, returning their bytes with their number.
+    fn attributes(&mut self) -> io::Result<&'a [u8]> {
+        let start = self.position;
+
+        let count = self.u16()?;
+        for _ in 0..count {
+            self.take(2)?;
+            let length = self.u32()?;
+            self.take(length as usize)?;
+        }
+
+        Ok(&self.bytes[start..self.position])
+    }
+}
+
+/// Instructions, with the code array they were decompiled from.
+type RawCode<'a> = (Vec<Instruction>, &'a [u8]);
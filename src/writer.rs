@@ -1,7 +1,10 @@
 use byteorder::{BigEndian, WriteBytesExt};
-use std::io::SeekFrom;
-use std::io::{self, Seek, Write};
+use std::borrow::Cow;
+use std::collections::HashMap;
+use std::error::Error;
+use std::io::{self, Write};
 
+use crate::attribute_names;
 use crate::enums::{
     AccessFlag, Attribute, Constant, ElementValue, Instruction, StackMapFrameType, TargetInfo,
     VerificationType,
@@ -14,22 +17,48 @@ use crate::structs::{
     Annotation, Field, Method, ModuleExports, ModuleOpens, ModuleProvides, ModuleRequires,
     TypeAnnotation,
 };
-use crate::JVMClass;
+use crate::{JVMClass, JavaError};
 
-pub fn write_constant_pool<W: Write>(
-    w: &mut W,
-    constants: &Vec<Constant>,
-) -> Result<(), io::Error> {
-    w.write_u16::<BigEndian>(constants.len() as u16)?;
+pub fn write_class<W: Write>(w: &mut W, jvm: &JVMClass) -> Result<(), io::Error> {
+    w.write_u32::<BigEndian>(0xCAFEBABE)?;
+
+    w.write_u16::<BigEndian>(jvm.minor)?;
+    w.write_u16::<BigEndian>(jvm.major)?;
+
+    write_constant_pool(w, &jvm.constants)?;
+    let names = utf8_indices(&jvm.constants);
+
+    let access_flags = compact_class_flags(&jvm.access_flags);
+    w.write_u16::<BigEndian>(access_flags)?;
+
+    w.write_u16::<BigEndian>(jvm.this_class)?;
+    w.write_u16::<BigEndian>(jvm.super_class)?;
+
+    write_interfaces(w, &jvm.interfaces)?;
+    write_fields(w, &jvm.fields, jvm, &names)?;
+    write_methods(w, &jvm.methods, jvm, &names)?;
+    write_attributes(w, &jvm.attributes, jvm, &names)?;
+
+    Ok(())
+}
+
+pub fn write_constant_pool<W: Write>(w: &mut W, constants: &[Constant]) -> Result<(), io::Error> {
+    w.write_u16::<BigEndian>(constant_pool_count(constants)?)?;
 
     for cnst in constants.iter() {
         match cnst {
             Constant::Utf8(string) => {
                 w.write_u8(1)?;
 
-                let bytes = string.as_bytes();
-                w.write_u16::<BigEndian>(bytes.len() as u16)?;
-                w.write(&bytes).unwrap();
+                let bytes = encode_modified_utf8(string);
+                write_count(w, bytes.len(), "a Utf8 constant")?;
+                w.write_all(&bytes)?;
+            }
+            Constant::Utf8Bytes(bytes) => {
+                w.write_u8(1)?;
+
+                write_count(w, bytes.len(), "a Utf8 constant")?;
+                w.write_all(bytes)?;
             }
             Constant::Integer(value) => {
                 w.write_u8(3)?;
@@ -39,7 +68,7 @@ pub fn write_constant_pool<W: Write>(
             Constant::Float(value) => {
                 w.write_u8(4)?;
 
-                w.write_f32::<BigEndian>(*value)?;
+                w.write_u32::<BigEndian>(value.to_bits())?;
             }
             Constant::Long(value) => {
                 w.write_u8(5)?;
@@ -49,7 +78,7 @@ pub fn write_constant_pool<W: Write>(
             Constant::Double(value) => {
                 w.write_u8(6)?;
 
-                w.write_f64::<BigEndian>(*value)?;
+                w.write_u64::<BigEndian>(value.to_bits())?;
             }
             Constant::Class { name_index } => {
                 w.write_u8(7)?;
@@ -103,7 +132,7 @@ pub fn write_constant_pool<W: Write>(
             } => {
                 w.write_u8(15)?;
 
-                w.write_u8(*reference_kind)?;
+                w.write_u8(reference_kind.kind())?;
                 w.write_u16::<BigEndian>(*reference_index)?;
             }
             Constant::MethodType { descriptor_index } => {
@@ -148,17 +177,106 @@ pub fn write_constant_pool<W: Write>(
     Ok(())
 }
 
-pub fn write_attributes<W: Write + Seek>(
+/// Encodes a string in the JVM's modified UTF-8: NUL is encoded on two bytes and supplementary
+/// characters as a surrogate pair of three bytes each.
+pub fn encode_modified_utf8(string: &str) -> Vec<u8> {
+    let mut bytes = Vec::with_capacity(string.len());
+
+    for unit in string.encode_utf16() {
+        match unit {
+            0x01..=0x7F => bytes.push(unit as u8),
+            0x00 | 0x80..=0x7FF => {
+                bytes.push(0xC0 | (unit >> 6) as u8);
+                bytes.push(0x80 | (unit & 0x3F) as u8);
+            }
+            _ => {
+                bytes.push(0xE0 | (unit >> 12) as u8);
+                bytes.push(0x80 | ((unit >> 6) & 0x3F) as u8);
+                bytes.push(0x80 | (unit & 0x3F) as u8);
+            }
+        }
+    }
+
+    bytes
+}
+
+/// Computes `constant_pool_count` the way the reader consumes it: the reserved index 0, one slot per
+/// entry and two per long/double. `Invalid` entries are not written, so they don't count, and the
+/// count is right even if the pool doesn't start with one.
+fn constant_pool_count(constants: &[Constant]) -> Result<u16, io::Error> {
+    let slots: usize = constants
+        .iter()
+        .map(|cnst| match cnst {
+            Constant::Long(..) | Constant::Double(..) => 2,
+            Constant::Invalid => 0,
+            _ => 1,
+        })
+        .sum();
+
+    u16::try_from(slots + 1).map_err(|_| too_many_entries("the constant pool", slots + 1))
+}
+
+/// Writes the `u16` count of `section`, failing with `JavaError::TooManyEntries` instead of
+/// truncating it.
+fn write_count<W: Write>(w: &mut W, count: usize, section: &'static str) -> Result<(), io::Error> {
+    let count = u16::try_from(count).map_err(|_| too_many_entries(section, count))?;
+    w.write_u16::<BigEndian>(count)
+}
+
+/// Same as `write_count`, for the few counts stored in a `u8`.
+fn write_count_u8<W: Write>(
     w: &mut W,
+    count: usize,
+    section: &'static str,
+) -> Result<(), io::Error> {
+    let count = u8::try_from(count).map_err(|_| too_many_entries(section, count))?;
+    w.write_u8(count)
+}
+
+fn too_many_entries(section: &'static str, count: usize) -> io::Error {
+    io::Error::new(
+        io::ErrorKind::InvalidInput,
+        JavaError::TooManyEntries { section, count },
+    )
+}
+
+/// Unwraps the `JavaError` an `io::Error` of the writer may carry, so callers can match on it.
+pub(crate) fn unwrap_java_error(error: io::Error) -> Box<dyn Error> {
+    match error.get_ref() {
+        Some(inner) if inner.is::<JavaError>() => error.into_inner().expect("checked above"),
+        _ => error.into(),
+    }
+}
+
+/// The index of each `Utf8` constant by its value, the first one if it is duplicated, so the
+/// names of the attributes are found without scanning the pool each time.
+pub(crate) fn utf8_indices(constants: &[Constant]) -> HashMap<&str, u16> {
+    let mut indices = HashMap::new();
+    for (index, constant) in constants.iter().enumerate() {
+        if let Constant::Utf8(string) = constant {
+            indices.entry(string.as_str()).or_insert(index as u16);
+        }
+    }
+
+    indices
+}
+
+pub fn write_attributes<W: Write>(
+    out: &mut W,
     attributes: &Vec<Attribute>,
     jvm: &JVMClass,
+    names: &HashMap<&str, u16>,
 ) -> Result<(), io::Error> {
-    w.write_u16::<BigEndian>(attributes.len() as u16)?;
+    write_count(out, attributes.len(), "attributes")?;
 
     for attribute in attributes {
-        let attr_start = w.seek(SeekFrom::Current(0))?;
-        w.write_u16::<BigEndian>(0)?;
-        w.write_u32::<BigEndian>(0)?;
+        // fail before copying the content of an attribute too large to be stored
+        if let Some(len) = expected_length(attribute) {
+            attribute_length(attribute.name(), len)?;
+        }
+
+        // the length comes first, so the content is written to a buffer
+        let w = &mut vec![];
 
         let attr_name = match attribute {
             Attribute::Code {
@@ -167,12 +285,13 @@ pub fn write_attributes<W: Write + Seek>(
                 max_locals,
                 exception_table,
                 attributes,
+                ..
             } => {
                 w.write_u16::<BigEndian>(*max_stack)?;
                 w.write_u16::<BigEndian>(*max_locals)?;
-                compile(w, code)?;
+                compile(w, code, jvm)?;
 
-                w.write_u16::<BigEndian>(exception_table.len() as u16)?;
+                write_count(w, exception_table.len(), "the exception table")?;
                 for entry in exception_table {
                     w.write_u16::<BigEndian>(entry.start_pc)?;
                     w.write_u16::<BigEndian>(entry.end_pc)?;
@@ -180,22 +299,22 @@ pub fn write_attributes<W: Write + Seek>(
                     w.write_u16::<BigEndian>(entry.catch_type)?;
                 }
 
-                write_attributes(w, attributes, jvm)?;
+                write_attributes(w, attributes, jvm, names)?;
 
-                "Code"
+                attribute_names::CODE
             }
             Attribute::LineNumberTable(line_number_table) => {
-                w.write_u16::<BigEndian>(line_number_table.len() as u16)?;
+                write_count(w, line_number_table.len(), "LineNumberTable")?;
 
                 for line in line_number_table {
                     w.write_u16::<BigEndian>(line.start_pc)?;
                     w.write_u16::<BigEndian>(line.line_number)?;
                 }
 
-                "LineNumberTable"
+                attribute_names::LINE_NUMBER_TABLE
             }
             Attribute::StackMapTable(frames) => {
-                w.write_u16::<BigEndian>(frames.len() as u16)?;
+                write_count(w, frames.len(), "StackMapTable")?;
 
                 for frame in frames {
                     match frame.frame_type {
@@ -208,6 +327,7 @@ pub fn write_attributes<W: Write + Seek>(
                         }
                         StackMapFrameType::SameLocals1StackItemFrameExtended => {
                             w.write_u8(247)?;
+                            w.write_u16::<BigEndian>(frame.offset_delta)?;
                             write_verification_type(w, &frame.stack[0])?;
                         }
                         StackMapFrameType::ChopFrame(frame_type) => {
@@ -227,11 +347,12 @@ pub fn write_attributes<W: Write + Seek>(
                         }
                         StackMapFrameType::FullFrame => {
                             w.write_u8(255)?;
-                            w.write_u16::<BigEndian>(frame.locals.len() as u16)?;
+                            w.write_u16::<BigEndian>(frame.offset_delta)?;
+                            write_count(w, frame.locals.len(), "the locals of a full frame")?;
                             for verification_type in &frame.locals {
                                 write_verification_type(w, verification_type)?;
                             }
-                            w.write_u16::<BigEndian>(frame.stack.len() as u16)?;
+                            write_count(w, frame.stack.len(), "the stack of a full frame")?;
                             for verification_type in &frame.stack {
                                 write_verification_type(w, verification_type)?;
                             }
@@ -239,38 +360,42 @@ pub fn write_attributes<W: Write + Seek>(
                     }
                 }
 
-                "StackMapTable"
+                attribute_names::STACK_MAP_TABLE
             }
             Attribute::Exceptions(exceptions) => {
-                w.write_u16::<BigEndian>(exceptions.len() as u16)?;
+                write_count(w, exceptions.len(), "Exceptions")?;
 
                 for exception in exceptions {
                     w.write_u16::<BigEndian>(*exception)?;
                 }
 
-                "Exceptions"
+                attribute_names::EXCEPTIONS
             }
             Attribute::SourceFile { sourcefile_index } => {
                 w.write_u16::<BigEndian>(*sourcefile_index)?;
 
-                "SourceFile"
+                attribute_names::SOURCE_FILE
             }
             Attribute::BootstrapMethods(bootstrap_methods) => {
-                w.write_u16::<BigEndian>(bootstrap_methods.len() as u16)?;
+                write_count(w, bootstrap_methods.len(), "BootstrapMethods")?;
 
                 for bootstrap_method in bootstrap_methods {
                     w.write_u16::<BigEndian>(bootstrap_method.bootstrap_method_ref)?;
-                    w.write_u16::<BigEndian>(bootstrap_method.bootstrap_arguments.len() as u16)?;
+                    write_count(
+                        w,
+                        bootstrap_method.bootstrap_arguments.len(),
+                        "bootstrap arguments",
+                    )?;
 
                     for arg in &bootstrap_method.bootstrap_arguments {
                         w.write_u16::<BigEndian>(*arg)?;
                     }
                 }
 
-                "BootstrapMethods"
+                attribute_names::BOOTSTRAP_METHODS
             }
             Attribute::InnerClasses(inner_classes) => {
-                w.write_u16::<BigEndian>(inner_classes.len() as u16)?;
+                write_count(w, inner_classes.len(), "InnerClasses")?;
 
                 for inner_class in inner_classes {
                     let inner_class_info_index = &inner_class.inner_class_info_index;
@@ -286,24 +411,24 @@ pub fn write_attributes<W: Write + Seek>(
                     w.write_u16::<BigEndian>(inner_class_access_flags)?;
                 }
 
-                "InnerClasses"
+                attribute_names::INNER_CLASSES
             }
             Attribute::RuntimeVisibleAnnotations(annotations) => {
                 write_annotations(w, annotations)?;
 
-                "RuntimeVisibleAnnotations"
+                attribute_names::RUNTIME_VISIBLE_ANNOTATIONS
             }
             Attribute::RuntimeInvisibleAnnotations(annotations) => {
                 write_annotations(w, annotations)?;
 
-                "RuntimeInvisibleAnnotations"
+                attribute_names::RUNTIME_INVISIBLE_ANNOTATIONS
             }
             Attribute::ConstantValue {
                 constantvalue_index,
             } => {
                 w.write_u16::<BigEndian>(*constantvalue_index)?;
 
-                "ConstantValue"
+                attribute_names::CONSTANT_VALUE
             }
             Attribute::EnclosingMethod {
                 class_index,
@@ -312,32 +437,32 @@ pub fn write_attributes<W: Write + Seek>(
                 w.write_u16::<BigEndian>(*class_index)?;
                 w.write_u16::<BigEndian>(*method_index)?;
 
-                "EnclosingMethod"
+                attribute_names::ENCLOSING_METHOD
             }
-            Attribute::Synthetic => "Synthetic",
+            Attribute::Synthetic => attribute_names::SYNTHETIC,
             Attribute::Signature { signature_index } => {
                 w.write_u16::<BigEndian>(*signature_index)?;
 
-                "Signature"
+                attribute_names::SIGNATURE
             }
             Attribute::SourceDebugExtension { debug_extension } => {
-                w.write(&debug_extension)?;
+                w.write_all(debug_extension)?;
 
-                "SourceDebugExtension"
+                attribute_names::SOURCE_DEBUG_EXTENSION
             }
-            Attribute::Deprecated => "Deprecated",
+            Attribute::Deprecated => attribute_names::DEPRECATED,
             Attribute::ModuleMainClass(main_class_index) => {
                 w.write_u16::<BigEndian>(*main_class_index)?;
 
-                "ModuleMainClass"
+                attribute_names::MODULE_MAIN_CLASS
             }
             Attribute::NestHost(host_class_index) => {
                 w.write_u16::<BigEndian>(*host_class_index)?;
 
-                "NestHost"
+                attribute_names::NEST_HOST
             }
             Attribute::LocalVariableTable(local_variable_table) => {
-                w.write_u16::<BigEndian>(local_variable_table.len() as u16)?;
+                write_count(w, local_variable_table.len(), "LocalVariableTable")?;
 
                 for local_variable in local_variable_table {
                     w.write_u16::<BigEndian>(local_variable.start_pc)?;
@@ -347,10 +472,10 @@ pub fn write_attributes<W: Write + Seek>(
                     w.write_u16::<BigEndian>(local_variable.index)?;
                 }
 
-                "LocalVariableTable"
+                attribute_names::LOCAL_VARIABLE_TABLE
             }
             Attribute::LocalVariableTypeTable(local_variable_type_table) => {
-                w.write_u16::<BigEndian>(local_variable_type_table.len() as u16)?;
+                write_count(w, local_variable_type_table.len(), "LocalVariableTypeTable")?;
 
                 for local_variable_type in local_variable_type_table {
                     w.write_u16::<BigEndian>(local_variable_type.start_pc)?;
@@ -360,33 +485,33 @@ pub fn write_attributes<W: Write + Seek>(
                     w.write_u16::<BigEndian>(local_variable_type.index)?;
                 }
 
-                "LocalVariableTypeTable"
+                attribute_names::LOCAL_VARIABLE_TYPE_TABLE
             }
             Attribute::RuntimeVisibleParameterAnnotations(parameters_annotations) => {
-                w.write_u8(parameters_annotations.len() as u8)?;
+                write_count_u8(w, parameters_annotations.len(), "parameter annotations")?;
 
                 for parameters_annotation in parameters_annotations {
-                    write_annotations(w, &parameters_annotation)?;
+                    write_annotations(w, parameters_annotation)?;
                 }
 
-                "RuntimeVisibleParameterAnnotations"
+                attribute_names::RUNTIME_VISIBLE_PARAMETER_ANNOTATIONS
             }
             Attribute::RuntimeInvisibleParameterAnnotations(parameters_annotations) => {
-                w.write_u8(parameters_annotations.len() as u8)?;
+                write_count_u8(w, parameters_annotations.len(), "parameter annotations")?;
 
                 for parameters_annotation in parameters_annotations {
-                    write_annotations(w, &parameters_annotation)?;
+                    write_annotations(w, parameters_annotation)?;
                 }
 
-                "RuntimeInvisibleParameterAnnotations"
+                attribute_names::RUNTIME_INVISIBLE_PARAMETER_ANNOTATIONS
             }
             Attribute::AnnotationDefault(element_value) => {
                 write_element_value(w, element_value)?;
 
-                "AnnotationDefault"
+                attribute_names::ANNOTATION_DEFAULT
             }
             Attribute::MethodParameters(parameters) => {
-                w.write_u8(parameters.len() as u8)?;
+                write_count_u8(w, parameters.len(), "MethodParameters")?;
 
                 for parameter in parameters {
                     let access_flags = compact_method_parameter_flags(&parameter.access_flags);
@@ -395,7 +520,7 @@ pub fn write_attributes<W: Write + Seek>(
                     w.write_u16::<BigEndian>(access_flags)?;
                 }
 
-                "MethodParameters"
+                attribute_names::METHOD_PARAMETERS
             }
             Attribute::Module {
                 module_name_index,
@@ -415,95 +540,288 @@ pub fn write_attributes<W: Write + Seek>(
                 write_module_exports(w, exports)?;
                 write_module_opens(w, opens)?;
 
+                write_count(w, uses.len(), "module uses")?;
                 for used in uses {
                     w.write_u16::<BigEndian>(*used)?;
                 }
 
                 write_module_provides(w, provides)?;
 
-                "Module"
+                attribute_names::MODULE
             }
             Attribute::ModulePackages(packages_index) => {
-                w.write_u16::<BigEndian>(packages_index.len() as u16)?;
+                write_count(w, packages_index.len(), "ModulePackages")?;
 
                 for package_index in packages_index {
                     w.write_u16::<BigEndian>(*package_index)?;
                 }
 
-                "ModulePackages"
+                attribute_names::MODULE_PACKAGES
             }
             Attribute::NestMembers(classes) => {
-                w.write_u16::<BigEndian>(classes.len() as u16)?;
+                write_count(w, classes.len(), "NestMembers")?;
 
                 for class in classes {
                     w.write_u16::<BigEndian>(*class)?;
                 }
 
-                "NestMembers"
+                attribute_names::NEST_MEMBERS
             }
             Attribute::PermittedSubclasses(classes) => {
-                w.write_u16::<BigEndian>(classes.len() as u16)?;
+                write_count(w, classes.len(), "PermittedSubclasses")?;
 
                 for class in classes {
                     w.write_u16::<BigEndian>(*class)?;
                 }
 
-                "PermittedSubclasses"
+                attribute_names::PERMITTED_SUBCLASSES
             }
             Attribute::Record(components) => {
-                w.write_u16::<BigEndian>(components.len() as u16)?;
+                write_count(w, components.len(), "Record")?;
 
                 for component in components {
                     w.write_u16::<BigEndian>(component.name_index)?;
                     w.write_u16::<BigEndian>(component.descriptor_index)?;
-                    write_attributes(w, &component.attributes, jvm)?;
+                    write_attributes(w, &component.attributes, jvm, names)?;
                 }
 
-                "Record"
+                attribute_names::RECORD
             }
             Attribute::RuntimeInvisibleTypeAnnotations(annotations) => {
-                w.write_u16::<BigEndian>(annotations.len() as u16)?;
+                write_count(w, annotations.len(), "type annotations")?;
 
                 for annotation in annotations {
-                    write_type_annotation(w, &annotation)?;
+                    write_type_annotation(w, annotation)?;
                 }
 
-                "RuntimeInvisibleTypeAnnotations"
+                attribute_names::RUNTIME_INVISIBLE_TYPE_ANNOTATIONS
             }
             Attribute::RuntimeVisibleTypeAnnotations(annotations) => {
-                w.write_u16::<BigEndian>(annotations.len() as u16)?;
+                write_count(w, annotations.len(), "type annotations")?;
 
                 for annotation in annotations {
-                    write_type_annotation(w, &annotation)?;
+                    write_type_annotation(w, annotation)?;
                 }
 
-                "RuntimeVisibleTypeAnnotations"
+                attribute_names::RUNTIME_VISIBLE_TYPE_ANNOTATIONS
             }
             Attribute::Unknown { name, data } => {
-                w.write(&data)?;
+                w.write_all(data)?;
                 name
             }
         };
 
-        let string_index = jvm.get_string_index(attr_name).unwrap();
-
-        let attr_end = w.seek(SeekFrom::Current(0))?;
-        let attr_len = attr_end - attr_start - 6;
-        w.seek(SeekFrom::Start(attr_start))?;
-        w.write_u16::<BigEndian>(string_index)?;
-        w.write_u32::<BigEndian>(attr_len as u32)?;
-        w.seek(SeekFrom::Start(attr_end))?;
+        let string_index = *names.get(attr_name).ok_or_else(|| {
+            io::Error::new(
+                io::ErrorKind::InvalidInput,
+                JavaError::MissingAttributeName(attr_name.into()),
+            )
+        })?;
+
+        debug_assert!(
+            expected_length(attribute).is_none_or(|len| len == w.len() as u64),
+            "{attr_name} is {} bytes long, expected {:?}",
+            w.len(),
+            expected_length(attribute)
+        );
+
+        out.write_u16::<BigEndian>(string_index)?;
+        out.write_u32::<BigEndian>(attribute_length(attr_name, w.len() as u64)?)?;
+        out.write_all(w)?;
     }
 
     Ok(())
 }
 
-pub fn write_fields<W: Write + Seek>(
+fn attribute_length(name: &str, len: u64) -> Result<u32, io::Error> {
+    u32::try_from(len).map_err(|_| {
+        io::Error::new(
+            io::ErrorKind::InvalidInput,
+            JavaError::AttributeTooLarge {
+                name: name.into(),
+                len,
+            },
+        )
+    })
+}
+
+/// The length of the content of `attribute`, computed from its structure to check the writer.
+/// `None` for the attributes with a content too involved to be worth it, like annotations.
+fn expected_length(attribute: &Attribute) -> Option<u64> {
+    let list = |count: usize, size: u64| 2 + count as u64 * size;
+
+    let len = match attribute {
+        Attribute::Code {
+            code,
+            exception_table,
+            attributes,
+            ..
+        } => {
+            let code_length: u64 = code.iter().map(|inst| inst.size() as u64).sum();
+            let mut attributes_length = 2;
+            for attribute in attributes {
+                attributes_length += 6 + expected_length(attribute)?;
+            }
+
+            8 + code_length + list(exception_table.len(), 8) + attributes_length
+        }
+        Attribute::BootstrapMethods(bootstrap_methods) => {
+            let methods_length: u64 = bootstrap_methods
+                .iter()
+                .map(|method| 2 + list(method.bootstrap_arguments.len(), 2))
+                .sum();
+
+            2 + methods_length
+        }
+        Attribute::ConstantValue { .. }
+        | Attribute::ModuleMainClass(..)
+        | Attribute::NestHost(..)
+        | Attribute::Signature { .. }
+        | Attribute::SourceFile { .. } => 2,
+        Attribute::Deprecated | Attribute::Synthetic => 0,
+        Attribute::EnclosingMethod { .. } => 4,
+        Attribute::Exceptions(classes)
+        | Attribute::ModulePackages(classes)
+        | Attribute::NestMembers(classes)
+        | Attribute::PermittedSubclasses(classes) => list(classes.len(), 2),
+        Attribute::InnerClasses(inner_classes) => list(inner_classes.len(), 8),
+        Attribute::LineNumberTable(line_numbers) => list(line_numbers.len(), 4),
+        Attribute::LocalVariableTable(locals) => list(locals.len(), 10),
+        Attribute::LocalVariableTypeTable(locals) => list(locals.len(), 10),
+        Attribute::MethodParameters(parameters) => 1 + parameters.len() as u64 * 4,
+        Attribute::SourceDebugExtension { debug_extension } => debug_extension.len() as u64,
+        Attribute::Unknown { data, .. } => data.len() as u64,
+        _ => return None,
+    };
+
+    Some(len)
+}
+
+/// Returns the class to write: `jvm` itself, or a copy with the constants it needs added to
+/// the constant pool if some of them are missing. These are the names of the `Unknown`
+/// attributes, and the values of the constant instructions written as `ldc_w` or `ldc2_w`.
+pub fn with_missing_constants(jvm: &JVMClass) -> Cow<'_, JVMClass> {
+    let mut names = vec![];
+    unknown_attribute_names(&jvm.attributes, &mut names);
+    for member in jvm.fields.iter().map(|field| &field.0) {
+        unknown_attribute_names(&member.attributes, &mut names);
+    }
+    let mut constants = vec![];
+    for member in jvm.methods.iter().map(|method| &method.0) {
+        unknown_attribute_names(&member.attributes, &mut names);
+        for attribute in &member.attributes {
+            if let Attribute::Code { code, .. } = attribute {
+                constants.extend(code.iter().filter_map(widened_constant));
+            }
+        }
+    }
+
+    let indices = utf8_indices(&jvm.constants);
+    names.retain(|name| !indices.contains_key(name));
+    constants.retain(|constant| !jvm.constants.contains(constant));
+    if names.is_empty() && constants.is_empty() {
+        return Cow::Borrowed(jvm);
+    }
+
+    let mut jvm = jvm.clone();
+    for name in names {
+        jvm.get_or_add_utf8_index(name);
+    }
+    for constant in constants {
+        jvm.constant_pool().intern(constant);
+    }
+
+    Cow::Owned(jvm)
+}
+
+/// The constant loaded by a `IConst`, `LConst`, `FConst` or `DConst` without a shorter form.
+fn widened_constant(inst: &Instruction) -> Option<Constant> {
+    match inst {
+        Instruction::IConst(value) if inst.opcode() == 0x13 => Some(Constant::Integer(*value)),
+        Instruction::LConst(value) if inst.opcode() == 0x14 => Some(Constant::Long(*value)),
+        Instruction::FConst(value) if inst.opcode() == 0x13 => Some(Constant::Float(*value)),
+        Instruction::DConst(value) if inst.opcode() == 0x14 => Some(Constant::Double(*value)),
+        _ => None,
+    }
+}
+
+/// Returns the name of the first attribute, nested ones included, missing from the constant pool.
+pub fn missing_attribute_name(jvm: &JVMClass) -> Option<&str> {
+    let members = jvm
+        .fields
+        .iter()
+        .map(|field| &field.0)
+        .chain(jvm.methods.iter().map(|method| &method.0));
+
+    let names = utf8_indices(&jvm.constants);
+    std::iter::once(&jvm.attributes)
+        .chain(members.map(|member| &member.attributes))
+        .find_map(|attributes| missing_name(&names, attributes))
+}
+
+/// Returns the kind and the name of the first field or method with more than one access level.
+pub fn conflicting_access_levels(jvm: &JVMClass) -> Option<(&'static str, u16)> {
+    let fields = jvm.fields.iter().map(|field| ("field", &field.0));
+    let methods = jvm.methods.iter().map(|method| ("method", &method.0));
+
+    fields
+        .chain(methods)
+        .find(|(_, member)| member.access_levels() > 1)
+        .map(|(kind, member)| (kind, member.name))
+}
+
+/// Returns the index of the first `Ldc` loading a constant that isn't in the constant pool.
+pub fn invalid_ldc(jvm: &JVMClass) -> Option<u8> {
+    jvm.methods
+        .iter()
+        .flat_map(|method| &method.0.attributes)
+        .filter_map(|attribute| match attribute {
+            Attribute::Code { code, .. } => Some(code),
+            _ => None,
+        })
+        .flatten()
+        .find_map(|inst| match inst {
+            Instruction::Ldc(id) if jvm.get_constant(*id as u16).is_err() => Some(*id),
+            _ => None,
+        })
+}
+
+fn missing_name<'a>(names: &HashMap<&str, u16>, attributes: &'a [Attribute]) -> Option<&'a str> {
+    attributes.iter().find_map(|attribute| {
+        let nested = match attribute {
+            Attribute::Code { attributes, .. } => missing_name(names, attributes),
+            Attribute::Record(components) => components
+                .iter()
+                .find_map(|component| missing_name(names, &component.attributes)),
+            _ => None,
+        };
+
+        nested.or_else(|| Some(attribute.name()).filter(|name| !names.contains_key(name)))
+    })
+}
+
+fn unknown_attribute_names<'a>(attributes: &'a [Attribute], names: &mut Vec<&'a str>) {
+    for attribute in attributes {
+        match attribute {
+            Attribute::Unknown { name, .. } => names.push(name),
+            Attribute::Code { attributes, .. } => unknown_attribute_names(attributes, names),
+            Attribute::Record(components) => {
+                for component in components {
+                    unknown_attribute_names(&component.attributes, names);
+                }
+            }
+            _ => {}
+        }
+    }
+}
+
+pub fn write_fields<W: Write>(
     w: &mut W,
     fields: &Vec<Field>,
     jvm: &JVMClass,
+    names: &HashMap<&str, u16>,
 ) -> Result<(), io::Error> {
-    w.write_u16::<BigEndian>(fields.len() as u16)?;
+    write_count(w, fields.len(), "fields")?;
 
     for field in fields {
         let member_data = &field.0;
@@ -511,14 +829,14 @@ pub fn write_fields<W: Write + Seek>(
         w.write_u16::<BigEndian>(access_flags)?;
         w.write_u16::<BigEndian>(member_data.name)?;
         w.write_u16::<BigEndian>(member_data.descriptor)?;
-        write_attributes(w, &member_data.attributes, jvm)?;
+        write_attributes(w, &member_data.attributes, jvm, names)?;
     }
 
     Ok(())
 }
 
 pub fn write_interfaces<W: Write>(w: &mut W, interfaces: &Vec<u16>) -> Result<(), io::Error> {
-    w.write_u16::<BigEndian>(interfaces.len() as u16)?;
+    write_count(w, interfaces.len(), "interfaces")?;
 
     for interface in interfaces {
         w.write_u16::<BigEndian>(*interface)?;
@@ -527,12 +845,13 @@ pub fn write_interfaces<W: Write>(w: &mut W, interfaces: &Vec<u16>) -> Result<()
     Ok(())
 }
 
-pub fn write_methods<W: Write + Seek>(
+pub fn write_methods<W: Write>(
     w: &mut W,
     methods: &Vec<Method>,
     jvm: &JVMClass,
+    names: &HashMap<&str, u16>,
 ) -> Result<(), io::Error> {
-    w.write_u16::<BigEndian>(methods.len() as u16)?;
+    write_count(w, methods.len(), "methods")?;
 
     for method in methods {
         let member_data = &method.0;
@@ -540,49 +859,49 @@ pub fn write_methods<W: Write + Seek>(
         w.write_u16::<BigEndian>(access_flags)?;
         w.write_u16::<BigEndian>(member_data.name)?;
         w.write_u16::<BigEndian>(member_data.descriptor)?;
-        write_attributes(w, &member_data.attributes, jvm)?;
+        write_attributes(w, &member_data.attributes, jvm, names)?;
     }
 
     Ok(())
 }
 
-pub fn compact_class_flags(flags: &Vec<AccessFlag>) -> u16 {
+pub fn compact_class_flags(flags: &[AccessFlag]) -> u16 {
     compact_flags(flags, &CLASS_FLAGS)
 }
 
-fn compact_inner_class_flags(flags: &Vec<AccessFlag>) -> u16 {
+fn compact_inner_class_flags(flags: &[AccessFlag]) -> u16 {
     compact_flags(flags, &INNER_CLASS_FLAGS)
 }
 
-fn compact_field_flags(flags: &Vec<AccessFlag>) -> u16 {
+fn compact_field_flags(flags: &[AccessFlag]) -> u16 {
     compact_flags(flags, &FIELD_FLAGS)
 }
 
-fn compact_method_flags(flags: &Vec<AccessFlag>) -> u16 {
+fn compact_method_flags(flags: &[AccessFlag]) -> u16 {
     compact_flags(flags, &METHOD_FLAGS)
 }
 
-fn compact_method_parameter_flags(flags: &Vec<AccessFlag>) -> u16 {
+fn compact_method_parameter_flags(flags: &[AccessFlag]) -> u16 {
     compact_flags(flags, &METHOD_PARAMETER_FLAGS)
 }
 
-fn compact_module_flags(flags: &Vec<AccessFlag>) -> u16 {
+fn compact_module_flags(flags: &[AccessFlag]) -> u16 {
     compact_flags(flags, &MODULE_FLAGS)
 }
 
-fn compact_module_requires_flags(flags: &Vec<AccessFlag>) -> u16 {
+fn compact_module_requires_flags(flags: &[AccessFlag]) -> u16 {
     compact_flags(flags, &MODULE_REQUIRES_FLAGS)
 }
 
-fn compact_module_opens_flags(flags: &Vec<AccessFlag>) -> u16 {
+fn compact_module_opens_flags(flags: &[AccessFlag]) -> u16 {
     compact_flags(flags, &MODULE_OPENS_FLAGS)
 }
 
-fn compact_module_exports_flags(flags: &Vec<AccessFlag>) -> u16 {
+fn compact_module_exports_flags(flags: &[AccessFlag]) -> u16 {
     compact_flags(flags, &MODULE_EXPORTS_FLAGS)
 }
 
-fn compact_flags<T: Copy + std::cmp::PartialEq>(flags: &Vec<T>, mapping: &[(u16, T)]) -> u16 {
+fn compact_flags<T: Copy + std::cmp::PartialEq>(flags: &[T], mapping: &[(u16, T)]) -> u16 {
     mapping
         .iter()
         .filter(|(_, flag)| flags.contains(flag))
@@ -615,11 +934,8 @@ fn write_verification_type<W: Write>(
     Ok(())
 }
 
-fn write_annotations<W: Write + Seek>(
-    w: &mut W,
-    annotations: &Vec<Annotation>,
-) -> Result<(), io::Error> {
-    w.write_u16::<BigEndian>(annotations.len() as u16)?;
+fn write_annotations<W: Write>(w: &mut W, annotations: &Vec<Annotation>) -> Result<(), io::Error> {
+    write_count(w, annotations.len(), "annotations")?;
 
     for annotation in annotations {
         write_annotation(w, annotation)?;
@@ -628,12 +944,13 @@ fn write_annotations<W: Write + Seek>(
     Ok(())
 }
 
-fn write_type_annotation<W: Write + Seek>(
+fn write_type_annotation<W: Write>(
     w: &mut W,
     type_annotation: &TypeAnnotation,
 ) -> Result<(), io::Error> {
     write_target_info(w, &type_annotation.target_info)?;
 
+    write_count_u8(w, type_annotation.target_path.len(), "a type path")?;
     for path in &type_annotation.target_path {
         w.write_u8(path.type_path_kind)?;
         w.write_u8(path.type_argument_index)?;
@@ -644,10 +961,7 @@ fn write_type_annotation<W: Write + Seek>(
     Ok(())
 }
 
-fn write_target_info<W: Write + Seek>(
-    w: &mut W,
-    target_info: &TargetInfo,
-) -> Result<(), io::Error> {
+fn write_target_info<W: Write>(w: &mut W, target_info: &TargetInfo) -> Result<(), io::Error> {
     match target_info {
         TargetInfo::TypeParameter {
             target_type,
@@ -679,11 +993,12 @@ fn write_target_info<W: Write + Seek>(
             w.write_u8(*formal_parameter_index)?;
         }
         TargetInfo::Throws { throws_type_index } => {
+            w.write_u8(0x17)?;
             w.write_u16::<BigEndian>(*throws_type_index)?;
         }
         TargetInfo::Localvar { target_type, table } => {
             w.write_u8(*target_type)?;
-            w.write_u16::<BigEndian>(table.len() as u16)?;
+            write_count(w, table.len(), "a local variable target")?;
 
             for local_var in table {
                 w.write_u16::<BigEndian>(local_var.start_pc)?;
@@ -717,9 +1032,13 @@ fn write_target_info<W: Write + Seek>(
     Ok(())
 }
 
-fn write_annotation<W: Write + Seek>(w: &mut W, annotation: &Annotation) -> Result<(), io::Error> {
+fn write_annotation<W: Write>(w: &mut W, annotation: &Annotation) -> Result<(), io::Error> {
     w.write_u16::<BigEndian>(annotation.type_index)?;
-    w.write_u16::<BigEndian>(annotation.element_value_pairs.len() as u16)?;
+    write_count(
+        w,
+        annotation.element_value_pairs.len(),
+        "element-value pairs",
+    )?;
 
     for pair in &annotation.element_value_pairs {
         w.write_u16::<BigEndian>(pair.element_name_index)?;
@@ -729,10 +1048,7 @@ fn write_annotation<W: Write + Seek>(w: &mut W, annotation: &Annotation) -> Resu
     Ok(())
 }
 
-fn write_element_value<W: Write + Seek>(
-    w: &mut W,
-    element_value: &ElementValue,
-) -> Result<(), io::Error> {
+fn write_element_value<W: Write>(w: &mut W, element_value: &ElementValue) -> Result<(), io::Error> {
     match element_value {
         ElementValue::ConstValueIndex {
             tag,
@@ -759,10 +1075,10 @@ fn write_element_value<W: Write + Seek>(
         }
         ElementValue::ArrayValue(values) => {
             w.write_u8(b'[')?;
-            w.write_u16::<BigEndian>(values.len() as u16)?;
+            write_count(w, values.len(), "an array element value")?;
 
             for value in values {
-                write_element_value(w, &value)?;
+                write_element_value(w, value)?;
             }
         }
     }
@@ -770,11 +1086,11 @@ fn write_element_value<W: Write + Seek>(
     Ok(())
 }
 
-fn write_module_requires<W: Write + Seek>(
+fn write_module_requires<W: Write>(
     w: &mut W,
     requires: &Vec<ModuleRequires>,
 ) -> Result<(), io::Error> {
-    w.write_u16::<BigEndian>(requires.len() as u16)?;
+    write_count(w, requires.len(), "module requires")?;
 
     for require in requires {
         w.write_u16::<BigEndian>(require.requires_index)?;
@@ -786,17 +1102,18 @@ fn write_module_requires<W: Write + Seek>(
     Ok(())
 }
 
-fn write_module_exports<W: Write + Seek>(
+fn write_module_exports<W: Write>(
     w: &mut W,
     exports: &Vec<ModuleExports>,
 ) -> Result<(), io::Error> {
-    w.write_u16::<BigEndian>(exports.len() as u16)?;
+    write_count(w, exports.len(), "module exports")?;
 
     for export in exports {
         w.write_u16::<BigEndian>(export.exports_index)?;
         let exports_flags = compact_module_exports_flags(&export.exports_flags);
         w.write_u16::<BigEndian>(exports_flags)?;
 
+        write_count(w, export.exports_to_index.len(), "module exports to")?;
         for export_to_index in &export.exports_to_index {
             w.write_u16::<BigEndian>(*export_to_index)?;
         }
@@ -805,17 +1122,15 @@ fn write_module_exports<W: Write + Seek>(
     Ok(())
 }
 
-fn write_module_opens<W: Write + Seek>(
-    w: &mut W,
-    opens: &Vec<ModuleOpens>,
-) -> Result<(), io::Error> {
-    w.write_u16::<BigEndian>(opens.len() as u16)?;
+fn write_module_opens<W: Write>(w: &mut W, opens: &Vec<ModuleOpens>) -> Result<(), io::Error> {
+    write_count(w, opens.len(), "module opens")?;
 
     for open in opens {
         w.write_u16::<BigEndian>(open.opens_index)?;
         let opens_flags = compact_module_opens_flags(&open.opens_flags);
         w.write_u16::<BigEndian>(opens_flags)?;
 
+        write_count(w, open.opens_to_index.len(), "module opens to")?;
         for open_to_index in &open.opens_to_index {
             w.write_u16::<BigEndian>(*open_to_index)?;
         }
@@ -824,15 +1139,16 @@ fn write_module_opens<W: Write + Seek>(
     Ok(())
 }
 
-fn write_module_provides<W: Write + Seek>(
+fn write_module_provides<W: Write>(
     w: &mut W,
     provides: &Vec<ModuleProvides>,
 ) -> Result<(), io::Error> {
-    w.write_u16::<BigEndian>(provides.len() as u16)?;
+    write_count(w, provides.len(), "module provides")?;
 
     for provide in provides {
         w.write_u16::<BigEndian>(provide.provides_index)?;
 
+        write_count(w, provide.provides_with_index.len(), "module provides with")?;
         for provide_with_index in &provide.provides_with_index {
             w.write_u16::<BigEndian>(*provide_with_index)?;
         }
@@ -841,48 +1157,45 @@ fn write_module_provides<W: Write + Seek>(
     Ok(())
 }
 
-fn compile<W: Write + Seek>(w: &mut W, code: &Vec<Instruction>) -> Result<(), io::Error> {
-    let code_start = w.seek(SeekFrom::Current(0))?;
-    w.write_u32::<BigEndian>(0)?;
+fn compile<W: Write>(
+    out: &mut W,
+    code: &Vec<Instruction>,
+    jvm: &JVMClass,
+) -> Result<(), io::Error> {
+    // the length comes first, so the code is written to a buffer
+    let w = &mut vec![];
 
     for inst in code {
+        if let Some(constant) = widened_constant(inst) {
+            let index = jvm
+                .constants
+                .iter()
+                .position(|existing| *existing == constant)
+                .ok_or_else(|| {
+                    io::Error::new(
+                        io::ErrorKind::InvalidInput,
+                        format!("{constant} missing from the constant pool"),
+                    )
+                })?;
+            w.write_u8(inst.opcode())?;
+            w.write_u16::<BigEndian>(index as u16)?;
+            continue;
+        }
+
         match inst {
             Instruction::Nop => w.write_u8(0x00)?,
             Instruction::ANull => w.write_u8(0x01)?,
-            Instruction::IConst(i) => match i {
-                -1 => w.write_u8(0x02)?,
-                0 => w.write_u8(0x03)?,
-                1 => w.write_u8(0x04)?,
-                2 => w.write_u8(0x05)?,
-                3 => w.write_u8(0x06)?,
-                4 => w.write_u8(0x07)?,
-                5 => w.write_u8(0x08)?,
-                _ => unreachable!(),
-            },
-            Instruction::LConst(l) => match l {
-                0 => w.write_u8(0x09)?,
-                1 => w.write_u8(0x0A)?,
-                _ => unreachable!(),
-            },
-            Instruction::FConst(f) => {
-                if *f == 0.0 {
-                    w.write_u8(0x0B)?;
-                } else if *f == 1.0 {
-                    w.write_u8(0x0C)?;
-                } else if *f == 2.0 {
-                    w.write_u8(0x0D)?;
-                } else {
-                    unreachable!();
+            Instruction::IConst(i) => {
+                let opcode = inst.opcode();
+                w.write_u8(opcode)?;
+                match opcode {
+                    0x10 => w.write_i8(*i as i8)?,
+                    0x11 => w.write_i16::<BigEndian>(*i as i16)?,
+                    _ => {}
                 }
             }
-            Instruction::DConst(d) => {
-                if *d == 0.0 {
-                    w.write_u8(0x0E)?;
-                } else if *d == 1.0 {
-                    w.write_u8(0x0F)?;
-                } else {
-                    unreachable!();
-                }
+            Instruction::LConst(..) | Instruction::FConst(..) | Instruction::DConst(..) => {
+                w.write_u8(inst.opcode())?
             }
             Instruction::Bipush(index) => {
                 w.write_u8(0x10)?;
@@ -1171,12 +1484,12 @@ fn compile<W: Write + Seek>(w: &mut W, code: &Vec<Instruction>) -> Result<(), io
                     w.write_u8(0)?;
                 }
 
-                w.write_u32::<BigEndian>(*default)?;
-                w.write_u32::<BigEndian>(*minimum)?;
-                w.write_u32::<BigEndian>(*maximum)?;
+                w.write_i32::<BigEndian>(*default)?;
+                w.write_i32::<BigEndian>(*minimum)?;
+                w.write_i32::<BigEndian>(*maximum)?;
 
                 for jump_target in jump_targets {
-                    w.write_u32::<BigEndian>(*jump_target)?;
+                    w.write_i32::<BigEndian>(*jump_target)?;
                 }
             }
             Instruction::LookupSwitch {
@@ -1190,12 +1503,12 @@ fn compile<W: Write + Seek>(w: &mut W, code: &Vec<Instruction>) -> Result<(), io
                     w.write_u8(0)?;
                 }
 
-                w.write_u32::<BigEndian>(*default)?;
+                w.write_i32::<BigEndian>(*default)?;
                 w.write_u32::<BigEndian>(pairs.len() as u32)?;
 
                 for pair in pairs {
-                    w.write_u32::<BigEndian>(pair.value)?;
-                    w.write_u32::<BigEndian>(pair.target)?;
+                    w.write_i32::<BigEndian>(pair.value)?;
+                    w.write_i32::<BigEndian>(pair.target)?;
                 }
             }
             Instruction::IReturn => w.write_u8(0xAC)?,
@@ -1236,6 +1549,7 @@ fn compile<W: Write + Seek>(w: &mut W, code: &Vec<Instruction>) -> Result<(), io
                 w.write_u8(0xB9)?;
                 w.write_u16::<BigEndian>(*index)?;
                 w.write_u8(*count)?;
+                w.write_u8(0)?;
             }
             Instruction::InvokeDynamic(index) => {
                 w.write_u8(0xBA)?;
@@ -1248,7 +1562,7 @@ fn compile<W: Write + Seek>(w: &mut W, code: &Vec<Instruction>) -> Result<(), io
             }
             Instruction::NewArray(atype) => {
                 w.write_u8(0xBC)?;
-                w.write_u8(*atype)?;
+                w.write_u8(atype.atype())?;
             }
             Instruction::ANewArray(index) => {
                 w.write_u8(0xBD)?;
@@ -1281,11 +1595,11 @@ fn compile<W: Write + Seek>(w: &mut W, code: &Vec<Instruction>) -> Result<(), io
             }
             Instruction::GotoW(branch) => {
                 w.write_u8(0xC8)?;
-                w.write_u32::<BigEndian>(*branch)?;
+                w.write_i32::<BigEndian>(*branch)?;
             }
             Instruction::JsrW(branch) => {
                 w.write_u8(0xC9)?;
-                w.write_u32::<BigEndian>(*branch)?;
+                w.write_i32::<BigEndian>(*branch)?;
             }
             _ => {
                 w.write_u8(0xC4)?;
@@ -1310,17 +1624,14 @@ fn compile<W: Write + Seek>(w: &mut W, code: &Vec<Instruction>) -> Result<(), io
                 w.write_u16::<BigEndian>(index)?;
 
                 if let Some(count) = count {
-                    w.write_u16::<BigEndian>(count)?;
+                    w.write_i16::<BigEndian>(count)?;
                 }
             }
         }
     }
 
-    let code_end = w.seek(SeekFrom::Current(0))?;
-    w.seek(SeekFrom::Start(code_start))?;
-    let code_len = code_end - code_start - 4;
-    w.write_u32::<BigEndian>(code_len as u32)?;
-    w.seek(SeekFrom::Start(code_end))?;
+    out.write_u32::<BigEndian>(w.len() as u32)?;
+    out.write_all(w)?;
 
     Ok(())
 }
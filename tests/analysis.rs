@@ -0,0 +1,635 @@
+mod common;
+
+use class_rs::analysis::{
+    check_max_stack, compute_max_locals, compute_max_stack, generate_stack_map, peephole,
+    peephole_code, reachable_instructions, rewrite_branches, shift_code_locals, shift_locals,
+    LabeledCode,
+};
+use class_rs::descriptor::parse_method_descriptor;
+use class_rs::{
+    AccessFlag, Attribute, Constant, ExceptionTableEntry, Instruction, JVMClass, JavaError,
+    LineNumber, MapResolver, StackMapFrame, StackMapFrameType, VerificationType, VerifyOptions,
+};
+
+#[test]
+fn peephole_removes_nops_and_dup_pop() {
+    let mut code = vec![
+        Instruction::ILoad(0),
+        Instruction::Nop,
+        Instruction::Ifeq(8),
+        Instruction::Nop,
+        Instruction::ILoad(0),
+        Instruction::Dup,
+        Instruction::Pop,
+        Instruction::IReturn,
+        // A branch target, so it stays.
+        Instruction::Nop,
+        Instruction::IConst(0),
+        Instruction::IReturn,
+    ];
+
+    assert_eq!(peephole(&mut code), 3);
+    assert_eq!(
+        code,
+        vec![
+            Instruction::ILoad(0),
+            Instruction::Ifeq(5),
+            Instruction::ILoad(0),
+            Instruction::IReturn,
+            Instruction::Nop,
+            Instruction::IConst(0),
+            Instruction::IReturn,
+        ]
+    );
+
+    assert_eq!(peephole(&mut code), 0);
+}
+
+#[test]
+fn peephole_code_keeps_the_exception_table_and_line_numbers_in_sync() {
+    let mut code_attribute = Attribute::Code {
+        code: vec![
+            // the start of the handled range, so it stays
+            Instruction::Nop,
+            Instruction::Nop,
+            Instruction::ILoad(0),
+            Instruction::IReturn,
+            // the end of the range and the handler, so it stays
+            Instruction::Nop,
+            Instruction::Nop,
+            Instruction::Pop,
+            Instruction::IConst(0),
+            Instruction::IReturn,
+        ],
+        max_stack: 1,
+        max_locals: 1,
+        exception_table: vec![ExceptionTableEntry {
+            start_pc: 0,
+            end_pc: 4,
+            handler_pc: 4,
+            catch_type: 0,
+        }],
+        attributes: vec![Attribute::LineNumberTable(vec![
+            LineNumber {
+                start_pc: 0,
+                line_number: 1,
+            },
+            LineNumber {
+                start_pc: 4,
+                line_number: 2,
+            },
+        ])],
+    };
+
+    assert_eq!(
+        peephole_code(&mut code_attribute, &[VerificationType::Integer]).unwrap(),
+        2
+    );
+
+    let Attribute::Code {
+        code,
+        exception_table,
+        attributes,
+        ..
+    } = &code_attribute
+    else {
+        unreachable!();
+    };
+    assert_eq!(
+        *code,
+        vec![
+            Instruction::Nop,
+            Instruction::ILoad(0),
+            Instruction::IReturn,
+            Instruction::Nop,
+            Instruction::Pop,
+            Instruction::IConst(0),
+            Instruction::IReturn,
+        ]
+    );
+    assert_eq!(
+        exception_table[0],
+        ExceptionTableEntry {
+            start_pc: 0,
+            end_pc: 3,
+            handler_pc: 3,
+            catch_type: 0,
+        }
+    );
+    assert_eq!(
+        attributes[0],
+        Attribute::LineNumberTable(vec![
+            LineNumber {
+                start_pc: 0,
+                line_number: 1,
+            },
+            LineNumber {
+                start_pc: 3,
+                line_number: 2,
+            },
+        ])
+    );
+}
+
+#[test]
+fn code_after_goto_is_unreachable() {
+    let code = vec![
+        Instruction::Goto(5),
+        Instruction::IConst(1),
+        Instruction::Pop,
+        Instruction::Return,
+        Instruction::AThrow,
+    ];
+
+    assert_eq!(
+        reachable_instructions(&code, &[]),
+        vec![true, false, false, true, false]
+    );
+
+    let handler = ExceptionTableEntry {
+        start_pc: 0,
+        end_pc: 5,
+        handler_pc: 6,
+        catch_type: 0,
+    };
+    assert_eq!(
+        reachable_instructions(&code, &[handler]),
+        vec![true, false, false, true, true]
+    );
+}
+
+#[test]
+fn compute_max_stack_reports_where_the_stack_overflows() {
+    let jvm = JVMClass::template("Overflow");
+    let code = vec![Instruction::IConst(0); 65536];
+
+    // the 65536th push, at pc 65535, is one more than a max_stack can hold
+    assert!(matches!(
+        compute_max_stack(&code, &[], &jvm),
+        Err(JavaError::StackOverflow(65535))
+    ));
+}
+
+#[test]
+fn computes_the_frame_sizes_of_a_nested_expression() {
+    let jvm = common::load("Loops");
+    let code = common::code(&jvm, "nested", "(IIII)I");
+
+    // `c * d` is computed with `a`, `a + b`, `c - d` and `b` still on the stack
+    assert_eq!(compute_max_stack(code, &[], &jvm).unwrap(), 5);
+    assert_eq!(compute_max_locals(code, "(IIII)I", true).unwrap(), 4);
+    assert_eq!(compute_max_locals(code, "(IIII)I", false).unwrap(), 5);
+
+    let wide = [Instruction::DStoreW(300), Instruction::Return];
+    assert_eq!(compute_max_locals(&wide, "()V", true).unwrap(), 302);
+}
+
+#[test]
+fn verify_frame_sizes_rejects_undersized_declarations() {
+    let mut jvm = common::load("Loops");
+    assert!(jvm
+        .find_method("nested", "(IIII)I")
+        .unwrap()
+        .verify_frame_sizes(&jvm)
+        .is_ok());
+
+    let index = jvm
+        .methods
+        .iter()
+        .position(|method| jvm.get_string(method.0.name).unwrap() == "nested")
+        .unwrap();
+    if let Some(Attribute::Code { max_stack, .. }) = jvm.methods[index].code_mut() {
+        *max_stack = 4;
+    }
+    assert!(matches!(
+        jvm.methods[index].verify_frame_sizes(&jvm),
+        Err(JavaError::MaxStackTooSmall {
+            declared: 4,
+            needed: 5
+        })
+    ));
+
+    if let Some(Attribute::Code {
+        max_stack,
+        max_locals,
+        ..
+    }) = jvm.methods[index].code_mut()
+    {
+        *max_stack = 5;
+        *max_locals = 3;
+    }
+    assert!(matches!(
+        jvm.methods[index].verify_frame_sizes(&jvm),
+        Err(JavaError::MaxLocalsTooSmall {
+            declared: 3,
+            needed: 4
+        })
+    ));
+}
+
+#[test]
+fn check_max_stack_rejects_an_undersized_max_stack() {
+    let jvm = common::load("Loops");
+    let code = common::code(&jvm, "nested", "(IIII)I");
+
+    assert!(check_max_stack(code, &[], 5, true, "(IIII)I", &jvm).is_ok());
+    assert!(matches!(
+        check_max_stack(code, &[], 4, true, "(IIII)I", &jvm),
+        Err(JavaError::StackOverflow(_))
+    ));
+}
+
+#[test]
+fn check_max_stack_checks_the_exception_handlers() {
+    let jvm = JVMClass::template("Handler");
+    let code = [
+        Instruction::IConst(0),
+        Instruction::IReturn,
+        // the handler starts with the exception on the stack
+        Instruction::IConst(1),
+        Instruction::Pop,
+        Instruction::AThrow,
+    ];
+    let exception_table = [ExceptionTableEntry {
+        start_pc: 0,
+        end_pc: 1,
+        handler_pc: 2,
+        catch_type: 0,
+    }];
+
+    assert!(check_max_stack(&code, &[], 1, true, "()I", &jvm).is_ok());
+    assert!(matches!(
+        check_max_stack(&code, &exception_table, 1, true, "()I", &jvm),
+        Err(JavaError::StackOverflow(2))
+    ));
+    assert!(check_max_stack(&code, &exception_table, 2, true, "()I", &jvm).is_ok());
+}
+
+#[test]
+fn check_max_stack_rejects_an_underflow() {
+    let jvm = JVMClass::template("Underflow");
+    let code = [
+        Instruction::IConst(1),
+        Instruction::IAdd,
+        Instruction::IReturn,
+    ];
+
+    assert!(matches!(
+        check_max_stack(&code, &[], 2, true, "()I", &jvm),
+        Err(JavaError::StackUnderflow(1))
+    ));
+}
+
+#[test]
+fn shift_locals_moves_every_slot_from_slot_0() {
+    let mut code = vec![
+        Instruction::ILoad(0),
+        Instruction::IStore(3),
+        Instruction::IInc(1, 1),
+        Instruction::ILoad(255),
+        Instruction::Goto(-6),
+    ];
+    shift_locals(&mut code, 0, 1).unwrap();
+
+    assert_eq!(
+        code,
+        vec![
+            Instruction::ILoad(1),
+            Instruction::IStore(4),
+            Instruction::IInc(2, 1),
+            Instruction::ILoadW(256),
+            Instruction::Goto(-9),
+        ]
+    );
+}
+
+#[test]
+fn shift_code_locals_updates_the_debug_tables_and_frames() {
+    let mut jvm = common::load("Loops");
+    let sum = jvm
+        .methods
+        .iter()
+        .position(|method| jvm.get_string(method.0.name).unwrap() == "sum")
+        .unwrap();
+    let method = &mut jvm.methods[sum];
+    let code = method.code_mut().unwrap();
+
+    // a new local in slot 1, after the parameter `n` of `sum`
+    shift_code_locals(code, &[VerificationType::Integer], 1, 1).unwrap();
+
+    let Attribute::Code {
+        code: instructions,
+        max_locals,
+        attributes,
+        ..
+    } = code
+    else {
+        unreachable!();
+    };
+    assert_eq!(*max_locals, 4);
+    assert!(instructions.contains(&Instruction::ILoad(0)));
+    assert!(instructions.contains(&Instruction::IStore(2)));
+    assert!(instructions.contains(&Instruction::IInc(3, 1)));
+
+    let mut slots: Vec<_> = attributes
+        .iter()
+        .find_map(|attr| match attr {
+            Attribute::LocalVariableTable(table) => Some(table),
+            _ => None,
+        })
+        .unwrap()
+        .iter()
+        .map(|local| local.index)
+        .collect();
+    slots.sort();
+    assert_eq!(slots, vec![0, 2, 3]);
+
+    assert!(jvm.verify(VerifyOptions { stack_maps: true }).is_ok());
+}
+
+/// The `StackMapTable` javac wrote for `method`, and the one generated from its code.
+fn javac_and_generated_frames(
+    jvm: &mut JVMClass,
+    name: &str,
+    descriptor: &str,
+) -> (Vec<StackMapFrame>, Vec<StackMapFrame>) {
+    let method = jvm.find_method(name, descriptor).unwrap().clone();
+    let Some(Attribute::Code {
+        code,
+        exception_table,
+        attributes,
+        ..
+    }) = method.code()
+    else {
+        unreachable!();
+    };
+    let javac = attributes
+        .iter()
+        .find_map(|attr| match attr {
+            Attribute::StackMapTable(frames) => Some(frames.clone()),
+            _ => None,
+        })
+        .unwrap_or_default();
+
+    let generated = generate_stack_map(
+        code,
+        exception_table,
+        name,
+        &parse_method_descriptor(descriptor).unwrap(),
+        method.is_static(),
+        jvm,
+        &MapResolver::new(),
+    )
+    .unwrap();
+    (javac, generated)
+}
+
+/// The pc of each frame.
+fn frame_offsets(frames: &[StackMapFrame]) -> Vec<u16> {
+    let mut offsets: Vec<u16> = vec![];
+    for frame in frames {
+        let delta = match frame.frame_type {
+            StackMapFrameType::SameFrame(frame_type) => frame_type as u16,
+            StackMapFrameType::SameLocals1StackItemFrame(frame_type) => frame_type as u16 - 64,
+            _ => frame.offset_delta,
+        };
+        offsets.push(offsets.last().map_or(delta, |last| last + delta + 1));
+    }
+    offsets
+}
+
+#[test]
+fn generated_frames_match_javac() {
+    let mut jvm = common::load("Loops");
+    let (javac, generated) = javac_and_generated_frames(&mut jvm, "max", "(II)I");
+    // the `return max` after `if (b > a) max = b;`, with the local `max`
+    assert_eq!(
+        javac,
+        vec![StackMapFrame {
+            frame_type: StackMapFrameType::AppendFrame(252),
+            offset_delta: 9,
+            locals: vec![VerificationType::Integer],
+            stack: vec![],
+        }]
+    );
+    assert_eq!(generated, javac);
+
+    // javac drops the locals going out of scope, the generated frames keep them
+    for (class, name, descriptor) in [
+        ("Loops", "sum", "(I)I"),
+        ("Switches", "sparse", "(I)I"),
+        ("Switches", "dense", "(I)I"),
+        ("TryFinally", "run", "(Ljava/lang/String;)I"),
+    ] {
+        let mut jvm = common::load(class);
+        let (javac, generated) = javac_and_generated_frames(&mut jvm, name, descriptor);
+        assert!(!javac.is_empty(), "{class}.{name}");
+        assert_eq!(
+            frame_offsets(&generated),
+            frame_offsets(&javac),
+            "{class}.{name}"
+        );
+    }
+}
+
+/// The index of the instruction each instruction of `code` jumps to, if it is a `goto` or an
+/// `if<cond>`.
+fn jump_indices(code: &[Instruction]) -> Vec<Option<usize>> {
+    let offsets: Vec<u32> = code
+        .iter()
+        .scan(0, |pc, inst| {
+            let start = *pc;
+            *pc += inst.size();
+            Some(start)
+        })
+        .collect();
+
+    code.iter()
+        .zip(&offsets)
+        .map(|(inst, pc)| {
+            let target = inst.branch_target(*pc)?;
+            offsets.iter().position(|offset| *offset == target)
+        })
+        .collect()
+}
+
+#[test]
+fn inserted_instructions_keep_the_branches_on_their_targets() {
+    let jvm = common::load("Loops");
+    let code = common::code(&jvm, "sum", "(I)I").to_vec();
+    let goto = code
+        .iter()
+        .position(|inst| matches!(inst, Instruction::Goto(_)))
+        .unwrap();
+    let before = jump_indices(&code);
+    assert!(before[goto].unwrap() < goto);
+
+    let mut labeled = LabeledCode::new(code.clone()).unwrap();
+    labeled.insert(goto, Instruction::Nop).unwrap();
+    labeled.insert(0, Instruction::Nop).unwrap();
+    assert_eq!(labeled.len(), code.len() + 2);
+    let edited = labeled.finish().unwrap();
+
+    // every instruction from the goto on moved by 2, the ones before it by 1
+    let moved = |index: usize| if index >= goto { index + 2 } else { index + 1 };
+    let after = jump_indices(&edited);
+    for (index, target) in before.iter().enumerate() {
+        assert_eq!(after[moved(index)], target.map(moved), "{:?}", code[index]);
+    }
+    assert_eq!(edited[goto + 1], Instruction::Nop);
+    assert!(matches!(edited[goto + 2], Instruction::Goto(offset) if offset < 0));
+
+    // removing the nops restores the original code
+    let mut labeled = LabeledCode::new(edited).unwrap();
+    labeled.remove(goto + 1);
+    labeled.remove(0);
+    assert_eq!(labeled.finish().unwrap(), code);
+}
+
+#[test]
+fn rewrites_branches_holding_instruction_indices() {
+    let mut code = vec![
+        Instruction::IConst(0),
+        Instruction::IStore(0),
+        Instruction::IInc(0, 1),
+        Instruction::ILoad(0),
+        Instruction::Bipush(10),
+        Instruction::IfIcmplt(2),
+        Instruction::Return,
+    ];
+    rewrite_branches(&mut code).unwrap();
+    assert_eq!(code[5], Instruction::IfIcmplt(-6));
+
+    let mut far = vec![Instruction::Nop; 40000];
+    far.push(Instruction::Goto(0));
+    rewrite_branches(&mut far).unwrap();
+    assert_eq!(far.last(), Some(&Instruction::GotoW(-40000)));
+
+    let mut dangling = vec![Instruction::Goto(5), Instruction::Return];
+    assert!(matches!(
+        rewrite_branches(&mut dangling),
+        Err(JavaError::InvalidBranchTarget(0))
+    ));
+}
+
+#[test]
+fn replaces_a_method_body() {
+    let mut jvm = common::load("Loops");
+    let index = jvm
+        .methods
+        .iter()
+        .position(|method| jvm.get_string(method.0.name).unwrap() == "max")
+        .unwrap();
+
+    // `a < b ? a : b`
+    let constants = jvm.constants.len();
+    let code = vec![
+        Instruction::ILoad(0),
+        Instruction::ILoad(1),
+        Instruction::IfIcmpge(5),
+        Instruction::ILoad(0),
+        Instruction::IReturn,
+        Instruction::ILoad(1),
+        Instruction::IReturn,
+    ];
+    jvm.set_method_code(index, code.clone(), vec![]).unwrap();
+    assert_eq!(jvm.constants.len(), constants);
+    assert!(jvm.verify(VerifyOptions { stack_maps: true }).is_ok());
+
+    let jvm = JVMClass::from_bytes(&jvm.to_bytes().unwrap()).unwrap();
+    let Some(Attribute::Code {
+        code: reloaded,
+        max_stack,
+        max_locals,
+        attributes,
+        ..
+    }) = jvm.find_method("max", "(II)I").unwrap().code()
+    else {
+        panic!("max has no code");
+    };
+    assert_eq!(*reloaded, code);
+    assert_eq!((*max_stack, *max_locals), (2, 2));
+    // the line numbers and local variables of the old body are gone
+    assert!(matches!(
+        attributes.as_slice(),
+        [Attribute::StackMapTable(_)]
+    ));
+}
+
+#[test]
+fn replaced_bodies_keep_their_exception_handlers() {
+    // static int parse(String s) {
+    //     try { return Integer.parseInt(s); } catch (NumberFormatException e) { return -1; }
+    // }
+    let mut jvm = JVMClass::template("Parser");
+    let parse_int = jvm.constant_pool().intern_methodref(
+        "java/lang/Integer",
+        "parseInt",
+        "(Ljava/lang/String;)I",
+    );
+    let catch_type = jvm
+        .constant_pool()
+        .intern_class("java/lang/NumberFormatException");
+    jvm.add_method(
+        "parse",
+        "(Ljava/lang/String;)I",
+        vec![AccessFlag::Public, AccessFlag::Static],
+        None,
+    );
+    // the template has no code, so the names of the attributes are missing
+    assert!(!jvm.constants.contains(&Constant::Utf8("Code".into())));
+
+    let exception_table = vec![ExceptionTableEntry {
+        start_pc: 0,
+        end_pc: 4,
+        handler_pc: 5,
+        catch_type,
+    }];
+    let code = vec![
+        Instruction::ALoad(0),
+        Instruction::InvokeStatic(parse_int),
+        Instruction::IReturn,
+        Instruction::Pop,
+        Instruction::IConst(-1),
+        Instruction::IReturn,
+    ];
+    jvm.set_method_code(0, code, exception_table.clone())
+        .unwrap();
+
+    assert!(jvm.constants.contains(&Constant::Utf8("Code".into())));
+    assert!(jvm
+        .constants
+        .contains(&Constant::Utf8("StackMapTable".into())));
+    assert!(jvm.validate().is_ok());
+
+    let jvm = JVMClass::from_bytes(&jvm.to_bytes().unwrap()).unwrap();
+    assert!(jvm.verify(VerifyOptions { stack_maps: true }).is_ok());
+    let Some(Attribute::Code {
+        max_stack,
+        exception_table: reloaded,
+        attributes,
+        ..
+    }) = jvm
+        .find_method("parse", "(Ljava/lang/String;)I")
+        .unwrap()
+        .code()
+    else {
+        panic!("parse has no code");
+    };
+    assert_eq!(*reloaded, exception_table);
+    assert_eq!(*max_stack, 1);
+    // the handler is a branch target, with the exception on the stack
+    let [Attribute::StackMapTable(frames)] = attributes.as_slice() else {
+        panic!("{attributes:?}");
+    };
+    assert_eq!(frames.len(), 1);
+    // the frame type holds the offset, 64 + 5
+    assert_eq!(
+        frames[0].frame_type,
+        StackMapFrameType::SameLocals1StackItemFrame(69)
+    );
+    assert!(matches!(
+        frames[0].stack.as_slice(),
+        [VerificationType::Object { cpool_index }] if *cpool_index == catch_type
+    ));
+}
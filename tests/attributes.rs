@@ -0,0 +1,641 @@
+mod common;
+
+use class_rs::attribute_names;
+use class_rs::{
+    AccessFlag, Annotation, Attribute, ClassView, Constant, ElementValue, ElementValuePair,
+    EnclosingMethod, Instruction, JVMClass, JavaError, LineNumber, LocalVariable, MemberData,
+    ResolvedElementValue, TargetInfo,
+};
+
+/// One attribute of each kind defined by the specification, with its name.
+fn samples() -> Vec<(&'static str, Attribute)> {
+    vec![
+        (
+            attribute_names::ANNOTATION_DEFAULT,
+            Attribute::AnnotationDefault(ElementValue::ClassInfoIndex(1)),
+        ),
+        (
+            attribute_names::BOOTSTRAP_METHODS,
+            Attribute::BootstrapMethods(vec![]),
+        ),
+        (
+            attribute_names::CODE,
+            Attribute::Code {
+                code: vec![Instruction::Return],
+                max_stack: 0,
+                max_locals: 0,
+                exception_table: vec![],
+                attributes: vec![],
+            },
+        ),
+        (
+            attribute_names::CONSTANT_VALUE,
+            Attribute::ConstantValue {
+                constantvalue_index: 1,
+            },
+        ),
+        (attribute_names::DEPRECATED, Attribute::Deprecated),
+        (
+            attribute_names::ENCLOSING_METHOD,
+            Attribute::EnclosingMethod {
+                class_index: 1,
+                method_index: 0,
+            },
+        ),
+        (attribute_names::EXCEPTIONS, Attribute::Exceptions(vec![])),
+        (
+            attribute_names::INNER_CLASSES,
+            Attribute::InnerClasses(vec![]),
+        ),
+        (
+            attribute_names::LINE_NUMBER_TABLE,
+            Attribute::LineNumberTable(vec![]),
+        ),
+        (
+            attribute_names::LOCAL_VARIABLE_TABLE,
+            Attribute::LocalVariableTable(vec![]),
+        ),
+        (
+            attribute_names::LOCAL_VARIABLE_TYPE_TABLE,
+            Attribute::LocalVariableTypeTable(vec![]),
+        ),
+        (
+            attribute_names::METHOD_PARAMETERS,
+            Attribute::MethodParameters(vec![]),
+        ),
+        (
+            attribute_names::MODULE,
+            Attribute::Module {
+                module_name_index: 1,
+                module_flags: vec![],
+                module_version_index: 0,
+                requires: vec![],
+                exports: vec![],
+                opens: vec![],
+                uses: vec![],
+                provides: vec![],
+            },
+        ),
+        (
+            attribute_names::MODULE_MAIN_CLASS,
+            Attribute::ModuleMainClass(1),
+        ),
+        (
+            attribute_names::MODULE_PACKAGES,
+            Attribute::ModulePackages(vec![]),
+        ),
+        (attribute_names::NEST_HOST, Attribute::NestHost(1)),
+        (
+            attribute_names::NEST_MEMBERS,
+            Attribute::NestMembers(vec![]),
+        ),
+        (
+            attribute_names::PERMITTED_SUBCLASSES,
+            Attribute::PermittedSubclasses(vec![]),
+        ),
+        (attribute_names::RECORD, Attribute::Record(vec![])),
+        (
+            attribute_names::RUNTIME_INVISIBLE_ANNOTATIONS,
+            Attribute::RuntimeInvisibleAnnotations(vec![]),
+        ),
+        (
+            attribute_names::RUNTIME_INVISIBLE_PARAMETER_ANNOTATIONS,
+            Attribute::RuntimeInvisibleParameterAnnotations(vec![]),
+        ),
+        (
+            attribute_names::RUNTIME_INVISIBLE_TYPE_ANNOTATIONS,
+            Attribute::RuntimeInvisibleTypeAnnotations(vec![]),
+        ),
+        (
+            attribute_names::RUNTIME_VISIBLE_ANNOTATIONS,
+            Attribute::RuntimeVisibleAnnotations(vec![]),
+        ),
+        (
+            attribute_names::RUNTIME_VISIBLE_PARAMETER_ANNOTATIONS,
+            Attribute::RuntimeVisibleParameterAnnotations(vec![]),
+        ),
+        (
+            attribute_names::RUNTIME_VISIBLE_TYPE_ANNOTATIONS,
+            Attribute::RuntimeVisibleTypeAnnotations(vec![]),
+        ),
+        (
+            attribute_names::SIGNATURE,
+            Attribute::Signature { signature_index: 1 },
+        ),
+        (
+            attribute_names::SOURCE_DEBUG_EXTENSION,
+            Attribute::SourceDebugExtension {
+                debug_extension: b"SMAP".to_vec(),
+            },
+        ),
+        (
+            attribute_names::SOURCE_FILE,
+            Attribute::SourceFile {
+                sourcefile_index: 1,
+            },
+        ),
+        (
+            attribute_names::STACK_MAP_TABLE,
+            Attribute::StackMapTable(vec![]),
+        ),
+        (attribute_names::SYNTHETIC, Attribute::Synthetic),
+    ]
+}
+
+#[test]
+fn reader_and_writer_agree_on_attribute_names() {
+    for (name, attribute) in samples() {
+        assert_eq!(attribute.name(), name);
+
+        let mut jvm = JVMClass::template("Sample");
+        jvm.get_or_add_utf8_index(name);
+        jvm.attributes.push(attribute);
+
+        let bytes = jvm.to_bytes().unwrap_or_else(|e| panic!("{name}: {e}"));
+        let reloaded = JVMClass::from_bytes(&bytes).unwrap_or_else(|e| panic!("{name}: {e}"));
+
+        let [read] = reloaded.attributes.as_slice() else {
+            panic!("{name}: {:?}", reloaded.attributes);
+        };
+        assert!(
+            !matches!(read, Attribute::Unknown { .. }),
+            "{name} was read as an unknown attribute"
+        );
+        assert_eq!(read.name(), name);
+    }
+}
+
+#[test]
+fn try_catch_finally_handlers_survive_a_round_trip() {
+    let bytes = common::fixture("TryFinally");
+    let jvm = JVMClass::from_bytes(&bytes).unwrap();
+
+    let Some(Attribute::Code {
+        exception_table, ..
+    }) = jvm
+        .find_method("run", "(Ljava/lang/String;)I")
+        .unwrap()
+        .code()
+    else {
+        panic!("run has no code");
+    };
+
+    let catch_types: Vec<_> = exception_table
+        .iter()
+        .map(|entry| match entry.catch_type {
+            0 => "any",
+            id => jvm.get_class_name(id).unwrap(),
+        })
+        .collect();
+    assert_eq!(
+        catch_types,
+        vec!["java/lang/NumberFormatException", "any", "any"]
+    );
+
+    let stored = jvm.to_bytes().unwrap();
+    assert!(stored == bytes);
+    assert_eq!(JVMClass::from_bytes(&stored).unwrap(), jvm);
+}
+
+#[test]
+fn builds_and_stores_array_and_nested_annotation_values() {
+    let mut jvm = JVMClass::template("Annotated");
+    let strings = ElementValue::string_array(&mut jvm, &["a", "b"]);
+    let ints = ElementValue::int_array(&mut jvm, &[1, 2, 3]);
+    let inner = Annotation {
+        type_index: jvm.get_or_add_utf8_index("LBar;"),
+        element_value_pairs: vec![],
+    };
+    let foo = Annotation {
+        type_index: jvm.get_or_add_utf8_index("LFoo;"),
+        element_value_pairs: vec![
+            ElementValuePair {
+                element_name_index: jvm.get_or_add_utf8_index("value"),
+                value: strings,
+            },
+            ElementValuePair {
+                element_name_index: jvm.get_or_add_utf8_index("counts"),
+                value: ints,
+            },
+            ElementValuePair {
+                element_name_index: jvm.get_or_add_utf8_index("bar"),
+                value: ElementValue::nested(inner),
+            },
+        ],
+    };
+    jvm.get_or_add_utf8_index(attribute_names::RUNTIME_VISIBLE_ANNOTATIONS);
+    jvm.attributes
+        .push(Attribute::RuntimeVisibleAnnotations(vec![foo.clone()]));
+
+    let jvm = JVMClass::from_bytes(&jvm.to_bytes().unwrap()).unwrap();
+    let Some(Attribute::RuntimeVisibleAnnotations(annotations)) =
+        jvm.find_attribute(attribute_names::RUNTIME_VISIBLE_ANNOTATIONS)
+    else {
+        panic!("the annotation wasn't stored");
+    };
+    assert_eq!(annotations, &vec![foo.clone()]);
+
+    let resolved = ElementValue::AnnotationValue(foo).resolve(&jvm).unwrap();
+    assert_eq!(
+        resolved,
+        ResolvedElementValue::Annotation {
+            type_descriptor: "LFoo;".into(),
+            values: vec![
+                (
+                    "value".into(),
+                    ResolvedElementValue::Array(vec![
+                        ResolvedElementValue::String("a".into()),
+                        ResolvedElementValue::String("b".into()),
+                    ])
+                ),
+                (
+                    "counts".into(),
+                    ResolvedElementValue::Array(vec![
+                        ResolvedElementValue::Int(1),
+                        ResolvedElementValue::Int(2),
+                        ResolvedElementValue::Int(3),
+                    ])
+                ),
+                (
+                    "bar".into(),
+                    ResolvedElementValue::Annotation {
+                        type_descriptor: "LBar;".into(),
+                        values: vec![],
+                    }
+                ),
+            ],
+        }
+    );
+}
+
+#[test]
+fn finds_class_attributes_by_name() {
+    let mut jvm = common::load("Hello");
+    let Some(Attribute::SourceFile { sourcefile_index }) = jvm.find_attribute("SourceFile") else {
+        panic!("no SourceFile in {:?}", jvm.attributes);
+    };
+    assert_eq!(jvm.get_string(*sourcefile_index).unwrap(), "Hello.java");
+    assert!(jvm.find_attribute("Code").is_none());
+
+    jvm.attributes.push(Attribute::Unknown {
+        name: "com.example.Custom".into(),
+        data: vec![],
+    });
+    let custom = jvm.find_attribute("com.example.Custom").unwrap();
+    assert_eq!(custom.name(), "com.example.Custom");
+}
+
+#[test]
+fn maps_pcs_to_source_lines() {
+    let mut jvm = common::load("Loops");
+    let expected = [
+        (0, 5),
+        (3, 6),
+        (9, 7),
+        (12, 7),
+        (13, 6),
+        (16, 6),
+        (19, 9),
+        (20, 9),
+    ];
+
+    let sum = jvm.find_method("sum", "(I)I").unwrap();
+    for (pc, line) in expected {
+        assert_eq!(sum.line_for_pc(pc), Some(line), "pc {pc}");
+    }
+
+    let index = jvm
+        .methods
+        .iter()
+        .position(|method| jvm.get_string(method.0.name).unwrap() == "sum")
+        .unwrap();
+    let sum = &mut jvm.methods[index];
+    let Some(Attribute::Code { attributes, .. }) = sum.0.attributes.first_mut() else {
+        panic!("sum has no code");
+    };
+    let Some(Attribute::LineNumberTable(table)) = attributes
+        .iter_mut()
+        .find(|attr| matches!(attr, Attribute::LineNumberTable(_)))
+    else {
+        panic!("sum has no line numbers");
+    };
+    table.reverse();
+    // the first statement has no line anymore
+    table.pop();
+    for (pc, line) in expected.into_iter().skip(1) {
+        assert_eq!(sum.line_for_pc(pc), Some(line), "pc {pc}");
+    }
+    assert_eq!(sum.line_for_pc(0), None);
+    assert_eq!(sum.line_for_pc(1), None);
+}
+
+#[test]
+fn resolves_enum_constants_of_annotations() {
+    let jvm = common::load("Tagged");
+    let Some(Attribute::RuntimeVisibleAnnotations(annotations)) =
+        jvm.find_attribute(attribute_names::RUNTIME_VISIBLE_ANNOTATIONS)
+    else {
+        panic!("Tagged has no annotations");
+    };
+    let enum_constant = |type_descriptor: &str, constant_name: &str| ResolvedElementValue::Enum {
+        type_descriptor: type_descriptor.into(),
+        constant_name: constant_name.into(),
+    };
+
+    let resolved: Vec<_> = annotations
+        .iter()
+        .map(|annotation| {
+            ElementValue::AnnotationValue(annotation.clone())
+                .resolve(&jvm)
+                .unwrap()
+        })
+        .collect();
+    assert_eq!(
+        resolved,
+        [
+            ResolvedElementValue::Annotation {
+                type_descriptor: "Ljava/lang/annotation/Retention;".into(),
+                values: vec![(
+                    "value".into(),
+                    enum_constant("Ljava/lang/annotation/RetentionPolicy;", "RUNTIME")
+                )],
+            },
+            ResolvedElementValue::Annotation {
+                type_descriptor: "Ljava/lang/annotation/Target;".into(),
+                values: vec![(
+                    "value".into(),
+                    ResolvedElementValue::Array(vec![
+                        enum_constant("Ljava/lang/annotation/ElementType;", "TYPE_USE"),
+                        enum_constant("Ljava/lang/annotation/ElementType;", "TYPE_PARAMETER"),
+                    ])
+                )],
+            },
+        ]
+    );
+
+    let dangling = ElementValue::EnumConstValue {
+        type_name_index: jvm.constants.len() as u16,
+        const_name_index: 1,
+    };
+    assert!(dangling.resolve(&jvm).is_err());
+}
+
+#[test]
+fn zero_indices_resolve_to_none() {
+    let mut anonymous = common::load("Outer$1");
+    let Some(Attribute::InnerClasses(inner_classes)) = anonymous.find_attribute("InnerClasses")
+    else {
+        panic!("Outer$1 has no InnerClasses");
+    };
+    let [entry] = inner_classes.as_slice() else {
+        panic!("{inner_classes:?}");
+    };
+    assert_eq!(entry.inner_name_index, 0);
+    assert_eq!(entry.inner_class(&anonymous).unwrap(), "fixtures/Outer$1");
+    assert_eq!(entry.outer_class(&anonymous).unwrap(), None);
+    assert_eq!(entry.inner_name(&anonymous).unwrap(), None);
+
+    let enclosing = anonymous.enclosing_method().unwrap().unwrap();
+    assert_eq!(
+        enclosing,
+        EnclosingMethod {
+            class: "fixtures/Outer",
+            method: Some(("anonymous", "()Ljava/lang/Runnable;")),
+        }
+    );
+    for attribute in &mut anonymous.attributes {
+        if let Attribute::EnclosingMethod { method_index, .. } = attribute {
+            *method_index = 0;
+        }
+    }
+    let enclosing = anonymous.enclosing_method().unwrap().unwrap();
+    assert_eq!(enclosing.method, None);
+
+    let inner = common::load("Outer$Inner");
+    let Some(Attribute::InnerClasses(inner_classes)) = inner.find_attribute("InnerClasses") else {
+        panic!("Outer$Inner has no InnerClasses");
+    };
+    assert_eq!(inner_classes[0].inner_name(&inner).unwrap(), Some("Inner"));
+    assert_eq!(
+        inner_classes[0].outer_class(&inner).unwrap(),
+        Some("fixtures/Outer")
+    );
+
+    assert_eq!(inner.get_string_opt(0).unwrap(), None);
+    assert!(inner.get_string_opt(inner.constants.len() as u16).is_err());
+}
+
+#[test]
+fn stores_unknown_attributes_with_names_missing_from_the_pool() {
+    let mut jvm = JVMClass::template("Custom");
+    let unknown = Attribute::Unknown {
+        name: "com.example.Custom".into(),
+        data: vec![1, 2, 3],
+    };
+    jvm.attributes.push(unknown.clone());
+    let constants = jvm.constants.len();
+
+    let reloaded = JVMClass::from_bytes(&jvm.to_bytes().unwrap()).unwrap();
+    assert_eq!(reloaded.attributes, vec![unknown]);
+    assert_eq!(jvm.constants.len(), constants);
+
+    // other attributes still need their name in the pool
+    jvm.attributes.push(Attribute::Synthetic);
+    assert!(jvm.to_bytes().is_err());
+}
+
+#[test]
+fn throws_type_annotations_round_trip() {
+    let jvm = common::load("TypeAnnotated");
+    let throws = |jvm: &JVMClass| {
+        let foo = jvm.find_methods_named("foo").next().unwrap();
+        jvm.type_annotations(&foo.0)
+            .into_iter()
+            .find(|annotation| matches!(annotation.target_info, TargetInfo::Throws { .. }))
+            .cloned()
+            .unwrap()
+    };
+
+    let annotation = throws(&jvm);
+    assert_eq!(
+        annotation.target_info,
+        TargetInfo::Throws {
+            throws_type_index: 0
+        }
+    );
+    assert_eq!(
+        jvm.get_string(annotation.annotation.type_index).unwrap(),
+        "Lfixtures/Tagged;"
+    );
+
+    let bytes = jvm.to_bytes().unwrap();
+    assert!(bytes == common::fixture("TypeAnnotated"));
+    assert_eq!(throws(&JVMClass::from_bytes(&bytes).unwrap()), annotation);
+}
+
+#[test]
+fn describes_type_annotations_with_their_member() {
+    let jvm = common::load("TypeAnnotated");
+    let describe = |member: &MemberData| -> Vec<String> {
+        jvm.type_annotations(member)
+            .into_iter()
+            .map(|annotation| annotation.describe(&jvm, Some(member)))
+            .collect()
+    };
+
+    let name = &jvm.fields[0].0;
+    assert_eq!(describe(name), ["@fixtures.Tagged on type of field name"]);
+
+    let foo = jvm.find_methods_named("foo").next().unwrap();
+    let mut descriptions = describe(&foo.0);
+    descriptions.sort();
+    assert_eq!(
+        descriptions,
+        [
+            "@fixtures.Tagged on throws clause 0 of method foo",
+            "@fixtures.Tagged on type parameter 0 bound 1 of method foo",
+        ]
+    );
+}
+
+// the zeroed buffer is allocated lazily, and the writer fails before reading it
+#[cfg(target_pointer_width = "64")]
+#[test]
+fn attributes_too_large_for_their_length_are_errors() {
+    let mut jvm = JVMClass::template("Huge");
+    jvm.get_or_add_utf8_index("com.example.Huge");
+    let len = u32::MAX as usize + 1;
+    jvm.attributes.push(Attribute::Unknown {
+        name: "com.example.Huge".into(),
+        data: vec![0; len],
+    });
+
+    let error = jvm.to_bytes().unwrap_err();
+    assert!(matches!(
+        error.downcast_ref::<JavaError>(),
+        Some(JavaError::AttributeTooLarge { name, len: 4294967296 }) if name == "com.example.Huge"
+    ));
+    assert_eq!(
+        error.to_string(),
+        "Attribute com.example.Huge is too large (4294967296 bytes)"
+    );
+}
+
+/// A writer accepting `capacity` bytes, then failing.
+struct Full {
+    capacity: usize,
+}
+
+impl std::io::Write for Full {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        if self.capacity == 0 {
+            return Err(std::io::ErrorKind::WriteZero.into());
+        }
+        let written = buf.len().min(self.capacity);
+        self.capacity -= written;
+        Ok(written)
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        Ok(())
+    }
+}
+
+#[test]
+fn storing_without_the_code_name_is_an_error() {
+    let mut jvm = JVMClass::template("Nameless");
+    jvm.add_method(
+        "run",
+        "()V",
+        vec![AccessFlag::Static],
+        Some(Attribute::Code {
+            max_stack: 0,
+            max_locals: 0,
+            code: vec![Instruction::Return],
+            exception_table: vec![],
+            attributes: vec![],
+        }),
+    );
+    let code = jvm.get_string_index("Code").unwrap();
+    jvm.constants[code as usize] = Constant::Utf8("Renamed".into());
+
+    let error = jvm.to_bytes().unwrap_err();
+    assert!(matches!(
+        error.downcast_ref::<JavaError>(),
+        Some(JavaError::MissingAttributeName(name)) if name == "Code"
+    ));
+}
+
+#[test]
+fn write_errors_are_returned() {
+    let jvm = common::load("Hello");
+    let size = jvm.to_bytes().unwrap().len();
+
+    for capacity in [0, 9, size / 2, size - 1] {
+        let error = jvm.store(&mut Full { capacity }).unwrap_err();
+        assert!(error.downcast_ref::<std::io::Error>().is_some(), "{error}");
+    }
+}
+
+#[test]
+fn nested_code_attributes_get_their_own_lengths() {
+    let mut jvm = JVMClass::template("Nested");
+    let name = jvm.get_or_add_utf8_index("x");
+    let descriptor = jvm.get_or_add_utf8_index("I");
+    let lines = Attribute::LineNumberTable(vec![
+        LineNumber {
+            start_pc: 0,
+            line_number: 1,
+        },
+        LineNumber {
+            start_pc: 2,
+            line_number: 2,
+        },
+    ]);
+    let locals = Attribute::LocalVariableTable(vec![LocalVariable {
+        start_pc: 0,
+        length: 3,
+        name_index: name,
+        descriptor_index: descriptor,
+        index: 0,
+    }]);
+    jvm.add_method(
+        "nested",
+        "(I)I",
+        vec![AccessFlag::Static],
+        Some(Attribute::Code {
+            max_stack: 1,
+            max_locals: 1,
+            code: vec![
+                Instruction::ILoad(0),
+                Instruction::Nop,
+                Instruction::IReturn,
+            ],
+            exception_table: vec![],
+            attributes: vec![lines.clone(), locals.clone()],
+        }),
+    );
+    let bytes = jvm.to_bytes().unwrap();
+
+    let view = ClassView::new(&bytes).unwrap();
+    let method = view.methods().next().unwrap();
+    let code = method.attributes().next().unwrap();
+    assert_eq!(code.name().unwrap(), "Code");
+    // max_stack, max_locals, the code, the handlers and the two attributes with their headers
+    let lines_length = 2 + 2 * 4;
+    let locals_length = 2 + 10;
+    assert_eq!(
+        code.data().len(),
+        2 + 2 + 4 + 3 + 2 + 2 + 6 + lines_length + 6 + locals_length
+    );
+
+    // checked, every declared length must match what is read
+    let mut reloaded = JVMClass::new();
+    reloaded.load_checked(&mut bytes.as_slice()).unwrap();
+    let Some(Attribute::Code { attributes, .. }) =
+        reloaded.find_method("nested", "(I)I").unwrap().code()
+    else {
+        unreachable!();
+    };
+    assert_eq!(attributes, &vec![lines, locals]);
+}
@@ -0,0 +1,220 @@
+use class_rs::{
+    AccessFlag, Assembler, ClassBuilder, CodeBuilder, Instruction, JVMClass, JavaError,
+    VerifyOptions,
+};
+
+#[test]
+fn named_locals_end_up_in_the_local_variable_table() {
+    let mut jvm = JVMClass::template("Locals");
+    let code = CodeBuilder::new()
+        .emit(Instruction::ILoad(0))
+        .emit(Instruction::IStore(1))
+        .emit(Instruction::ILoad(1))
+        .emit(Instruction::IReturn)
+        .max_stack(1)
+        .max_locals(2)
+        .local("x", "I", 0, 0, 4)
+        .local("y", "I", 1, 2, 2)
+        .build(&mut jvm)
+        .unwrap();
+    jvm.add_method(
+        "copy",
+        "(I)I",
+        vec![AccessFlag::Public, AccessFlag::Static],
+        Some(code),
+    );
+
+    let jvm = JVMClass::from_bytes(&jvm.to_bytes().unwrap()).unwrap();
+    let code = jvm.find_method("copy", "(I)I").unwrap().code().unwrap();
+
+    let x = code.local_variable_at(0, 3).unwrap();
+    assert_eq!(jvm.get_string(x.name_index).unwrap(), "x");
+    assert_eq!(jvm.get_string(x.descriptor_index).unwrap(), "I");
+
+    let y = code.local_variable_at(1, 2).unwrap();
+    assert_eq!(jvm.get_string(y.name_index).unwrap(), "y");
+
+    // `y` isn't assigned yet before pc 2.
+    assert!(code.local_variable_at(1, 1).is_none());
+}
+
+#[test]
+fn assembles_a_counting_loop() {
+    let code = Assembler::new()
+        .emit(Instruction::IConst(0))
+        .emit(Instruction::IStore(0))
+        .label("loop")
+        .emit(Instruction::IInc(0, 1))
+        .emit(Instruction::ILoad(0))
+        .emit(Instruction::Bipush(10))
+        .branch(Instruction::IfIcmplt(0), "loop")
+        .emit(Instruction::Return)
+        .finish()
+        .unwrap();
+
+    assert_eq!(
+        code,
+        vec![
+            Instruction::IConst(0),
+            Instruction::IStore(0),
+            Instruction::IInc(0, 1),
+            Instruction::ILoad(0),
+            Instruction::Bipush(10),
+            Instruction::IfIcmplt(-6),
+            Instruction::Return,
+        ]
+    );
+}
+
+#[test]
+fn far_gotos_become_goto_w() {
+    let mut assembler = Assembler::new();
+    assembler.label("start");
+    for _ in 0..40000 {
+        assembler.emit(Instruction::Nop);
+    }
+    assembler.goto_label("start");
+
+    let code = assembler.finish().unwrap();
+    assert_eq!(code.last(), Some(&Instruction::GotoW(-40000)));
+}
+
+#[test]
+fn undefined_labels_are_errors() {
+    let result = Assembler::new().goto_label("nowhere").finish();
+    assert!(matches!(result, Err(JavaError::InvalidLabel(label)) if label == "nowhere"));
+}
+
+#[test]
+fn builds_a_loadable_empty_class() {
+    let jvm = ClassBuilder::new("Empty", "java/lang/Object")
+        .add_default_constructor()
+        .build();
+
+    let jvm = JVMClass::from_bytes(&jvm.to_bytes().unwrap()).unwrap();
+    assert_eq!(jvm.this_class_name().unwrap(), "Empty");
+    assert_eq!(
+        jvm.get_class_name(jvm.super_class).unwrap(),
+        "java/lang/Object"
+    );
+    assert!(jvm.is_public());
+    assert!(jvm.access_flags.contains(&AccessFlag::Super));
+    assert!(jvm.validate().is_ok());
+    assert!(jvm.verify(VerifyOptions { stack_maps: true }).is_ok());
+
+    let init = jvm.find_method("<init>", "()V").unwrap();
+    assert!(init.verify_frame_sizes(&jvm).is_ok());
+    let code = init.code().unwrap().instructions();
+    assert_eq!(code.len(), 3);
+    assert_eq!(code[0], Instruction::ALoad(0));
+    let Instruction::InvokeSpecial(index) = code[1] else {
+        panic!("{:?}", code[1]);
+    };
+    assert_eq!(
+        jvm.resolve_ref(index).unwrap(),
+        ("java/lang/Object".into(), "<init>".into(), "()V".into())
+    );
+    assert_eq!(code[2], Instruction::Return);
+}
+
+#[test]
+fn code_builder_resolves_switches_and_backward_gotos() {
+    let code = CodeBuilder::new()
+        .label("start")
+        .emit(Instruction::ILoad(0))
+        .switch(
+            Instruction::TableSwitch {
+                padding: 0,
+                minimum: 1,
+                maximum: 2,
+                jump_targets: vec![0, 0],
+                default: 0,
+            },
+            "other",
+            &["one", "two"],
+        )
+        .label("one")
+        .emit(Instruction::IConst(1))
+        .emit(Instruction::IReturn)
+        .label("two")
+        .emit(Instruction::IConst(2))
+        .emit(Instruction::IReturn)
+        .label("other")
+        .emit(Instruction::IInc(0, -1))
+        .goto("start")
+        .instructions()
+        .unwrap();
+
+    // the switch at pc 1 is padded to pc 4, and its 20 bytes of operands end at pc 24
+    assert_eq!(
+        code[1],
+        Instruction::TableSwitch {
+            padding: 2,
+            minimum: 1,
+            maximum: 2,
+            jump_targets: vec![23, 25],
+            default: 27,
+        }
+    );
+    // from the goto at pc 31 back to pc 0
+    assert_eq!(code.last(), Some(&Instruction::Goto(-31)));
+}
+
+#[test]
+fn ldc_widens_indices_past_255() {
+    let mut jvm = JVMClass::template("Constants");
+    let near = jvm.constant_pool().intern_string("near");
+    for i in 0..300 {
+        jvm.constant_pool().intern_utf8(&format!("filler {i}"));
+    }
+    let far = jvm.constant_pool().intern_string("far");
+    assert!(near <= 255 && far > 255);
+    assert_eq!(Instruction::ldc(near), Instruction::Ldc(near as u8));
+    assert_eq!(Instruction::ldc(far), Instruction::LdcW(far));
+
+    let code = CodeBuilder::new()
+        .ldc(near)
+        .emit(Instruction::Pop)
+        .ldc(far)
+        .emit(Instruction::AReturn)
+        .max_stack(1)
+        .build(&mut jvm)
+        .unwrap();
+    jvm.add_method(
+        "far",
+        "()Ljava/lang/Object;",
+        vec![AccessFlag::Public, AccessFlag::Static],
+        Some(code),
+    );
+    let reloaded = JVMClass::from_bytes(&jvm.to_bytes().unwrap()).unwrap();
+    let method = reloaded.find_method("far", "()Ljava/lang/Object;").unwrap();
+    assert_eq!(
+        method.code().unwrap().instructions(),
+        [
+            Instruction::Ldc(near as u8),
+            Instruction::Pop,
+            Instruction::LdcW(far),
+            Instruction::AReturn,
+        ]
+    );
+
+    // a `ldc` of a constant missing from the pool can't be stored
+    let mut jvm = JVMClass::template("Truncated");
+    let code = CodeBuilder::new()
+        .emit(Instruction::Ldc(200))
+        .emit(Instruction::AReturn)
+        .max_stack(1)
+        .build(&mut jvm)
+        .unwrap();
+    jvm.add_method(
+        "truncated",
+        "()Ljava/lang/Object;",
+        vec![AccessFlag::Public, AccessFlag::Static],
+        Some(code),
+    );
+    let error = jvm.to_bytes().unwrap_err();
+    assert!(matches!(
+        error.downcast_ref::<JavaError>(),
+        Some(JavaError::InvalidConstantId(200))
+    ));
+}
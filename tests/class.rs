@@ -0,0 +1,607 @@
+mod common;
+
+use std::time::Instant;
+
+use class_rs::{
+    attribute_names, AccessFlag, Attribute, ClassBuilder, ClassVersion, Constant, Field,
+    Instruction, JVMClass, JavaError, LineNumber, MemberData, MethodParameter, VerifyOptions,
+};
+
+#[test]
+fn display_summarizes_the_class() {
+    let jvm = common::load("Hello");
+    let summary = jvm.to_string();
+
+    assert!(summary.contains("class fixtures/Hello"), "{summary}");
+    assert!(summary.contains("extends Object"), "{summary}");
+    assert!(
+        summary.contains(&format!("{} methods", jvm.methods.len())),
+        "{summary}"
+    );
+    assert!(summary.contains("2 interfaces, 2 fields"), "{summary}");
+    assert!(summary.contains("Java 17"), "{summary}");
+}
+
+#[test]
+fn removes_an_interface_by_name() {
+    let mut jvm = common::load("Hello");
+    assert_eq!(jvm.interfaces.len(), 2);
+
+    assert!(jvm.remove_interface("java/lang/Runnable"));
+    assert!(!jvm.remove_interface("java/lang/Runnable"));
+
+    let names: Vec<_> = jvm
+        .interfaces
+        .iter()
+        .map(|id| jvm.get_class_name(*id).unwrap())
+        .collect();
+    assert_eq!(names, vec!["java/util/function/Supplier"]);
+}
+
+#[test]
+fn add_interface_index_rejects_non_class_constants() {
+    let mut jvm = JVMClass::template("Interfaces");
+    let utf8 = jvm.get_or_add_utf8_index("java/lang/Runnable");
+
+    assert!(jvm.add_interface_index(utf8).is_err());
+    assert!(jvm.add_interface_index(u16::MAX).is_err());
+    assert!(jvm.interfaces.is_empty());
+
+    let class = jvm.constant_pool().intern_class("java/lang/Runnable");
+    jvm.add_interface_index(class).unwrap();
+    assert_eq!(jvm.interfaces, vec![class]);
+}
+
+#[test]
+fn constructors_read_the_same_class() {
+    let path = common::fixtures_dir().join("Hello.class");
+    let bytes = common::fixture("Hello");
+
+    let from_file = JVMClass::from_file(&path).unwrap();
+    let from_bytes = JVMClass::from_bytes(&bytes).unwrap();
+    let from_reader = JVMClass::from_reader(&mut bytes.as_slice()).unwrap();
+    assert_eq!(from_file, from_bytes);
+    assert_eq!(from_reader, from_bytes);
+
+    let mut loaded = JVMClass::new();
+    loaded.load(&mut bytes.as_slice()).unwrap();
+    assert_eq!(loaded, from_bytes);
+}
+
+#[test]
+fn failed_load_leaves_the_class_unchanged() {
+    let mut jvm = common::load("Hello");
+    let bytes = common::fixture("Hello");
+
+    assert!(jvm.load(&mut &bytes[..bytes.len() / 2]).is_err());
+    assert_eq!(jvm, common::load("Hello"));
+
+    assert!(JVMClass::from_file(common::fixtures_dir().join("Missing.class")).is_err());
+}
+
+#[test]
+fn finds_fields_by_name() {
+    let mut jvm = common::load("Hello");
+
+    let count = jvm.field_by_name("count").unwrap();
+    assert_eq!(jvm.get_string(count.0.descriptor).unwrap(), "I");
+    assert!(count.is_private());
+    assert!(jvm.field_by_name("missing").is_none());
+
+    jvm.field_by_name_mut("count")
+        .unwrap()
+        .0
+        .set_flag(AccessFlag::Volatile, true);
+    assert!(jvm.field_by_name("count").unwrap().is_volatile());
+    assert!(!jvm.field_by_name("names").unwrap().is_volatile());
+}
+
+#[test]
+fn makes_a_method_private() {
+    let mut jvm = common::load("Loops");
+    let sum = jvm
+        .methods
+        .iter()
+        .position(|method| jvm.get_string(method.0.name).unwrap() == "sum")
+        .unwrap();
+    let member = &mut jvm.methods[sum].0;
+    assert!(member.has_flag(AccessFlag::Public));
+
+    // setting a flag twice keeps a single copy
+    member.set_flag(AccessFlag::Private, true);
+    member.set_flag(AccessFlag::Private, true);
+    assert_eq!(member.access_flags.len(), 3);
+    let error = jvm.to_bytes().unwrap_err();
+    assert!(matches!(
+        error.downcast_ref::<JavaError>(),
+        Some(JavaError::InvalidAccessFlags(message)) if message.contains("more than one access level")
+    ));
+
+    let member = &mut jvm.methods[sum].0;
+    member.clear_access_level();
+    member.set_flag(AccessFlag::Private, true);
+    member.set_flag(AccessFlag::Final, false);
+
+    let jvm = JVMClass::from_bytes(&jvm.to_bytes().unwrap()).unwrap();
+    let sum = jvm.find_method("sum", "(I)I").unwrap();
+    assert!(sum.is_private() && sum.is_static());
+    assert!(!sum.is_public() && !sum.0.has_flag(AccessFlag::Protected));
+    assert_eq!(sum.0.access_flags.len(), 2);
+}
+
+#[test]
+fn template_stores_and_reloads() {
+    let jvm = JVMClass::template("com/example/Foo");
+    let bytes = jvm.to_bytes().unwrap();
+    let reloaded = JVMClass::from_bytes(&bytes).unwrap();
+    assert_eq!(reloaded, jvm);
+
+    assert_eq!(reloaded.version(), ClassVersion::Java8);
+    assert_eq!(reloaded.access_flags, [AccessFlag::Super]);
+    assert_eq!(reloaded.this_class_name().unwrap(), "com/example/Foo");
+    assert_eq!(
+        reloaded.get_class_name(reloaded.super_class).unwrap(),
+        "java/lang/Object"
+    );
+    assert!(reloaded.fields.is_empty() && reloaded.methods.is_empty());
+    assert!(reloaded.attributes.is_empty());
+    assert!(reloaded.verify(VerifyOptions { stack_maps: true }).is_ok());
+
+    // an empty class has no version nor name to be loaded with
+    assert_eq!(JVMClass::default(), JVMClass::new());
+    assert_eq!(JVMClass::default().major, 0);
+    assert!(JVMClass::default().validate().is_err());
+}
+
+#[test]
+fn to_bytes_matches_store_into_a_plain_writer() {
+    let jvm = common::load("Hello");
+
+    let mut stored = vec![];
+    jvm.store(&mut stored).unwrap();
+
+    assert!(jvm.to_bytes().unwrap() == stored);
+    assert!(stored == common::fixture("Hello"));
+}
+
+#[test]
+fn access_flag_predicates() {
+    let jvm = common::load("Hello");
+    assert!(jvm.is_public());
+    assert!(!jvm.is_interface() && !jvm.is_enum() && !jvm.is_module());
+
+    let main = jvm.find_method("main", "([Ljava/lang/String;)V").unwrap();
+    assert!(main.is_public() && main.is_static());
+    assert!(!main.is_abstract() && !main.is_synthetic());
+
+    let bridge = jvm.find_method("get", "()Ljava/lang/Object;").unwrap();
+    assert!(bridge.is_bridge() && bridge.is_synthetic());
+    assert!(bridge.0.has_flag(AccessFlag::Bridge));
+
+    let names = jvm.field_by_name("names").unwrap();
+    assert!(names.is_private() && names.is_final());
+    assert!(!names.is_static());
+
+    let tagged = common::load("Tagged");
+    assert!(tagged.is_interface() && tagged.is_annotation() && tagged.is_abstract());
+    let value = tagged.find_method("value", "()Ljava/lang/String;").unwrap();
+    assert!(value.is_abstract());
+}
+
+#[test]
+fn finds_overloaded_methods() {
+    let jvm = common::load("Hello");
+
+    let descriptors: Vec<_> = jvm
+        .find_methods_named("add")
+        .map(|method| jvm.get_string(method.0.descriptor).unwrap())
+        .collect();
+    assert_eq!(descriptors, vec!["(Ljava/lang/String;)V", "(II)I", "(JJ)J"]);
+
+    assert!(jvm.find_method("add", "(II)I").is_some());
+    assert!(jvm.find_method("add", "(I)I").is_none());
+    assert!(jvm.find_method("add", "(II)").is_none());
+    assert!(jvm.find_methods_named("missing").next().is_none());
+
+    assert!(jvm.find_field("count", "I").is_some());
+    assert!(jvm.find_field("count", "J").is_none());
+}
+
+#[test]
+fn classes_with_nan_constants_compare_equal() {
+    let bytes = common::fixture("Strings");
+    let loaded = JVMClass::from_bytes(&bytes).unwrap();
+    let reloaded = JVMClass::from_bytes(&loaded.to_bytes().unwrap()).unwrap();
+
+    assert!(loaded
+        .constants
+        .iter()
+        .any(|constant| matches!(constant, class_rs::Constant::Double(value) if value.is_nan())));
+    assert_eq!(loaded, reloaded);
+    assert_eq!(loaded.clone(), loaded);
+
+    let mut changed = loaded.clone();
+    changed.minor = 1;
+    assert_ne!(changed, loaded);
+}
+
+#[test]
+fn identifies_the_java_release() {
+    let mut jvm = common::load("Hello");
+    assert_eq!((jvm.major, jvm.minor), (61, 0));
+    assert_eq!(jvm.version(), ClassVersion::Java17);
+    assert!(!jvm.supports_preview());
+
+    jvm.set_version(ClassVersion::Java8);
+    assert_eq!((jvm.major, jvm.minor), (52, 0));
+    assert_eq!(jvm.version(), ClassVersion::Java8);
+
+    jvm.minor = 0xFFFF;
+    assert!(jvm.supports_preview());
+
+    jvm.set_version(ClassVersion::Java1_1);
+    assert_eq!((jvm.major, jvm.minor), (45, 3));
+
+    jvm.set_version(ClassVersion::Unknown(70, 0));
+    assert_eq!(jvm.version(), ClassVersion::Unknown(70, 0));
+}
+
+#[test]
+fn toggles_preview_features() {
+    let mut jvm = common::load("Hello");
+    assert!(!jvm.is_preview());
+
+    jvm.set_preview(true);
+    assert!(jvm.is_preview());
+    assert!(jvm.validate_version().is_ok());
+    let bytes = jvm.to_bytes().unwrap();
+    // the minor version follows the magic number
+    assert_eq!(bytes[4..8], [0xFF, 0xFF, 0, 61]);
+    assert!(JVMClass::from_bytes(&bytes).unwrap().is_preview());
+
+    jvm.set_preview(false);
+    assert_eq!(jvm.to_bytes().unwrap()[4..8], [0, 0, 0, 61]);
+
+    // preview features start with Java 12
+    jvm.set_preview(true);
+    jvm.major = 55;
+    assert!(matches!(
+        jvm.validate_version(),
+        Err(JavaError::UnsupportedVersion(55, 0xFFFF))
+    ));
+    jvm.major = 56;
+    assert!(jvm.validate_version().is_ok());
+}
+
+#[test]
+fn iterates_every_method_body() {
+    let jvm = common::load("Loops");
+    let bodies: Vec<_> = jvm
+        .all_code()
+        .map(|(method, code)| {
+            (
+                jvm.get_string(method.0.name).unwrap(),
+                code.instructions().len(),
+                code.max_stack().unwrap(),
+                code.max_locals().unwrap(),
+            )
+        })
+        .collect();
+    assert_eq!(
+        bodies,
+        [
+            ("<init>", 3, 1, 1),
+            ("sum", 15, 2, 3),
+            ("max", 9, 2, 3),
+            ("nested", 16, 5, 4),
+        ]
+    );
+
+    // only `Code` attributes have code
+    let source_file = jvm.find_attribute("SourceFile").unwrap();
+    assert!(source_file.instructions().is_empty());
+    assert_eq!(source_file.max_stack(), None);
+    assert_eq!(source_file.max_locals(), None);
+
+    // the element of an annotation is abstract
+    let tagged = common::load("Tagged");
+    assert_eq!(tagged.methods.len(), 1);
+    assert_eq!(tagged.all_code().count(), 0);
+}
+
+/// A class with `count` fields, each with its own name and a `Synthetic` and a `Deprecated`
+/// attribute, so both the pool and the number of attributes grow with `count`.
+fn many_fields(count: usize) -> JVMClass {
+    let mut jvm = JVMClass::template("Many");
+    jvm.get_or_add_utf8_index("Synthetic");
+    jvm.get_or_add_utf8_index("Deprecated");
+    let descriptor = jvm.get_or_add_utf8_index("I");
+    for i in 0..count {
+        // pushed rather than interned, which would scan the pool each time
+        let name = jvm.push_constant(Constant::Utf8(format!("field{i}")));
+        jvm.fields.push(Field(MemberData {
+            access_flags: vec![AccessFlag::Private],
+            name,
+            descriptor,
+            attributes: vec![Attribute::Synthetic, Attribute::Deprecated],
+        }));
+    }
+
+    jvm
+}
+
+#[test]
+fn storing_scales_linearly_with_the_pool() {
+    let time = |jvm: &JVMClass| {
+        // the fastest of a few runs, to leave out the noise
+        (0..3)
+            .map(|_| {
+                let start = Instant::now();
+                jvm.to_bytes().unwrap();
+                start.elapsed()
+            })
+            .min()
+            .unwrap()
+    };
+
+    let small = time(&many_fields(2000));
+    let large = time(&many_fields(16000));
+    // 8 times the fields: 8 times longer if linear, 64 times if quadratic
+    assert!(
+        large < small * 24,
+        "{small:?} for 2000 fields, {large:?} for 16000"
+    );
+}
+
+#[test]
+fn attribute_names_match_decoded_strings() {
+    let mut jvm = JVMClass::template("Named");
+    let name = "com.example.\u{0}\u{1F600}";
+    let first = jvm.get_or_add_utf8_index(name);
+    // a duplicate added after the first one isn't used
+    jvm.push_constant(Constant::Utf8(name.into()));
+    jvm.attributes.push(Attribute::Unknown {
+        name: name.into(),
+        data: vec![],
+    });
+
+    let bytes = jvm.to_bytes().unwrap();
+    let reloaded = JVMClass::from_bytes(&bytes).unwrap();
+    assert_eq!(reloaded.find_attribute(name).unwrap().name(), name);
+    let attributes_start = bytes.len() - 2 - 6;
+    assert_eq!(bytes[attributes_start..attributes_start + 2], [0, 1]);
+    assert_eq!(
+        u16::from_be_bytes([bytes[attributes_start + 2], bytes[attributes_start + 3]]),
+        first
+    );
+}
+
+/// Only implements `Write`, like a network stream or a compressor.
+struct Stream(Vec<u8>);
+
+impl std::io::Write for Stream {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        // a few bytes at a time, so every write must be completed
+        let written = buf.len().min(3);
+        self.0.extend(&buf[..written]);
+        Ok(written)
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        Ok(())
+    }
+}
+
+#[test]
+fn stores_into_a_stream() {
+    for name in ["TryFinally", "WideLocals", "module-info"] {
+        let bytes = common::fixture(name);
+        let jvm = JVMClass::from_bytes(&bytes).unwrap();
+
+        let mut stream = Stream(vec![]);
+        jvm.store(&mut stream).unwrap();
+        assert!(stream.0 == bytes, "{name}");
+    }
+}
+
+#[test]
+fn counts_too_large_for_the_format_are_errors() {
+    let mut jvm = ClassBuilder::new("Big", "java/lang/Object").build();
+    let constants = jvm.constants.len();
+    for i in 0..70000 {
+        jvm.push_constant(Constant::Utf8(format!("constant {i}")));
+    }
+    let error = jvm.to_bytes().unwrap_err();
+    assert!(matches!(
+        error.downcast_ref::<JavaError>(),
+        Some(JavaError::TooManyEntries {
+            section: "the constant pool",
+            count,
+        }) if *count == constants + 70000
+    ));
+
+    let mut jvm = ClassBuilder::new("Big", "java/lang/Object").build();
+    jvm.constant_pool()
+        .intern_utf8(attribute_names::METHOD_PARAMETERS);
+    let parameter = MethodParameter {
+        name_index: 0,
+        access_flags: vec![],
+    };
+    let descriptor = format!("({})V", "I".repeat(256));
+    let many = jvm.add_method(
+        "many",
+        &descriptor,
+        vec![AccessFlag::Public, AccessFlag::Abstract],
+        None,
+    );
+    many.0
+        .attributes
+        .push(Attribute::MethodParameters(vec![parameter; 256]));
+    let error = jvm.to_bytes().unwrap_err();
+    assert!(matches!(
+        error.downcast_ref::<JavaError>(),
+        Some(JavaError::TooManyEntries {
+            section: "MethodParameters",
+            count: 256,
+        })
+    ));
+}
+
+#[test]
+fn sets_the_source_file() {
+    let mut jvm = JVMClass::template("Sourced");
+    assert!(jvm.source_file().is_none());
+
+    jvm.set_source_file("Sourced.java");
+    let mut jvm = JVMClass::from_bytes(&jvm.to_bytes().unwrap()).unwrap();
+    assert_eq!(jvm.source_file().unwrap().unwrap(), "Sourced.java");
+
+    jvm.set_source_file("Renamed.java");
+    let jvm = JVMClass::from_bytes(&jvm.to_bytes().unwrap()).unwrap();
+    assert_eq!(jvm.source_file().unwrap().unwrap(), "Renamed.java");
+    assert_eq!(jvm.attributes.len(), 1);
+
+    let hello = common::load("Hello");
+    assert_eq!(hello.source_file().unwrap().unwrap(), "Hello.java");
+}
+
+#[test]
+fn reads_and_sets_nestmates() {
+    let mut outer = common::load("Outer");
+    let mut inner = common::load("Outer$Inner");
+    assert!(outer.nest_host().is_none());
+    assert_eq!(
+        outer.nest_members().unwrap().unwrap(),
+        ["fixtures/Outer$Inner", "fixtures/Outer$1"]
+    );
+    assert!(inner.nest_members().is_none());
+    assert_eq!(
+        inner.nest_host().unwrap().unwrap(),
+        outer.this_class_name().unwrap()
+    );
+
+    // a class is either the host of its nest or a member
+    assert!(matches!(
+        outer.set_nest_host("fixtures/Other"),
+        Err(JavaError::InconsistentNest)
+    ));
+    assert!(matches!(
+        inner.set_nest_members(&["fixtures/Other"]),
+        Err(JavaError::InconsistentNest)
+    ));
+
+    outer
+        .set_nest_members(&["fixtures/Outer$Inner", "fixtures/Outer$Other"])
+        .unwrap();
+    let outer = JVMClass::from_bytes(&outer.to_bytes().unwrap()).unwrap();
+    assert_eq!(
+        outer.nest_members().unwrap().unwrap(),
+        ["fixtures/Outer$Inner", "fixtures/Outer$Other"]
+    );
+
+    let mut other = JVMClass::template("fixtures/Outer$Other");
+    other.set_nest_host("fixtures/Outer").unwrap();
+    let other = JVMClass::from_bytes(&other.to_bytes().unwrap()).unwrap();
+    assert_eq!(other.nest_host().unwrap().unwrap(), "fixtures/Outer");
+}
+
+#[test]
+fn adds_a_getter_and_removes_members() {
+    let mut jvm = ClassBuilder::new("Counter", "java/lang/Object")
+        .add_default_constructor()
+        .build();
+    jvm.add_field("value", "I", vec![AccessFlag::Private]);
+    let value = jvm.constant_pool().intern_fieldref("Counter", "value", "I");
+    jvm.add_method(
+        "getValue",
+        "()I",
+        vec![AccessFlag::Public],
+        Some(Attribute::Code {
+            max_stack: 1,
+            max_locals: 1,
+            code: vec![
+                Instruction::ALoad(0),
+                Instruction::GetField(value),
+                Instruction::IReturn,
+            ],
+            exception_table: vec![],
+            attributes: vec![Attribute::LineNumberTable(vec![LineNumber {
+                start_pc: 0,
+                line_number: 3,
+            }])],
+        }),
+    );
+    assert!(jvm.get_string_index("LineNumberTable").is_ok());
+
+    let mut jvm = JVMClass::from_bytes(&jvm.to_bytes().unwrap()).unwrap();
+    assert!(jvm.verify(VerifyOptions { stack_maps: true }).is_ok());
+    let getter = jvm.find_method("getValue", "()I").unwrap();
+    assert_eq!(getter.line_for_pc(1), Some(3));
+
+    let removed = jvm.remove_method("getValue", "()I").unwrap();
+    assert_eq!(jvm.get_string(removed.0.name).unwrap(), "getValue");
+    assert!(jvm.remove_method("getValue", "()I").is_none());
+    assert!(jvm.remove_field("value", "I").is_some());
+    assert!(jvm.fields.is_empty());
+    assert_eq!(jvm.methods.len(), 1);
+    assert!(jvm.validate().is_ok());
+}
+
+#[test]
+fn counts_instructions_by_mnemonic() {
+    let loops = common::load("Loops");
+    let max = loops.find_method("max", "(II)I").unwrap().code().unwrap();
+
+    let mut jvm = JVMClass::template("Counted");
+    jvm.access_flags.push(AccessFlag::Abstract);
+    jvm.add_method(
+        "max",
+        "(II)I",
+        vec![AccessFlag::Public, AccessFlag::Static],
+        Some(max.clone()),
+    );
+    jvm.add_method(
+        "run",
+        "()V",
+        vec![AccessFlag::Public, AccessFlag::Abstract],
+        None,
+    );
+    jvm.add_method(
+        "wide",
+        "()V",
+        vec![AccessFlag::Public, AccessFlag::Static],
+        Some(Attribute::Code {
+            max_stack: 1,
+            max_locals: 301,
+            code: vec![
+                Instruction::ILoadW(300),
+                Instruction::ILoad(4),
+                Instruction::IStore(0),
+                Instruction::Return,
+            ],
+            exception_table: vec![],
+            attributes: vec![],
+        }),
+    );
+
+    // int max = a; if (b > a) max = b; return max;
+    let histogram = jvm.opcode_histogram();
+    for (mnemonic, count) in [
+        ("iload_0", 2),
+        ("iload_1", 2),
+        ("istore_2", 2),
+        ("iload_2", 1),
+        ("if_icmple", 1),
+        ("ireturn", 1),
+        ("iload", 2),
+        ("istore_0", 1),
+        ("return", 1),
+    ] {
+        assert_eq!(histogram.get(mnemonic), Some(&count), "{mnemonic}");
+    }
+    assert_eq!(histogram.values().sum::<u64>(), 13);
+    assert_eq!(jvm.instruction_count(), 13);
+
+    assert_eq!(JVMClass::template("Empty").instruction_count(), 0);
+    assert!(JVMClass::template("Empty").opcode_histogram().is_empty());
+}
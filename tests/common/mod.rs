@@ -0,0 +1,28 @@
+#![allow(dead_code)]
+
+use std::path::{Path, PathBuf};
+
+use class_rs::{Instruction, JVMClass};
+
+/// The directory of the class files compiled from `tests/fixtures/src` by `build.sh`.
+pub fn fixtures_dir() -> PathBuf {
+    Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures")
+}
+
+/// The bytes of the fixture `name`, e.g. `Hello` for `tests/fixtures/Hello.class`.
+pub fn fixture(name: &str) -> Vec<u8> {
+    let path = fixtures_dir().join(format!("{name}.class"));
+    std::fs::read(&path).unwrap_or_else(|e| panic!("{}: {e}", path.display()))
+}
+
+pub fn load(name: &str) -> JVMClass {
+    JVMClass::from_bytes(&fixture(name)).unwrap_or_else(|e| panic!("{name}: {e}"))
+}
+
+/// The instructions of the method `name` with the descriptor `descriptor`.
+pub fn code<'a>(jvm: &'a JVMClass, name: &str, descriptor: &str) -> &'a [Instruction] {
+    jvm.find_method(name, descriptor)
+        .and_then(|method| method.code())
+        .unwrap_or_else(|| panic!("no code for {name}{descriptor}"))
+        .instructions()
+}
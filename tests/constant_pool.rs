@@ -0,0 +1,618 @@
+mod common;
+
+use class_rs::{
+    decode_modified_utf8, encode_modified_utf8, AccessFlag, Attribute, Constant, Instruction,
+    JVMClass, JavaError, MemberData, Method,
+};
+
+#[test]
+fn resolves_string_concat_invokedynamic() {
+    let jvm = common::load("Hello");
+    let id = common::code(&jvm, "run", "()V")
+        .iter()
+        .find_map(|inst| match inst {
+            Instruction::InvokeDynamic(id) => Some(*id),
+            _ => None,
+        })
+        .unwrap();
+
+    let info = jvm.dynamic_info(id).unwrap();
+    assert_eq!(info.name, "makeConcatWithConstants");
+    assert_eq!(info.descriptor, "(Ljava/lang/String;)Ljava/lang/String;");
+
+    let Constant::MethodHandle {
+        reference_index, ..
+    } = jvm
+        .get_constant(info.bootstrap.bootstrap_method_ref)
+        .unwrap()
+    else {
+        panic!("the bootstrap method isn't a method handle");
+    };
+    let (owner, name, _) = jvm.resolve_ref(*reference_index).unwrap();
+    assert_eq!(owner, "java/lang/invoke/StringConcatFactory");
+    assert_eq!(name, "makeConcatWithConstants");
+
+    let recipe = info.bootstrap.bootstrap_arguments[0];
+    let Constant::String { string_index } = jvm.get_constant(recipe).unwrap() else {
+        panic!("the recipe isn't a string");
+    };
+    assert_eq!(jvm.get_string(*string_index).unwrap(), "Hello, \u{1}!");
+}
+
+#[test]
+fn dynamic_info_rejects_other_constants() {
+    let jvm = common::load("Hello");
+    assert!(jvm.dynamic_info(jvm.this_class).is_err());
+    assert!(jvm.dynamic_info(u16::MAX).is_err());
+}
+
+#[test]
+fn javac_nul_and_supplementary_characters_round_trip() {
+    let bytes = common::fixture("Strings");
+    let jvm = JVMClass::from_bytes(&bytes).unwrap();
+
+    let expected = "a\u{0}b\u{1F600}";
+    assert!(jvm.get_string_index(expected).is_ok());
+
+    let encoded = encode_modified_utf8(expected);
+    assert_eq!(
+        encoded,
+        [b'a', 0xC0, 0x80, b'b', 0xED, 0xA0, 0xBD, 0xED, 0xB8, 0x80]
+    );
+    assert!(bytes.windows(encoded.len()).any(|window| window == encoded));
+    assert_eq!(decode_modified_utf8(&encoded).unwrap(), expected);
+
+    assert!(jvm.to_bytes().unwrap() == bytes);
+}
+
+#[test]
+fn overlong_forms_other_than_nul_are_kept_as_bytes() {
+    // `A` on two bytes, then `A` on three bytes.
+    for overlong in [vec![0xC1, 0x81], vec![0xE0, 0x81, 0x81]] {
+        assert!(decode_modified_utf8(&overlong).is_err());
+
+        let mut jvm = JVMClass::template("Overlong");
+        let id = jvm.push_constant(Constant::Utf8Bytes(overlong.clone()));
+
+        let reloaded = JVMClass::from_bytes(&jvm.to_bytes().unwrap()).unwrap();
+        assert_eq!(
+            reloaded.get_constant(id).unwrap(),
+            &Constant::Utf8Bytes(overlong)
+        );
+    }
+
+    assert_eq!(decode_modified_utf8(&[0xC0, 0x80]).unwrap(), "\u{0}");
+}
+
+#[test]
+fn float_bit_patterns_round_trip_exactly() {
+    let signaling_nan = f32::from_bits(0x7F80_0001);
+    let quiet_nan = f32::from_bits(0x7FC0_0000);
+    let signaling_nan_double = f64::from_bits(0x7FF0_0000_0000_0001);
+
+    let mut jvm = JVMClass::template("Floats");
+    let mut pool = jvm.constant_pool();
+    let signaling = pool.intern_float(signaling_nan);
+    let quiet = pool.intern_float(quiet_nan);
+    let negative_zero = pool.intern_float(-0.0);
+    let zero = pool.intern_float(0.0);
+    let signaling_double = pool.intern_double(signaling_nan_double);
+    let negative_zero_double = pool.intern_double(-0.0);
+
+    assert_ne!(signaling, quiet);
+    assert_ne!(negative_zero, zero);
+    assert_eq!(pool.intern_float(signaling_nan), signaling);
+
+    let bytes = jvm.to_bytes().unwrap();
+    let reloaded = JVMClass::from_bytes(&bytes).unwrap();
+    assert_eq!(
+        reloaded.get_float(signaling).unwrap().to_bits(),
+        0x7F80_0001
+    );
+    assert_eq!(reloaded.get_float(quiet).unwrap().to_bits(), 0x7FC0_0000);
+    assert_eq!(
+        reloaded.get_float(negative_zero).unwrap().to_bits(),
+        (-0.0f32).to_bits()
+    );
+    assert_eq!(
+        reloaded.get_double(signaling_double).unwrap().to_bits(),
+        0x7FF0_0000_0000_0001
+    );
+    assert_eq!(
+        reloaded.get_double(negative_zero_double).unwrap().to_bits(),
+        (-0.0f64).to_bits()
+    );
+    assert!(reloaded.to_bytes().unwrap() == bytes);
+}
+
+#[test]
+fn class_names_are_not_rewritten() {
+    let mut jvm = JVMClass::template("Object");
+    let object = jvm.this_class;
+    let java_object = jvm.super_class;
+
+    assert_eq!(jvm.get_class_name(object).unwrap(), "Object");
+    assert_eq!(jvm.get_class_name(java_object).unwrap(), "java/lang/Object");
+    assert_eq!(jvm.get_string(java_object).unwrap(), "java/lang/Object");
+    assert_eq!(jvm.get_display_name(java_object).unwrap(), "Object");
+
+    let utf8 = jvm.get_or_add_utf8_index("java/lang/String");
+    assert_eq!(jvm.get_display_name(utf8).unwrap(), "java/lang/String");
+    assert!(jvm.get_class_name(utf8).is_err());
+}
+
+#[test]
+fn interning_reuses_existing_constants() {
+    let mut jvm = JVMClass::template("Interned");
+    let mut pool = jvm.constant_pool();
+    let first = pool.intern_string("hello");
+    let second = pool.intern_string("hello");
+    assert_eq!(first, second);
+
+    let bytes = jvm.to_bytes().unwrap();
+    let reloaded = JVMClass::from_bytes(&bytes).unwrap();
+    let hellos = reloaded
+        .constants
+        .iter()
+        .filter(|constant| **constant == Constant::Utf8("hello".into()))
+        .count();
+    assert_eq!(hellos, 1);
+}
+
+#[test]
+fn longs_and_doubles_take_two_slots() {
+    let mut jvm = JVMClass::template("Wide");
+    let mut pool = jvm.constant_pool();
+    let long = pool.intern_long(1 << 40);
+    let double = pool.intern_double(0.5);
+    let methodref = pool.intern_methodref("java/lang/String", "length", "()I");
+
+    assert_eq!(double, long + 2);
+    assert_eq!(jvm.constants[long as usize + 1], Constant::Invalid);
+    assert_eq!(jvm.constants[double as usize + 1], Constant::Invalid);
+    assert_eq!(pool_ref(&jvm, methodref), "java/lang/String.length()I");
+
+    let reloaded = JVMClass::from_bytes(&jvm.to_bytes().unwrap()).unwrap();
+    assert_eq!(reloaded.get_long(long).unwrap(), 1 << 40);
+    assert_eq!(reloaded.get_double(double).unwrap(), 0.5);
+    assert_eq!(pool_ref(&reloaded, methodref), "java/lang/String.length()I");
+}
+
+fn pool_ref(jvm: &JVMClass, id: u16) -> String {
+    let (owner, name, descriptor) = jvm.resolve_ref(id).unwrap();
+    format!("{owner}.{name}{descriptor}")
+}
+
+#[test]
+fn map_utf8_relocates_a_package() {
+    let mut jvm = common::load("Outer$Inner");
+    let relocate = |s: &str| {
+        s.contains("fixtures/")
+            .then(|| s.replace("fixtures/", "relocated/"))
+    };
+
+    let changed = jvm.map_utf8(relocate);
+    assert!(changed >= 2, "{changed}");
+    assert_eq!(jvm.this_class_name().unwrap(), "relocated/Outer$Inner");
+
+    let outer = jvm.field_by_name("this$0").unwrap();
+    assert_eq!(
+        jvm.get_string(outer.0.descriptor).unwrap(),
+        "Lrelocated/Outer;"
+    );
+    assert!(jvm
+        .constants
+        .iter()
+        .all(|constant| !matches!(constant, Constant::Utf8(s) if s.contains("fixtures/"))));
+
+    assert_eq!(jvm.map_utf8(relocate), 0);
+}
+
+#[test]
+fn interleaved_longs_and_utf8s_land_at_their_index() {
+    let mut bytes = vec![0xCA, 0xFE, 0xBA, 0xBE, 0, 0, 0, 52];
+    // 1 Long, 3 Utf8, 4 Long, 6 Long, 8 Class, 9 Utf8, 10 Utf8, 11 Double, 13 Utf8
+    bytes.extend([0, 14]);
+    let long = |bytes: &mut Vec<u8>, value: i64| {
+        bytes.push(5);
+        bytes.extend(value.to_be_bytes());
+    };
+    let utf8 = |bytes: &mut Vec<u8>, value: &str| {
+        bytes.push(1);
+        bytes.extend((value.len() as u16).to_be_bytes());
+        bytes.extend(value.as_bytes());
+    };
+    long(&mut bytes, 1);
+    utf8(&mut bytes, "Longs");
+    long(&mut bytes, -2);
+    long(&mut bytes, i64::MAX);
+    bytes.extend([7, 0, 3]);
+    utf8(&mut bytes, "after");
+    utf8(&mut bytes, "longs");
+    bytes.push(6);
+    bytes.extend(0.25f64.to_be_bytes());
+    utf8(&mut bytes, "last");
+    // public class Longs, without super class, interfaces, members nor attributes
+    bytes.extend([0, 1, 0, 8, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0]);
+
+    let jvm = JVMClass::from_bytes(&bytes).unwrap();
+    assert_eq!(jvm.constants.len(), 14);
+    assert_eq!(jvm.get_long(1).unwrap(), 1);
+    assert_eq!(jvm.constants[2], Constant::Invalid);
+    assert_eq!(jvm.get_string(3).unwrap(), "Longs");
+    assert_eq!(jvm.get_long(4).unwrap(), -2);
+    assert_eq!(jvm.get_long(6).unwrap(), i64::MAX);
+    assert_eq!(jvm.constants[7], Constant::Invalid);
+    assert_eq!(jvm.this_class_name().unwrap(), "Longs");
+    assert_eq!(jvm.get_string(9).unwrap(), "after");
+    assert_eq!(jvm.get_string(10).unwrap(), "longs");
+    assert_eq!(jvm.get_double(11).unwrap(), 0.25);
+    assert_eq!(jvm.get_string(13).unwrap(), "last");
+
+    assert!(jvm.to_bytes().unwrap() == bytes);
+}
+
+#[test]
+fn hand_built_pools_get_their_phantom_slots() {
+    let mut jvm = JVMClass::new();
+    jvm.set_version(class_rs::ClassVersion::Java8);
+    // numbered from 1 as in a class file, without the entry 0 nor the slot after the long
+    jvm.constants = vec![
+        Constant::Utf8("Hand".into()),
+        Constant::Class { name_index: 1 },
+        Constant::Utf8("java/lang/Object".into()),
+        Constant::Class { name_index: 3 },
+        Constant::Long(7),
+        Constant::Utf8("after".into()),
+    ];
+    jvm.this_class = 2;
+    jvm.super_class = 4;
+
+    let bytes = jvm.to_bytes().unwrap();
+    assert_eq!(u16::from_be_bytes([bytes[8], bytes[9]]), 8);
+
+    let reloaded = JVMClass::from_bytes(&bytes).unwrap();
+    assert_eq!(reloaded.this_class_name().unwrap(), "Hand");
+    assert_eq!(reloaded.get_long(5).unwrap(), 7);
+    assert_eq!(reloaded.constants[6], Constant::Invalid);
+    assert_eq!(reloaded.get_string(7).unwrap(), "after");
+    assert!(reloaded.to_bytes().unwrap() == bytes);
+}
+
+#[test]
+fn resolves_member_references() {
+    let jvm = common::load("Hello");
+    let code = common::code(&jvm, "add", "(Ljava/lang/String;)V");
+    let references: Vec<_> = code
+        .iter()
+        .filter_map(|inst| match inst {
+            Instruction::GetField(id) | Instruction::InvokeInterface { index: id, .. } => {
+                Some(jvm.resolve_ref(*id).unwrap())
+            }
+            _ => None,
+        })
+        .collect();
+    assert_eq!(
+        references[0],
+        (
+            "fixtures/Hello".to_string(),
+            "names".to_string(),
+            "Ljava/util/List;".to_string()
+        )
+    );
+    assert_eq!(
+        references[1],
+        (
+            "java/util/List".to_string(),
+            "add".to_string(),
+            "(Ljava/lang/Object;)Z".to_string()
+        )
+    );
+
+    let Some(Instruction::InvokeInterface { index: id, .. }) = code
+        .iter()
+        .find(|inst| matches!(inst, Instruction::InvokeInterface { .. }))
+    else {
+        unreachable!();
+    };
+    let Constant::InterfaceMethodref {
+        name_and_type_index,
+        ..
+    } = jvm.get_constant(*id).unwrap()
+    else {
+        panic!("not an interface method");
+    };
+    assert_eq!(
+        jvm.resolve_name_and_type(*name_and_type_index).unwrap(),
+        ("add".to_string(), "(Ljava/lang/Object;)Z".to_string())
+    );
+    assert!(jvm.resolve_name_and_type(*id).is_err());
+}
+
+#[test]
+fn dangling_name_and_types_are_errors() {
+    let mut jvm = JVMClass::template("Dangling");
+    let class_index = jvm.this_class;
+    let dangling = jvm.constants.len() as u16 + 10;
+    let field = jvm.push_constant(Constant::Fieldref {
+        class_index,
+        name_and_type_index: dangling,
+    });
+
+    assert!(matches!(
+        jvm.resolve_ref(field),
+        Err(JavaError::InvalidConstantId(id)) if id == dangling
+    ));
+    assert!(matches!(
+        jvm.resolve_ref(dangling),
+        Err(JavaError::InvalidConstantId(_))
+    ));
+    assert!(matches!(
+        jvm.resolve_ref(class_index),
+        Err(JavaError::ConstantTypeError(_))
+    ));
+}
+
+#[test]
+fn typed_accessors_check_the_constant() {
+    let mut jvm = JVMClass::template("Typed");
+    let mut pool = jvm.constant_pool();
+    let int = pool.intern_integer(42);
+    let long = pool.intern_long(-1);
+    let float = pool.intern_float(1.5);
+    let double = pool.intern_double(0.25);
+    let utf8 = pool.intern_utf8("text");
+
+    assert_eq!(jvm.get_integer(int).unwrap(), 42);
+    assert_eq!(jvm.get_long(long).unwrap(), -1);
+    assert_eq!(jvm.get_float(float).unwrap(), 1.5);
+    assert_eq!(jvm.get_double(double).unwrap(), 0.25);
+
+    assert!(matches!(
+        jvm.get_integer(utf8),
+        Err(JavaError::ConstantTypeError(_))
+    ));
+    assert!(matches!(
+        jvm.get_long(int),
+        Err(JavaError::ConstantTypeError(_))
+    ));
+    // the entry 0, the slot after a long and past the end of the pool
+    for id in [0, long + 1, jvm.constants.len() as u16] {
+        assert!(matches!(
+            jvm.get_constant(id),
+            Err(JavaError::InvalidConstantId(invalid)) if invalid == id
+        ));
+        assert!(jvm.get_constant_mut(id).is_err());
+    }
+
+    *jvm.get_constant_mut(int).unwrap() = Constant::Integer(7);
+    assert_eq!(jvm.get_integer(int).unwrap(), 7);
+}
+
+#[test]
+fn prunes_orphan_constants() {
+    let bytes = common::fixture("Hello");
+    let mut jvm = JVMClass::from_bytes(&bytes).unwrap();
+    assert_eq!(jvm.clone().prune_constants(), 0);
+
+    jvm.insert_constant(1, Constant::Utf8("orphan".into()))
+        .unwrap();
+    jvm.insert_constant(5, Constant::Long(1 << 40)).unwrap();
+    let orphan = jvm.push_constant(Constant::Utf8("another orphan".into()));
+    assert!(jvm.validate().is_ok());
+
+    assert_eq!(jvm.prune_constants(), 4);
+    assert!(jvm.get_string_index("orphan").is_err());
+    assert!(jvm.get_constant(orphan).is_err());
+    // every index went back to its place
+    assert!(jvm.to_bytes().unwrap() == bytes);
+}
+
+/// The constant an instruction refers to, resolved.
+fn operand(jvm: &JVMClass, inst: &Instruction) -> Option<String> {
+    Some(match inst {
+        Instruction::GetField(id)
+        | Instruction::InvokeVirtual(id)
+        | Instruction::InvokeSpecial(id)
+        | Instruction::InvokeStatic(id)
+        | Instruction::InvokeInterface { index: id, .. } => pool_ref(jvm, *id),
+        Instruction::New(id) => jvm.get_class_name(*id).unwrap().into(),
+        Instruction::Ldc(id) => jvm.get_string(*id as u16).unwrap().into(),
+        Instruction::LdcW(id) => jvm.get_string(*id).unwrap().into(),
+        Instruction::InvokeDynamic(id) => {
+            let info = jvm.dynamic_info(*id).unwrap();
+            let Constant::MethodHandle {
+                reference_index, ..
+            } = jvm
+                .get_constant(info.bootstrap.bootstrap_method_ref)
+                .unwrap()
+            else {
+                unreachable!();
+            };
+            let recipe = jvm
+                .get_string(info.bootstrap.bootstrap_arguments[0])
+                .unwrap();
+            format!(
+                "{}{} by {} with {recipe:?}",
+                info.name,
+                info.descriptor,
+                pool_ref(jvm, *reference_index)
+            )
+        }
+        _ => return None,
+    })
+}
+
+#[test]
+fn moves_methods_between_classes() {
+    let hello = common::load("Hello");
+    let mut target = JVMClass::template("Target");
+
+    for (name, descriptor) in [
+        ("main", "([Ljava/lang/String;)V"),
+        ("get", "()Ljava/lang/String;"),
+    ] {
+        let method = hello.find_method(name, descriptor).unwrap();
+        let moved = target.remap_method(&hello, method).unwrap();
+        target.methods.push(moved);
+
+        let resolve = |jvm: &JVMClass| -> Vec<_> {
+            common::code(jvm, name, descriptor)
+                .iter()
+                .filter_map(|inst| operand(jvm, inst))
+                .collect()
+        };
+        assert!(!resolve(&hello).is_empty());
+        assert_eq!(resolve(&target), resolve(&hello));
+    }
+    assert!(target.validate().is_ok());
+
+    let reloaded = JVMClass::from_bytes(&target.to_bytes().unwrap()).unwrap();
+    assert_eq!(reloaded.constants, target.constants);
+    assert_eq!(reloaded.attributes, target.attributes);
+    assert_eq!(
+        operand(
+            &reloaded,
+            &common::code(&reloaded, "get", "()Ljava/lang/String;")[3]
+        )
+        .unwrap(),
+        "makeConcatWithConstants(I)Ljava/lang/String; by \
+         java/lang/invoke/StringConcatFactory.makeConcatWithConstants\
+         (Ljava/lang/invoke/MethodHandles$Lookup;Ljava/lang/String;Ljava/lang/invoke/MethodType;\
+         Ljava/lang/String;[Ljava/lang/Object;)Ljava/lang/invoke/CallSite; with \"\\u{1} names\""
+    );
+}
+
+#[test]
+fn resolves_the_bootstrap_method_of_a_lambda() {
+    let mut jvm = common::load("Hello");
+    // the string concatenations of `run` and `get`, and the lambda
+    assert_eq!(jvm.bootstrap_methods().unwrap().len(), 3);
+    assert_eq!(jvm.get_bootstrap_methods().len(), 3);
+
+    let lambda = common::code(&jvm, "run", "()V")
+        .iter()
+        .rev()
+        .find_map(|inst| match inst {
+            Instruction::InvokeDynamic(id) => Some(*id),
+            _ => None,
+        })
+        .unwrap();
+    let (bootstrap, name, descriptor) = jvm.resolve_indy(lambda).unwrap();
+    assert_eq!(name, "run");
+    assert_eq!(descriptor, "(Lfixtures/Hello;)Ljava/lang/Runnable;");
+    let Constant::InvokeDynamic {
+        bootstrap_method_attr_index,
+        ..
+    } = jvm.get_constant(lambda).unwrap()
+    else {
+        unreachable!();
+    };
+    assert_eq!(
+        &bootstrap,
+        jvm.get_bootstrap_method(*bootstrap_method_attr_index)
+    );
+    let Constant::MethodHandle {
+        reference_index, ..
+    } = jvm.get_constant(bootstrap.bootstrap_method_ref).unwrap()
+    else {
+        panic!("the bootstrap method isn't a method handle");
+    };
+    assert!(pool_ref(&jvm, *reference_index)
+        .starts_with("java/lang/invoke/LambdaMetafactory.metafactory("));
+    // the interface method type, the implementation and the instantiated method type
+    assert_eq!(bootstrap.bootstrap_arguments.len(), 3);
+
+    assert!(matches!(
+        jvm.resolve_indy(jvm.this_class),
+        Err(JavaError::ConstantTypeError(_))
+    ));
+
+    jvm.attributes
+        .retain(|attr| !matches!(attr, Attribute::BootstrapMethods(_)));
+    assert!(jvm.bootstrap_methods().is_none());
+    assert!(matches!(
+        jvm.resolve_indy(lambda),
+        Err(JavaError::MissingBootstrapMethods)
+    ));
+}
+
+#[test]
+fn pushed_and_inserted_longs_take_two_slots() {
+    let mut jvm = JVMClass::template("Pushed");
+    let long = jvm.push_constant(Constant::Long(5));
+    let utf8 = jvm.push_constant(Constant::Utf8("after".into()));
+    assert_eq!(utf8, long + 2);
+    assert_eq!(jvm.constants[long as usize + 1], Constant::Invalid);
+
+    // before the name of the class, so every index after it moves by two
+    let name = jvm.get_string_index("Pushed").unwrap();
+    jvm.insert_constant(name, Constant::Double(0.5)).unwrap();
+    assert_eq!(jvm.get_double(name).unwrap(), 0.5);
+    assert_eq!(jvm.constants[name as usize + 1], Constant::Invalid);
+    assert_eq!(jvm.this_class_name().unwrap(), "Pushed");
+    assert_eq!(jvm.get_long(long + 2).unwrap(), 5);
+    assert_eq!(jvm.get_string(utf8 + 2).unwrap(), "after");
+
+    assert!(matches!(
+        jvm.insert_constant(0, Constant::Integer(1)),
+        Err(JavaError::InvalidConstantId(0))
+    ));
+    let before = jvm.clone();
+    assert!(jvm.insert_constant(long + 3, Constant::Integer(1)).is_err());
+    assert_eq!(jvm, before);
+
+    let reloaded = JVMClass::from_bytes(&jvm.to_bytes().unwrap()).unwrap();
+    assert_eq!(reloaded, jvm);
+}
+
+#[test]
+fn normalizing_renumbers_the_constants_after_a_double() {
+    let mut jvm = JVMClass::new();
+    jvm.set_version(class_rs::ClassVersion::Java8);
+    jvm.constants = vec![
+        Constant::Utf8("Raw".into()),
+        Constant::Class { name_index: 1 },
+        Constant::Utf8("java/lang/Object".into()),
+        Constant::Class { name_index: 3 },
+        Constant::Double(2.5),
+        Constant::Utf8("text".into()),
+        Constant::String { string_index: 6 },
+        Constant::Utf8("get".into()),
+        Constant::Utf8("()Ljava/lang/String;".into()),
+        Constant::Utf8("Code".into()),
+    ];
+    jvm.this_class = 2;
+    jvm.super_class = 4;
+    jvm.methods.push(Method(MemberData {
+        access_flags: vec![AccessFlag::Public, AccessFlag::Static],
+        name: 8,
+        descriptor: 9,
+        attributes: vec![Attribute::Code {
+            max_stack: 1,
+            max_locals: 0,
+            code: vec![Instruction::Ldc(7), Instruction::AReturn],
+            exception_table: vec![],
+            attributes: vec![],
+        }],
+    }));
+
+    jvm.normalize_pool().unwrap();
+    assert_eq!(jvm.constants.len(), 12);
+    assert_eq!(jvm.constants[0], Constant::Invalid);
+    assert_eq!(jvm.get_double(5).unwrap(), 2.5);
+    assert_eq!(jvm.constants[6], Constant::Invalid);
+    assert_eq!(jvm.this_class_name().unwrap(), "Raw");
+    assert_eq!(jvm.constants[8], Constant::String { string_index: 7 });
+    assert_eq!(
+        common::code(&jvm, "get", "()Ljava/lang/String;"),
+        [Instruction::Ldc(8), Instruction::AReturn]
+    );
+    assert!(jvm.validate().is_ok());
+
+    let normalized = jvm.clone();
+    jvm.normalize_pool().unwrap();
+    assert_eq!(jvm, normalized);
+    let reloaded = JVMClass::from_bytes(&jvm.to_bytes().unwrap()).unwrap();
+    assert_eq!(reloaded.constants, jvm.constants);
+}
@@ -0,0 +1,85 @@
+mod common;
+
+use class_rs::descriptor::{
+    parse_field_descriptor, parse_method_descriptor, FieldType, MethodDescriptor,
+};
+
+#[test]
+fn parses_nested_arrays_and_objects() {
+    assert_eq!(
+        parse_method_descriptor("(Ljava/lang/String;[IJ)V").unwrap(),
+        MethodDescriptor {
+            params: vec![
+                FieldType::Object("java/lang/String".into()),
+                FieldType::Array(Box::new(FieldType::Int)),
+                FieldType::Long,
+            ],
+            ret: None,
+        }
+    );
+
+    let matrix = parse_field_descriptor("[[[Ljava/util/List;").unwrap();
+    assert_eq!(
+        matrix,
+        FieldType::Array(Box::new(FieldType::Array(Box::new(FieldType::Array(
+            Box::new(FieldType::Object("java/util/List".into()))
+        )))))
+    );
+    assert_eq!(matrix.to_java_string(), "java.util.List[][][]");
+}
+
+#[test]
+fn rejects_malformed_descriptors() {
+    for descriptor in ["", "V", "[", "L;", "Ljava/lang/String", "II", "Q"] {
+        assert!(parse_field_descriptor(descriptor).is_err(), "{descriptor}");
+    }
+    for descriptor in ["", "()", "(I", "I)V", "(V)V", "()II", "(L;)V"] {
+        assert!(parse_method_descriptor(descriptor).is_err(), "{descriptor}");
+    }
+}
+
+#[test]
+fn parses_the_descriptor_of_a_method() {
+    let jvm = common::load("Hello");
+    let add = jvm.find_method("add", "(JJ)J").unwrap();
+
+    let descriptor = add.parsed_descriptor(&jvm).unwrap();
+    assert_eq!(descriptor.params, vec![FieldType::Long, FieldType::Long]);
+    assert_eq!(descriptor.ret, Some(FieldType::Long));
+    assert_eq!(
+        descriptor.to_java_string(Some("add")),
+        "long add(long, long)"
+    );
+}
+
+#[test]
+fn renders_descriptors_as_java_source() {
+    let main = parse_method_descriptor("(Ljava/lang/String;I)V").unwrap();
+    assert_eq!(main.to_java_string(None), "void (java.lang.String, int)");
+    assert_eq!(
+        main.to_java_string(Some("foo")),
+        "void foo(java.lang.String, int)"
+    );
+    assert_eq!(
+        parse_method_descriptor("()[[I")
+            .unwrap()
+            .to_java_string(Some("matrix")),
+        "int[][] matrix()"
+    );
+
+    for (descriptor, java) in [
+        ("Z", "boolean"),
+        ("B", "byte"),
+        ("C", "char"),
+        ("S", "short"),
+        ("F", "float"),
+        ("D", "double"),
+        ("[[I", "int[][]"),
+        ("[Ljava/util/Map$Entry;", "java.util.Map$Entry[]"),
+    ] {
+        assert_eq!(
+            parse_field_descriptor(descriptor).unwrap().to_java_string(),
+            java
+        );
+    }
+}
@@ -0,0 +1,21 @@
+mod common;
+
+#[test]
+fn disassembles_with_resolved_constants_and_absolute_targets() {
+    let text = common::load("Hello").disassemble().unwrap();
+
+    for expected in [
+        "public super class fixtures/Hello extends java/lang/Object implements java/lang/Runnable, java/util/function/Supplier",
+        "private count:I",
+        "public static main:([Ljava/lang/String;)V",
+        "ldc           #17                // String \"world\"",
+        "invokevirtual #19                // Method fixtures/Hello.add:(Ljava/lang/String;)V",
+        "// InterfaceMethod java/util/List.add:(Ljava/lang/Object;)Z",
+    ] {
+        assert!(text.contains(expected), "{expected} not in\n{text}");
+    }
+
+    let text = common::load("Loops").disassemble().unwrap();
+    assert!(text.contains("6: if_icmpge     19"), "{text}");
+    assert!(text.contains("16: goto          4"), "{text}");
+}
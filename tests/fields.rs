@@ -0,0 +1,64 @@
+use class_rs::{AccessFlag, Attribute, ConstValue, Constant, JVMClass};
+
+fn add_constant(jvm: &mut JVMClass, name: &str, descriptor: &str, value: ConstValue) {
+    let flags = vec![AccessFlag::Public, AccessFlag::Static, AccessFlag::Final];
+    jvm.add_field(name, descriptor, flags);
+
+    let mut field = jvm.fields.pop().unwrap();
+    field.set_constant_value(jvm, value);
+    jvm.fields.push(field);
+}
+
+fn constant_value<'a>(jvm: &'a JVMClass, name: &str) -> &'a Constant {
+    let field = jvm.field_by_name(name).unwrap();
+    let ids: Vec<_> = field
+        .0
+        .attributes
+        .iter()
+        .filter_map(|attr| match attr {
+            Attribute::ConstantValue {
+                constantvalue_index,
+            } => Some(*constantvalue_index),
+            _ => None,
+        })
+        .collect();
+
+    let [id] = ids.as_slice() else {
+        panic!("{name} has {} constant values", ids.len());
+    };
+    jvm.get_constant(*id).unwrap()
+}
+
+#[test]
+fn sets_int_string_and_double_constant_values() {
+    let mut jvm = JVMClass::template("Constants");
+    add_constant(&mut jvm, "X", "I", ConstValue::Int(5));
+    add_constant(
+        &mut jvm,
+        "NAME",
+        "Ljava/lang/String;",
+        ConstValue::String("hi".into()),
+    );
+    add_constant(&mut jvm, "RATIO", "D", ConstValue::Double(2.5));
+
+    let jvm = JVMClass::from_bytes(&jvm.to_bytes().unwrap()).unwrap();
+    assert_eq!(constant_value(&jvm, "X"), &Constant::Integer(5));
+    assert_eq!(constant_value(&jvm, "RATIO"), &Constant::Double(2.5));
+
+    let Constant::String { string_index } = constant_value(&jvm, "NAME") else {
+        panic!("NAME isn't a string constant");
+    };
+    assert_eq!(jvm.get_string(*string_index).unwrap(), "hi");
+}
+
+#[test]
+fn setting_a_constant_value_again_replaces_it() {
+    let mut jvm = JVMClass::template("Constants");
+    add_constant(&mut jvm, "X", "I", ConstValue::Int(5));
+
+    let mut field = jvm.fields.pop().unwrap();
+    field.set_constant_value(&mut jvm, ConstValue::Int(6));
+    jvm.fields.push(field);
+
+    assert_eq!(constant_value(&jvm, "X"), &Constant::Integer(6));
+}
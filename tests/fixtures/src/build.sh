@@ -0,0 +1,14 @@
+#!/bin/sh
+# Regenerates the class files of the fixtures, with javac 17.
+set -e
+cd "$(dirname "$0")"
+out=$(mktemp -d)
+
+javac --release 17 -g -d "$out/classes" fixtures/*.java
+javac --release 17 -d "$out/dep" modules/fixtures.dep/module-info.java modules/fixtures.dep/fixtures/dep/*.java
+javac --release 17 --module-version 1.0 -p "$out/dep" -d "$out/app" \
+    modules/fixtures.app/module-info.java $(find modules/fixtures.app/fixtures -name '*.java')
+
+cp "$out"/classes/fixtures/*.class ..
+cp "$out/app/module-info.class" ..
+rm -r "$out"
@@ -0,0 +1,9 @@
+package fixtures;
+
+import java.util.List;
+import java.util.Map;
+
+public class Generic<T extends Comparable<? super T>> {
+    public static <E extends Comparable<? super E>> void sort(List<? extends E> list, Map<String, ? super E> sink) {
+    }
+}
@@ -0,0 +1,44 @@
+package fixtures;
+
+import java.util.ArrayList;
+import java.util.List;
+import java.util.function.Supplier;
+
+public class Hello implements Runnable, Supplier<String> {
+    private final List<String> names = new ArrayList<>();
+    private int count;
+
+    public static void main(String[] args) {
+        Hello hello = new Hello();
+        hello.add("world");
+        hello.run();
+    }
+
+    public void add(String name) {
+        names.add(name);
+        count++;
+    }
+
+    public int add(int a, int b) {
+        return a + b;
+    }
+
+    public long add(long a, long b) {
+        return a + b;
+    }
+
+    @Override
+    public void run() {
+        for (String name : names) {
+            System.out.println("Hello, " + name + "!");
+        }
+
+        Runnable printer = () -> System.out.println(count);
+        printer.run();
+    }
+
+    @Override
+    public String get() {
+        return names.size() + " names";
+    }
+}
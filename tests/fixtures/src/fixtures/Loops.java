@@ -0,0 +1,23 @@
+package fixtures;
+
+public class Loops {
+    public static int sum(int n) {
+        int total = 0;
+        for (int i = 0; i < n; i++) {
+            total += i;
+        }
+        return total;
+    }
+
+    public static int max(int a, int b) {
+        int max = a;
+        if (b > a) {
+            max = b;
+        }
+        return max;
+    }
+
+    public static int nested(int a, int b, int c, int d) {
+        return (a + b) * (c - d) / (a * (b + c * d));
+    }
+}
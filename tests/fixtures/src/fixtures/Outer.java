@@ -0,0 +1,19 @@
+package fixtures;
+
+public class Outer {
+    private int secret;
+
+    public class Inner {
+        int peek() {
+            return secret;
+        }
+    }
+
+    public Runnable anonymous() {
+        return new Runnable() {
+            public void run() {
+                secret++;
+            }
+        };
+    }
+}
@@ -0,0 +1,4 @@
+package fixtures;
+
+public record Point(int x, int y) {
+}
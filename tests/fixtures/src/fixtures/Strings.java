@@ -0,0 +1,14 @@
+package fixtures;
+
+public class Strings {
+    public static final String NUL_AND_EMOJI = "a\u0000b\uD83D\uDE00";
+    public static final float NEGATIVE_ZERO_F = -0.0f;
+    public static final double NEGATIVE_ZERO = -0.0;
+    public static final double NAN = Double.NaN;
+    public static final long BIG = 1234567890123L;
+    public static final long MINUS_ONE = -1L;
+
+    public static String get() {
+        return NUL_AND_EMOJI;
+    }
+}
@@ -0,0 +1,34 @@
+package fixtures;
+
+public class Switches {
+    public static int sparse(int n) {
+        switch (n) {
+            case -5:
+                return 40;
+            case 1:
+                return 10;
+            case 100:
+                return 20;
+            case 10000:
+                return 30;
+            default:
+                return 0;
+        }
+    }
+
+    public static int dense(int n) {
+        n = n * 2;
+        switch (n) {
+            case 0:
+                return 1;
+            case 1:
+                return 2;
+            case 2:
+                return 3;
+            case 3:
+                return 5;
+            default:
+                return -1;
+        }
+    }
+}
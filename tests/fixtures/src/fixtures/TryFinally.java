@@ -0,0 +1,16 @@
+package fixtures;
+
+public class TryFinally {
+    private int state;
+
+    public int run(String s) {
+        try {
+            state = Integer.parseInt(s);
+        } catch (NumberFormatException e) {
+            state = -1;
+        } finally {
+            state++;
+        }
+        return state;
+    }
+}
@@ -0,0 +1,10 @@
+package fixtures;
+
+import java.io.IOException;
+
+public class TypeAnnotated {
+    public @Tagged("field") String name;
+
+    public <T extends @Tagged Comparable<T>> void foo(T t) throws @Tagged IOException {
+    }
+}
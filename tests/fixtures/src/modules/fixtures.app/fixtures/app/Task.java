@@ -0,0 +1,6 @@
+package fixtures.app;
+
+public class Task implements Runnable {
+    public void run() {
+    }
+}
@@ -0,0 +1,4 @@
+package fixtures.app.internal;
+
+public class Secret {
+}
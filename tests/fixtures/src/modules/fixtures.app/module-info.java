@@ -0,0 +1,10 @@
+module fixtures.app {
+    requires java.logging;
+    requires transitive fixtures.dep;
+
+    exports fixtures.app;
+    exports fixtures.app.internal to java.logging;
+
+    uses java.lang.Runnable;
+    provides java.lang.Runnable with fixtures.app.Task;
+}
@@ -0,0 +1,4 @@
+package fixtures.dep;
+
+public class Dep {
+}
@@ -0,0 +1,3 @@
+module fixtures.dep {
+    exports fixtures.dep;
+}
@@ -0,0 +1,448 @@
+mod common;
+
+use class_rs::analysis::uses_subroutines;
+use class_rs::{
+    AccessFlag, ArrayType, Attribute, ClassVersion, ClassView, Constant, ExceptionTableEntry,
+    Instruction, JVMClass, StackMapFrameType,
+};
+
+#[test]
+fn wide_instructions_round_trip() {
+    let bytes = common::fixture("WideLocals");
+    let jvm = JVMClass::from_bytes(&bytes).unwrap();
+    let code = common::code(&jvm, "many", "(Z)I");
+
+    assert!(code.contains(&Instruction::IStoreW(260)));
+    assert!(code.contains(&Instruction::ILoadW(260)));
+    assert!(code.contains(&Instruction::IIncW(260, 1000)));
+    assert!(code.contains(&Instruction::IIncW(259, -300)));
+
+    // `v258 -= 300`, slot 0 being the parameter: wide iinc 259 -300
+    let iinc = [0xC4, 0x84, 0x01, 0x03, 0xFE, 0xD4];
+    assert!(bytes.windows(iinc.len()).any(|window| window == iinc));
+
+    // So many locals need the frames with explicit offsets: 247 and 255.
+    let Some(Attribute::Code { attributes, .. }) = jvm.find_method("many", "(Z)I").unwrap().code()
+    else {
+        unreachable!();
+    };
+    let frames = attributes
+        .iter()
+        .find_map(|attr| match attr {
+            Attribute::StackMapTable(frames) => Some(frames),
+            _ => None,
+        })
+        .unwrap();
+    let has = |frame_type| frames.iter().any(|frame| frame.frame_type == frame_type);
+    assert!(has(StackMapFrameType::SameLocals1StackItemFrameExtended));
+    assert!(has(StackMapFrameType::FullFrame));
+
+    assert!(jvm.to_bytes().unwrap() == bytes);
+}
+
+#[test]
+fn resolves_branch_targets() {
+    let jvm = common::load("Loops");
+    let code = jvm.find_method("sum", "(I)I").unwrap().code().unwrap();
+    let instructions = code.instructions();
+    let offsets = code.instruction_offsets();
+
+    let (if_index, _) = instructions
+        .iter()
+        .enumerate()
+        .find(|(_, inst)| matches!(inst, Instruction::IfIcmpge(_)))
+        .unwrap();
+    let (goto_index, _) = instructions
+        .iter()
+        .enumerate()
+        .find(|(_, inst)| matches!(inst, Instruction::Goto(_)))
+        .unwrap();
+
+    // the loop exits after the goto, which jumps back to the condition
+    let exit = goto_index + 1;
+    let condition = if_index - 2;
+    assert_eq!(
+        instructions[if_index].branch_target(offsets[if_index]),
+        Some(offsets[exit])
+    );
+    assert_eq!(
+        instructions[goto_index].branch_target(offsets[goto_index]),
+        Some(offsets[condition])
+    );
+    assert_eq!(instructions[0].branch_target(0), None);
+
+    let targets = code.absolute_targets().unwrap();
+    assert_eq!(targets[if_index], vec![exit]);
+    assert_eq!(targets[goto_index], vec![condition]);
+    assert!(targets[0].is_empty());
+}
+
+#[test]
+fn instruction_offsets_match_the_code_array() {
+    for name in ["Switches", "WideLocals", "Loops", "TryFinally"] {
+        let bytes = common::fixture(name);
+        let jvm = JVMClass::from_bytes(&bytes).unwrap();
+        let view = ClassView::new(&bytes).unwrap();
+        for (method, method_view) in jvm.methods.iter().zip(view.methods()) {
+            let Some(code) = method.code() else {
+                continue;
+            };
+            let instructions = code.instructions();
+            let offsets = code.instruction_offsets();
+            let length = method_view.code_bytes().unwrap().len() as u32;
+
+            assert_eq!(offsets.len(), instructions.len());
+            assert_eq!(offsets.first(), Some(&0));
+            assert!(offsets.windows(2).all(|pair| pair[0] < pair[1]));
+            // the last instruction is a return or a throw, with no padding
+            let last = instructions.last().unwrap();
+            assert_eq!(offsets.last().unwrap() + last.size(), length, "{name}");
+
+            for (inst, pc) in instructions.iter().zip(&offsets) {
+                if let Some(target) = inst.branch_target(*pc) {
+                    assert!(offsets.contains(&target), "{name}: {inst:?} at {pc}");
+                }
+            }
+            assert!(code.absolute_targets().is_ok(), "{name}");
+        }
+    }
+}
+
+#[test]
+fn decodes_iinc_of_a_counting_loop() {
+    let bytes = common::fixture("Loops");
+    let jvm = JVMClass::from_bytes(&bytes).unwrap();
+    let code = common::code(&jvm, "sum", "(I)I");
+
+    // `i++`, `i` being in slot 2 after `n` and `total`
+    let iinc = code
+        .iter()
+        .position(|inst| *inst == Instruction::IInc(2, 1));
+    assert!(iinc.is_some());
+
+    // the instructions after it are still in sync
+    assert!(matches!(code[iinc.unwrap() + 1], Instruction::Goto(_)));
+    assert_eq!(code.last(), Some(&Instruction::IReturn));
+
+    assert!(jvm.to_bytes().unwrap() == bytes);
+}
+
+#[test]
+fn sparse_switches_keep_their_padding() {
+    let bytes = common::fixture("Switches");
+    let jvm = JVMClass::from_bytes(&bytes).unwrap();
+    let code = common::code(&jvm, "sparse", "(I)I");
+
+    // iload_0 then lookupswitch at pc 1: two bytes of padding to reach pc 4
+    assert_eq!(code[0], Instruction::ILoad(0));
+    let Instruction::LookupSwitch { padding, pairs, .. } = &code[1] else {
+        panic!("{:?}", code[1]);
+    };
+    assert_eq!(*padding, 2);
+    let keys: Vec<_> = pairs.iter().map(|pair| pair.value).collect();
+    assert_eq!(keys, vec![-5, 1, 100, 10000]);
+
+    let dense = common::code(&jvm, "dense", "(I)I");
+    let switch = dense
+        .iter()
+        .find(|inst| matches!(inst, Instruction::TableSwitch { .. }))
+        .unwrap();
+    // iload_0, iconst_2, imul, istore_0, iload_0: the tableswitch is at pc 5
+    assert!(matches!(
+        switch,
+        Instruction::TableSwitch { padding: 2, .. }
+    ));
+
+    assert!(jvm.to_bytes().unwrap() == bytes);
+}
+
+#[test]
+fn switches_can_jump_backwards() {
+    let mut jvm = JVMClass::template("Backwards");
+    let code = Attribute::Code {
+        max_stack: 1,
+        max_locals: 1,
+        code: vec![
+            Instruction::ILoad(0),
+            // at pc 1, padded to pc 4, 16 bytes of operands
+            Instruction::TableSwitch {
+                padding: 2,
+                minimum: 0,
+                maximum: 0,
+                jump_targets: vec![19],
+                default: -1,
+            },
+            Instruction::Return,
+        ],
+        exception_table: vec![],
+        attributes: vec![],
+    };
+    jvm.add_method(
+        "spin",
+        "(I)V",
+        vec![AccessFlag::Public, AccessFlag::Static],
+        Some(code),
+    );
+
+    let bytes = jvm.to_bytes().unwrap();
+    let default = [0xFF, 0xFF, 0xFF, 0xFF, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 19];
+    assert!(bytes.windows(default.len()).any(|window| window == default));
+
+    let jvm = JVMClass::from_bytes(&bytes).unwrap();
+    let code = jvm.find_method("spin", "(I)V").unwrap().code().unwrap();
+    assert!(matches!(
+        &code.instructions()[1],
+        Instruction::TableSwitch { default: -1, .. }
+    ));
+    assert_eq!(code.absolute_targets().unwrap()[1], vec![0, 2]);
+}
+
+#[test]
+fn opcodes_and_mnemonics_use_the_compact_forms() {
+    let cases = [
+        (Instruction::ILoad(0), 0x1A, "iload_0"),
+        (Instruction::ILoad(4), 0x15, "iload"),
+        (Instruction::ILoadW(300), 0xC4, "iload"),
+        (Instruction::AStore(3), 0x4E, "astore_3"),
+        (Instruction::IConst(-1), 0x02, "iconst_m1"),
+        (Instruction::Bipush(16), 0x10, "bipush"),
+        (Instruction::InvokeVirtual(1), 0xB6, "invokevirtual"),
+        (Instruction::Return, 0xB1, "return"),
+        (
+            Instruction::TableSwitch {
+                padding: 0,
+                minimum: 0,
+                maximum: 0,
+                jump_targets: vec![0],
+                default: 0,
+            },
+            0xAA,
+            "tableswitch",
+        ),
+    ];
+
+    for (inst, opcode, mnemonic) in cases {
+        assert_eq!(inst.opcode(), opcode, "{inst:?}");
+        assert_eq!(inst.mnemonic(), mnemonic, "{inst:?}");
+    }
+}
+
+#[test]
+fn constants_without_a_compact_form_are_widened() {
+    let mut jvm = JVMClass::template("Constants");
+    let code = Attribute::Code {
+        max_stack: 6,
+        max_locals: 0,
+        code: vec![
+            Instruction::IConst(100),
+            Instruction::IConst(-200),
+            Instruction::IConst(40000),
+            Instruction::IConst(100000),
+            Instruction::LConst(7),
+            Instruction::FConst(2.0),
+            Instruction::Return,
+        ],
+        exception_table: vec![],
+        attributes: vec![],
+    };
+    jvm.add_method(
+        "push",
+        "()V",
+        vec![AccessFlag::Public, AccessFlag::Static],
+        Some(code),
+    );
+    let pool_size = jvm.constants.len();
+
+    let bytes = jvm.to_bytes().unwrap();
+    // the written pool holds the new constants, the class itself is unchanged
+    assert_eq!(jvm.constants.len(), pool_size);
+
+    let jvm = JVMClass::from_bytes(&bytes).unwrap();
+    let code = jvm.find_method("push", "()V").unwrap().code().unwrap();
+    let code = code.instructions();
+    assert_eq!(code[0], Instruction::Bipush(100));
+    assert_eq!(code[1], Instruction::Sipush(-200));
+    let constant = |inst: &Instruction| match inst {
+        Instruction::LdcW(index) | Instruction::Ldc2W(index) => {
+            jvm.get_constant(*index).unwrap().clone()
+        }
+        _ => panic!("{inst:?}"),
+    };
+    assert_eq!(constant(&code[2]), Constant::Integer(40000));
+    assert_eq!(constant(&code[3]), Constant::Integer(100000));
+    assert_eq!(constant(&code[4]), Constant::Long(7));
+    assert_eq!(code[5], Instruction::FConst(2.0));
+}
+
+#[test]
+fn decompile_with_raw_returns_the_bytes_read() {
+    let bytes = common::fixture("Switches");
+    let view = ClassView::new(&bytes).unwrap();
+    let sparse = view
+        .methods()
+        .find(|method| method.name().unwrap() == "sparse")
+        .unwrap();
+
+    let (instructions, raw) = sparse.decompile_with_raw().unwrap().unwrap();
+    // iload_0, lookupswitch and its two bytes of padding
+    assert_eq!(&raw[..4], &[0x1A, 0xAB, 0, 0]);
+    let mut array = (raw.len() as u32).to_be_bytes().to_vec();
+    array.extend(raw);
+    assert!(bytes.windows(array.len()).any(|window| window == array));
+
+    let jvm = JVMClass::from_bytes(&bytes).unwrap();
+    assert_eq!(instructions, common::code(&jvm, "sparse", "(I)I"));
+}
+
+#[test]
+fn displays_instructions_compactly() {
+    let cases = [
+        (Instruction::ILoad(2), "iload_2"),
+        (Instruction::ILoad(7), "iload 7"),
+        (Instruction::ILoadW(300), "wide iload 300"),
+        (Instruction::IInc(1, -1), "iinc 1, -1"),
+        (Instruction::IConst(100000), "ldc_w 100000"),
+        (Instruction::Bipush(0xFF), "bipush -1"),
+        (Instruction::InvokeVirtual(12), "invokevirtual #12"),
+        (
+            Instruction::InvokeInterface { index: 3, count: 2 },
+            "invokeinterface #3, 2",
+        ),
+        (Instruction::NewArray(ArrayType::Int), "newarray int"),
+        (Instruction::Goto(15), "goto +15"),
+        (Instruction::Ifeq(-4), "ifeq -4"),
+        (Instruction::Return, "return"),
+        (
+            Instruction::TableSwitch {
+                padding: 0,
+                minimum: 1,
+                maximum: 2,
+                jump_targets: vec![20, 22],
+                default: -3,
+            },
+            "tableswitch 1 to 2 [+20, +22], default -3",
+        ),
+    ];
+
+    for (inst, text) in cases {
+        assert_eq!(inst.to_string(), text);
+    }
+}
+
+#[test]
+fn displays_attributes_as_one_line_summaries() {
+    let jvm = common::load("Loops");
+    let code = jvm.find_method("max", "(II)I").unwrap().code().unwrap();
+    assert_eq!(
+        code.to_string(),
+        "Code[max_stack=2, max_locals=3, 9 instructions]"
+    );
+
+    let Attribute::Code { attributes, .. } = code else {
+        unreachable!();
+    };
+    let summaries: Vec<_> = attributes.iter().map(ToString::to_string).collect();
+    assert_eq!(
+        summaries,
+        [
+            "LineNumberTable[4 lines]",
+            "LocalVariableTable[3 variables]",
+            "StackMapTable[1 frame]"
+        ]
+    );
+    assert_eq!(Attribute::Synthetic.to_string(), "Synthetic");
+}
+
+/// A Java 5 class with the subroutines old compilers used for `finally` blocks.
+fn class_with_subroutines() -> JVMClass {
+    let mut jvm = JVMClass::template("Legacy");
+    jvm.set_version(ClassVersion::Java5);
+    jvm.get_or_add_utf8_index("Code");
+
+    // static int f(int x) { try { return x; } finally { x = 0; } }
+    jvm.add_method(
+        "f",
+        "(I)I",
+        vec![AccessFlag::Static],
+        Some(Attribute::Code {
+            max_stack: 1,
+            max_locals: 4,
+            code: vec![
+                Instruction::ILoad(0),
+                Instruction::IStore(1),
+                Instruction::Jsr(11),
+                Instruction::ILoad(1),
+                Instruction::IReturn,
+                Instruction::AStore(2),
+                Instruction::Jsr(5),
+                Instruction::ALoad(2),
+                Instruction::AThrow,
+                Instruction::AStore(3),
+                Instruction::IConst(0),
+                Instruction::IStore(0),
+                Instruction::Ret(3),
+            ],
+            exception_table: vec![ExceptionTableEntry {
+                start_pc: 0,
+                end_pc: 5,
+                handler_pc: 7,
+                catch_type: 0,
+            }],
+            attributes: vec![],
+        }),
+    );
+
+    // a subroutine before its wide call
+    jvm.add_method(
+        "g",
+        "()V",
+        vec![AccessFlag::Static],
+        Some(Attribute::Code {
+            max_stack: 1,
+            max_locals: 1,
+            code: vec![
+                Instruction::Goto(6),
+                Instruction::AStore(0),
+                Instruction::Ret(0),
+                Instruction::JsrW(-3),
+                Instruction::Return,
+            ],
+            exception_table: vec![],
+            attributes: vec![],
+        }),
+    );
+
+    jvm
+}
+
+#[test]
+fn resolves_the_targets_of_subroutines() {
+    let bytes = class_with_subroutines().to_bytes().unwrap();
+    let jvm = JVMClass::from_bytes(&bytes).unwrap();
+    assert_eq!(jvm.major, 49);
+    assert!(jvm.validate().is_ok());
+
+    let f = jvm.find_method("f", "(I)I").unwrap().code().unwrap();
+    assert!(uses_subroutines(f.instructions()));
+    let offsets = f.instruction_offsets();
+    // both calls jump to the `astore_3` saving the return address
+    assert_eq!(f.instructions()[2].branch_target(offsets[2]), Some(13));
+    assert_eq!(f.instructions()[6].branch_target(offsets[6]), Some(13));
+    let targets = f.absolute_targets().unwrap();
+    assert_eq!(targets[2], [9]);
+    assert_eq!(targets[6], [9]);
+    assert_eq!(f.instructions()[12], Instruction::Ret(3));
+
+    let g = jvm.find_method("g", "()V").unwrap().code().unwrap();
+    assert_eq!(g.instructions()[3], Instruction::JsrW(-3));
+    assert_eq!(g.instructions()[3].branch_target(6), Some(3));
+    assert_eq!(g.absolute_targets().unwrap()[3], [1]);
+    // jsr_w -3, signed
+    let jsr_w = [0xC9, 0xFF, 0xFF, 0xFF, 0xFD];
+    assert!(bytes.windows(jsr_w.len()).any(|window| window == jsr_w));
+
+    let hello = common::load("Hello");
+    assert!(!hello
+        .all_code()
+        .any(|(_, code)| uses_subroutines(code.instructions())));
+}
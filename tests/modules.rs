@@ -0,0 +1,111 @@
+mod common;
+
+use class_rs::AccessFlag;
+
+#[test]
+fn resolves_module_and_requires_versions() {
+    let jvm = common::load("module-info");
+
+    assert!(jvm.is_module());
+    assert_eq!(jvm.module_version().unwrap(), Some("1.0"));
+
+    let info = jvm.module_info().unwrap().unwrap();
+    assert_eq!(info.name, "fixtures.app");
+    assert_eq!(info.version.as_deref(), Some("1.0"));
+
+    let version = |name: &str| {
+        let (_, _, version) = info
+            .requires
+            .iter()
+            .find(|(required, ..)| required == name)
+            .unwrap_or_else(|| panic!("{name} isn't required"));
+        version.clone()
+    };
+    assert!(version("java.base").is_some());
+    assert_eq!(version("fixtures.dep"), None);
+}
+
+#[test]
+fn unversioned_module_and_plain_class_have_no_version() {
+    let mut jvm = common::load("module-info");
+    for attr in &mut jvm.attributes {
+        if let class_rs::Attribute::Module {
+            module_version_index,
+            ..
+        } = attr
+        {
+            *module_version_index = 0;
+        }
+    }
+    assert_eq!(jvm.module_version().unwrap(), None);
+
+    let jvm = class_rs::JVMClass::template("NotAModule");
+    assert_eq!(jvm.module_version().unwrap(), None);
+    assert!(jvm.module_info().is_none());
+}
+
+#[test]
+fn module_info_is_valid_without_super_class_nor_members() {
+    let jvm = common::load("module-info");
+
+    assert!(jvm.is_module_info());
+    assert_eq!(jvm.this_class_name().unwrap(), "module-info");
+    assert_eq!(jvm.super_class, 0);
+    assert!(jvm.fields.is_empty() && jvm.methods.is_empty());
+    assert!(jvm.validate().is_ok());
+
+    assert!(!common::load("Hello").is_module_info());
+}
+
+#[test]
+fn module_info_with_a_super_class_is_invalid() {
+    let mut jvm = common::load("module-info");
+    jvm.super_class = jvm.constant_pool().intern_class("java/lang/Object");
+
+    let errors = jvm.validate().unwrap_err();
+    assert!(matches!(
+        errors.as_slice(),
+        [class_rs::JavaError::InvalidModuleInfo(_)]
+    ));
+}
+
+#[test]
+fn lists_requires_exports_and_services() {
+    let info = common::load("module-info").module_info().unwrap().unwrap();
+    assert!(info.flags.is_empty());
+
+    let requires: Vec<_> = info
+        .requires
+        .iter()
+        .map(|(name, flags, _)| (name.as_str(), flags.as_slice()))
+        .collect();
+    assert_eq!(
+        requires,
+        [
+            ("java.base", &[AccessFlag::Mandated][..]),
+            ("java.logging", &[]),
+            ("fixtures.dep", &[AccessFlag::Transitive]),
+        ]
+    );
+
+    assert_eq!(
+        info.exports,
+        [
+            ("fixtures/app".into(), vec![], vec![]),
+            (
+                "fixtures/app/internal".into(),
+                vec![],
+                vec!["java.logging".into()]
+            ),
+        ]
+    );
+    assert!(info.opens.is_empty());
+    assert_eq!(info.uses, ["java/lang/Runnable"]);
+    assert_eq!(
+        info.provides,
+        [(
+            "java/lang/Runnable".into(),
+            vec!["fixtures/app/Task".into()]
+        )]
+    );
+}
@@ -0,0 +1,401 @@
+mod common;
+
+use std::io::Read;
+
+use class_rs::{
+    AccessFlag, Annotation, ArrayType, Attribute, ClassVersion, Constant, ElementValue,
+    ElementValuePair, Instruction, JVMClass, JavaError, ReferenceKind, StackMapFrame,
+    StackMapFrameType, TargetInfo, TypeAnnotation, VerificationType,
+};
+
+/// A reader returning at most one byte per `read`, like a slow stream.
+struct OneByte<'a>(&'a [u8]);
+
+impl Read for OneByte<'_> {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        match (self.0.split_first(), buf.first_mut()) {
+            (Some((byte, rest)), Some(first)) => {
+                *first = *byte;
+                self.0 = rest;
+                Ok(1)
+            }
+            _ => Ok(0),
+        }
+    }
+}
+
+/// A class with a method whose code array is `code`, which doesn't need to be valid.
+fn class_with_code(code: &[u8]) -> Vec<u8> {
+    let mut jvm = JVMClass::template("Raw");
+    let placeholder = vec![Instruction::Nop; code.len()];
+    jvm.add_method(
+        "raw",
+        "()V",
+        vec![AccessFlag::Static],
+        Some(Attribute::Code {
+            code: placeholder,
+            max_stack: 0,
+            max_locals: 0,
+            exception_table: vec![],
+            attributes: vec![],
+        }),
+    );
+    let mut bytes = jvm.to_bytes().unwrap();
+
+    // the code array is near the end, after the counts of the members that could match too
+    let mut array = (code.len() as u32).to_be_bytes().to_vec();
+    array.extend(vec![0; code.len()]);
+    let start = bytes
+        .windows(array.len())
+        .rposition(|window| window == array)
+        .unwrap()
+        + 4;
+    bytes[start..start + code.len()].copy_from_slice(code);
+    bytes
+}
+
+fn read_error(bytes: &[u8]) -> JavaError {
+    let error = JVMClass::from_bytes(bytes).unwrap_err();
+    match error.downcast::<JavaError>() {
+        Ok(error) => *error,
+        Err(error) => panic!("not a JavaError: {error}"),
+    }
+}
+
+#[test]
+fn reads_large_constants_and_code_one_byte_at_a_time() {
+    let mut jvm = JVMClass::template("Slow");
+    let big = "x".repeat(60000);
+    jvm.get_or_add_utf8_index(&big);
+
+    let mut code = vec![Instruction::Nop; 20000];
+    code.push(Instruction::Return);
+    jvm.add_method(
+        "slow",
+        "()V",
+        vec![AccessFlag::Public, AccessFlag::Static],
+        Some(Attribute::Code {
+            code: code.clone(),
+            max_stack: 0,
+            max_locals: 0,
+            exception_table: vec![],
+            attributes: vec![],
+        }),
+    );
+
+    let bytes = jvm.to_bytes().unwrap();
+    let read = JVMClass::from_reader(&mut OneByte(&bytes)).unwrap();
+    assert!(read.get_string_index(&big).is_ok());
+    assert_eq!(common::code(&read, "slow", "()V"), code.as_slice());
+
+    let fixture = common::fixture("Hello");
+    let hello = JVMClass::from_reader(&mut OneByte(&fixture)).unwrap();
+    assert_eq!(hello, common::load("Hello"));
+}
+
+#[test]
+fn truncated_input_is_an_error() {
+    let bytes = common::fixture("Hello");
+    for len in [0, 3, 9, bytes.len() / 2, bytes.len() - 1] {
+        assert!(JVMClass::from_bytes(&bytes[..len]).is_err(), "{len}");
+    }
+}
+
+#[test]
+fn unknown_opcodes_are_errors() {
+    // nop, then the unassigned 0xCB
+    let error = read_error(&class_with_code(&[0x00, 0xCB, 0xB1]));
+    assert!(
+        matches!(
+            error,
+            JavaError::UnknownOpcode {
+                opcode: 0xCB,
+                offset: 1
+            }
+        ),
+        "{error:?}"
+    );
+}
+
+#[test]
+fn non_zero_padding_is_an_error() {
+    // invokeinterface #1, 1, with a padding of 1
+    let error = read_error(&class_with_code(&[0xB9, 0x00, 0x01, 0x01, 0x01, 0xB1]));
+    assert!(
+        matches!(
+            error,
+            JavaError::NonZeroPadding {
+                opcode: 0xB9,
+                offset: 0
+            }
+        ),
+        "{error:?}"
+    );
+
+    // nop, then invokedynamic #1 with a padding of 1
+    let error = read_error(&class_with_code(&[
+        0x00, 0xBA, 0x00, 0x01, 0x00, 0x01, 0xB1,
+    ]));
+    assert!(
+        matches!(
+            error,
+            JavaError::NonZeroPadding {
+                opcode: 0xBA,
+                offset: 1
+            }
+        ),
+        "{error:?}"
+    );
+}
+
+#[test]
+fn unknown_constant_tags_are_errors() {
+    let mut bytes = JVMClass::template("Tags").to_bytes().unwrap();
+    // the tag of the first constant, after the magic, the version and the count
+    bytes[10] = 2;
+
+    let error = read_error(&bytes);
+    assert!(
+        matches!(error, JavaError::UnknownConstantTag(2)),
+        "{error:?}"
+    );
+}
+
+#[test]
+fn decodes_the_type_of_primitive_arrays() {
+    // iconst_1, newarray int, pop, return
+    let code = [0x04, 0xBC, 0x0A, 0x57, 0xB1];
+    let bytes = class_with_code(&code);
+    let jvm = JVMClass::from_bytes(&bytes).unwrap();
+    let instructions = common::code(&jvm, "raw", "()V");
+    assert_eq!(instructions[1], Instruction::NewArray(ArrayType::Int));
+
+    let stored = jvm.to_bytes().unwrap();
+    assert!(stored.windows(code.len()).any(|window| window == code));
+
+    // 12 is past `long`
+    let error = read_error(&class_with_code(&[0x04, 0xBC, 0x0C, 0x57, 0xB1]));
+    assert!(
+        matches!(
+            error,
+            JavaError::InvalidArrayType {
+                atype: 12,
+                offset: 1
+            }
+        ),
+        "{error:?}"
+    );
+}
+
+#[test]
+fn unknown_reference_kinds_are_errors() {
+    let mut jvm = JVMClass::template("Handle");
+    let method = jvm
+        .constant_pool()
+        .intern_interface_methodref("Handle", "run", "()V");
+    jvm.push_constant(Constant::MethodHandle {
+        reference_kind: ReferenceKind::InvokeInterface,
+        reference_index: method,
+    });
+    let mut bytes = jvm.to_bytes().unwrap();
+    assert!(JVMClass::from_bytes(&bytes).is_ok());
+
+    let [high, low] = method.to_be_bytes();
+    let handle = [15, 9, high, low];
+    let kind = bytes
+        .windows(handle.len())
+        .position(|window| window == handle)
+        .unwrap()
+        + 1;
+    bytes[kind] = 10;
+    assert!(matches!(
+        read_error(&bytes),
+        JavaError::InvalidReferenceKind(10)
+    ));
+}
+
+#[test]
+fn attributes_longer_than_their_content_are_skipped() {
+    let mut jvm = JVMClass::template("Padded");
+    jvm.set_source_file("Padded.java");
+    jvm.get_or_add_utf8_index("Deprecated");
+    jvm.attributes.push(Attribute::Deprecated);
+    let mut bytes = jvm.to_bytes().unwrap();
+
+    // the class attributes come last: count, SourceFile, then Deprecated
+    let source_file = bytes.len() - 6 - 8;
+    assert_eq!(&bytes[source_file + 2..source_file + 6], &[0, 0, 0, 2]);
+    bytes[source_file + 5] = 4;
+    bytes.splice(source_file + 8..source_file + 8, [0xAB, 0xCD]);
+
+    let mut lenient = JVMClass::new();
+    lenient.load(&mut bytes.as_slice()).unwrap();
+    assert_eq!(lenient.source_file().unwrap().unwrap(), "Padded.java");
+    assert_eq!(lenient.attributes.last(), Some(&Attribute::Deprecated));
+
+    let mut checked = JVMClass::new();
+    let error = checked.load_checked(&mut bytes.as_slice()).unwrap_err();
+    assert!(matches!(
+        error.downcast_ref::<JavaError>(),
+        Some(JavaError::AttributeLengthMismatch {
+            name,
+            declared: 4,
+            read: 2,
+        }) if name == "SourceFile"
+    ));
+}
+
+#[test]
+fn peeks_at_the_header_only() {
+    let bytes = common::fixture("WideLocals");
+    let mut cursor = std::io::Cursor::new(bytes.as_slice());
+    let metadata = JVMClass::peek_metadata(&mut cursor).unwrap();
+
+    assert_eq!(metadata.name, "fixtures/WideLocals");
+    assert_eq!(metadata.super_name.as_deref(), Some("java/lang/Object"));
+    assert_eq!(metadata.version, ClassVersion::Java17);
+    assert_eq!(metadata.flags, vec![AccessFlag::Public, AccessFlag::Super]);
+    // the code of its many locals is left unread
+    let read = cursor.position() as usize;
+    assert!(read * 2 < bytes.len(), "{read} of {} bytes", bytes.len());
+
+    let metadata = JVMClass::peek_metadata(&mut common::fixture("module-info").as_slice()).unwrap();
+    assert_eq!(metadata.name, "module-info");
+    assert_eq!(metadata.super_name, None);
+
+    let mut bytes = bytes;
+    bytes[..4].copy_from_slice(&[0xCA, 0xFE, 0xD0, 0x0D]);
+    let error = JVMClass::peek_metadata(&mut bytes.as_slice()).unwrap_err();
+    assert!(matches!(
+        error.downcast_ref::<JavaError>(),
+        Some(JavaError::InvalidMagic(0xCAFED00D))
+    ));
+}
+
+fn eof_section(bytes: &[u8]) -> &'static str {
+    match read_error(bytes) {
+        JavaError::UnexpectedEof { section, source } => {
+            assert_eq!(source.kind(), std::io::ErrorKind::UnexpectedEof);
+            section
+        }
+        error => panic!("{error:?}"),
+    }
+}
+
+#[test]
+fn truncated_input_names_the_section() {
+    let bytes = common::fixture("Hello");
+    let mut cursor = std::io::Cursor::new(bytes.as_slice());
+    JVMClass::peek_metadata(&mut cursor).unwrap();
+    // the access flags, this_class and super_class follow the constant pool
+    let pool_end = cursor.position() as usize - 6;
+
+    assert_eq!(eof_section(&bytes[..20]), "the constant pool");
+    assert_eq!(eof_section(&bytes[..pool_end + 1]), "the class header");
+    assert_eq!(eof_section(&bytes[..pool_end + 7]), "interfaces");
+    assert_eq!(eof_section(&bytes[..bytes.len() - 1]), "class attributes");
+
+    // the class attributes count, then the end of the last method
+    let code = class_with_code(&[0xB1]);
+    assert_eq!(eof_section(&code[..code.len() - 3]), "method attributes");
+    // a bipush without its operand
+    assert_eq!(eof_section(&class_with_code(&[0x10])), "code");
+
+    let error = JVMClass::from_bytes(&code[..code.len() - 3]).unwrap_err();
+    assert_eq!(
+        error.to_string(),
+        "Unexpected EOF while reading method attributes"
+    );
+    let source = std::error::Error::source(error.as_ref()).unwrap();
+    assert!(source.downcast_ref::<std::io::Error>().is_some());
+}
+
+/// Replaces the only occurrence of `from` in `bytes` by `to`.
+fn patch(bytes: &mut [u8], from: &[u8], to: &[u8]) {
+    let positions: Vec<_> = bytes
+        .windows(from.len())
+        .enumerate()
+        .filter(|(_, window)| *window == from)
+        .map(|(position, _)| position)
+        .collect();
+    assert_eq!(positions.len(), 1, "{from:?}");
+    bytes[positions[0]..positions[0] + to.len()].copy_from_slice(to);
+}
+
+#[test]
+fn unknown_tags_in_attributes_are_errors() {
+    let mut jvm = JVMClass::template("Tags");
+    let mut pool = jvm.constant_pool();
+    let annotation = pool.intern_utf8("LTagged;");
+    let name = pool.intern_utf8("value");
+    let value = pool.intern_integer(0x1234);
+    pool.intern_utf8("RuntimeVisibleAnnotations");
+    pool.intern_utf8("RuntimeVisibleTypeAnnotations");
+    jvm.attributes
+        .push(Attribute::RuntimeVisibleAnnotations(vec![Annotation {
+            type_index: annotation,
+            element_value_pairs: vec![ElementValuePair {
+                element_name_index: name,
+                value: ElementValue::ConstValueIndex {
+                    tag: b'I',
+                    const_value_index: value,
+                },
+            }],
+        }]));
+    jvm.attributes
+        .push(Attribute::RuntimeVisibleTypeAnnotations(vec![
+            TypeAnnotation {
+                target_info: TargetInfo::Supertype {
+                    supertype_index: 0xFFFF,
+                },
+                target_path: vec![],
+                annotation: Annotation {
+                    type_index: annotation,
+                    element_value_pairs: vec![],
+                },
+            },
+        ]));
+    let bytes = jvm.to_bytes().unwrap();
+    assert!(JVMClass::from_bytes(&bytes).is_ok());
+
+    let [high, low] = value.to_be_bytes();
+    let mut tag = bytes.clone();
+    patch(&mut tag, &[b'I', high, low], b"X");
+    assert!(matches!(
+        read_error(&tag),
+        JavaError::UnknownElementValueTag(b'X')
+    ));
+
+    let mut target = bytes;
+    patch(&mut target, &[0x10, 0xFF, 0xFF], &[0x30]);
+    assert!(matches!(
+        read_error(&target),
+        JavaError::UnknownTargetType(0x30)
+    ));
+}
+
+#[test]
+fn unknown_verification_types_are_errors() {
+    let bytes = common::fixture("Loops");
+    let jvm = JVMClass::from_bytes(&bytes).unwrap();
+    let max = jvm.find_method("max", "(II)I").unwrap().code().unwrap();
+    let Attribute::Code { attributes, .. } = max else {
+        unreachable!();
+    };
+    assert!(
+        attributes.contains(&Attribute::StackMapTable(vec![StackMapFrame {
+            frame_type: StackMapFrameType::AppendFrame(252),
+            offset_delta: 9,
+            locals: vec![VerificationType::Integer],
+            stack: vec![],
+        }]))
+    );
+
+    // append_frame 252, offset_delta 9, int
+    let mut bytes = bytes;
+    patch(&mut bytes, &[252, 0, 9, 1], &[252, 0, 9, 9]);
+    assert!(matches!(
+        read_error(&bytes),
+        JavaError::UnknownVerificationTypeTag(9)
+    ));
+}
@@ -0,0 +1,66 @@
+mod common;
+
+use class_rs::{Attribute, JVMClass, JavaError, RecordComponent};
+
+#[test]
+fn resolves_record_components() {
+    let jvm = common::load("Point");
+    assert_eq!(
+        jvm.record_components().unwrap().unwrap(),
+        vec![("x".into(), "I".into()), ("y".into(), "I".into())]
+    );
+
+    let Some(Attribute::Record(components)) = jvm.find_attribute("Record") else {
+        panic!("no Record attribute");
+    };
+    // `int` components have no generic signature
+    assert!(components
+        .iter()
+        .all(|component| component.signature(&jvm).is_none()));
+
+    assert!(common::load("Hello").record_components().is_none());
+}
+
+#[test]
+fn reads_the_signature_of_a_component() {
+    let mut jvm = JVMClass::template("Names");
+    let mut pool = jvm.constant_pool();
+    let component = RecordComponent {
+        name_index: pool.intern_utf8("names"),
+        descriptor_index: pool.intern_utf8("Ljava/util/List;"),
+        attributes: vec![Attribute::Signature {
+            signature_index: pool.intern_utf8("Ljava/util/List<Ljava/lang/String;>;"),
+        }],
+    };
+    pool.intern_utf8("Signature");
+    pool.intern_utf8("Record");
+    jvm.attributes.push(Attribute::Record(vec![component]));
+
+    let jvm = JVMClass::from_bytes(&jvm.to_bytes().unwrap()).unwrap();
+    assert_eq!(
+        jvm.record_components().unwrap().unwrap(),
+        vec![("names".into(), "Ljava/util/List;".into())]
+    );
+    let Some(Attribute::Record(components)) = jvm.find_attribute("Record") else {
+        unreachable!();
+    };
+    assert_eq!(
+        components[0].signature(&jvm).as_deref(),
+        Some("Ljava/util/List<Ljava/lang/String;>;")
+    );
+}
+
+#[test]
+fn reports_a_dangling_component_name() {
+    let mut jvm = common::load("Point");
+    for attribute in &mut jvm.attributes {
+        if let Attribute::Record(components) = attribute {
+            components[0].name_index = 0xFFFF;
+        }
+    }
+
+    assert!(matches!(
+        jvm.record_components(),
+        Some(Err(JavaError::InvalidConstantId(0xFFFF)))
+    ));
+}
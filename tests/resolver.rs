@@ -0,0 +1,61 @@
+mod common;
+
+use class_rs::{AccessFlag, JVMClass, MapResolver};
+
+fn runnable() -> JVMClass {
+    let mut jvm = JVMClass::template("java/lang/Runnable");
+    jvm.access_flags = vec![
+        AccessFlag::Public,
+        AccessFlag::Interface,
+        AccessFlag::Abstract,
+    ];
+    jvm.add_method(
+        "run",
+        "()V",
+        vec![AccessFlag::Public, AccessFlag::Abstract],
+        None,
+    );
+    jvm
+}
+
+#[test]
+fn detects_overridden_interface_method() {
+    let jvm = common::load("Hello");
+    let mut resolver = MapResolver::new();
+    resolver.insert("java/lang/Runnable", runnable());
+
+    let run = jvm.find_method("run", "()V").unwrap();
+    assert!(jvm.overrides(run, &resolver).unwrap());
+
+    let add = jvm.find_method("add", "(II)I").unwrap();
+    assert!(!jvm.overrides(add, &resolver).unwrap());
+
+    let main = jvm.find_method("main", "([Ljava/lang/String;)V").unwrap();
+    assert!(!jvm.overrides(main, &resolver).unwrap());
+}
+
+#[test]
+fn detects_overridden_super_method() {
+    let mut base = JVMClass::template("fixtures/Base");
+    base.add_method("greet", "()V", vec![AccessFlag::Public], None);
+    base.add_method("hidden", "()V", vec![AccessFlag::Private], None);
+
+    let mut child = JVMClass::template("fixtures/Child");
+    child.super_class = child.constant_pool().intern_class("fixtures/Base");
+    child.add_method("greet", "()V", vec![AccessFlag::Public], None);
+    child.add_method("hidden", "()V", vec![AccessFlag::Public], None);
+
+    let mut resolver = MapResolver::new();
+    resolver.insert("fixtures/Base", base);
+
+    assert_eq!(
+        child.super_chain(&resolver).unwrap(),
+        vec!["fixtures/Base", "java/lang/Object"]
+    );
+
+    let greet = child.find_method("greet", "()V").unwrap();
+    assert!(child.overrides(greet, &resolver).unwrap());
+
+    let hidden = child.find_method("hidden", "()V").unwrap();
+    assert!(!child.overrides(hidden, &resolver).unwrap());
+}
@@ -0,0 +1,30 @@
+mod common;
+
+use class_rs::JVMClass;
+
+#[test]
+fn fixtures_are_stored_byte_for_byte() {
+    let mut count = 0;
+    for entry in std::fs::read_dir(common::fixtures_dir()).unwrap() {
+        let path = entry.unwrap().path();
+        if path.extension() != Some("class".as_ref()) {
+            continue;
+        }
+
+        let bytes = std::fs::read(&path).unwrap();
+        let jvm =
+            JVMClass::from_bytes(&bytes).unwrap_or_else(|e| panic!("{}: {e}", path.display()));
+        let stored = jvm
+            .to_bytes()
+            .unwrap_or_else(|e| panic!("{}: {e}", path.display()));
+
+        assert!(
+            stored == bytes,
+            "{} isn't stored identically",
+            path.display()
+        );
+        count += 1;
+    }
+
+    assert!(count > 0, "no fixtures found");
+}
@@ -0,0 +1,59 @@
+#![cfg(feature = "serde")]
+
+mod common;
+
+use class_rs::{Constant, JVMClass};
+
+fn json_round_trip(jvm: &JVMClass) -> JVMClass {
+    let json = serde_json::to_string(jvm).unwrap();
+    serde_json::from_str(&json).unwrap()
+}
+
+#[test]
+fn javac_classes_round_trip_through_json() {
+    for name in ["Hello", "Strings", "Switches", "TryFinally", "module-info"] {
+        let jvm = common::load(name);
+        assert_eq!(json_round_trip(&jvm), jvm, "{name}");
+    }
+}
+
+#[test]
+fn nan_and_infinities_keep_their_bits() {
+    let mut jvm = JVMClass::template("Floats");
+    let mut pool = jvm.constant_pool();
+    let floats = [
+        f32::from_bits(0x7F80_0001),
+        f32::NAN,
+        f32::INFINITY,
+        f32::NEG_INFINITY,
+        -0.0,
+    ]
+    .map(|value| pool.intern_float(value));
+    let doubles = [
+        f64::from_bits(0x7FF0_0000_0000_0001),
+        f64::NAN,
+        f64::INFINITY,
+        f64::NEG_INFINITY,
+        -0.0,
+    ]
+    .map(|value| pool.intern_double(value));
+
+    let copy = json_round_trip(&jvm);
+    assert_eq!(copy, jvm);
+    for id in floats {
+        let (Constant::Float(a), Constant::Float(b)) =
+            (&jvm.constants[id as usize], &copy.constants[id as usize])
+        else {
+            unreachable!();
+        };
+        assert_eq!(a.to_bits(), b.to_bits());
+    }
+    for id in doubles {
+        let (Constant::Double(a), Constant::Double(b)) =
+            (&jvm.constants[id as usize], &copy.constants[id as usize])
+        else {
+            unreachable!();
+        };
+        assert_eq!(a.to_bits(), b.to_bits());
+    }
+}
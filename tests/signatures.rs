@@ -0,0 +1,111 @@
+mod common;
+
+use class_rs::signature::{
+    parse_class_signature, parse_field_signature, parse_method_signature, ClassTypeSignature,
+    SimpleClassTypeSignature, TypeArgument, TypeParameter, TypeSignature,
+};
+use class_rs::{Attribute, JavaError};
+
+fn class(name: &str, type_arguments: Vec<TypeArgument>) -> ClassTypeSignature {
+    ClassTypeSignature {
+        segments: vec![SimpleClassTypeSignature {
+            name: name.into(),
+            type_arguments,
+        }],
+    }
+}
+
+fn variable(name: &str) -> TypeSignature {
+    TypeSignature::TypeVariable(name.into())
+}
+
+#[test]
+fn parses_the_signature_of_a_generic_method() {
+    let jvm = common::load("Generic");
+    let sort = jvm.find_methods_named("sort").next().unwrap();
+    let signature = sort.generic_signature(&jvm).unwrap().unwrap();
+
+    // <E extends Comparable<? super E>>
+    assert_eq!(
+        signature.type_parameters,
+        [TypeParameter {
+            name: "E".into(),
+            class_bound: None,
+            interface_bounds: vec![TypeSignature::Class(class(
+                "java/lang/Comparable",
+                vec![TypeArgument::Super(variable("E"))]
+            ))],
+        }]
+    );
+    // List<? extends E>, Map<String, ? super E>
+    assert_eq!(
+        signature.params,
+        [
+            TypeSignature::Class(class(
+                "java/util/List",
+                vec![TypeArgument::Extends(variable("E"))]
+            )),
+            TypeSignature::Class(class(
+                "java/util/Map",
+                vec![
+                    TypeArgument::Exact(TypeSignature::Class(class("java/lang/String", vec![]))),
+                    TypeArgument::Super(variable("E")),
+                ]
+            )),
+        ]
+    );
+    assert_eq!(signature.ret, None);
+    assert!(signature.throws.is_empty());
+
+    // the constructor isn't generic
+    let init = jvm.find_methods_named("<init>").next().unwrap();
+    assert!(init.generic_signature(&jvm).is_none());
+}
+
+#[test]
+fn parses_class_and_field_signatures() {
+    let jvm = common::load("Generic");
+    let Some(Attribute::Signature { signature_index }) = jvm.find_attribute("Signature") else {
+        panic!("Generic has no signature");
+    };
+    let signature = parse_class_signature(jvm.get_string(*signature_index).unwrap()).unwrap();
+    assert_eq!(signature.type_parameters.len(), 1);
+    assert_eq!(signature.type_parameters[0].name, "T");
+    assert_eq!(signature.super_class.name(), "java/lang/Object");
+    assert!(signature.interfaces.is_empty());
+
+    let TypeSignature::Class(entry) =
+        parse_field_signature("Ljava/util/Map<TK;TV;>.Entry<TK;TV;>;").unwrap()
+    else {
+        panic!("not a class type");
+    };
+    assert_eq!(entry.name(), "java/util/Map$Entry");
+    assert_eq!(entry.segments[1].type_arguments.len(), 2);
+
+    assert_eq!(
+        parse_field_signature("[[TT;").unwrap(),
+        TypeSignature::Array(Box::new(TypeSignature::Array(Box::new(variable("T")))))
+    );
+    assert_eq!(
+        parse_method_signature("<T:Ljava/lang/Object;>()TT;^TT;")
+            .unwrap()
+            .throws,
+        [variable("T")]
+    );
+}
+
+#[test]
+fn malformed_signatures_are_errors() {
+    for signature in ["", "I", "Ljava/util/List<TT;>", "LFoo;;", "[V"] {
+        assert!(
+            matches!(
+                parse_field_signature(signature),
+                Err(JavaError::InvalidSignature(s)) if s == signature
+            ),
+            "{signature}"
+        );
+    }
+    assert!(parse_method_signature("(I").is_err());
+    assert!(parse_method_signature("()V^I").is_err());
+    assert!(parse_class_signature("<T>Ljava/lang/Object;").is_err());
+}
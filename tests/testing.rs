@@ -0,0 +1,54 @@
+#![cfg(feature = "testing")]
+
+mod common;
+
+use std::panic;
+
+use class_rs::testing::{assert_roundtrips, assert_semantic_roundtrip};
+use class_rs::ClassView;
+
+#[test]
+fn javac_classes_round_trip_both_ways() {
+    for name in [
+        "Hello",
+        "Switches",
+        "TryFinally",
+        "WideLocals",
+        "module-info",
+    ] {
+        let bytes = common::fixture(name);
+        assert_roundtrips(&bytes);
+        assert_semantic_roundtrip(&bytes);
+    }
+}
+
+#[test]
+fn non_zero_padding_only_round_trips_semantically() {
+    let mut bytes = common::fixture("Switches");
+    let view = ClassView::new(&bytes).unwrap();
+    let code = view
+        .methods()
+        .find(|method| method.name().unwrap() == "sparse")
+        .unwrap()
+        .code_bytes()
+        .unwrap();
+    let start = bytes
+        .windows(code.len())
+        .position(|window| window == code)
+        .unwrap();
+    // iload_0, lookupswitch then 2 bytes of padding
+    assert_eq!(bytes[start + 1], 0xAB);
+    bytes[start + 2] = 0xFF;
+
+    assert_semantic_roundtrip(&bytes);
+    let strict = panic::catch_unwind(|| assert_roundtrips(&bytes));
+    let message = strict.unwrap_err();
+    let message = message.downcast_ref::<String>().unwrap();
+    assert_eq!(
+        *message,
+        format!(
+            "The stored class differs at offset {}: 0x00 instead of 0xFF",
+            start + 2
+        )
+    );
+}
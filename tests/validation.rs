@@ -0,0 +1,330 @@
+mod common;
+
+use class_rs::{
+    validate_class_name, AccessFlag, Attribute, ClassVersion, Constant, Instruction, JVMClass,
+    JavaError, ReferenceKind, VerifyOptions,
+};
+
+/// Adds a new `Class` constant for `name`, even if the pool already has one.
+fn push_class(jvm: &mut JVMClass, name: &str) -> u16 {
+    let name_index = jvm.get_or_add_utf8_index(name);
+    jvm.constants.push(Constant::Class { name_index });
+    (jvm.constants.len() - 1) as u16
+}
+
+fn errors(jvm: &JVMClass) -> Vec<JavaError> {
+    jvm.validate().err().unwrap_or_default()
+}
+
+#[test]
+fn template_is_valid() {
+    assert!(JVMClass::template("Valid").validate().is_ok());
+}
+
+#[test]
+fn rejects_self_extension() {
+    let mut jvm = JVMClass::template("Looping");
+    jvm.super_class = jvm.this_class;
+
+    assert!(matches!(
+        errors(&jvm).as_slice(),
+        [JavaError::SelfInheritance(name)] if name == "Looping"
+    ));
+}
+
+#[test]
+fn rejects_self_extension_through_another_constant() {
+    let mut jvm = JVMClass::template("Looping");
+    jvm.super_class = push_class(&mut jvm, "Looping");
+    assert_ne!(jvm.super_class, jvm.this_class);
+
+    assert!(matches!(
+        errors(&jvm).as_slice(),
+        [JavaError::SelfInheritance(name)] if name == "Looping"
+    ));
+}
+
+#[test]
+fn rejects_duplicate_interface() {
+    let mut jvm = JVMClass::template("Twice");
+    let runnable = push_class(&mut jvm, "java/lang/Runnable");
+    jvm.interfaces = vec![runnable, runnable];
+
+    assert!(matches!(
+        errors(&jvm).as_slice(),
+        [JavaError::DuplicateInterface(name)] if name == "java/lang/Runnable"
+    ));
+}
+
+#[test]
+fn rejects_duplicate_interface_through_another_constant_once() {
+    let mut jvm = JVMClass::template("Thrice");
+    let first = push_class(&mut jvm, "java/lang/Runnable");
+    let second = push_class(&mut jvm, "java/lang/Runnable");
+    let other = push_class(&mut jvm, "java/lang/Cloneable");
+    jvm.interfaces = vec![first, other, second, first];
+
+    assert!(matches!(
+        errors(&jvm).as_slice(),
+        [JavaError::DuplicateInterface(name)] if name == "java/lang/Runnable"
+    ));
+}
+
+#[test]
+fn rejects_versions_before_45() {
+    let mut jvm = JVMClass::template("Ancient");
+    jvm.major = 44;
+
+    assert!(matches!(
+        jvm.validate_version(),
+        Err(JavaError::UnsupportedVersion(44, 0))
+    ));
+    assert!(matches!(
+        errors(&jvm).as_slice(),
+        [JavaError::UnsupportedVersion(44, 0)]
+    ));
+}
+
+#[test]
+fn rejects_minor_versions_of_java_8() {
+    let mut jvm = JVMClass::template("Minor");
+    jvm.major = 52;
+
+    jvm.minor = 3;
+    assert!(matches!(
+        jvm.validate_version(),
+        Err(JavaError::UnsupportedVersion(52, 3))
+    ));
+
+    // Java 8 has no preview features
+    jvm.minor = 0xFFFF;
+    assert!(jvm.validate_version().is_err());
+
+    jvm.major = 61;
+    assert!(jvm.validate_version().is_ok());
+
+    jvm.major = 45;
+    jvm.minor = 3;
+    assert!(jvm.validate_version().is_ok());
+}
+
+#[test]
+fn javac_classes_are_valid() {
+    for name in [
+        "Hello",
+        "Loops",
+        "Switches",
+        "Point",
+        "Outer",
+        "Generic",
+        "TypeAnnotated",
+    ] {
+        if let Err(errors) = common::load(name).validate() {
+            panic!("{name}: {errors:?}");
+        }
+    }
+}
+
+#[test]
+fn collects_every_dangling_or_mistyped_reference() {
+    let mut jvm = JVMClass::template("Dangling");
+    let class = jvm.this_class;
+    let utf8 = jvm.get_or_add_utf8_index("name");
+    let name_and_type = jvm.constant_pool().intern_name_and_type("name", "I");
+
+    // a class instead of a Utf8
+    jvm.push_constant(Constant::NameAndType {
+        name_index: class,
+        descriptor_index: utf8,
+    });
+    // out of the pool
+    jvm.push_constant(Constant::Fieldref {
+        class_index: 1000,
+        name_and_type_index: name_and_type,
+    });
+    // a Utf8 instead of a Fieldref
+    jvm.push_constant(Constant::MethodHandle {
+        reference_kind: ReferenceKind::GetField,
+        reference_index: utf8,
+    });
+    // the class has no bootstrap methods
+    jvm.push_constant(Constant::InvokeDynamic {
+        bootstrap_method_attr_index: 0,
+        name_and_type_index: name_and_type,
+    });
+
+    let errors = errors(&jvm);
+    assert_eq!(errors.len(), 4, "{errors:?}");
+    assert!(matches!(errors[0], JavaError::ConstantTypeError(_)));
+    assert!(matches!(errors[1], JavaError::InvalidConstantId(1000)));
+    assert!(matches!(errors[2], JavaError::ConstantTypeError(_)));
+    assert!(matches!(errors[3], JavaError::BootstrapMethodNotFound(0)));
+}
+
+#[test]
+fn verify_reports_every_problem_at_once() {
+    let mut jvm = common::load("Loops");
+    jvm.minor = 3;
+    jvm.super_class = jvm.this_class;
+    jvm.access_flags
+        .extend([class_rs::AccessFlag::Final, class_rs::AccessFlag::Abstract]);
+    let source_file = jvm.find_attribute("SourceFile").unwrap().clone();
+    jvm.attributes.push(source_file);
+
+    let errors = jvm.verify(VerifyOptions::default()).unwrap_err();
+    assert_eq!(errors.len(), 4, "{errors:?}");
+    assert!(matches!(errors[0], JavaError::UnsupportedVersion(61, 3)));
+    assert!(errors
+        .iter()
+        .any(|error| matches!(error, JavaError::SelfInheritance(_))));
+    assert!(errors
+        .iter()
+        .any(|error| matches!(error, JavaError::InvalidAccessFlags(_))));
+    assert!(errors
+        .iter()
+        .any(|error| matches!(error, JavaError::DuplicateAttribute(name) if name == "SourceFile")));
+}
+
+#[test]
+fn verify_checks_stack_maps_on_demand() {
+    let mut jvm = common::load("Loops");
+    let nested = jvm
+        .methods
+        .iter()
+        .position(|method| jvm.get_string(method.0.name).unwrap() == "nested")
+        .unwrap();
+    let Some(Attribute::Code { max_stack, .. }) = jvm.methods[nested].code_mut() else {
+        unreachable!();
+    };
+    *max_stack = 2;
+
+    assert!(jvm.verify(VerifyOptions::default()).is_ok());
+
+    let errors = jvm.verify(VerifyOptions { stack_maps: true }).unwrap_err();
+    assert_eq!(errors.len(), 1, "{errors:?}");
+}
+
+#[test]
+fn method_handles_must_match_their_kind() {
+    let jvm = common::load("Hello");
+    let (id, kind) = jvm
+        .constants
+        .iter()
+        .enumerate()
+        .find_map(|(id, constant)| match constant {
+            Constant::MethodHandle { reference_kind, .. } => Some((id as u16, *reference_kind)),
+            _ => None,
+        })
+        .unwrap();
+    // the bootstrap method of the string concatenations
+    assert_eq!(kind, ReferenceKind::InvokeStatic);
+    assert!(jvm.validate_method_handle(id).is_ok());
+
+    let mut jvm = JVMClass::template("Handles");
+    let mut pool = jvm.constant_pool();
+    let field = pool.intern_fieldref("Handles", "x", "I");
+    let method = pool.intern_methodref("Handles", "run", "()V");
+    let init = pool.intern_methodref("Handles", "<init>", "()V");
+    let handle = |jvm: &mut JVMClass, reference_kind, reference_index| {
+        jvm.push_constant(Constant::MethodHandle {
+            reference_kind,
+            reference_index,
+        })
+    };
+
+    let valid = [
+        handle(&mut jvm, ReferenceKind::GetField, field),
+        handle(&mut jvm, ReferenceKind::PutStatic, field),
+        handle(&mut jvm, ReferenceKind::InvokeVirtual, method),
+        handle(&mut jvm, ReferenceKind::NewInvokeSpecial, init),
+    ];
+    for id in valid {
+        assert!(jvm.validate_method_handle(id).is_ok(), "#{id}");
+    }
+
+    let invalid = [
+        handle(&mut jvm, ReferenceKind::GetField, method),
+        handle(&mut jvm, ReferenceKind::InvokeStatic, field),
+        handle(&mut jvm, ReferenceKind::InvokeInterface, method),
+        handle(&mut jvm, ReferenceKind::NewInvokeSpecial, method),
+        handle(&mut jvm, ReferenceKind::InvokeVirtual, init),
+    ];
+    for id in invalid {
+        assert!(
+            matches!(
+                jvm.validate_method_handle(id),
+                Err(JavaError::ConstantTypeError(_))
+            ),
+            "#{id}"
+        );
+    }
+    assert_eq!(errors(&jvm).len(), invalid.len());
+}
+
+#[test]
+fn class_names_must_match_their_path() {
+    let jvm = common::load("Hello");
+    assert_eq!(jvm.this_class_name().unwrap(), "fixtures/Hello");
+    assert!(validate_class_name(&jvm, "fixtures/Hello").is_ok());
+
+    let error = validate_class_name(&jvm, "fixtures/Other").unwrap_err();
+    assert!(matches!(
+        &error,
+        JavaError::ClassNameMismatch { expected, declared }
+            if expected == "fixtures/Other" && declared == "fixtures/Hello"
+    ));
+    assert_eq!(
+        error.to_string(),
+        "Expected the class fixtures/Other, but it declares fixtures/Hello"
+    );
+
+    // names in java/lang are compared in full
+    let jvm = JVMClass::template("java/lang/Custom");
+    assert!(validate_class_name(&jvm, "java/lang/Custom").is_ok());
+    assert!(validate_class_name(&jvm, "Custom").is_err());
+}
+
+#[test]
+fn reports_the_oldest_release_able_to_hold_the_class() {
+    let mut jvm = JVMClass::template("Dynamic");
+    assert_eq!(jvm.min_required_version(), ClassVersion::Java1_1);
+
+    let name_and_type = jvm
+        .constant_pool()
+        .intern_name_and_type("run", "()Ljava/lang/Runnable;");
+    let call_site = jvm.push_constant(Constant::InvokeDynamic {
+        bootstrap_method_attr_index: 0,
+        name_and_type_index: name_and_type,
+    });
+    jvm.add_method(
+        "lambda",
+        "()Ljava/lang/Runnable;",
+        vec![AccessFlag::Public, AccessFlag::Static],
+        Some(Attribute::Code {
+            max_stack: 1,
+            max_locals: 0,
+            code: vec![Instruction::InvokeDynamic(call_site), Instruction::AReturn],
+            exception_table: vec![],
+            attributes: vec![],
+        }),
+    );
+    assert_eq!(jvm.min_required_version(), ClassVersion::Java7);
+
+    // the instruction alone is enough
+    jvm.constants[call_site as usize] = Constant::Integer(0);
+    assert_eq!(jvm.min_required_version(), ClassVersion::Java7);
+
+    jvm.attributes.push(Attribute::PermittedSubclasses(vec![]));
+    assert_eq!(jvm.min_required_version(), ClassVersion::Java17);
+
+    for (name, version) in [
+        ("Loops", ClassVersion::Java6),
+        ("Hello", ClassVersion::Java7),
+        ("TypeAnnotated", ClassVersion::Java8),
+        ("module-info", ClassVersion::Java9),
+        ("Outer", ClassVersion::Java11),
+        ("Point", ClassVersion::Java16),
+    ] {
+        assert_eq!(common::load(name).min_required_version(), version, "{name}");
+    }
+}
@@ -0,0 +1,87 @@
+mod common;
+
+use std::borrow::Cow;
+
+use class_rs::{ClassView, Constant, JVMClass};
+
+#[test]
+fn view_agrees_with_the_parsed_class() {
+    for name in [
+        "Hello",
+        "Switches",
+        "TryFinally",
+        "Outer$Inner",
+        "module-info",
+    ] {
+        let bytes = common::fixture(name);
+        let jvm = JVMClass::from_bytes(&bytes).unwrap();
+        let view = ClassView::new(&bytes).unwrap();
+
+        assert_eq!(view.version(), jvm.version(), "{name}");
+        assert_eq!(view.access_flags(), jvm.access_flags, "{name}");
+        assert_eq!(view.class_name().unwrap(), jvm.this_class_name().unwrap());
+        let super_name = match jvm.super_class {
+            0 => None,
+            id => Some(jvm.get_class_name(id).unwrap()),
+        };
+        assert_eq!(view.super_class_name().unwrap().as_deref(), super_name);
+        let interfaces: Vec<_> = view.interface_names().map(Result::unwrap).collect();
+        let expected: Vec<_> = jvm
+            .interfaces
+            .iter()
+            .map(|id| jvm.get_class_name(*id).unwrap())
+            .collect();
+        assert_eq!(interfaces, expected, "{name}");
+        assert_eq!(view.fields().count(), jvm.fields.len(), "{name}");
+
+        let methods: Vec<_> = view.methods().collect();
+        assert_eq!(methods.len(), jvm.methods.len(), "{name}");
+        for (method, expected) in methods.iter().zip(&jvm.methods) {
+            let descriptor = method.descriptor().unwrap();
+            let method_name = method.name().unwrap();
+            assert_eq!(method_name, jvm.get_string(expected.0.name).unwrap());
+            assert_eq!(descriptor, jvm.get_string(expected.0.descriptor).unwrap());
+            assert_eq!(method.access_flags(), expected.0.access_flags);
+
+            let code = expected.code();
+            assert_eq!(
+                method.instructions().unwrap(),
+                code.map(|code| code.instructions().to_vec()),
+                "{name}.{method_name}{descriptor}"
+            );
+            assert_eq!(
+                method.decompile_with_raw().unwrap().map(|(_, raw)| raw),
+                method.code_bytes()
+            );
+        }
+
+        let attributes: Vec<_> = view.attributes().map(|attr| attr.name().unwrap()).collect();
+        let expected: Vec<_> = jvm.attributes.iter().map(|attr| attr.name()).collect();
+        assert_eq!(attributes, expected, "{name}");
+    }
+}
+
+#[test]
+fn strings_are_borrowed_unless_they_need_decoding() {
+    let bytes = common::fixture("Strings");
+    let view = ClassView::new(&bytes).unwrap();
+    assert!(matches!(
+        view.class_name().unwrap(),
+        Cow::Borrowed("fixtures/Strings")
+    ));
+
+    let jvm = JVMClass::from_bytes(&bytes).unwrap();
+    let nul = jvm
+        .constants
+        .iter()
+        .position(|constant| matches!(constant, Constant::Utf8(s) if s == "a\u{0}b\u{1F600}"))
+        .unwrap() as u16;
+    // a NUL is 2 bytes and a supplementary character 6 bytes in modified UTF-8
+    let string = view.get_string(nul).unwrap();
+    assert!(matches!(string, Cow::Owned(_)));
+    assert_eq!(string, "a\u{0}b\u{1F600}");
+    assert_eq!(view.get_constant(nul).unwrap(), jvm.constants[nul as usize]);
+
+    assert!(view.get_constant(0).is_err());
+    assert!(view.get_string(0).is_err());
+}